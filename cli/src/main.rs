@@ -5,7 +5,11 @@ extern crate log;
 
 use app::*;
 use clap::{AppSettings, Arg, SubCommand};
+use db::category::Category;
 use db::establish_connection;
+use db::ledger::Ledger;
+use db::login_attempt::Scope;
+use db::user::User;
 use rust_decimal::Decimal;
 use serde_json::json;
 use std::env;
@@ -130,6 +134,46 @@ async fn main() {
                                     .required(true)
                                     .help("The activation code"),
                             ),
+                        SubCommand::with_name("set-password")
+                            .about("Sets a new password for a user account")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The user's email address"),
+                            )
+                            .arg(
+                                Arg::with_name("password")
+                                    .required(true)
+                                    .help("The new password"),
+                            ),
+                        SubCommand::with_name("grant-admin")
+                            .about("Grants administrator privileges to a user account")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The user's email address"),
+                            ),
+                        SubCommand::with_name("revoke-admin")
+                            .about("Revokes administrator privileges from a user account")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The user's email address"),
+                            ),
+                        SubCommand::with_name("disable-two-factor")
+                            .about("Disables two-factor authentication for a user account")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The user's email address"),
+                            ),
+                        SubCommand::with_name("export")
+                            .about("Exports all data of a user account as JSON")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The user's email address"),
+                            ),
                     ])
                     .setting(AppSettings::SubcommandRequiredElseHelp),
             )
@@ -153,6 +197,135 @@ async fn main() {
                     ])
                     .setting(AppSettings::SubcommandRequiredElseHelp),
             )
+            .subcommand(
+                SubCommand::with_name("invitation")
+                    .about("Commands for managing registration invitations")
+                    .subcommands(vec![
+                        SubCommand::with_name("add")
+                            .about("Invites someone to register, sends them the invitation and prints the code")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the person to invite",
+                            )),
+                        SubCommand::with_name("list").about("Lists the pending invitations"),
+                        SubCommand::with_name("delete")
+                            .about("Revokes an invitation")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address for which to revoke the invitation",
+                            )),
+                        SubCommand::with_name("purge").about("Purges expired invitations"),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("password-reset-code")
+                    .about("Commands for managing password reset codes")
+                    .subcommands(vec![
+                        SubCommand::with_name("get")
+                            .about("Retrieves a password reset code")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address for which to retrieve a password reset code",
+                            )),
+                        SubCommand::with_name("delete")
+                            .about("Deletes a password reset code")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address for which to delete the password reset code",
+                            )),
+                        SubCommand::with_name("purge")
+                            .about("Purges expired password reset codes"),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("login-attempt")
+                    .about("Commands for managing failed login attempts and lockouts")
+                    .subcommands(vec![
+                        SubCommand::with_name("get")
+                            .about("Shows the failed login attempts for an account")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address for which to show the failed login attempts",
+                            )),
+                        SubCommand::with_name("delete")
+                            .about("Clears the failed login attempts and lockout of an account")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address for which to clear the failed login attempts",
+                            )),
+                        SubCommand::with_name("delete-ip")
+                            .about("Clears the failed login attempts and lockout of an IP address")
+                            .arg(Arg::with_name("ip").required(true).help(
+                                "The IP address for which to clear the failed login attempts",
+                            )),
+                        SubCommand::with_name("purge")
+                            .about("Purges failed login attempts that are no longer relevant"),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("user-session")
+                    .about("Commands for managing the sessions of logged in users")
+                    .subcommands(vec![
+                        SubCommand::with_name("list")
+                            .about("Lists the active sessions of a user")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the user for which to list the sessions",
+                            )),
+                        SubCommand::with_name("delete")
+                            .about("Logs out a user everywhere by ending all their sessions")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the user for which to end the sessions",
+                            )),
+                        SubCommand::with_name("purge").about("Purges expired sessions"),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("api-token")
+                    .about("Commands for managing personal API tokens")
+                    .subcommands(vec![
+                        SubCommand::with_name("add")
+                            .about("Creates a new API token and prints it")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the user for which to create the token",
+                            ))
+                            .arg(
+                                Arg::with_name("name")
+                                    .required(true)
+                                    .help("A name that describes what the token is used for"),
+                            )
+                            .arg(
+                                Arg::with_name("scope")
+                                    .long("scope")
+                                    .short("s")
+                                    .takes_value(true)
+                                    .possible_values(&["read", "write"])
+                                    .default_value("read")
+                                    .help("The access granted by the token"),
+                            )
+                            .arg(
+                                Arg::with_name("expires_in")
+                                    .long("expires-in")
+                                    .short("e")
+                                    .takes_value(true)
+                                    .help("The number of days after which the token expires"),
+                            ),
+                        SubCommand::with_name("list")
+                            .about("Lists the API tokens of a user")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the user for which to list the tokens",
+                            )),
+                        SubCommand::with_name("delete")
+                            .about("Revokes an API token")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the user the token belongs to",
+                            ))
+                            .arg(
+                                Arg::with_name("id")
+                                    .required(true)
+                                    .help("The ID of the token to revoke"),
+                            ),
+                        SubCommand::with_name("purge").about("Purges expired API tokens"),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
             .subcommand(
                 SubCommand::with_name("category")
                     .about("Commands for managing categories")
@@ -180,6 +353,13 @@ async fn main() {
                                     .short("p")
                                     .takes_value(true)
                                     .help("The ID of the parent category"),
+                            )
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
                             ),
                         SubCommand::with_name("get")
                             .about("Outputs a category as JSON data")
@@ -195,6 +375,48 @@ async fn main() {
                     ])
                     .setting(AppSettings::SubcommandRequiredElseHelp),
             )
+            .subcommand(
+                SubCommand::with_name("ledger")
+                    .about("Commands for managing ledgers")
+                    .subcommands(vec![
+                        SubCommand::with_name("add")
+                            .about("Create a new ledger")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that will own the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("name")
+                                    .required(true)
+                                    .help("The ledger name"),
+                            ),
+                        SubCommand::with_name("list")
+                            .about("Lists the ledgers of a user as a JSON data array")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account for which to list the ledgers",
+                            )),
+                        SubCommand::with_name("invite")
+                            .about("Invites a user to join a ledger")
+                            .arg(Arg::with_name("id").required(true).help("The ledger ID"))
+                            .arg(Arg::with_name("inviter").required(true).help(
+                                "The email address of the ledger owner that sends the invitation",
+                            ))
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The email address of the user to invite"),
+                            )
+                            .arg(
+                                Arg::with_name("role")
+                                    .long("role")
+                                    .short("r")
+                                    .takes_value(true)
+                                    .possible_values(&["owner", "editor", "viewer"])
+                                    .default_value("editor")
+                                    .help("The role of the invited user"),
+                            ),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
             .subcommand(
                 SubCommand::with_name("expense")
                     .about("Commands for managing expenses")
@@ -233,6 +455,44 @@ async fn main() {
                         SubCommand::with_name("delete")
                             .about("Deletes an expense")
                             .arg(Arg::with_name("id").required(true).help("The expense ID")),
+                        SubCommand::with_name("split")
+                            .about("Shares an expense among the members of its ledger")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that shares the expense",
+                            ))
+                            .arg(Arg::with_name("id").required(true).help("The expense ID"))
+                            .arg(
+                                Arg::with_name("paid_by")
+                                    .required(true)
+                                    .help("The email address of the user that paid the expense"),
+                            )
+                            .arg(
+                                Arg::with_name("participants")
+                                    .required(true)
+                                    .multiple(true)
+                                    .help("The email addresses of the participants. For percentage and exact splits the share is appended, e.g. \"jane@example.com=25\"."),
+                            )
+                            .arg(
+                                Arg::with_name("method")
+                                    .long("method")
+                                    .short("m")
+                                    .takes_value(true)
+                                    .possible_values(&["equal", "percentage", "exact"])
+                                    .default_value("equal")
+                                    .help("How the amount is divided among the participants"),
+                            ),
+                        SubCommand::with_name("search")
+                            .about("Searches expenses by description and category, and outputs them as a JSON data array")
+                            .arg(Arg::with_name("query").required(true).help("The search query"))
+                            .arg(Arg::with_name("email").takes_value(true).help("Optional email of user in whose ledger to search."))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .requires("email")
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
                         SubCommand::with_name("list")
                             .about("Lists expenses as a JSON data array")
                             .arg(Arg::with_name("email").takes_value(true).help("Optional email of user for which to return the expenses."))
@@ -241,6 +501,292 @@ async fn main() {
                     ])
                     .setting(AppSettings::SubcommandRequiredElseHelp),
             )
+            .subcommand(
+                SubCommand::with_name("balance")
+                    .about("Commands for managing balances between ledger members")
+                    .subcommands(vec![
+                        SubCommand::with_name("show")
+                            .about("Outputs the balances and debts of a ledger as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                        SubCommand::with_name("settle")
+                            .about("Records a payment between two members of a ledger")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that records the payment",
+                            ))
+                            .arg(
+                                Arg::with_name("payer")
+                                    .required(true)
+                                    .help("The email address of the user that paid"),
+                            )
+                            .arg(
+                                Arg::with_name("payee")
+                                    .required(true)
+                                    .help("The email address of the user that received the payment"),
+                            )
+                            .arg(
+                                Arg::with_name("amount")
+                                    .required(true)
+                                    .help("The amount that was paid"),
+                            )
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            )
+                            .arg(
+                                Arg::with_name("date")
+                                    .long("date")
+                                    .takes_value(true)
+                                    .help("The date of the payment, in the format YYYY-MM-DD. If omitted, today's date will be used."),
+                            ),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("goal")
+                    .about("Commands for managing savings goals")
+                    .subcommands(vec![
+                        SubCommand::with_name("add")
+                            .about("Creates a new savings goal")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that creates the goal",
+                            ))
+                            .arg(
+                                Arg::with_name("name")
+                                    .required(true)
+                                    .help("The name of the goal"),
+                            )
+                            .arg(
+                                Arg::with_name("target_amount")
+                                    .required(true)
+                                    .help("The amount to save"),
+                            )
+                            .arg(
+                                Arg::with_name("target_date")
+                                    .required(true)
+                                    .help("The date by which the amount should be saved, in the format YYYY-MM-DD"),
+                            )
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                        SubCommand::with_name("list")
+                            .about("Outputs the progress of the savings goals of a ledger as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                        SubCommand::with_name("contribute")
+                            .about("Adds money to a savings goal. Use a negative amount for a withdrawal.")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that makes the contribution",
+                            ))
+                            .arg(Arg::with_name("id").required(true).help("The goal ID"))
+                            .arg(
+                                Arg::with_name("amount")
+                                    .required(true)
+                                    .help("The amount to contribute"),
+                            )
+                            .arg(
+                                Arg::with_name("description")
+                                    .long("description")
+                                    .short("d")
+                                    .takes_value(true)
+                                    .help("Optional description of the contribution"),
+                            )
+                            .arg(
+                                Arg::with_name("date")
+                                    .long("date")
+                                    .takes_value(true)
+                                    .help("The date of the contribution, in the format YYYY-MM-DD. If omitted, today's date will be used."),
+                            )
+                            .setting(AppSettings::AllowNegativeNumbers),
+                        SubCommand::with_name("delete")
+                            .about("Deletes a savings goal")
+                            .arg(Arg::with_name("id").required(true).help("The goal ID")),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("budget")
+                    .about("Commands for managing budgets and recurring expenses")
+                    .subcommands(vec![
+                        SubCommand::with_name("set")
+                            .about("Sets the monthly budget of a category")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that sets the budget",
+                            ))
+                            .arg(
+                                Arg::with_name("category_id")
+                                    .required(true)
+                                    .help("The category ID"),
+                            )
+                            .arg(
+                                Arg::with_name("amount")
+                                    .required(true)
+                                    .help("The amount that may be spent per month"),
+                            ),
+                        SubCommand::with_name("remove")
+                            .about("Removes the budget of a category")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that removes the budget",
+                            ))
+                            .arg(
+                                Arg::with_name("category_id")
+                                    .required(true)
+                                    .help("The category ID"),
+                            ),
+                        SubCommand::with_name("list")
+                            .about("Outputs the budgets of a ledger as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                        SubCommand::with_name("add-recurring")
+                            .about("Adds an expense that comes back every month")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of the account that adds the recurring expense",
+                            ))
+                            .arg(
+                                Arg::with_name("category_id")
+                                    .required(true)
+                                    .help("The category ID"),
+                            )
+                            .arg(
+                                Arg::with_name("amount")
+                                    .required(true)
+                                    .help("The monthly amount"),
+                            )
+                            .arg(
+                                Arg::with_name("day_of_month")
+                                    .required(true)
+                                    .help("The day of the month on which the expense is due, from 1 to 31"),
+                            )
+                            .arg(
+                                Arg::with_name("description")
+                                    .long("description")
+                                    .short("d")
+                                    .takes_value(true)
+                                    .help("Optional description of the recurring expense"),
+                            ),
+                        SubCommand::with_name("list-recurring")
+                            .about("Outputs the recurring expenses of a ledger as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                        SubCommand::with_name("delete-recurring")
+                            .about("Deletes a recurring expense")
+                            .arg(
+                                Arg::with_name("id")
+                                    .required(true)
+                                    .help("The recurring expense ID"),
+                            ),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
+            .subcommand(
+                SubCommand::with_name("report")
+                    .about("Commands for reporting on spending")
+                    .subcommands(vec![
+                        SubCommand::with_name("forecast")
+                            .about("Outputs the spending forecast of a ledger as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            )
+                            .arg(
+                                Arg::with_name("months")
+                                    .long("months")
+                                    .short("m")
+                                    .takes_value(true)
+                                    .default_value("3")
+                                    .help("The number of months following the current month to forecast"),
+                            ),
+                        SubCommand::with_name("compare")
+                            .about("Compares the spending per category in two periods and outputs it as JSON data")
+                            .arg(Arg::with_name("email").required(true).help(
+                                "The email address of a member of the ledger",
+                            ))
+                            .arg(
+                                Arg::with_name("start")
+                                    .required(true)
+                                    .help("The start date of the period, in the format YYYY-MM-DD"),
+                            )
+                            .arg(
+                                Arg::with_name("end")
+                                    .required(true)
+                                    .help("The end date of the period, in the format YYYY-MM-DD"),
+                            )
+                            .arg(
+                                Arg::with_name("previous_start")
+                                    .long("previous-start")
+                                    .takes_value(true)
+                                    .requires("previous_end")
+                                    .help("The start date of the period to compare with. If omitted, the same period one year earlier is used."),
+                            )
+                            .arg(
+                                Arg::with_name("previous_end")
+                                    .long("previous-end")
+                                    .takes_value(true)
+                                    .requires("previous_start")
+                                    .help("The end date of the period to compare with"),
+                            )
+                            .arg(
+                                Arg::with_name("parent_id")
+                                    .long("parent")
+                                    .short("p")
+                                    .takes_value(true)
+                                    .help("The ID of a category. If given, its subcategories are compared instead of the root categories."),
+                            )
+                            .arg(
+                                Arg::with_name("ledger_id")
+                                    .long("ledger")
+                                    .short("l")
+                                    .takes_value(true)
+                                    .help("The ID of the ledger. If omitted, the user's default ledger will be used."),
+                            ),
+                    ])
+                    .setting(AppSettings::SubcommandRequiredElseHelp),
+            )
             .subcommand(
                 SubCommand::with_name("notify")
                     .about("Send a notification")
@@ -253,11 +799,24 @@ async fn main() {
                                     .help("The email address to activate"),
                             ),
                     )
+                    .subcommand(
+                        SubCommand::with_name("password-reset")
+                            .about("Send a password reset email")
+                            .arg(
+                                Arg::with_name("email")
+                                    .required(true)
+                                    .help("The email address of the account to reset"),
+                            ),
+                    )
                     .setting(AppSettings::SubcommandRequiredElseHelp),
             )
             .subcommand(
                 SubCommand::with_name("mailgun-mock-server").about("Start the Mailgun mock server"),
             )
+            .subcommand(
+                SubCommand::with_name("oidc-mock-server")
+                    .about("Start the OpenID Connect mock server"),
+            )
             .setting(AppSettings::SubcommandRequiredElseHelp)
             .get_matches();
 
@@ -277,40 +836,235 @@ async fn main() {
                 .unwrap_or_exit();
             }
             ("delete", Some(arguments)) => {
-                db::user::delete(
-                    &establish_connection(config.database_url()).unwrap_or_exit(),
-                    arguments.value_of("email").unwrap(),
-                )
-                .unwrap_or_exit();
+                db::user::delete(
+                    &establish_connection(config.database_url()).unwrap_or_exit(),
+                    arguments.value_of("email").unwrap(),
+                )
+                .unwrap_or_exit();
+            }
+            ("activate", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let activation_code = arguments.value_of("code").unwrap().parse().unwrap_or_exit();
+                db::activation_code::activate_user(&connection, user, activation_code)
+                    .unwrap_or_exit();
+            }
+            ("set-password", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let password = arguments.value_of("password").unwrap();
+                db::user::set_password(&connection, user, password, &config).unwrap_or_exit();
+            }
+            ("grant-admin", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                db::user::set_admin(&connection, user, true).unwrap_or_exit();
+            }
+            ("revoke-admin", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                db::user::set_admin(&connection, user, false).unwrap_or_exit();
+            }
+            ("disable-two-factor", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                db::two_factor::disable(&connection, &user).unwrap_or_exit();
+            }
+            ("export", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let export = db::export::get(&connection, &user).unwrap_or_exit();
+                println!("{}", json!(export));
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("activation-code", Some(arguments)) => match arguments.subcommand() {
+            ("get", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let activation_code = db::activation_code::get(&connection, &user).unwrap_or_exit();
+                println!("{}", activation_code.code);
+            }
+            ("delete", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                db::activation_code::delete(&connection, &user).unwrap_or_exit();
+            }
+            ("purge", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::activation_code::purge(&connection).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("invitation", Some(arguments)) => match arguments.subcommand() {
+            ("add", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let invitation =
+                    db::registration_invitation::create(&connection, email, None).unwrap_or_exit();
+                notifications::registration_invitation(&invitation, None, &config)
+                    .await
+                    .unwrap_or_exit();
+                println!("{}", invitation.code);
+            }
+            ("list", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                for invitation in db::registration_invitation::list(&connection).unwrap_or_exit() {
+                    println!("{}\t{}", invitation.email, invitation.expiration_time);
+                }
+            }
+            ("delete", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                db::registration_invitation::delete(&connection, email).unwrap_or_exit();
             }
-            ("activate", Some(arguments)) => {
+            ("purge", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::registration_invitation::purge(&connection).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("password-reset-code", Some(arguments)) => match arguments.subcommand() {
+            ("get", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 let email = arguments.value_of("email").unwrap();
                 let user = db::user::read(&connection, email).unwrap_or_exit();
-                let activation_code = arguments.value_of("code").unwrap().parse().unwrap_or_exit();
-                db::activation_code::activate_user(&connection, user, activation_code)
-                    .unwrap_or_exit();
+                let password_reset_code =
+                    db::password_reset_code::get(&connection, &user).unwrap_or_exit();
+                println!("{}", password_reset_code.code);
+            }
+            ("delete", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                db::password_reset_code::delete(&connection, &user).unwrap_or_exit();
+            }
+            ("purge", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::password_reset_code::purge(&connection).unwrap_or_exit();
             }
             ("", None) => {}
             _ => unreachable!(),
         },
-        ("activation-code", Some(arguments)) => match arguments.subcommand() {
+        ("login-attempt", Some(arguments)) => match arguments.subcommand() {
             ("get", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                match db::login_attempt::read(&connection, Scope::Account, email).unwrap_or_exit() {
+                    Some(login_attempt) => {
+                        println!("Failed attempts: {}", login_attempt.attempts);
+                        println!("Last attempt: {}", login_attempt.last_attempt_time);
+                        if login_attempt.is_locked() {
+                            println!("Locked until: {}", login_attempt.locked_until.unwrap());
+                        }
+                    }
+                    None => println!("Failed attempts: 0"),
+                }
+            }
+            ("delete", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                db::login_attempt::delete(&connection, Scope::Account, email).unwrap_or_exit();
+            }
+            ("delete-ip", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let ip = arguments.value_of("ip").unwrap();
+                db::login_attempt::delete(&connection, Scope::Ip, ip).unwrap_or_exit();
+            }
+            ("purge", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::login_attempt::purge(&connection).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("user-session", Some(arguments)) => match arguments.subcommand() {
+            ("list", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 let email = arguments.value_of("email").unwrap();
                 let user = db::user::read(&connection, email).unwrap_or_exit();
-                let activation_code = db::activation_code::get(&connection, &user).unwrap_or_exit();
-                println!("{}", activation_code.code);
+                for user_session in db::user_session::list(&connection, &user).unwrap_or_exit() {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        user_session.id,
+                        user_session.created,
+                        user_session.last_seen,
+                        user_session.ip_address.unwrap_or_default(),
+                        user_session.user_agent.unwrap_or_default()
+                    );
+                }
             }
             ("delete", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 let email = arguments.value_of("email").unwrap();
                 let user = db::user::read(&connection, email).unwrap_or_exit();
-                db::activation_code::delete(&connection, &user).unwrap_or_exit();
+                let count = db::user_session::delete_all(&connection, &user, None).unwrap_or_exit();
+                println!("Ended {} session(s).", count);
             }
             ("purge", _) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
-                db::activation_code::purge(&connection).unwrap_or_exit();
+                db::user_session::purge(&connection, &config).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("api-token", Some(arguments)) => match arguments.subcommand() {
+            ("add", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let scope = arguments
+                    .value_of("scope")
+                    .unwrap()
+                    .parse()
+                    .unwrap_or_exit();
+                let expires_in = assert_integer_argument(
+                    arguments.value_of("expires_in"),
+                    "number of days until expiration",
+                );
+                let expiration_time = expires_in.map(|days| {
+                    chrono::Local::now().naive_local() + chrono::Duration::days(days.into())
+                });
+                let (_, token) = db::api_token::create(
+                    &connection,
+                    &user,
+                    arguments.value_of("name").unwrap(),
+                    scope,
+                    expiration_time,
+                )
+                .unwrap_or_exit();
+                println!("{}", token);
+            }
+            ("list", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                println!(
+                    "{}",
+                    json!(db::api_token::list(&connection, &user).unwrap_or_exit())
+                );
+            }
+            ("delete", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let id = assert_integer_argument(arguments.value_of("id"), "token ID").unwrap();
+                db::api_token::delete(&connection, &user, id).unwrap_or_exit();
+            }
+            ("purge", _) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::api_token::purge(&connection).unwrap_or_exit();
             }
             ("", None) => {}
             _ => unreachable!(),
@@ -338,9 +1092,12 @@ async fn main() {
                     None => None,
                 };
 
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+
                 db::category::create(
                     &establish_connection(config.database_url()).unwrap_or_exit(),
                     &user,
+                    &ledger,
                     arguments.value_of("name").unwrap(),
                     arguments.value_of("description"),
                     parent.as_ref(),
@@ -365,10 +1122,11 @@ async fn main() {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 let email = arguments.value_of("email").unwrap();
                 let user = db::user::read(&connection, email).unwrap_or_exit();
+                let ledger = db::ledger::get_default(&connection, &user).unwrap_or_exit();
 
                 db::category::populate_categories(
                     &establish_connection(config.database_url()).unwrap_or_exit(),
-                    &user,
+                    &ledger,
                     &config,
                 )
                 .unwrap_or_exit();
@@ -376,6 +1134,46 @@ async fn main() {
             ("", None) => {}
             _ => unreachable!(),
         },
+        ("ledger", Some(arguments)) => match arguments.subcommand() {
+            ("add", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let ledger =
+                    db::ledger::create(&connection, &user, arguments.value_of("name").unwrap())
+                        .unwrap_or_exit();
+                println!("{}", json!(ledger));
+            }
+            ("list", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let ledgers = db::ledger::get_ledgers(&connection, &user).unwrap_or_exit();
+                println!("{}", json!(ledgers));
+            }
+            ("invite", Some(arguments)) => {
+                let id = assert_integer_argument(arguments.value_of("id"), "ledger ID").unwrap();
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let inviter = arguments.value_of("inviter").unwrap();
+                let inviter = db::user::read(&connection, inviter).unwrap_or_exit();
+                let ledger = db::ledger::read(&connection, id, Some(inviter.id))
+                    .expect_or_exit(format!("Ledger with ID {} could not be loaded", id).as_str());
+                let role = arguments.value_of("role").unwrap().parse().unwrap_or_exit();
+                let invitation = db::ledger_invitation::create(
+                    &connection,
+                    &ledger,
+                    &inviter,
+                    arguments.value_of("email").unwrap(),
+                    role,
+                )
+                .unwrap_or_exit();
+                notifications::ledger_invitation(&invitation, &ledger, &inviter, &config)
+                    .await
+                    .unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
         ("expense", Some(arguments)) => match arguments.subcommand() {
             ("add", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
@@ -437,6 +1235,63 @@ async fn main() {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 db::expense::delete(&connection, id).unwrap_or_exit();
             }
+            ("split", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let id = assert_integer_argument(arguments.value_of("id"), "expense ID").unwrap();
+                let expense = db::expense::read(&connection, id)
+                    .expect_or_exit(format!("Expense with ID {} could not be loaded", id).as_str());
+                let paid_by = db::user::read(&connection, arguments.value_of("paid_by").unwrap())
+                    .unwrap_or_exit();
+                let method = arguments
+                    .value_of("method")
+                    .unwrap()
+                    .parse()
+                    .unwrap_or_exit();
+
+                // Participants are passed as an email address, optionally followed by their share.
+                let participants: Vec<(i32, Option<Decimal>)> = arguments
+                    .values_of("participants")
+                    .unwrap()
+                    .map(|participant| {
+                        let mut parts = participant.splitn(2, '=');
+                        let email = parts.next().unwrap();
+                        let participant = db::user::read(&connection, email).unwrap_or_exit();
+                        let share = parts.next().map(|share| {
+                            Decimal::from_str(share)
+                                .map_err(|_| format!("Invalid share for {}: {}", email, share))
+                                .unwrap_or_exit()
+                        });
+                        (participant.id, share)
+                    })
+                    .collect();
+
+                let shares = db::expense_share::split(
+                    &connection,
+                    &user,
+                    &expense,
+                    paid_by.id,
+                    method,
+                    &participants,
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(shares));
+            }
+            ("search", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let ledger = arguments.value_of("email").map(|email| {
+                    let user = db::user::read(&connection, email).unwrap_or_exit();
+                    get_ledger(&config, &user, arguments.value_of("ledger_id"))
+                });
+                let expenses = db::expense::search(
+                    &connection,
+                    arguments.value_of("query").unwrap(),
+                    ledger.as_ref(),
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(expenses));
+            }
             ("list", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
                 match arguments.is_present("count") {
@@ -467,6 +1322,253 @@ async fn main() {
             ("", None) => {}
             _ => unreachable!(),
         },
+        ("balance", Some(arguments)) => match arguments.subcommand() {
+            ("show", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let balances = db::settlement::get_balances(&connection, &ledger).unwrap_or_exit();
+                let debts = db::settlement::get_debts(&balances);
+                println!("{}", json!({ "balances": balances, "debts": debts }));
+            }
+            ("settle", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let payer = db::user::read(&connection, arguments.value_of("payer").unwrap())
+                    .unwrap_or_exit();
+                let payee = db::user::read(&connection, arguments.value_of("payee").unwrap())
+                    .unwrap_or_exit();
+                let amount = Decimal::from_str(arguments.value_of("amount").unwrap())
+                    .map_err(|_| "Amount should be in the format \"149.99\"")
+                    .unwrap_or_exit();
+                let date = arguments.value_of("date").map(|d| {
+                    chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                        .map_err(|_| {
+                            "The date should be valid and in the format YYYY-MM-DD".to_string()
+                        })
+                        .unwrap_or_exit()
+                });
+
+                let settlement = db::settlement::create(
+                    &connection,
+                    &user,
+                    &ledger,
+                    payer.id,
+                    payee.id,
+                    &amount,
+                    date.as_ref(),
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(settlement));
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("goal", Some(arguments)) => match arguments.subcommand() {
+            ("add", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let target_amount = Decimal::from_str(arguments.value_of("target_amount").unwrap())
+                    .map_err(|_| "Amount should be in the format \"149.99\"")
+                    .unwrap_or_exit();
+                let target_date = chrono::NaiveDate::parse_from_str(
+                    arguments.value_of("target_date").unwrap(),
+                    "%Y-%m-%d",
+                )
+                .map_err(|_| "The date should be valid and in the format YYYY-MM-DD".to_string())
+                .unwrap_or_exit();
+
+                let goal = db::goal::create(
+                    &connection,
+                    &user,
+                    &ledger,
+                    arguments.value_of("name").unwrap(),
+                    &target_amount,
+                    &target_date,
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(goal));
+            }
+            ("list", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let today = chrono::Utc::now().naive_utc().date();
+                let progress = db::goal::get_progress(&connection, &ledger, today).unwrap_or_exit();
+                println!("{}", json!(progress));
+            }
+            ("contribute", Some(arguments)) => {
+                let id = assert_integer_argument(arguments.value_of("id"), "goal ID").unwrap();
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let goal = db::goal::read(&connection, id, Some(user.id))
+                    .expect_or_exit(format!("Goal with ID {} could not be loaded", id).as_str());
+                let amount = Decimal::from_str(arguments.value_of("amount").unwrap())
+                    .map_err(|_| "Amount should be in the format \"149.99\"")
+                    .unwrap_or_exit();
+                let date = arguments.value_of("date").map(|d| {
+                    chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+                        .map_err(|_| {
+                            "The date should be valid and in the format YYYY-MM-DD".to_string()
+                        })
+                        .unwrap_or_exit()
+                });
+
+                let contribution = db::goal::add_contribution(
+                    &connection,
+                    &user,
+                    &goal,
+                    &amount,
+                    arguments.value_of("description"),
+                    date.as_ref(),
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(contribution));
+            }
+            ("delete", Some(arguments)) => {
+                let id = assert_integer_argument(arguments.value_of("id"), "goal ID").unwrap();
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::goal::delete(&connection, id).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("budget", Some(arguments)) => match arguments.subcommand() {
+            ("set", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let category = get_category(&config, &user, arguments.value_of("category_id"));
+                let amount = Decimal::from_str(arguments.value_of("amount").unwrap())
+                    .map_err(|_| "Amount should be in the format \"149.99\"")
+                    .unwrap_or_exit();
+
+                let budget =
+                    db::budget::set_budget(&connection, &user, &category, &amount).unwrap_or_exit();
+                println!("{}", json!(budget));
+            }
+            ("remove", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let category = get_category(&config, &user, arguments.value_of("category_id"));
+                db::budget::remove_budget(&connection, &user, &category).unwrap_or_exit();
+            }
+            ("list", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let budgets = db::budget::get_budgets(&connection, &ledger).unwrap_or_exit();
+                println!("{}", json!(budgets));
+            }
+            ("add-recurring", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let category = get_category(&config, &user, arguments.value_of("category_id"));
+                let amount = Decimal::from_str(arguments.value_of("amount").unwrap())
+                    .map_err(|_| "Amount should be in the format \"149.99\"")
+                    .unwrap_or_exit();
+                let day_of_month: u32 = arguments
+                    .value_of("day_of_month")
+                    .unwrap()
+                    .parse()
+                    .map_err(|_| "The day of the month must be a positive integer")
+                    .unwrap_or_exit();
+
+                let recurring_expense = db::budget::add_recurring_expense(
+                    &connection,
+                    &user,
+                    &category,
+                    &amount,
+                    arguments.value_of("description"),
+                    day_of_month,
+                )
+                .unwrap_or_exit();
+                println!("{}", json!(recurring_expense));
+            }
+            ("list-recurring", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let recurring_expenses =
+                    db::budget::get_recurring_expenses(&connection, &ledger).unwrap_or_exit();
+                println!("{}", json!(recurring_expenses));
+            }
+            ("delete-recurring", Some(arguments)) => {
+                let id = assert_integer_argument(arguments.value_of("id"), "recurring expense ID")
+                    .unwrap();
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                db::budget::delete_recurring_expense(&connection, id).unwrap_or_exit();
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
+        ("report", Some(arguments)) => match arguments.subcommand() {
+            ("forecast", Some(arguments)) => {
+                let months: u32 = arguments
+                    .value_of("months")
+                    .unwrap()
+                    .parse()
+                    .map_err(|_| "The number of months must be a positive integer")
+                    .unwrap_or_exit();
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let today = chrono::Utc::now().naive_utc().date();
+                let forecast = db::forecast::get_forecast(&connection, &ledger, today, months)
+                    .unwrap_or_exit();
+                println!("{}", json!(forecast));
+            }
+            ("compare", Some(arguments)) => {
+                let parent_id =
+                    assert_integer_argument(arguments.value_of("parent_id"), "parent ID");
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let user = db::user::read(&connection, arguments.value_of("email").unwrap())
+                    .unwrap_or_exit();
+                let ledger = get_ledger(&config, &user, arguments.value_of("ledger_id"));
+                let current = db::report::Period {
+                    start: parse_date(arguments.value_of("start").unwrap()),
+                    end: parse_date(arguments.value_of("end").unwrap()),
+                };
+                let previous = match (
+                    arguments.value_of("previous_start"),
+                    arguments.value_of("previous_end"),
+                ) {
+                    (Some(start), Some(end)) => db::report::Period {
+                        start: parse_date(start),
+                        end: parse_date(end),
+                    },
+                    _ => current
+                        .shift(-12)
+                        .expect_or_exit("The previous period lies outside the supported dates"),
+                };
+                let parent = parent_id.map(|id| {
+                    db::category::read(&connection, id, Some(user.id))
+                        .filter(|category| category.ledger_id == ledger.id)
+                        .expect_or_exit(
+                            format!("Category with ID {} could not be loaded", id).as_str(),
+                        )
+                });
+
+                let comparison =
+                    db::report::compare(&connection, &ledger, &previous, &current, parent.as_ref())
+                        .unwrap_or_exit();
+                println!("{}", json!(comparison));
+            }
+            ("", None) => {}
+            _ => unreachable!(),
+        },
         ("notify", Some(notify)) => match notify.subcommand() {
             ("activate", Some(arguments)) => {
                 let connection = establish_connection(config.database_url()).unwrap_or_exit();
@@ -477,16 +1579,54 @@ async fn main() {
                     .await
                     .unwrap_or_exit();
             }
+            ("password-reset", Some(arguments)) => {
+                let connection = establish_connection(config.database_url()).unwrap_or_exit();
+                let email = arguments.value_of("email").unwrap();
+                let user = db::user::read(&connection, email).unwrap_or_exit();
+                let password_reset_code =
+                    db::password_reset_code::get(&connection, &user).unwrap_or_exit();
+                notifications::password_reset(&user, &password_reset_code, &config)
+                    .await
+                    .unwrap_or_exit();
+            }
             ("", None) => {}
             _ => unreachable!(),
         },
         ("mailgun-mock-server", _) => {
             mailgun_mock::serve(config).await.unwrap_or_exit();
         }
+        ("oidc-mock-server", _) => {
+            oidc_mock::serve(config).await.unwrap_or_exit();
+        }
         ("", None) => {}
         _ => unreachable!(),
     }
 
+    // Returns the ledger with the given ID, or the user's default ledger if no ID is given.
+    fn get_ledger(config: &AppConfig, user: &User, ledger_id: Option<&str>) -> Ledger {
+        let connection = establish_connection(config.database_url()).unwrap_or_exit();
+        match assert_integer_argument(ledger_id, "ledger ID") {
+            Some(id) => db::ledger::read(&connection, id, Some(user.id))
+                .expect_or_exit(format!("Ledger with ID {} could not be loaded", id).as_str()),
+            None => db::ledger::get_default(&connection, user).unwrap_or_exit(),
+        }
+    }
+
+    // Returns the category with the given ID, if it belongs to one of the user's ledgers.
+    fn get_category(config: &AppConfig, user: &User, category_id: Option<&str>) -> Category {
+        let connection = establish_connection(config.database_url()).unwrap_or_exit();
+        let id = assert_integer_argument(category_id, "category ID").unwrap();
+        db::category::read(&connection, id, Some(user.id))
+            .expect_or_exit(format!("Category with ID {} could not be loaded", id).as_str())
+    }
+
+    // Parses a date in the format YYYY-MM-DD.
+    fn parse_date(date: &str) -> chrono::NaiveDate {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| "The date should be valid and in the format YYYY-MM-DD".to_string())
+            .unwrap_or_exit()
+    }
+
     // Checks that the given argument can be cast to an integer.
     fn assert_integer_argument(arg: Option<&str>, arg_type: &str) -> Option<i32> {
         let msg = format!("The {} must be an integer", arg_type);
@@ -0,0 +1,20 @@
+-- Categories are handed back to the first owner of the ledger they belong to.
+ALTER TABLE categories ADD COLUMN user_id INTEGER REFERENCES users (id) ON DELETE CASCADE;
+UPDATE categories SET user_id = (
+  SELECT MIN(user_id) FROM ledger_members WHERE ledger_members.ledger_id = categories.ledger_id AND role = 'owner'
+);
+DELETE FROM expenses WHERE category_id IN (SELECT id FROM categories WHERE user_id IS NULL);
+DELETE FROM categories WHERE user_id IS NULL;
+ALTER TABLE categories ALTER COLUMN user_id SET NOT NULL;
+
+DROP INDEX categories_unique_rootcat_index;
+DROP INDEX categories_unique_subcat_index;
+ALTER TABLE categories DROP COLUMN ledger_id;
+CREATE UNIQUE INDEX categories_unique_rootcat_index ON categories (name, user_id) WHERE parent_id IS NULL;
+CREATE UNIQUE INDEX categories_unique_subcat_index ON categories (name, user_id, parent_id) WHERE parent_id IS NOT NULL;
+
+ALTER TABLE expenses DROP COLUMN ledger_id;
+
+DROP TABLE ledger_invitations;
+DROP TABLE ledger_members;
+DROP TABLE ledgers;
@@ -0,0 +1,44 @@
+CREATE TABLE ledgers (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(100) NOT NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE TABLE ledger_members (
+  ledger_id INTEGER NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  role VARCHAR(10) NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+  PRIMARY KEY (ledger_id, user_id)
+);
+
+CREATE TABLE ledger_invitations (
+  id SERIAL PRIMARY KEY,
+  ledger_id INTEGER NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+  email VARCHAR(100) NOT NULL,
+  role VARCHAR(10) NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+  invited_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  expiration_time TIMESTAMP NOT NULL DEFAULT now() + interval '7' day,
+  UNIQUE (ledger_id, email)
+);
+
+-- Every existing user gets a personal ledger which takes over the ownership of their categories and
+-- expenses.
+ALTER TABLE ledgers ADD COLUMN migrated_user_id INTEGER;
+INSERT INTO ledgers (name, created, migrated_user_id) SELECT 'Personal', created, id FROM users;
+INSERT INTO ledger_members (ledger_id, user_id, role) SELECT id, migrated_user_id, 'owner' FROM ledgers;
+
+ALTER TABLE categories ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE categories SET ledger_id = ledgers.id FROM ledgers WHERE ledgers.migrated_user_id = categories.user_id;
+ALTER TABLE categories ALTER COLUMN ledger_id SET NOT NULL;
+
+DROP INDEX categories_unique_rootcat_index;
+DROP INDEX categories_unique_subcat_index;
+ALTER TABLE categories DROP COLUMN user_id;
+CREATE UNIQUE INDEX categories_unique_rootcat_index ON categories (name, ledger_id) WHERE parent_id IS NULL;
+CREATE UNIQUE INDEX categories_unique_subcat_index ON categories (name, ledger_id, parent_id) WHERE parent_id IS NOT NULL;
+
+ALTER TABLE expenses ADD COLUMN ledger_id INTEGER REFERENCES ledgers (id) ON DELETE CASCADE;
+UPDATE expenses SET ledger_id = categories.ledger_id FROM categories WHERE categories.id = expenses.category_id;
+ALTER TABLE expenses ALTER COLUMN ledger_id SET NOT NULL;
+
+ALTER TABLE ledgers DROP COLUMN migrated_user_id;
@@ -0,0 +1,3 @@
+DROP TABLE settlements;
+DROP TABLE expense_shares;
+DROP TABLE expense_splits;
@@ -0,0 +1,22 @@
+CREATE TABLE expense_splits (
+  expense_id INTEGER PRIMARY KEY REFERENCES expenses (id) ON DELETE CASCADE,
+  paid_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  method VARCHAR(10) NOT NULL CHECK (method IN ('equal', 'percentage', 'exact'))
+);
+
+CREATE TABLE expense_shares (
+  expense_id INTEGER NOT NULL REFERENCES expense_splits (expense_id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  amount NUMERIC(9, 2) NOT NULL,
+  PRIMARY KEY (expense_id, user_id)
+);
+
+CREATE TABLE settlements (
+  id SERIAL PRIMARY KEY,
+  ledger_id INTEGER NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+  payer_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  payee_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  amount NUMERIC(9, 2) NOT NULL CHECK (amount > 0),
+  date DATE NOT NULL,
+  CHECK (payer_id <> payee_id)
+);
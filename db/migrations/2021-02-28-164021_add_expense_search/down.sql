@@ -0,0 +1,7 @@
+DROP TRIGGER categories_search_vector_trigger ON categories;
+DROP FUNCTION categories_search_vector_update();
+DROP TRIGGER expenses_search_vector_trigger ON expenses;
+DROP FUNCTION expenses_search_vector_update();
+ALTER TABLE expenses DROP COLUMN search_vector;
+DROP FUNCTION expense_search_query(TEXT);
+DROP FUNCTION category_path_names(INTEGER);
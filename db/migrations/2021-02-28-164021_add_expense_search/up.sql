@@ -0,0 +1,55 @@
+-- Returns the names of the given category and all its ancestors, separated by spaces.
+CREATE FUNCTION category_path_names(category_id INTEGER) RETURNS TEXT AS $$
+  WITH RECURSIVE path (id, name, parent_id) AS (
+    SELECT id, name, parent_id FROM categories WHERE id = category_id
+    UNION ALL
+    SELECT c.id, c.name, c.parent_id FROM categories c JOIN path p ON c.id = p.parent_id
+  )
+  SELECT coalesce(string_agg(name, ' '), '') FROM path;
+$$ LANGUAGE SQL STABLE;
+
+-- Converts a search query entered by the user into a tsquery, using the same text search
+-- configuration as the search vector of the expenses.
+CREATE FUNCTION expense_search_query(query TEXT) RETURNS tsquery AS $$
+  SELECT plainto_tsquery('english', query);
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- The search vector contains the description of the expense, and the names of its category and the
+-- parent categories. Matches in the description are ranked higher.
+ALTER TABLE expenses ADD COLUMN search_vector tsvector;
+
+CREATE FUNCTION expenses_search_vector_update() RETURNS trigger AS $$
+BEGIN
+  NEW.search_vector :=
+    setweight(to_tsvector('english', coalesce(NEW.description, '')), 'A') ||
+    setweight(to_tsvector('english', category_path_names(NEW.category_id)), 'B');
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER expenses_search_vector_trigger
+  BEFORE INSERT OR UPDATE OF description, category_id ON expenses
+  FOR EACH ROW EXECUTE PROCEDURE expenses_search_vector_update();
+
+-- When a category is renamed or moved, update the search vectors of the expenses in the category
+-- and all its subcategories.
+CREATE FUNCTION categories_search_vector_update() RETURNS trigger AS $$
+BEGIN
+  WITH RECURSIVE subcategories (id) AS (
+    SELECT NEW.id
+    UNION ALL
+    SELECT c.id FROM categories c JOIN subcategories s ON c.parent_id = s.id
+  )
+  UPDATE expenses SET category_id = category_id WHERE category_id IN (SELECT id FROM subcategories);
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER categories_search_vector_trigger
+  AFTER UPDATE OF name, parent_id ON categories
+  FOR EACH ROW EXECUTE PROCEDURE categories_search_vector_update();
+
+UPDATE expenses SET category_id = category_id;
+ALTER TABLE expenses ALTER COLUMN search_vector SET NOT NULL;
+
+CREATE INDEX expenses_search_vector_index ON expenses USING GIN (search_vector);
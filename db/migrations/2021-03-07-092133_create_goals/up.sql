@@ -0,0 +1,17 @@
+CREATE TABLE goals (
+  id SERIAL PRIMARY KEY,
+  ledger_id INTEGER NOT NULL REFERENCES ledgers (id) ON DELETE CASCADE,
+  name VARCHAR(100) NOT NULL,
+  target_amount NUMERIC(9, 2) NOT NULL CHECK (target_amount > 0),
+  target_date DATE NOT NULL,
+  created TIMESTAMP NOT NULL
+);
+
+CREATE TABLE goal_contributions (
+  id SERIAL PRIMARY KEY,
+  goal_id INTEGER NOT NULL REFERENCES goals (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  amount NUMERIC(9, 2) NOT NULL CHECK (amount <> 0),
+  description VARCHAR(255),
+  date DATE NOT NULL
+);
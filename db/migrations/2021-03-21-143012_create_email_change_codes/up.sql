@@ -0,0 +1,7 @@
+CREATE TABLE email_change_codes (
+  id SERIAL PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  email VARCHAR NOT NULL,
+  code INTEGER NOT NULL,
+  expiration_time TIMESTAMP NOT NULL DEFAULT now() + interval '30' minute,
+  attempts SMALLINT NOT NULL DEFAULT 0
+);
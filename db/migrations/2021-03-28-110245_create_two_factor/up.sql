@@ -0,0 +1,14 @@
+CREATE TABLE totp_secrets (
+  id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  secret VARCHAR NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT FALSE,
+  last_used_step BIGINT
+);
+
+CREATE TABLE recovery_codes (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  code_hash VARCHAR NOT NULL
+);
+
+CREATE INDEX recovery_codes_user_id_idx ON recovery_codes(user_id);
@@ -0,0 +1,8 @@
+CREATE TABLE login_attempts (
+  scope VARCHAR NOT NULL,
+  identifier VARCHAR NOT NULL,
+  attempts SMALLINT NOT NULL DEFAULT 0,
+  last_attempt_time TIMESTAMP NOT NULL DEFAULT now(),
+  locked_until TIMESTAMP,
+  PRIMARY KEY (scope, identifier)
+);
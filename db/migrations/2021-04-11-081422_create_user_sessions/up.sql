@@ -0,0 +1,11 @@
+CREATE TABLE user_sessions (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  token_hash VARCHAR NOT NULL UNIQUE,
+  created TIMESTAMP NOT NULL DEFAULT now(),
+  last_seen TIMESTAMP NOT NULL DEFAULT now(),
+  ip_address VARCHAR,
+  user_agent VARCHAR
+);
+
+CREATE INDEX user_sessions_user_id_idx ON user_sessions(user_id);
@@ -0,0 +1,12 @@
+CREATE TABLE api_tokens (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  token_hash VARCHAR NOT NULL UNIQUE,
+  scope VARCHAR NOT NULL,
+  created TIMESTAMP NOT NULL DEFAULT now(),
+  expiration_time TIMESTAMP,
+  last_used TIMESTAMP
+);
+
+CREATE INDEX api_tokens_user_id_idx ON api_tokens(user_id);
@@ -0,0 +1,11 @@
+CREATE TABLE user_identities (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  issuer VARCHAR NOT NULL,
+  subject VARCHAR NOT NULL,
+  created TIMESTAMP NOT NULL DEFAULT now(),
+  last_login TIMESTAMP,
+  UNIQUE (issuer, subject)
+);
+
+CREATE INDEX user_identities_user_id_idx ON user_identities(user_id);
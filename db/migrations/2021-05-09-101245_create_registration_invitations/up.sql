@@ -0,0 +1,7 @@
+CREATE TABLE registration_invitations (
+  id SERIAL PRIMARY KEY,
+  email VARCHAR(100) NOT NULL UNIQUE,
+  code VARCHAR(20) NOT NULL,
+  invited_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+  expiration_time TIMESTAMP NOT NULL
+);
@@ -0,0 +1,7 @@
+CREATE TABLE user_preferences (
+  user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  locale VARCHAR(10) NOT NULL DEFAULT 'en',
+  currency VARCHAR(3) NOT NULL DEFAULT 'EUR',
+  date_format VARCHAR(20) NOT NULL DEFAULT 'iso',
+  first_day_of_week VARCHAR(10) NOT NULL DEFAULT 'monday'
+);
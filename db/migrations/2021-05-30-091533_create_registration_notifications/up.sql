@@ -0,0 +1,4 @@
+CREATE TABLE registration_notifications (
+  user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+  sent_time TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+ALTER TABLE totp_secrets ADD COLUMN failed_attempts SMALLINT NOT NULL DEFAULT 0;
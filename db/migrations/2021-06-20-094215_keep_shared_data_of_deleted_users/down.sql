@@ -0,0 +1,39 @@
+-- Data that refers to deleted users cannot be kept.
+DELETE FROM goal_contributions WHERE user_id IS NULL;
+ALTER TABLE goal_contributions DROP CONSTRAINT goal_contributions_user_id_fkey;
+ALTER TABLE goal_contributions ADD CONSTRAINT goal_contributions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE goal_contributions ALTER COLUMN user_id SET NOT NULL;
+
+DELETE FROM settlements WHERE payer_id IS NULL OR payee_id IS NULL;
+ALTER TABLE settlements DROP CONSTRAINT settlements_payee_id_fkey;
+ALTER TABLE settlements ADD CONSTRAINT settlements_payee_id_fkey
+  FOREIGN KEY (payee_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE settlements ALTER COLUMN payee_id SET NOT NULL;
+ALTER TABLE settlements DROP CONSTRAINT settlements_payer_id_fkey;
+ALTER TABLE settlements ADD CONSTRAINT settlements_payer_id_fkey
+  FOREIGN KEY (payer_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE settlements ALTER COLUMN payer_id SET NOT NULL;
+
+DELETE FROM expense_splits WHERE paid_by IS NULL;
+DELETE FROM expense_splits WHERE expense_id IN (
+  SELECT expense_id FROM expense_shares WHERE user_id IS NULL
+);
+ALTER TABLE expense_shares DROP CONSTRAINT expense_shares_user_id_fkey;
+ALTER TABLE expense_shares ADD CONSTRAINT expense_shares_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE expense_shares ALTER COLUMN user_id SET NOT NULL;
+ALTER TABLE expense_shares DROP CONSTRAINT expense_shares_expense_id_user_id_key;
+ALTER TABLE expense_shares DROP COLUMN id;
+ALTER TABLE expense_shares ADD PRIMARY KEY (expense_id, user_id);
+
+ALTER TABLE expense_splits DROP CONSTRAINT expense_splits_paid_by_fkey;
+ALTER TABLE expense_splits ADD CONSTRAINT expense_splits_paid_by_fkey
+  FOREIGN KEY (paid_by) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE expense_splits ALTER COLUMN paid_by SET NOT NULL;
+
+DELETE FROM expenses WHERE user_id IS NULL;
+ALTER TABLE expenses DROP CONSTRAINT expenses_user_id_fkey;
+ALTER TABLE expenses ADD CONSTRAINT expenses_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+ALTER TABLE expenses ALTER COLUMN user_id SET NOT NULL;
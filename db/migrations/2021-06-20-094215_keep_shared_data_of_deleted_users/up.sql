@@ -0,0 +1,35 @@
+-- Expenses, settlements and goal contributions in shared ledgers are kept when a member deletes
+-- their account. The references to the deleted user are cleared instead.
+ALTER TABLE expenses ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE expenses DROP CONSTRAINT expenses_user_id_fkey;
+ALTER TABLE expenses ADD CONSTRAINT expenses_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
+
+ALTER TABLE expense_splits ALTER COLUMN paid_by DROP NOT NULL;
+ALTER TABLE expense_splits DROP CONSTRAINT expense_splits_paid_by_fkey;
+ALTER TABLE expense_splits ADD CONSTRAINT expense_splits_paid_by_fkey
+  FOREIGN KEY (paid_by) REFERENCES users (id) ON DELETE SET NULL;
+
+-- The user is part of the primary key of the shares, so they get their own ID.
+ALTER TABLE expense_shares DROP CONSTRAINT expense_shares_pkey;
+ALTER TABLE expense_shares ADD COLUMN id SERIAL PRIMARY KEY;
+ALTER TABLE expense_shares ADD CONSTRAINT expense_shares_expense_id_user_id_key
+  UNIQUE (expense_id, user_id);
+ALTER TABLE expense_shares ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE expense_shares DROP CONSTRAINT expense_shares_user_id_fkey;
+ALTER TABLE expense_shares ADD CONSTRAINT expense_shares_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
+
+ALTER TABLE settlements ALTER COLUMN payer_id DROP NOT NULL;
+ALTER TABLE settlements DROP CONSTRAINT settlements_payer_id_fkey;
+ALTER TABLE settlements ADD CONSTRAINT settlements_payer_id_fkey
+  FOREIGN KEY (payer_id) REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE settlements ALTER COLUMN payee_id DROP NOT NULL;
+ALTER TABLE settlements DROP CONSTRAINT settlements_payee_id_fkey;
+ALTER TABLE settlements ADD CONSTRAINT settlements_payee_id_fkey
+  FOREIGN KEY (payee_id) REFERENCES users (id) ON DELETE SET NULL;
+
+ALTER TABLE goal_contributions ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE goal_contributions DROP CONSTRAINT goal_contributions_user_id_fkey;
+ALTER TABLE goal_contributions ADD CONSTRAINT goal_contributions_user_id_fkey
+  FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE SET NULL;
@@ -0,0 +1,2 @@
+DROP TABLE recurring_expenses;
+DROP TABLE budgets;
@@ -0,0 +1,15 @@
+-- The amount that is planned to be spent in a category per month.
+CREATE TABLE budgets (
+  category_id INTEGER PRIMARY KEY REFERENCES categories (id) ON DELETE CASCADE,
+  amount NUMERIC(9, 2) NOT NULL CHECK (amount > 0)
+);
+
+-- An expense that comes back every month on the same day, such as the rent. Recurring expenses are
+-- taken into account by the spending forecast.
+CREATE TABLE recurring_expenses (
+  id SERIAL PRIMARY KEY,
+  category_id INTEGER NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+  amount NUMERIC(9, 2) NOT NULL CHECK (amount > 0),
+  description VARCHAR(255),
+  day_of_month SMALLINT NOT NULL CHECK (day_of_month BETWEEN 1 AND 31)
+);
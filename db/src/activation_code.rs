@@ -198,6 +198,24 @@ pub fn get(
     }
 }
 
+/// Returns the activation code of the given user as it is stored in the database. The code might be
+/// expired or have exceeded the maximum number of attempts.
+///
+/// Unlike `get()` this does not create a new activation code and does not count as an attempt, so
+/// it can be used to inspect the state of the activation, e.g. by an administrator.
+pub fn find(connection: &PgConnection, user: &User) -> Option<ActivationCode> {
+    read(connection, user.id)
+}
+
+/// Returns the activation codes of the given users. Like `find()` this does not create new codes,
+/// so users that do not have an activation code are omitted.
+pub fn find_all(connection: &PgConnection, users: &[User]) -> QueryResult<Vec<ActivationCode>> {
+    let ids: Vec<i32> = users.iter().map(|user| user.id).collect();
+    dsl::activation_codes
+        .filter(dsl::id.eq_any(ids))
+        .load::<ActivationCode>(connection)
+}
+
 /// Activates the given user if the given activation code is valid.
 pub fn activate_user(
     connection: &PgConnection,
@@ -414,6 +432,8 @@ mod tests {
             let user = User {
                 id: 1,
                 activated: false,
+                admin: false,
+                disabled: false,
                 email: "non-existing-user@example.com".to_string(),
                 created: chrono::Local::now().naive_local(),
                 password: "hunter2".to_string(),
@@ -570,6 +590,36 @@ mod tests {
         });
     }
 
+    // Tests super::find() and super::find_all().
+    #[test]
+    fn test_find() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = user::create(&connection, "test@example.com", "mypass", &config).unwrap();
+            let other_user =
+                user::create(&connection, "other@example.com", "mypass", &config).unwrap();
+            assert!(find(&connection, &user).is_none());
+            assert_eq!(
+                Ok(vec![]),
+                find_all(&connection, &[user.clone(), other_user.clone()])
+            );
+
+            // Finding the activation code does not increase the attempt counter.
+            let activation_code = get(&connection, &user).unwrap();
+            assert_eq!(Some(activation_code.clone()), find(&connection, &user));
+            assert_eq!(Some(activation_code.clone()), find(&connection, &user));
+
+            // Only the users that have an activation code are included.
+            assert_eq!(
+                Ok(vec![activation_code]),
+                find_all(&connection, &[user, other_user])
+            );
+
+            Ok(())
+        });
+    }
+
     // Tests super::read().
     #[test]
     fn test_read() {
@@ -724,6 +774,8 @@ mod tests {
         let mut user = User {
             id: 1,
             activated: false,
+            admin: false,
+            disabled: false,
             email: "user@example.com".to_string(),
             created: chrono::Local::now().naive_local(),
             password: "hunter2".to_string(),
@@ -0,0 +1,413 @@
+use super::schema::api_tokens::dsl;
+use super::schema::{api_tokens, users};
+use super::user::User;
+use super::user_session::{generate_token, hash_token};
+use chrono::NaiveDateTime;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io::Write;
+use std::{fmt, str::FromStr};
+
+// The prefix of API tokens. This makes it easy to recognize a leaked token, e.g. in a repository.
+const TOKEN_PREFIX: &str = "ft_";
+
+// The maximum length of the name of an API token.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// The scope of an API token, which determines what can be done with it. Scopes are ordered by the
+/// permissions they grant, so a scope can be compared against the minimum scope required for an
+/// operation.
+#[derive(
+    AsExpression,
+    Clone,
+    Copy,
+    Debug,
+    Eq,
+    FromSqlRow,
+    JsonSchema,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum Scope {
+    // Can read data.
+    Read,
+    // Can also create, update and delete data.
+    Write,
+}
+
+impl Scope {
+    /// Returns the machine name of the scope, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::api_token::Scope;
+    /// #
+    /// assert_eq!("read", Scope::Read.as_str());
+    /// assert_eq!("write", Scope::Write.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+
+    /// Returns whether or not the scope allows to create, update and delete data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::api_token::Scope;
+    /// #
+    /// assert!(Scope::Write.can_write());
+    /// assert!(!Scope::Read.can_write());
+    /// ```
+    pub fn can_write(self) -> bool {
+        self >= Scope::Write
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Scope {
+    type Err = ApiTokenErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(Scope::Read),
+            "write" => Ok(Scope::Write),
+            _ => Err(ApiTokenErrorKind::InvalidScope(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Scope {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Scope {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let scope = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        scope
+            .parse()
+            .map_err(|e: ApiTokenErrorKind| e.to_string().into())
+    }
+}
+
+/// A personal API token which allows scripts to access the API on behalf of a user. The token
+/// itself is only stored as a hash, it is shown to the user once when it is created.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub scope: Scope,
+    pub created: NaiveDateTime,
+    pub expiration_time: Option<NaiveDateTime>,
+    pub last_used: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    /// Returns whether or not the token has expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::api_token::{ApiToken, Scope};
+    /// # use chrono::{Duration, Local};
+    /// #
+    /// let now = Local::now().naive_local();
+    /// let mut api_token = ApiToken {
+    ///     id: 1,
+    ///     user_id: 1,
+    ///     name: "Phone".to_string(),
+    ///     token_hash: "".to_string(),
+    ///     scope: Scope::Read,
+    ///     created: now,
+    ///     expiration_time: None,
+    ///     last_used: None,
+    /// };
+    ///
+    /// // Tokens without an expiration time never expire.
+    /// assert_eq!(api_token.is_expired(), false);
+    ///
+    /// api_token.expiration_time = Some(now + Duration::days(1));
+    /// assert_eq!(api_token.is_expired(), false);
+    ///
+    /// api_token.expiration_time = Some(now - Duration::days(1));
+    /// assert_eq!(api_token.is_expired(), true);
+    /// ```
+    pub fn is_expired(&self) -> bool {
+        match self.expiration_time {
+            Some(expiration_time) => expiration_time < chrono::Local::now().naive_local(),
+            None => false,
+        }
+    }
+}
+
+// Possible errors thrown when handling API tokens.
+#[derive(Debug, PartialEq)]
+pub enum ApiTokenErrorKind {
+    // A new token could not be created due to a database error.
+    CreationFailed(diesel::result::Error),
+    // A token could not be deleted due to a database error.
+    DeletionFailed(diesel::result::Error),
+    // The expiration time lies in the past.
+    InvalidExpirationTime(NaiveDateTime),
+    // The token name is empty or too long.
+    InvalidName(String),
+    // The given scope does not exist.
+    InvalidScope(String),
+    // The token does not exist or belongs to another user.
+    NotFound(i32),
+    // Expired tokens could not be purged due to a database error.
+    PurgingFailed(diesel::result::Error),
+    // Tokens could not be read due to a database error.
+    ReadFailed(diesel::result::Error),
+    // A token could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for ApiTokenErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiTokenErrorKind::CreationFailed(ref err) => {
+                write!(f, "Database error when creating API token: {}", err)
+            }
+            ApiTokenErrorKind::DeletionFailed(ref err) => {
+                write!(f, "Database error when deleting API token: {}", err)
+            }
+            ApiTokenErrorKind::InvalidExpirationTime(ref time) => {
+                write!(f, "The expiration time {} lies in the past", time)
+            }
+            ApiTokenErrorKind::InvalidName(ref name) => write!(
+                f,
+                "Invalid token name '{}'. The name should be between 1 and {} characters long",
+                name, MAX_NAME_LENGTH
+            ),
+            ApiTokenErrorKind::InvalidScope(ref scope) => {
+                write!(f, "Invalid API token scope: {}", scope)
+            }
+            ApiTokenErrorKind::NotFound(id) => write!(f, "API token {} not found", id),
+            ApiTokenErrorKind::PurgingFailed(ref err) => {
+                write!(f, "Database error when purging expired API tokens: {}", err)
+            }
+            ApiTokenErrorKind::ReadFailed(ref err) => {
+                write!(f, "Database error when reading API token: {}", err)
+            }
+            ApiTokenErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when updating API token: {}", err)
+            }
+        }
+    }
+}
+
+/// Creates a new API token for the given user. Returns the token record together with the token
+/// itself. The token is not stored and cannot be retrieved later.
+pub fn create(
+    connection: &PgConnection,
+    user: &User,
+    name: &str,
+    scope: Scope,
+    expiration_time: Option<NaiveDateTime>,
+) -> Result<(ApiToken, String), ApiTokenErrorKind> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(ApiTokenErrorKind::InvalidName(name.to_string()));
+    }
+    if let Some(expiration_time) = expiration_time {
+        if expiration_time < chrono::Local::now().naive_local() {
+            return Err(ApiTokenErrorKind::InvalidExpirationTime(expiration_time));
+        }
+    }
+
+    let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+    let api_token = diesel::insert_into(dsl::api_tokens)
+        .values((
+            dsl::user_id.eq(user.id),
+            dsl::name.eq(name),
+            dsl::token_hash.eq(hash_token(token.as_str())),
+            dsl::scope.eq(scope),
+            dsl::expiration_time.eq(expiration_time),
+        ))
+        .get_result(connection)
+        .map_err(ApiTokenErrorKind::CreationFailed)?;
+    Ok((api_token, token))
+}
+
+/// Returns the API token with the given token string together with its user. Returns `None` if
+/// there is no such token, if it has expired or if the user has been disabled. The last used time
+/// of the token is updated.
+pub fn authenticate(
+    connection: &PgConnection,
+    token: &str,
+) -> Result<Option<(ApiToken, User)>, ApiTokenErrorKind> {
+    let result = api_tokens::table
+        .inner_join(users::table)
+        .filter(dsl::token_hash.eq(hash_token(token)))
+        .filter(users::disabled.eq(false))
+        .first::<(ApiToken, User)>(connection)
+        .optional()
+        .map_err(ApiTokenErrorKind::ReadFailed)?;
+
+    match result {
+        Some((api_token, user)) if !api_token.is_expired() => {
+            let api_token = diesel::update(dsl::api_tokens.find(api_token.id))
+                .set(dsl::last_used.eq(chrono::Local::now().naive_local()))
+                .get_result(connection)
+                .map_err(ApiTokenErrorKind::UpdateFailed)?;
+            Ok(Some((api_token, user)))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Returns the API tokens of the given user, most recently created first.
+pub fn list(connection: &PgConnection, user: &User) -> Result<Vec<ApiToken>, ApiTokenErrorKind> {
+    dsl::api_tokens
+        .filter(dsl::user_id.eq(user.id))
+        .order((dsl::created.desc(), dsl::id.desc()))
+        .load(connection)
+        .map_err(ApiTokenErrorKind::ReadFailed)
+}
+
+/// Revokes the API token with the given ID. Only tokens of the given user can be revoked.
+pub fn delete(connection: &PgConnection, user: &User, id: i32) -> Result<(), ApiTokenErrorKind> {
+    let count = diesel::delete(
+        dsl::api_tokens
+            .filter(dsl::id.eq(id))
+            .filter(dsl::user_id.eq(user.id)),
+    )
+    .execute(connection)
+    .map_err(ApiTokenErrorKind::DeletionFailed)?;
+    match count {
+        0 => Err(ApiTokenErrorKind::NotFound(id)),
+        _ => Ok(()),
+    }
+}
+
+/// Purges expired API tokens.
+pub fn purge(connection: &PgConnection) -> Result<(), ApiTokenErrorKind> {
+    let now = chrono::Local::now().naive_local();
+    diesel::delete(dsl::api_tokens.filter(dsl::expiration_time.lt(now)))
+        .execute(connection)
+        .map_err(ApiTokenErrorKind::PurgingFailed)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::create_test_user;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use chrono::Duration;
+    use diesel::result::Error;
+
+    // Tests super::create() and super::authenticate().
+    #[test]
+    fn test_authenticate() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let (api_token, token) = create(&conn, &user, " Phone ", Scope::Write, None).unwrap();
+            assert!(token.starts_with(TOKEN_PREFIX));
+            assert_ne!(token, api_token.token_hash);
+            assert_eq!("Phone", api_token.name);
+            assert_eq!(Scope::Write, api_token.scope);
+            assert!(api_token.last_used.is_none());
+
+            // The token can be used to authenticate, which updates the last used time.
+            let (authenticated, token_user) = authenticate(&conn, &token).unwrap().unwrap();
+            assert_eq!(api_token.id, authenticated.id);
+            assert!(authenticated.last_used.is_some());
+            assert_eq!(user.id, token_user.id);
+            assert!(authenticate(&conn, "ft_invalid").unwrap().is_none());
+
+            // The tokens of disabled users are rejected.
+            let user = crate::user::set_disabled(&conn, user, true).unwrap();
+            assert!(authenticate(&conn, &token).unwrap().is_none());
+            let user = crate::user::set_disabled(&conn, user, false).unwrap();
+            assert!(authenticate(&conn, &token).unwrap().is_some());
+
+            // Expired tokens are rejected.
+            let yesterday = chrono::Local::now().naive_local() - Duration::days(1);
+            diesel::update(dsl::api_tokens.find(api_token.id))
+                .set(dsl::expiration_time.eq(yesterday))
+                .execute(&conn)?;
+            assert!(authenticate(&conn, &token).unwrap().is_none());
+
+            // Tokens need a name and cannot be created in an expired state.
+            assert_eq!(
+                Err(ApiTokenErrorKind::InvalidName("".to_string())),
+                create(&conn, &user, "  ", Scope::Read, None)
+            );
+            assert_eq!(
+                Err(ApiTokenErrorKind::InvalidExpirationTime(yesterday)),
+                create(&conn, &user, "Script", Scope::Read, Some(yesterday))
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::delete() and super::purge().
+    #[test]
+    fn test_delete() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let other_user = create_test_user(&conn, &config);
+            let tomorrow = chrono::Local::now().naive_local() + Duration::days(1);
+            let (api_token, token) = create(&conn, &user, "Phone", Scope::Read, None).unwrap();
+            let (expiring_token, _) =
+                create(&conn, &user, "Script", Scope::Read, Some(tomorrow)).unwrap();
+            let (other_token, _) = create(&conn, &other_user, "Phone", Scope::Read, None).unwrap();
+            assert_eq!(2, list(&conn, &user).unwrap().len());
+
+            // Tokens of other users cannot be revoked.
+            assert_eq!(
+                Err(ApiTokenErrorKind::NotFound(other_token.id)),
+                delete(&conn, &user, other_token.id)
+            );
+
+            // A revoked token can no longer be used.
+            delete(&conn, &user, api_token.id).unwrap();
+            assert!(authenticate(&conn, &token).unwrap().is_none());
+
+            // Only expired tokens are purged.
+            purge(&conn).unwrap();
+            assert_eq!(1, list(&conn, &user).unwrap().len());
+            let yesterday = chrono::Local::now().naive_local() - Duration::days(1);
+            diesel::update(dsl::api_tokens.find(expiring_token.id))
+                .set(dsl::expiration_time.eq(yesterday))
+                .execute(&conn)?;
+            purge(&conn).unwrap();
+            assert!(list(&conn, &user).unwrap().is_empty());
+            assert_eq!(1, list(&conn, &other_user).unwrap().len());
+
+            Ok(())
+        });
+    }
+}
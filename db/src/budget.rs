@@ -0,0 +1,377 @@
+use super::category::Category;
+use super::ledger::{self, Ledger};
+use super::schema::{budgets, categories, ledger_members, recurring_expenses};
+use super::user::User;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::fmt;
+
+/// The amount that is planned to be spent in a category per month.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct Budget {
+    pub category_id: i32,
+    pub amount: Decimal,
+}
+
+/// An expense that comes back every month on the same day, such as the rent. In months that have
+/// fewer days the expense is due on the last day of the month.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct RecurringExpense {
+    pub id: i32,
+    pub category_id: i32,
+    pub amount: Decimal,
+    pub description: Option<String>,
+    pub day_of_month: i16,
+}
+
+// Possible errors thrown when handling budgets and recurring expenses.
+#[derive(Debug, PartialEq)]
+pub enum BudgetErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The user does not have the required role in the ledger.
+    InsufficientPermissions(i32),
+    // The amount is zero or out of range.
+    InvalidAmount,
+    // The day of the month lies outside of the range 1-31.
+    InvalidDayOfMonth(u32),
+    // A category does not have a budget.
+    BudgetNotFound(i32),
+    // A recurring expense does not exist.
+    RecurringExpenseNotFound(i32),
+}
+
+impl fmt::Display for BudgetErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BudgetErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            BudgetErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to manage budgets in ledger {}",
+                id
+            ),
+            BudgetErrorKind::InvalidAmount => {
+                write!(f, "Amount should be between 0.01 and 9999999.99")
+            }
+            BudgetErrorKind::InvalidDayOfMonth(ref day) => {
+                write!(
+                    f,
+                    "The day of the month should be between 1 and 31, got {}",
+                    day
+                )
+            }
+            BudgetErrorKind::BudgetNotFound(ref id) => {
+                write!(f, "Category {} does not have a budget", id)
+            }
+            BudgetErrorKind::RecurringExpenseNotFound(ref id) => {
+                write!(f, "Recurring expense {} not found", id)
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for BudgetErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        BudgetErrorKind::DatabaseError(e)
+    }
+}
+
+/// Sets the monthly budget of the given category, replacing the previous budget if there is one.
+/// The user needs to be an editor or owner of the ledger of the category.
+pub fn set_budget(
+    connection: &PgConnection,
+    user: &User,
+    category: &Category,
+    amount: &Decimal,
+) -> Result<Budget, BudgetErrorKind> {
+    assert_can_edit(connection, category.ledger_id, user)?;
+    assert_valid_amount(amount)?;
+
+    Ok(diesel::insert_into(budgets::table)
+        .values((
+            budgets::category_id.eq(category.id),
+            budgets::amount.eq(amount),
+        ))
+        .on_conflict(budgets::category_id)
+        .do_update()
+        .set(budgets::amount.eq(amount))
+        .get_result(connection)?)
+}
+
+/// Removes the budget of the given category. The user needs to be an editor or owner of the
+/// ledger of the category.
+pub fn remove_budget(
+    connection: &PgConnection,
+    user: &User,
+    category: &Category,
+) -> Result<(), BudgetErrorKind> {
+    assert_can_edit(connection, category.ledger_id, user)?;
+
+    let result = diesel::delete(budgets::table.find(category.id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(BudgetErrorKind::BudgetNotFound(category.id));
+    }
+
+    Ok(())
+}
+
+/// Returns the budgets of the categories in the given ledger, ordered by category name.
+pub fn get_budgets(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<(Category, Budget)>, BudgetErrorKind> {
+    Ok(categories::table
+        .inner_join(budgets::table)
+        .filter(categories::ledger_id.eq(ledger.id))
+        .order((categories::name, categories::id))
+        .load(connection)?)
+}
+
+/// Adds a recurring expense to the given category. The user needs to be an editor or owner of the
+/// ledger of the category.
+pub fn add_recurring_expense(
+    connection: &PgConnection,
+    user: &User,
+    category: &Category,
+    amount: &Decimal,
+    description: Option<&str>,
+    day_of_month: u32,
+) -> Result<RecurringExpense, BudgetErrorKind> {
+    assert_can_edit(connection, category.ledger_id, user)?;
+    assert_valid_amount(amount)?;
+    if !(1..=31).contains(&day_of_month) {
+        return Err(BudgetErrorKind::InvalidDayOfMonth(day_of_month));
+    }
+
+    Ok(diesel::insert_into(recurring_expenses::table)
+        .values((
+            recurring_expenses::category_id.eq(category.id),
+            recurring_expenses::amount.eq(amount),
+            recurring_expenses::description.eq(description),
+            recurring_expenses::day_of_month.eq(day_of_month as i16),
+        ))
+        .get_result(connection)?)
+}
+
+/// Retrieves the recurring expense with the given ID, with optional filter on the ledger members.
+pub fn read_recurring_expense(
+    connection: &PgConnection,
+    id: i32,
+    user_id: Option<i32>,
+) -> Option<RecurringExpense> {
+    let recurring_expense = match user_id {
+        Some(user_id) => recurring_expenses::table
+            .inner_join(categories::table)
+            .filter(recurring_expenses::id.eq(id))
+            .filter(
+                categories::ledger_id.eq_any(
+                    ledger_members::table
+                        .filter(ledger_members::user_id.eq(user_id))
+                        .select(ledger_members::ledger_id),
+                ),
+            )
+            .select(recurring_expenses::all_columns)
+            .first::<RecurringExpense>(connection),
+        None => recurring_expenses::table
+            .find(id)
+            .first::<RecurringExpense>(connection),
+    };
+
+    recurring_expense.ok()
+}
+
+/// Deletes the recurring expense with the given ID.
+pub fn delete_recurring_expense(connection: &PgConnection, id: i32) -> Result<(), BudgetErrorKind> {
+    let result = diesel::delete(recurring_expenses::table.find(id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(BudgetErrorKind::RecurringExpenseNotFound(id));
+    }
+
+    Ok(())
+}
+
+/// Returns the recurring expenses in the given ledger together with their categories, ordered by
+/// the day of the month on which they are due.
+pub fn get_recurring_expenses(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<(RecurringExpense, Category)>, BudgetErrorKind> {
+    Ok(recurring_expenses::table
+        .inner_join(categories::table)
+        .filter(categories::ledger_id.eq(ledger.id))
+        .order((recurring_expenses::day_of_month, recurring_expenses::id))
+        .load(connection)?)
+}
+
+// Checks that the user is allowed to manage the budgets of the given ledger.
+fn assert_can_edit(
+    connection: &PgConnection,
+    ledger_id: i32,
+    user: &User,
+) -> Result<(), BudgetErrorKind> {
+    match ledger::get_role(connection, ledger_id, user.id)? {
+        Some(role) if role.can_edit() => Ok(()),
+        _ => Err(BudgetErrorKind::InsufficientPermissions(ledger_id)),
+    }
+}
+
+// Checks that the amount is in the range that can be stored.
+fn assert_valid_amount(amount: &Decimal) -> Result<(), BudgetErrorKind> {
+    if *amount <= Decimal::new(0, 2) || *amount > Decimal::new(999_999_999, 2) {
+        return Err(BudgetErrorKind::InvalidAmount);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::ledger::Role;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::set_budget(), super::remove_budget() and super::get_budgets().
+    #[test]
+    fn test_budgets() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let food = crate::category::create(&conn, &user, &ledger, "Food", None, None).unwrap();
+            let rent = crate::category::create(&conn, &user, &ledger, "Rent", None, None).unwrap();
+            let amount = Decimal::new(40000, 2);
+
+            assert_eq!(
+                Err(BudgetErrorKind::InsufficientPermissions(ledger.id)),
+                set_budget(&conn, &viewer, &food, &amount)
+            );
+            assert_eq!(
+                Err(BudgetErrorKind::InvalidAmount),
+                set_budget(&conn, &user, &food, &Decimal::zero())
+            );
+            assert!(get_budgets(&conn, &ledger).unwrap().is_empty());
+
+            // Setting the budget a second time replaces it.
+            let budget = set_budget(&conn, &user, &food, &amount).unwrap();
+            assert_eq!(food.id, budget.category_id);
+            let budget = set_budget(&conn, &user, &food, &Decimal::new(45000, 2)).unwrap();
+            assert_eq!(Decimal::new(45000, 2), budget.amount);
+            let rent_budget = set_budget(&conn, &user, &rent, &Decimal::new(90000, 2)).unwrap();
+            assert_eq!(
+                vec![(food.clone(), budget), (rent.clone(), rent_budget.clone())],
+                get_budgets(&conn, &ledger).unwrap()
+            );
+
+            assert_eq!(
+                Err(BudgetErrorKind::InsufficientPermissions(ledger.id)),
+                remove_budget(&conn, &viewer, &food)
+            );
+            assert!(remove_budget(&conn, &user, &food).is_ok());
+            assert_eq!(
+                Err(BudgetErrorKind::BudgetNotFound(food.id)),
+                remove_budget(&conn, &user, &food)
+            );
+            assert_eq!(
+                vec![(rent, rent_budget)],
+                get_budgets(&conn, &ledger).unwrap()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::add_recurring_expense(), super::read_recurring_expense(),
+    // super::get_recurring_expenses() and super::delete_recurring_expense().
+    #[test]
+    fn test_recurring_expenses() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let category =
+                crate::category::create(&conn, &user, &ledger, "Housing", None, None).unwrap();
+            let amount = Decimal::new(90000, 2);
+
+            assert_eq!(
+                Err(BudgetErrorKind::InsufficientPermissions(ledger.id)),
+                add_recurring_expense(&conn, &viewer, &category, &amount, None, 1)
+            );
+            assert_eq!(
+                Err(BudgetErrorKind::InvalidAmount),
+                add_recurring_expense(&conn, &user, &category, &Decimal::zero(), None, 1)
+            );
+            assert_eq!(
+                Err(BudgetErrorKind::InvalidDayOfMonth(0)),
+                add_recurring_expense(&conn, &user, &category, &amount, None, 0)
+            );
+            assert_eq!(
+                Err(BudgetErrorKind::InvalidDayOfMonth(32)),
+                add_recurring_expense(&conn, &user, &category, &amount, None, 32)
+            );
+
+            let insurance = add_recurring_expense(
+                &conn,
+                &user,
+                &category,
+                &Decimal::new(4500, 2),
+                Some("Insurance"),
+                28,
+            )
+            .unwrap();
+            let rent =
+                add_recurring_expense(&conn, &user, &category, &amount, Some("Rent"), 1).unwrap();
+            assert_eq!(Some("Rent".to_string()), rent.description);
+            assert_eq!(1, rent.day_of_month);
+
+            assert_eq!(
+                Some(rent.clone()),
+                read_recurring_expense(&conn, rent.id, None)
+            );
+            assert_eq!(
+                Some(rent.clone()),
+                read_recurring_expense(&conn, rent.id, Some(viewer.id))
+            );
+            let stranger = create_test_user(&conn, &config);
+            assert_eq!(
+                None,
+                read_recurring_expense(&conn, rent.id, Some(stranger.id))
+            );
+            assert_eq!(
+                vec![
+                    (rent.clone(), category.clone()),
+                    (insurance.clone(), category.clone())
+                ],
+                get_recurring_expenses(&conn, &ledger).unwrap()
+            );
+
+            assert!(delete_recurring_expense(&conn, rent.id).is_ok());
+            assert_eq!(None, read_recurring_expense(&conn, rent.id, None));
+            assert_eq!(
+                Err(BudgetErrorKind::RecurringExpenseNotFound(rent.id)),
+                delete_recurring_expense(&conn, rent.id)
+            );
+            assert_eq!(
+                vec![(insurance, category)],
+                get_recurring_expenses(&conn, &ledger).unwrap()
+            );
+
+            Ok(())
+        });
+    }
+}
@@ -1,5 +1,6 @@
-use super::schema::categories;
+use super::ledger::{self, Ledger};
 use super::schema::categories::dsl;
+use super::schema::{categories, ledger_members};
 use super::user::User;
 use app::AppConfig;
 use diesel::pg::PgConnection;
@@ -7,22 +8,23 @@ use diesel::prelude::*;
 use diesel::result::DatabaseErrorKind::{ForeignKeyViolation, UniqueViolation};
 use diesel::result::Error::DatabaseError;
 use diesel::{dsl::exists, select};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{from_reader, Value};
 use std::{fmt, fs::File};
 
-#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize, Deserialize)]
-#[belongs_to(User, foreign_key = "id")]
+#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize, Deserialize, JsonSchema)]
+#[belongs_to(Ledger)]
 #[table_name = "categories"]
 pub struct Category {
     pub id: i32,
     pub name: String,
     pub description: Option<String>,
-    pub user_id: i32,
     pub parent_id: Option<i32>,
+    pub ledger_id: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, JsonSchema, Serialize)]
 pub struct Categories {
     pub category: Option<Category>,
     pub children: Vec<Categories>,
@@ -44,10 +46,10 @@ impl From<Vec<Category>> for Categories {
         // the database should maintain the integrity of the relationships.
         let orphan_count = remaining_list.len();
         if orphan_count > 0 {
-            let user_id = remaining_list.first().map(|c| c.user_id).unwrap_or(0);
+            let ledger_id = remaining_list.first().map(|c| c.ledger_id).unwrap_or(0);
             warn!(
-                "User {} has {} orphaned {}",
-                user_id,
+                "Ledger {} has {} orphaned {}",
+                ledger_id,
                 orphan_count,
                 if orphan_count > 1 {
                     "categories"
@@ -116,17 +118,21 @@ fn get_child_categories_from_flat_list(
 // Possible errors thrown when handling categories.
 #[derive(Debug, PartialEq)]
 pub enum CategoryErrorKind {
-    // Default categories could not be created because the user already has categories.
+    // Default categories could not be created because the ledger already has categories.
     AlreadyPopulated(String),
     // The category with the given name and parent already exists.
     CategoryAlreadyExists {
         name: String,
         parent: Option<String>,
     },
+    // A category cannot be moved inside itself or one of its descendants.
+    CircularParent(i32),
     // A database error occurred.
     DatabaseError(diesel::result::Error),
     // A category could not be deleted because it has children.
     HasChildren(i32, String),
+    // The user does not have the required role in the ledger.
+    InsufficientPermissions(i32),
     // An error occurred while reading the file containing the default category layout.
     IoError(String, String),
     // The default category listing has malformed or unexpected JSON data.
@@ -135,15 +141,15 @@ pub enum CategoryErrorKind {
     MissingData(String),
     // The category does not exist.
     NotFound(i32),
-    // A category was passed that belongs to the wrong user.
-    ParentCategoryHasWrongUser,
+    // A parent category was passed that belongs to a different ledger.
+    ParentCategoryHasWrongLedger,
 }
 
 impl fmt::Display for CategoryErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &*self {
-            CategoryErrorKind::AlreadyPopulated(ref email) => {
-                write!(f, "Categories for user {} are already populated", email)
+            CategoryErrorKind::AlreadyPopulated(ref name) => {
+                write!(f, "Categories for ledger {} are already populated", name)
             }
             CategoryErrorKind::CategoryAlreadyExists { name, parent } => match parent {
                 Some(p) => write!(
@@ -153,12 +159,22 @@ impl fmt::Display for CategoryErrorKind {
                 ),
                 None => write!(f, "The root category '{}' already exists", name),
             },
+            CategoryErrorKind::CircularParent(ref id) => write!(
+                f,
+                "The category with ID {} cannot be moved inside itself or one of its children",
+                id
+            ),
             CategoryErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
             CategoryErrorKind::HasChildren(ref id, orphan_type) => write!(
                 f,
                 "The category with ID {} could not be deleted because it contains at least one {}",
                 id, orphan_type
             ),
+            CategoryErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to manage categories in ledger {}",
+                id
+            ),
             CategoryErrorKind::IoError(ref path, ref err) => {
                 write!(f, "I/O error when reading {}: {}", path, err)
             }
@@ -168,8 +184,8 @@ impl fmt::Display for CategoryErrorKind {
             ),
             CategoryErrorKind::MissingData(ref err) => write!(f, "Missing data for field: {}", err),
             CategoryErrorKind::NotFound(ref id) => write!(f, "Category {} not found", id),
-            CategoryErrorKind::ParentCategoryHasWrongUser => {
-                write!(f, "Parent category should be for the same user",)
+            CategoryErrorKind::ParentCategoryHasWrongLedger => {
+                write!(f, "Parent category should be in the same ledger")
             }
         }
     }
@@ -181,10 +197,11 @@ impl From<diesel::result::Error> for CategoryErrorKind {
     }
 }
 
-/// Creates a category.
+/// Creates a category in the given ledger. The user needs to be an editor or owner of the ledger.
 pub fn create(
     connection: &PgConnection,
     user: &User,
+    ledger: &Ledger,
     name: &str,
     description: Option<&str>,
     parent: Option<&Category>,
@@ -195,10 +212,16 @@ pub fn create(
         return Err(CategoryErrorKind::MissingData("category name".to_string()));
     }
 
-    // Check that the parent category belongs to the same user.
+    // Check that the user is allowed to add categories to the ledger.
+    match ledger::get_role(connection, ledger.id, user.id)? {
+        Some(role) if role.can_edit() => {}
+        _ => return Err(CategoryErrorKind::InsufficientPermissions(ledger.id)),
+    }
+
+    // Check that the parent category belongs to the same ledger.
     if let Some(parent) = parent {
-        if parent.user_id != user.id {
-            return Err(CategoryErrorKind::ParentCategoryHasWrongUser);
+        if parent.ledger_id != ledger.id {
+            return Err(CategoryErrorKind::ParentCategoryHasWrongLedger);
         }
     }
 
@@ -208,15 +231,15 @@ pub fn create(
         .values((
             dsl::name.eq(&name),
             dsl::description.eq(description),
-            dsl::user_id.eq(user.id),
             dsl::parent_id.eq(parent_id),
+            dsl::ledger_id.eq(ledger.id),
         ))
         .returning((
             dsl::id,
             dsl::name,
             dsl::description,
-            dsl::user_id,
             dsl::parent_id,
+            dsl::ledger_id,
         ))
         .get_result(connection);
 
@@ -231,12 +254,19 @@ pub fn create(
     result.map_err(CategoryErrorKind::DatabaseError)
 }
 
-/// Retrieves the category with the given ID, with optional user filter.
+/// Retrieves the category with the given ID, with an optional filter that only returns the
+/// category if the user with the given ID is a member of its ledger.
 pub fn read(connection: &PgConnection, id: i32, user_id: Option<i32>) -> Option<Category> {
     let category = match user_id {
         Some(user_id) => dsl::categories
             .filter(dsl::id.eq(id))
-            .filter(dsl::user_id.eq(user_id))
+            .filter(
+                dsl::ledger_id.eq_any(
+                    ledger_members::table
+                        .filter(ledger_members::user_id.eq(user_id))
+                        .select(ledger_members::ledger_id),
+                ),
+            )
             .first::<Category>(connection),
         None => dsl::categories.find(id).first::<Category>(connection),
     };
@@ -247,6 +277,68 @@ pub fn read(connection: &PgConnection, id: i32, user_id: Option<i32>) -> Option<
     }
 }
 
+/// Updates the name, description and parent of the given category. The user needs to be an editor
+/// or owner of the ledger. The category cannot be moved to a different ledger.
+pub fn update(
+    connection: &PgConnection,
+    user: &User,
+    category: &Category,
+    name: &str,
+    description: Option<&str>,
+    parent: Option<&Category>,
+) -> Result<Category, CategoryErrorKind> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CategoryErrorKind::MissingData("category name".to_string()));
+    }
+
+    match ledger::get_role(connection, category.ledger_id, user.id)? {
+        Some(role) if role.can_edit() => {}
+        _ => {
+            return Err(CategoryErrorKind::InsufficientPermissions(
+                category.ledger_id,
+            ))
+        }
+    }
+
+    if let Some(parent) = parent {
+        if parent.ledger_id != category.ledger_id {
+            return Err(CategoryErrorKind::ParentCategoryHasWrongLedger);
+        }
+
+        // Walk up the tree from the new parent to make sure the category doesn't become its own
+        // ancestor.
+        let mut ancestor_id = Some(parent.id);
+        while let Some(id) = ancestor_id {
+            if id == category.id {
+                return Err(CategoryErrorKind::CircularParent(category.id));
+            }
+            ancestor_id = dsl::categories
+                .find(id)
+                .select(dsl::parent_id)
+                .first::<Option<i32>>(connection)?;
+        }
+    }
+
+    let result = diesel::update(dsl::categories.find(category.id))
+        .set((
+            dsl::name.eq(&name),
+            dsl::description.eq(description),
+            dsl::parent_id.eq(parent.map(|c| c.id)),
+        ))
+        .get_result(connection);
+
+    // Convert a UniqueViolation to a more informative CategoryAlreadyExists error.
+    if let Err(DatabaseError(UniqueViolation, _)) = result {
+        return Err(CategoryErrorKind::CategoryAlreadyExists {
+            name: name.to_string(),
+            parent: parent.map(|p| p.name.clone()),
+        });
+    }
+
+    result.map_err(CategoryErrorKind::DatabaseError)
+}
+
 /// Deletes the category with the given ID.
 pub fn delete(connection: &PgConnection, id: i32) -> Result<(), CategoryErrorKind> {
     let result = diesel::delete(dsl::categories.filter(dsl::id.eq(id))).execute(connection);
@@ -271,44 +363,47 @@ pub fn delete(connection: &PgConnection, id: i32) -> Result<(), CategoryErrorKin
     Ok(())
 }
 
-/// Returns whether or not the given user has any categories.
-pub fn has_categories(connection: &PgConnection, user: &User) -> Result<bool, CategoryErrorKind> {
-    select(exists(dsl::categories.filter(dsl::user_id.eq(user.id))))
+/// Returns whether or not the given ledger has any categories.
+pub fn has_categories(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<bool, CategoryErrorKind> {
+    select(exists(dsl::categories.filter(dsl::ledger_id.eq(ledger.id))))
         .get_result(connection)
         .map_err(CategoryErrorKind::DatabaseError)
 }
 
-/// Returns the given user's categories as a flat list.
+/// Returns the given ledger's categories as a flat list.
 pub fn get_categories(
     connection: &PgConnection,
-    user: &User,
+    ledger: &Ledger,
 ) -> Result<Vec<Category>, CategoryErrorKind> {
     Ok(dsl::categories
-        .filter(dsl::user_id.eq(user.id))
+        .filter(dsl::ledger_id.eq(ledger.id))
         .load::<Category>(connection)?)
 }
 
-/// Returns the given user's categories as a tree.
+/// Returns the given ledger's categories as a tree.
 pub fn get_categories_tree(
     connection: &PgConnection,
-    user: &User,
+    ledger: &Ledger,
 ) -> Result<Categories, CategoryErrorKind> {
     let categories: Vec<Category> = dsl::categories
-        .filter(dsl::user_id.eq(user.id))
+        .filter(dsl::ledger_id.eq(ledger.id))
         .load::<Category>(connection)?;
     Ok(Categories::from(categories))
 }
 
-/// Creates a set of default categories for the given user. The categories are sourced from a JSON
+/// Creates a set of default categories in the given ledger. The categories are sourced from a JSON
 /// file which is set in the app configuration.
 pub fn populate_categories(
     connection: &PgConnection,
-    user: &User,
+    ledger: &Ledger,
     config: &AppConfig,
 ) -> Result<(), CategoryErrorKind> {
-    // Return an error if the user already has categories.
-    match has_categories(connection, user) {
-        Ok(true) => Err(CategoryErrorKind::AlreadyPopulated(user.email.clone())),
+    // Return an error if the ledger already has categories.
+    match has_categories(connection, ledger) {
+        Ok(true) => Err(CategoryErrorKind::AlreadyPopulated(ledger.name.clone())),
         Ok(false) => Ok(()),
         Err(e) => Err(e),
     }?;
@@ -320,17 +415,17 @@ pub fn populate_categories(
         from_reader(file).map_err(|_| CategoryErrorKind::MalformedCategoryList)?;
 
     connection.transaction::<(), CategoryErrorKind, _>(|| {
-        populate_categories_from_json(connection, user.id, &categories, None)
+        populate_categories_from_json(connection, ledger.id, &categories, None)
     })
 }
 
 // Creates child categories inside the given parent category using the given JSON data.
 // This is a recursive function intended for populating the initial set of categories for a new
-// user, using the JSON file that contains the category list.
+// ledger, using the JSON file that contains the category list.
 fn populate_categories_from_json(
     connection: &PgConnection,
-    // The user for which to create the categories.
-    user_id: i32,
+    // The ledger in which to create the categories.
+    ledger_id: i32,
     // The JSON data. Can be either:
     // - a JSON object: in this case a set of categories will be created using the object keys as
     //   category names. For each key we will recurse, passing the key as parent category and the
@@ -346,13 +441,14 @@ fn populate_categories_from_json(
     match json {
         Value::Object(o) => {
             let categories = o.keys().map(|k| (k.as_str(), None)).collect();
-            let category_ids = insert_child_categories(connection, user_id, parent_id, categories)?;
+            let category_ids =
+                insert_child_categories(connection, ledger_id, parent_id, categories)?;
             let iter = category_ids.iter().zip(o.keys());
             for (id, key) in iter {
                 let children = json
                     .get(key)
                     .ok_or(CategoryErrorKind::MalformedCategoryList)?;
-                populate_categories_from_json(connection, user_id, children, Some(*id))?;
+                populate_categories_from_json(connection, ledger_id, children, Some(*id))?;
             }
             Ok(())
         }
@@ -367,7 +463,7 @@ fn populate_categories_from_json(
 
             // Todo: add support for category descriptions.
             let categories = category_names.iter().map(|c| (*c, None)).collect();
-            insert_child_categories(connection, user_id, parent_id, categories)?;
+            insert_child_categories(connection, ledger_id, parent_id, categories)?;
             Ok(())
         }
         _ => Err(CategoryErrorKind::MalformedCategoryList),
@@ -375,11 +471,11 @@ fn populate_categories_from_json(
 }
 
 // Creates multiple child categories inside a parent category.
-// This is intended for initially populating the categories for a new user. No checks are done to
-// ensure that the passed in parent category belongs to the passed in user.
+// This is intended for initially populating the categories for a new ledger. No checks are done to
+// ensure that the passed in parent category belongs to the passed in ledger.
 fn insert_child_categories(
     connection: &PgConnection,
-    user_id: i32,
+    ledger_id: i32,
     // If the parent ID is omitted the categories will be created in the root.
     parent_id: Option<i32>,
     // A list of child categories consisting of a tuple containing the category name and an optional
@@ -391,8 +487,8 @@ fn insert_child_categories(
         records.push((
             dsl::name.eq(name),
             dsl::description.eq(description),
-            dsl::user_id.eq(user_id),
             dsl::parent_id.eq(parent_id),
+            dsl::ledger_id.eq(ledger_id),
         ));
     }
 
@@ -408,6 +504,7 @@ fn insert_child_categories(
 mod tests {
     use super::*;
     use crate::db_test::*;
+    use crate::ledger::Role;
     use crate::{establish_connection, get_database_url};
     use app::AppConfig;
     use diesel::result::Error;
@@ -422,30 +519,32 @@ mod tests {
         let config = AppConfig::from_test_defaults();
 
         conn.test_transaction::<_, Error, _>(|| {
-            // Create two test users that will serve as the owners of the test categories.
+            // Create two test users that will serve as the owners of the test ledgers.
             let user1 = create_test_user(&conn, &config);
             let user2 = create_test_user(&conn, &config);
+            let ledger1 = create_test_ledger(&conn, &user1);
+            let ledger2 = create_test_ledger(&conn, &user2);
 
             // At the start of the test we should have no categories.
             assert_category_count(&conn, 0);
 
             // Create a root category without a description.
             let name1 = "Housing";
-            let create_root_cat = || create(&conn, &user1, name1, None, None);
+            let create_root_cat = || create(&conn, &user1, &ledger1, name1, None, None);
             let rootcat = create_root_cat().unwrap();
-            assert_category(&rootcat, None, name1, None, user1.id, None);
+            assert_category(&rootcat, None, name1, None, ledger1.id, None);
             assert_category_count(&conn, 1);
 
-            // We can create a root category for a different user with the same name.
-            let rootcat_user2 = create(&conn, &user2, name1, None, None).unwrap();
-            assert_category(&rootcat_user2, None, name1, None, user2.id, None);
+            // We can create a root category in a different ledger with the same name.
+            let rootcat_user2 = create(&conn, &user2, &ledger2, name1, None, None).unwrap();
+            assert_category(&rootcat_user2, None, name1, None, ledger2.id, None);
             assert_category_count(&conn, 2);
 
             // We can create a root category with a description.
             let name2 = "Shopping";
             let desc = Some("Clothing, books, hobbies, …");
-            let rootcat_desc = create(&conn, &user1, name2, desc, None).unwrap();
-            assert_category(&rootcat_desc, None, name2, desc, user1.id, None);
+            let rootcat_desc = create(&conn, &user1, &ledger1, name2, desc, None).unwrap();
+            assert_category(&rootcat_desc, None, name2, desc, ledger1.id, None);
             assert_category_count(&conn, 3);
 
             // Check that if we try to create a root category with a name that already exists we get
@@ -488,11 +587,12 @@ mod tests {
             let mut categories = HashMap::new();
             for (id, (name, description, parent_id)) in test_cases {
                 let mut create_category = |u: &User| {
+                    let ledger = ledger::get_default(&conn, u).unwrap();
                     let parent = parent_id
                         .map(|id| categories.get(&(id, u.id)))
                         .unwrap_or(None);
-                    // Create the category for test user 1.
-                    let category = create(&conn, u, name, description, parent);
+                    // Create the category in the default ledger of the test user.
+                    let category = create(&conn, u, &ledger, name, description, parent);
                     categories.insert((id, u.id), category.unwrap());
                     count += 1;
                     assert_category_count(&conn, count);
@@ -508,8 +608,9 @@ mod tests {
             // parent category we get an error. We are using test case 5 (Sushi) which has test case
             // 4 (Japanese restaurants) as parent category.
             let parent = categories.get(&(4, user1.id));
+            let ledger1 = ledger::get_default(&conn, &user1).unwrap();
             assert_category_exists_err(
-                create(&conn, &user1, "Sushi", None, parent).unwrap_err(),
+                create(&conn, &user1, &ledger1, "Sushi", None, parent).unwrap_err(),
                 "Sushi",
                 parent,
             );
@@ -527,6 +628,7 @@ mod tests {
         connection.test_transaction::<_, Error, _>(|| {
             // Create a test user that will serve as the owner of the test categories.
             let user = create_test_user(&connection, &config);
+            let ledger = create_test_ledger(&connection, &user);
 
             let mut empty_names = vec![
                 "".to_string(),         // Empty string.
@@ -558,7 +660,7 @@ mod tests {
 
             for empty_name in empty_names {
                 let created_category =
-                    create(&connection, &user, &empty_name, None, None).unwrap_err();
+                    create(&connection, &user, &ledger, &empty_name, None, None).unwrap_err();
                 assert_eq!(
                     CategoryErrorKind::MissingData("category name".to_string()),
                     created_category
@@ -569,7 +671,7 @@ mod tests {
         });
     }
 
-    // Test that an error is returned when passing in a parent category from a different user.
+    // Test that an error is returned when passing in a parent category from a different ledger.
     #[test]
     fn test_create_with_invalid_parent_category() {
         let connection = establish_connection(&get_database_url()).unwrap();
@@ -578,23 +680,59 @@ mod tests {
         connection.test_transaction::<_, Error, _>(|| {
             // Create a test user that will serve as the owner of the test category.
             let user = create_test_user(&connection, &config);
+            let ledger = create_test_ledger(&connection, &user);
 
-            // Create a different user that owns some other category.
-            let other_user = create_test_user(&connection, &config);
+            // Create a different ledger that contains some other category.
+            let other_ledger = create_test_ledger(&connection, &user);
 
-            // Try creating a new category that has a parent category belonging to a different user.
-            // This should result in an error.
-            let other_user_cat = create(&connection, &other_user, "Utilities", None, None).unwrap();
+            // Try creating a new category that has a parent category belonging to a different
+            // ledger. This should result in an error.
+            let other_ledger_cat =
+                create(&connection, &user, &other_ledger, "Utilities", None, None).unwrap();
             let cat = create(
                 &connection,
                 &user,
+                &ledger,
                 "Telecommunication",
                 Some("Internet and telephone"),
-                Some(&other_user_cat),
+                Some(&other_ledger_cat),
             )
             .unwrap_err();
 
-            assert_eq!(CategoryErrorKind::ParentCategoryHasWrongUser, cat);
+            assert_eq!(CategoryErrorKind::ParentCategoryHasWrongLedger, cat);
+
+            Ok(())
+        });
+    }
+
+    // Test that only editors and owners of a ledger can create categories in it.
+    #[test]
+    fn test_create_with_insufficient_permissions() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        connection.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&connection, &config);
+            let ledger = create_test_ledger(&connection, &owner);
+
+            // Users that are not a member of the ledger cannot create categories.
+            let user = create_test_user(&connection, &config);
+            assert_eq!(
+                CategoryErrorKind::InsufficientPermissions(ledger.id),
+                create(&connection, &user, &ledger, "Pets", None, None).unwrap_err()
+            );
+
+            // Neither can viewers.
+            ledger::add_member(&connection, &ledger, &user, Role::Viewer).unwrap();
+            assert_eq!(
+                CategoryErrorKind::InsufficientPermissions(ledger.id),
+                create(&connection, &user, &ledger, "Pets", None, None).unwrap_err()
+            );
+
+            // Editors can.
+            ledger::set_role(&connection, &ledger, user.id, Role::Editor).unwrap();
+            let cat = create(&connection, &user, &ledger, "Pets", None, None).unwrap();
+            assert_category(&cat, None, "Pets", None, ledger.id, None);
 
             Ok(())
         });
@@ -616,15 +754,19 @@ mod tests {
                 create_test_user(&conn, &config),
                 create_test_user(&conn, &config),
             );
+            let ledgers = (
+                create_test_ledger(&conn, &users.0),
+                create_test_ledger(&conn, &users.1),
+            );
             let name = "Groceries";
             let cats = (
-                create(&conn, &users.0, name, None, None).unwrap(),
-                create(&conn, &users.1, name, None, None).unwrap(),
+                create(&conn, &users.0, &ledgers.0, name, None, None).unwrap(),
+                create(&conn, &users.1, &ledgers.1, name, None, None).unwrap(),
             );
 
             // Check reading of both categories, filtered by user 0.
             let result = read(&conn, cats.0.id, Some(users.0.id)).unwrap();
-            assert_category(&result, Some(cats.0.id), name, None, users.0.id, None);
+            assert_category(&result, Some(cats.0.id), name, None, ledgers.0.id, None);
             let result = read(&conn, cats.1.id, Some(users.0.id));
             assert!(result.is_none());
 
@@ -632,14 +774,19 @@ mod tests {
             let result = read(&conn, cats.0.id, Some(users.1.id));
             assert!(result.is_none());
             let result = read(&conn, cats.1.id, Some(users.1.id)).unwrap();
-            assert_category(&result, Some(cats.1.id), name, None, users.1.id, None);
+            assert_category(&result, Some(cats.1.id), name, None, ledgers.1.id, None);
 
             // Check the reading of both categories while not filtering on user.
             let result = read(&conn, cats.0.id, None).unwrap();
-            assert_category(&result, Some(cats.0.id), name, None, users.0.id, None);
+            assert_category(&result, Some(cats.0.id), name, None, ledgers.0.id, None);
 
             let result = read(&conn, cats.1.id, None).unwrap();
-            assert_category(&result, Some(cats.1.id), name, None, users.1.id, None);
+            assert_category(&result, Some(cats.1.id), name, None, ledgers.1.id, None);
+
+            // When user 1 becomes a member of the ledger of user 0 they can read its categories.
+            ledger::add_member(&conn, &ledgers.0, &users.1, Role::Viewer).unwrap();
+            let result = read(&conn, cats.0.id, Some(users.1.id)).unwrap();
+            assert_category(&result, Some(cats.0.id), name, None, ledgers.0.id, None);
 
             // Delete the categories. Now the `read()` function should return `None` again in all
             // cases.
@@ -658,6 +805,88 @@ mod tests {
         });
     }
 
+    // Tests super::update().
+    #[test]
+    fn test_update() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            let food = create(&conn, &user, &ledger, "Food", None, None).unwrap();
+            let travel = create(&conn, &user, &ledger, "Travel", None, None).unwrap();
+            let hotels = create(&conn, &user, &ledger, "Hotels", None, Some(&travel)).unwrap();
+
+            // Rename a category and move it to a different parent.
+            let updated = update(
+                &conn,
+                &user,
+                &hotels,
+                " Restaurants ",
+                Some("Eating out"),
+                Some(&food),
+            )
+            .unwrap();
+            assert_eq!(hotels.id, updated.id);
+            assert_eq!("Restaurants", updated.name);
+            assert_eq!(Some("Eating out".to_string()), updated.description);
+            assert_eq!(Some(food.id), updated.parent_id);
+            assert_eq!(Some(updated.clone()), read(&conn, hotels.id, None));
+
+            // A category can be moved to the root.
+            let updated = update(&conn, &user, &updated, "Restaurants", None, None).unwrap();
+            assert_eq!(None, updated.parent_id);
+
+            // The name is required.
+            let result = update(&conn, &user, &updated, " ", None, None);
+            assert_eq!(
+                CategoryErrorKind::MissingData("category name".to_string()),
+                result.unwrap_err()
+            );
+            // A category cannot be moved inside itself or one of its descendants.
+            let child = create(&conn, &user, &ledger, "Snacks", None, Some(&food)).unwrap();
+            for parent in &[&food, &child] {
+                let result = update(&conn, &user, &food, "Food", None, Some(parent));
+                assert_eq!(
+                    CategoryErrorKind::CircularParent(food.id),
+                    result.unwrap_err()
+                );
+            }
+
+            // The parent should be in the same ledger.
+            let other_ledger = create_test_ledger(&conn, &user);
+            let other = create(&conn, &user, &other_ledger, "Other", None, None).unwrap();
+            let result = update(&conn, &user, &food, "Food", None, Some(&other));
+            assert_eq!(
+                CategoryErrorKind::ParentCategoryHasWrongLedger,
+                result.unwrap_err()
+            );
+
+            // Viewers cannot update categories.
+            let viewer = create_test_user(&conn, &config);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let result = update(&conn, &viewer, &food, "Groceries", None, None);
+            assert_eq!(
+                CategoryErrorKind::InsufficientPermissions(ledger.id),
+                result.unwrap_err()
+            );
+
+            // The name should be unique within the parent. This is checked last since the failing
+            // query aborts the test transaction.
+            let result = update(&conn, &user, &updated, "Food", None, None);
+            assert_eq!(
+                CategoryErrorKind::CategoryAlreadyExists {
+                    name: "Food".to_string(),
+                    parent: None
+                },
+                result.unwrap_err()
+            );
+
+            Ok(())
+        });
+    }
+
     // Tests super::delete().
     #[test]
     fn test_delete() {
@@ -670,8 +899,9 @@ mod tests {
 
             // Create a root category. Now there should be one category.
             let user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
             let name = "Healthcare";
-            let cat = create(&conn, &user, name, None, None).unwrap();
+            let cat = create(&conn, &user, &ledger, name, None, None).unwrap();
             assert_category_count(&conn, 1);
 
             // Delete the category. This should not result in any errors, and there should again be
@@ -697,12 +927,13 @@ mod tests {
         conn.test_transaction::<_, Error, _>(|| {
             // Create a root category.
             let user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
             let name = "Lifestyle";
-            let parent_cat = create(&conn, &user, name, None, None).unwrap();
+            let parent_cat = create(&conn, &user, &ledger, name, None, None).unwrap();
 
             // Create a child category.
             let child_name = "Haircuts";
-            create(&conn, &user, child_name, None, Some(&parent_cat)).unwrap();
+            create(&conn, &user, &ledger, child_name, None, Some(&parent_cat)).unwrap();
 
             // Delete to delete the parent category. This should result in an error.
             let result = delete(&conn, parent_cat.id);
@@ -740,20 +971,21 @@ mod tests {
         });
     }
 
-    // Tests that an error is returned if default categories are created for a user that already has
-    // categories.
+    // Tests that an error is returned if default categories are created for a ledger that already
+    // has categories.
     #[test]
     fn test_populate_categories_with_existing_categories() {
         let conn = establish_connection(&get_database_url()).unwrap();
         let config = AppConfig::from_test_defaults();
 
         conn.test_transaction::<_, Error, _>(|| {
-            // Create a test user which has a category.
+            // Create a test ledger which has a category.
             let user = create_test_user(&conn, &config);
             create_test_category(&conn, &user);
+            let ledger = ledger::get_default(&conn, &user).unwrap();
             assert_eq!(
-                CategoryErrorKind::AlreadyPopulated(user.email.clone()),
-                populate_categories(&conn, &user, &config).unwrap_err()
+                CategoryErrorKind::AlreadyPopulated(ledger.name.clone()),
+                populate_categories(&conn, &ledger, &config).unwrap_err()
             );
 
             Ok(())
@@ -775,7 +1007,8 @@ mod tests {
             conn.test_transaction::<_, Error, _>(|| {
                 config.set_default_categories_json_path(test_file.to_string());
                 let user = create_test_user(&conn, &config);
-                let result = populate_categories(&conn, &user, &config);
+                let ledger = create_test_ledger(&conn, &user);
+                let result = populate_categories(&conn, &ledger, &config);
 
                 // An error should be returned.
                 assert_eq!(result, Err(CategoryErrorKind::MalformedCategoryList));
@@ -796,7 +1029,8 @@ mod tests {
 
         conn.test_transaction::<_, Error, _>(|| {
             let user = create_test_user(&conn, &config);
-            let result = populate_categories(&conn, &user, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            let result = populate_categories(&conn, &ledger, &config);
 
             // No error should be returned.
             assert_eq!(result, Ok(()));
@@ -816,7 +1050,7 @@ mod tests {
                 ("Water", Some("Utilities")),
             ];
 
-            let cats = get_categories(&conn, &user).unwrap();
+            let cats = get_categories(&conn, &ledger).unwrap();
             for (cat_name, expected_parent_cat_name) in expected_parent_cat_names {
                 // Check that there is exactly 1 category with the expected category name.
                 let cats_with_cat_name = cats
@@ -853,14 +1087,16 @@ mod tests {
         conn.test_transaction::<_, Error, _>(|| {
             let user1 = create_test_user(&conn, &config);
             let user2 = create_test_user(&conn, &config);
-            assert!(!has_categories(&conn, &user1).unwrap());
-            assert!(!has_categories(&conn, &user2).unwrap());
+            let ledger1 = ledger::get_default(&conn, &user1).unwrap();
+            let ledger2 = ledger::get_default(&conn, &user2).unwrap();
+            assert!(!has_categories(&conn, &ledger1).unwrap());
+            assert!(!has_categories(&conn, &ledger2).unwrap());
             create_test_category(&conn, &user1);
-            assert!(has_categories(&conn, &user1).unwrap());
-            assert!(!has_categories(&conn, &user2).unwrap());
+            assert!(has_categories(&conn, &ledger1).unwrap());
+            assert!(!has_categories(&conn, &ledger2).unwrap());
             create_test_category(&conn, &user2);
-            assert!(has_categories(&conn, &user1).unwrap());
-            assert!(has_categories(&conn, &user2).unwrap());
+            assert!(has_categories(&conn, &ledger1).unwrap());
+            assert!(has_categories(&conn, &ledger2).unwrap());
 
             Ok(())
         });
@@ -875,59 +1111,61 @@ mod tests {
         conn.test_transaction::<_, Error, _>(|| {
             let user1 = create_test_user(&conn, &config);
             let user2 = create_test_user(&conn, &config);
+            let ledger1 = ledger::get_default(&conn, &user1).unwrap();
+            let ledger2 = ledger::get_default(&conn, &user2).unwrap();
 
             // Initially both users don't have any categories.
             let no_cats: Vec<Category> = vec![];
-            assert_eq!(no_cats, get_categories(&conn, &user1).unwrap());
-            assert_eq!(no_cats, get_categories(&conn, &user2).unwrap());
+            assert_eq!(no_cats, get_categories(&conn, &ledger1).unwrap());
+            assert_eq!(no_cats, get_categories(&conn, &ledger2).unwrap());
 
             // Create a root category for user 1 and check that it is returned correctly.
             let user1_cat1 = create_test_category(&conn, &user1);
             assert_eq!(
                 vec![user1_cat1.clone()],
-                get_categories(&conn, &user1).unwrap()
+                get_categories(&conn, &ledger1).unwrap()
             );
-            assert_eq!(no_cats, get_categories(&conn, &user2).unwrap());
+            assert_eq!(no_cats, get_categories(&conn, &ledger2).unwrap());
 
             // Create a root category for user 2.
             let user2_cat1 = create_test_category(&conn, &user2);
             assert_eq!(
                 vec![user1_cat1.clone()],
-                get_categories(&conn, &user1).unwrap()
+                get_categories(&conn, &ledger1).unwrap()
             );
             assert_eq!(
                 vec![user2_cat1.clone()],
-                get_categories(&conn, &user2).unwrap()
+                get_categories(&conn, &ledger2).unwrap()
             );
 
             // Create a child category for user 1.
             let user1_cat2 = create_test_category_with_parent(&conn, &user1, Some(&user1_cat1));
             assert_eq!(
                 vec![user1_cat1.clone(), user1_cat2.clone()],
-                get_categories(&conn, &user1).unwrap()
+                get_categories(&conn, &ledger1).unwrap()
             );
             assert_eq!(
                 vec![user2_cat1.clone()],
-                get_categories(&conn, &user2).unwrap()
+                get_categories(&conn, &ledger2).unwrap()
             );
 
             // Create some more root and child categories for user 1.
             let user1_cat3 = create_test_category(&conn, &user1);
             assert_eq!(
                 vec![user1_cat1.clone(), user1_cat2.clone(), user1_cat3.clone()],
-                get_categories(&conn, &user1).unwrap()
+                get_categories(&conn, &ledger1).unwrap()
             );
             assert_eq!(
                 vec![user2_cat1.clone()],
-                get_categories(&conn, &user2).unwrap()
+                get_categories(&conn, &ledger2).unwrap()
             );
 
             let user1_cat4 = create_test_category_with_parent(&conn, &user1, Some(&user1_cat2));
             assert_eq!(
                 vec![user1_cat1, user1_cat2, user1_cat3, user1_cat4],
-                get_categories(&conn, &user1).unwrap()
+                get_categories(&conn, &ledger1).unwrap()
             );
-            assert_eq!(vec![user2_cat1], get_categories(&conn, &user2).unwrap());
+            assert_eq!(vec![user2_cat1], get_categories(&conn, &ledger2).unwrap());
 
             Ok(())
         });
@@ -947,7 +1185,8 @@ mod tests {
 
         conn.test_transaction::<_, Error, _>(|| {
             let user = create_test_user(&conn, &config);
-            populate_categories(&conn, &user, &config).unwrap();
+            let ledger = create_test_ledger(&conn, &user);
+            populate_categories(&conn, &ledger, &config).unwrap();
 
             let expected_categories = ExpectedCategories {
                 category: None,
@@ -990,8 +1229,8 @@ mod tests {
                 ],
             };
 
-            let cat_tree = get_categories_tree(&conn, &user).unwrap();
-            assert_category_tree(&expected_categories, &cat_tree, user.id, None);
+            let cat_tree = get_categories_tree(&conn, &ledger).unwrap();
+            assert_category_tree(&expected_categories, &cat_tree, ledger.id, None);
 
             Ok(())
         });
@@ -1000,11 +1239,11 @@ mod tests {
     #[test]
     // Tests the conversion of Vec<Category> into Categories.
     fn test_categories_from_vec_category() {
-        let user_id = rand::random::<i32>();
+        let ledger_id = rand::random::<i32>();
         let (vec_category, expected_categories) =
-            get_test_vec_category_and_expected_categories(user_id);
+            get_test_vec_category_and_expected_categories(ledger_id);
         let cat_tree = Categories::from(vec_category);
-        assert_category_tree(&expected_categories, &cat_tree, user_id, None);
+        assert_category_tree(&expected_categories, &cat_tree, ledger_id, None);
     }
 
     #[test]
@@ -1013,48 +1252,48 @@ mod tests {
     fn test_categories_from_vec_category_logs_warning_if_category_is_orphaned() {
         testing_logger::setup();
 
-        let user_id = rand::random::<i32>();
+        let ledger_id = rand::random::<i32>();
         let (mut vec_category, expected_categories) =
-            get_test_vec_category_and_expected_categories(user_id);
+            get_test_vec_category_and_expected_categories(ledger_id);
         // Append a category that has a parent ID that points to a non-existing category. We don't
         // append this to the `expected_categories` since the orphaned category should not be
         // returned by the function. Instead it should log a warning.
         vec_category.push(Category {
-            id: user_id,
+            id: ledger_id,
             name: "Orphan".to_string(),
             description: None,
-            user_id,
             parent_id: Some(98765),
+            ledger_id,
         });
         let cat_tree = Categories::from(vec_category.clone());
-        assert_category_tree(&expected_categories, &cat_tree, user_id, None);
+        assert_category_tree(&expected_categories, &cat_tree, ledger_id, None);
 
         // Check that a warning was logged.
         testing_logger::validate(|captured_logs| {
             assert_eq!(captured_logs.len(), 1);
             assert_eq!(
                 captured_logs[0].body,
-                format!("User {} has 1 orphaned category", user_id)
+                format!("Ledger {} has 1 orphaned category", ledger_id)
             );
             assert_eq!(captured_logs[0].level, Level::Warn);
         });
 
         // Add another orphaned category to check that the warning shows the updated count.
         vec_category.push(Category {
-            id: user_id,
+            id: ledger_id,
             name: "Another orphan".to_string(),
             description: None,
-            user_id,
             parent_id: Some(87654),
+            ledger_id,
         });
         let cat_tree = Categories::from(vec_category);
-        assert_category_tree(&expected_categories, &cat_tree, user_id, None);
+        assert_category_tree(&expected_categories, &cat_tree, ledger_id, None);
 
         testing_logger::validate(|captured_logs| {
             assert_eq!(captured_logs.len(), 1);
             assert_eq!(
                 captured_logs[0].body,
-                format!("User {} has 2 orphaned categories", user_id)
+                format!("Ledger {} has 2 orphaned categories", ledger_id)
             );
             assert_eq!(captured_logs[0].level, Level::Warn);
         });
@@ -1062,7 +1301,7 @@ mod tests {
 
     // Returns a tuple with a flat list of test categories as well as a list of expected categories.
     fn get_test_vec_category_and_expected_categories(
-        user_id: i32,
+        ledger_id: i32,
     ) -> (Vec<Category>, ExpectedCategories) {
         // Define a list of test categories. These are intentionally in non-alphabetical order so
         // that we can assert that the categories are sorted correctly.
@@ -1084,8 +1323,8 @@ mod tests {
                 id,
                 name: name.to_string(),
                 description: None,
-                user_id,
                 parent_id,
+                ledger_id,
             };
             categories.push(category);
         }
@@ -1124,11 +1363,11 @@ mod tests {
     }
 
     // Checks recursively that the passed in Categories tree matches the ExpectedCategories tree.
-    // Each category is checked that it belongs to the correct user and has the expected parent ID.
+    // Each category is checked to belong to the correct ledger and to have the expected parent ID.
     fn assert_category_tree(
         expected_categories: &ExpectedCategories,
         categories: &Categories,
-        expected_user_id: i32,
+        expected_ledger_id: i32,
         expected_parent_id: Option<i32>,
     ) {
         assert_eq!(
@@ -1136,7 +1375,7 @@ mod tests {
             categories.category.as_ref().map(|c| c.name.clone())
         );
         if let Some(cat) = categories.category.clone() {
-            assert_eq!(expected_user_id, cat.user_id);
+            assert_eq!(expected_ledger_id, cat.ledger_id);
             assert_eq!(expected_parent_id, cat.parent_id);
         }
 
@@ -1158,7 +1397,7 @@ mod tests {
                 assert_category_tree(
                     expected_child_cat,
                     actual_child_cat,
-                    expected_user_id,
+                    expected_ledger_id,
                     parent_id,
                 );
             }
@@ -1199,7 +1438,8 @@ mod tests {
         for test_case in test_cases {
             conn.test_transaction::<_, Error, _>(|| {
                 let user = create_test_user(&conn, &config);
-                let result = populate_categories_from_json(&conn, user.id, &test_case, None);
+                let ledger = create_test_ledger(&conn, &user);
+                let result = populate_categories_from_json(&conn, ledger.id, &test_case, None);
                 assert_eq!(
                     result.unwrap_err(),
                     CategoryErrorKind::MalformedCategoryList
@@ -1247,7 +1487,8 @@ mod tests {
         for (test_case, expected_root_count, expected_total_count) in test_cases {
             conn.test_transaction::<_, Error, _>(|| {
                 let user = create_test_user(&conn, &config);
-                let result = populate_categories_from_json(&conn, user.id, &test_case, None);
+                let ledger = create_test_ledger(&conn, &user);
+                let result = populate_categories_from_json(&conn, ledger.id, &test_case, None);
                 assert_eq!(result, Ok(()));
                 assert_root_category_count(&conn, expected_root_count);
                 assert_category_count(&conn, expected_total_count);
@@ -1267,7 +1508,7 @@ mod tests {
         let assert_cats = |cats: Vec<(&str, Option<&str>)>,
                            parent_id: Option<i32>,
                            result: Vec<i32>,
-                           user_id: i32| {
+                           ledger_id: i32| {
             // We should get back the 2 IDs of the created categories.
             assert_eq!(2, result.len());
 
@@ -1276,12 +1517,20 @@ mod tests {
                 let id = result.get(i).unwrap();
                 let (name, description) = cats.get(i).unwrap();
                 let category = read(&conn, *id, None).unwrap();
-                assert_category(&category, Some(*id), name, *description, user_id, parent_id);
+                assert_category(
+                    &category,
+                    Some(*id),
+                    name,
+                    *description,
+                    ledger_id,
+                    parent_id,
+                );
             }
         };
 
         conn.test_transaction::<_, Error, _>(|| {
             let user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
 
             // Initially there are no categories in the database.
             assert_category_count(&conn, 0);
@@ -1291,11 +1540,12 @@ mod tests {
                 ("Healthcare", None),
                 ("Housing", Some("Expenses related to a residence")),
             ];
-            let result = insert_child_categories(&conn, user.id, None, root_cats.clone()).unwrap();
+            let result =
+                insert_child_categories(&conn, ledger.id, None, root_cats.clone()).unwrap();
 
             // There should be 2 categories in the database now.
             assert_category_count(&conn, 2);
-            assert_cats(root_cats, None, result.clone(), user.id);
+            assert_cats(root_cats, None, result.clone(), ledger.id);
 
             // Create 2 child categories, one with a description and one without.
             let parent_id = result.get(0).unwrap();
@@ -1305,16 +1555,16 @@ mod tests {
                 ("Doctor", Some("Visiting a general practitioner")),
             ];
             let result =
-                insert_child_categories(&conn, user.id, Some(*parent_id), child_cats.clone())
+                insert_child_categories(&conn, ledger.id, Some(*parent_id), child_cats.clone())
                     .unwrap();
 
             // There should be 4 categories in the database now.
             assert_category_count(&conn, 4);
-            assert_cats(child_cats.clone(), Some(*parent_id), result, user.id);
+            assert_cats(child_cats.clone(), Some(*parent_id), result, ledger.id);
 
             // Inserting the same categories again should result in an error.
             let result =
-                insert_child_categories(&conn, user.id, Some(*parent_id), child_cats.clone());
+                insert_child_categories(&conn, ledger.id, Some(*parent_id), child_cats.clone());
             assert!(result.is_err());
 
             Ok(())
@@ -1331,8 +1581,8 @@ mod tests {
         name: &str,
         // The expected description.
         description: Option<&str>,
-        // The expected user ID of the category owner.
-        user_id: i32,
+        // The expected ID of the ledger the category belongs to.
+        ledger_id: i32,
         // The expected parent category ID.
         parent_id: Option<i32>,
     ) {
@@ -1341,7 +1591,7 @@ mod tests {
         }
         assert_eq!(name, category.name);
         assert_eq!(description.map(|d| d.to_string()), category.description);
-        assert_eq!(user_id, category.user_id);
+        assert_eq!(ledger_id, category.ledger_id);
         assert_eq!(parent_id, category.parent_id);
     }
 
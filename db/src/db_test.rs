@@ -2,6 +2,7 @@ use super::*;
 
 use crate::category::Category;
 use crate::expense::Expense;
+use crate::ledger::Ledger;
 use crate::user::User;
 use app::AppConfig;
 use rand::distributions::Alphanumeric;
@@ -21,18 +22,56 @@ pub fn create_test_user(conn: &PgConnection, config: &AppConfig) -> User {
     .unwrap()
 }
 
-/// Creates a test category using a random name.
+/// Enables two-factor authentication for the given user. Returns the recovery codes.
+pub fn enable_test_two_factor(conn: &PgConnection, user: &User, config: &AppConfig) -> Vec<String> {
+    crate::two_factor::start_enrollment(conn, user).unwrap();
+    let code = get_test_two_factor_code(conn, user, 0);
+    crate::two_factor::confirm_enrollment(conn, user, code.as_str(), config).unwrap()
+}
+
+/// Returns the TOTP code of the given user for the current time step plus the given offset. Since
+/// the code of the current time step is used to enable two-factor authentication, pass an offset of
+/// 1 to get a code that can be used to log in.
+pub fn get_test_two_factor_code(conn: &PgConnection, user: &User, offset: i64) -> String {
+    let totp_secret = crate::two_factor::read(conn, user.id).unwrap().unwrap();
+    let secret = base32::decode(
+        base32::Alphabet::RFC4648 { padding: false },
+        totp_secret.secret.as_str(),
+    )
+    .unwrap();
+    let step = chrono::Utc::now().timestamp() / crate::two_factor::TIME_STEP + offset;
+    format!(
+        "{:06}",
+        crate::two_factor::generate_code(&secret, step as u64)
+    )
+}
+
+/// Creates a test ledger using a random name.
+pub fn create_test_ledger(conn: &PgConnection, user: &User) -> Ledger {
+    crate::ledger::create(conn, user, random_string(10).as_str()).unwrap()
+}
+
+/// Creates a test category in the user's default ledger using a random name.
 pub fn create_test_category(conn: &PgConnection, user: &User) -> Category {
     create_test_category_with_parent(conn, user, None)
 }
 
-/// Creates a test child category using a random name.
+/// Creates a test child category in the user's default ledger using a random name.
 pub fn create_test_category_with_parent(
     conn: &PgConnection,
     user: &User,
     parent_cat: Option<&Category>,
 ) -> Category {
-    crate::category::create(conn, user, random_string(10).as_str(), None, parent_cat).unwrap()
+    let ledger = crate::ledger::get_default(conn, user).unwrap();
+    crate::category::create(
+        conn,
+        user,
+        &ledger,
+        random_string(10).as_str(),
+        None,
+        parent_cat,
+    )
+    .unwrap()
 }
 
 /// Creates a test expense containing a random amount.
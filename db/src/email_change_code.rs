@@ -0,0 +1,433 @@
+use super::schema::email_change_codes;
+use super::schema::email_change_codes::dsl;
+use super::user::{User, UserErrorKind};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use std::fmt;
+use validator::validate_email;
+
+// The minimum and maximum values for a random email change code.
+const MIN_VALUE: i32 = 100_000;
+const MAX_VALUE: i32 = 999_999;
+
+// The maximum number of email changes that can be attempted in 30 minutes.
+const MAX_ATTEMPTS: i16 = 5;
+
+/// A code that is sent to a new email address to verify that it belongs to the user. The email
+/// address of the user is only changed once the code has been confirmed.
+#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize)]
+#[belongs_to(User, foreign_key = "id")]
+pub struct EmailChangeCode {
+    pub id: i32,
+    /// The new email address.
+    pub email: String,
+    #[serde(skip)]
+    pub code: i32,
+    pub expiration_time: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub attempts: i16,
+}
+
+impl EmailChangeCode {
+    /// Returns whether or not the email change code is expired.
+    pub fn is_expired(&self) -> bool {
+        self.expiration_time.lt(&chrono::Local::now().naive_local())
+    }
+
+    /// Returns whether or not the maximum number of attempts have been exceeded.
+    pub fn attempts_exceeded(&self) -> bool {
+        self.attempts.gt(&MAX_ATTEMPTS)
+    }
+
+    /// Checks whether the email change code is valid. Will return an error if the code is expired
+    /// or has exceeded the maximum number of attempts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::email_change_code::{EmailChangeCode, EmailChangeCodeErrorKind};
+    /// #
+    /// let mut email_change_code = EmailChangeCode {
+    ///     id: 1,
+    ///     email: "new@example.com".to_string(),
+    ///     code: 123456,
+    ///     expiration_time: chrono::Local::now().checked_add_signed(chrono::Duration::minutes(30)).unwrap().naive_local(),
+    ///     attempts: 0,
+    /// };
+    ///
+    /// assert_eq!(Ok(()), email_change_code.validate());
+    ///
+    /// email_change_code.attempts = 6;
+    /// assert_eq!(Err(EmailChangeCodeErrorKind::MaxAttemptsExceeded), email_change_code.validate());
+    ///
+    /// email_change_code.expiration_time = chrono::Local::now().checked_sub_signed(chrono::Duration::seconds(1)).unwrap().naive_local();
+    /// assert_eq!(Err(EmailChangeCodeErrorKind::Expired), email_change_code.validate());
+    /// ```
+    pub fn validate(&self) -> Result<(), EmailChangeCodeErrorKind> {
+        if self.is_expired() {
+            return Err(EmailChangeCodeErrorKind::Expired);
+        }
+        if self.attempts_exceeded() {
+            return Err(EmailChangeCodeErrorKind::MaxAttemptsExceeded);
+        }
+        Ok(())
+    }
+}
+
+// Possible errors thrown when handling email change codes.
+#[derive(Debug, PartialEq)]
+pub enum EmailChangeCodeErrorKind {
+    // A new email change code could not be created due to a database error.
+    CreationFailed(diesel::result::Error),
+    // An email change code could not be deleted due to a database error.
+    DeletionFailed(diesel::result::Error),
+    // The email address could not be changed.
+    EmailUpdateFailed(UserErrorKind),
+    // The expiration time overflowed. Not expected to occur before the end of the year 262143.
+    ExpirationTimeOverflow,
+    // The email change code has expired.
+    Expired,
+    // The email change code is invalid.
+    InvalidCode,
+    // The new email address is not valid.
+    InvalidEmail(String),
+    // The maximum number of attempts to retrieve or validate an email change code has been
+    // exceeded.
+    MaxAttemptsExceeded,
+    // Expired email change codes could not be purged due to a database error.
+    PurgingFailed(diesel::result::Error),
+    // An existing email change code could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for EmailChangeCodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmailChangeCodeErrorKind::CreationFailed(ref err) => {
+                write!(f, "Database error when creating email change code: {}", err)
+            }
+            EmailChangeCodeErrorKind::DeletionFailed(ref err) => {
+                write!(f, "Database error when deleting email change code: {}", err)
+            }
+            EmailChangeCodeErrorKind::EmailUpdateFailed(ref err) => {
+                write!(f, "The email address could not be changed: {}", err)
+            }
+            EmailChangeCodeErrorKind::Expired => {
+                write!(f, "The confirmation code has expired")
+            }
+            EmailChangeCodeErrorKind::ExpirationTimeOverflow => {
+                write!(f, "Expiration time overflow")
+            }
+            EmailChangeCodeErrorKind::InvalidCode => {
+                write!(f, "Invalid confirmation code")
+            }
+            EmailChangeCodeErrorKind::InvalidEmail(ref email) => {
+                write!(f, "Invalid email address: {}", email)
+            }
+            EmailChangeCodeErrorKind::MaxAttemptsExceeded => {
+                write!(f, "The maximum number of allowed attempts to request or confirm a change of email address has been exceeded. Please wait 30 minutes and try again.")
+            }
+            EmailChangeCodeErrorKind::PurgingFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when purging expired email change codes: {}",
+                    err
+                )
+            }
+            EmailChangeCodeErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when updating email change code: {}", err)
+            }
+        }
+    }
+}
+
+/// Returns an email change code for changing the email address of the given user to the given
+/// address.
+///
+/// If the user already requested a change less than 30 minutes ago the existing code is reused,
+/// and the attempts counter is increased. This prevents the user from flooding inboxes with
+/// confirmation messages.
+pub fn get(
+    connection: &PgConnection,
+    user: &User,
+    email: &str,
+) -> Result<EmailChangeCode, EmailChangeCodeErrorKind> {
+    if !validate_email(email) {
+        return Err(EmailChangeCodeErrorKind::InvalidEmail(email.to_string()));
+    }
+
+    match read(connection, user.id) {
+        Some(c) if !c.is_expired() => {
+            // Possibly returns a MaxAttemptsExceeded error.
+            let c = increase_attempt_counter(connection, c)?;
+            if c.email == email {
+                return Ok(c);
+            }
+
+            // The user changed their mind about the new address. Send a fresh code to the new
+            // address, but keep the attempts counter and expiration time.
+            diesel::update(dsl::email_change_codes.filter(dsl::id.eq(c.id)))
+                .set((dsl::email.eq(email), dsl::code.eq(random_code())))
+                .returning((
+                    dsl::id,
+                    dsl::email,
+                    dsl::code,
+                    dsl::expiration_time,
+                    dsl::attempts,
+                ))
+                .get_result(connection)
+                .map_err(EmailChangeCodeErrorKind::UpdateFailed)
+        }
+        _ => create(connection, user.id, email),
+    }
+}
+
+/// Returns the pending email change for the given user, if there is one that has not expired.
+pub fn get_pending(connection: &PgConnection, user: &User) -> Option<EmailChangeCode> {
+    read(connection, user.id).filter(|c| !c.is_expired())
+}
+
+/// Changes the email address of the given user to the address the email change code was sent to,
+/// if the given code is valid. The email change code is deleted afterwards.
+pub fn change_email(
+    connection: &PgConnection,
+    user: User,
+    email_change_code: i32,
+) -> Result<User, EmailChangeCodeErrorKind> {
+    match read(connection, user.id) {
+        Some(c) => {
+            c.validate()?;
+            if c.code == email_change_code {
+                let user = super::user::set_email(connection, user, c.email.as_str())
+                    .map_err(EmailChangeCodeErrorKind::EmailUpdateFailed)?;
+                delete(connection, &user)?;
+                return Ok(user);
+            }
+            increase_attempt_counter(connection, c)?;
+            Err(EmailChangeCodeErrorKind::InvalidCode)
+        }
+        None => Err(EmailChangeCodeErrorKind::Expired),
+    }
+}
+
+/// Purges all expired email change codes.
+pub fn purge(connection: &PgConnection) -> Result<(), EmailChangeCodeErrorKind> {
+    let expiration_time = chrono::Local::now().naive_local();
+    diesel::delete(dsl::email_change_codes.filter(dsl::expiration_time.lt(expiration_time)))
+        .execute(connection)
+        .map_err(EmailChangeCodeErrorKind::PurgingFailed)?;
+    Ok(())
+}
+
+/// Deletes the email change code for the given user. This cancels a pending email change.
+pub fn delete(connection: &PgConnection, user: &User) -> Result<(), EmailChangeCodeErrorKind> {
+    diesel::delete(dsl::email_change_codes.filter(dsl::id.eq(user.id)))
+        .execute(connection)
+        .map_err(EmailChangeCodeErrorKind::DeletionFailed)?;
+    Ok(())
+}
+
+// Retrieves the email change code for the user with the given ID.
+//
+// Returns raw data from the database which may be stale. Use `get()` or `get_pending()` instead.
+fn read(connection: &PgConnection, id: i32) -> Option<EmailChangeCode> {
+    dsl::email_change_codes
+        .find(id)
+        .first::<EmailChangeCode>(connection)
+        .ok()
+}
+
+// Creates an email change code for the user with the given ID, valid for 30 minutes. An existing
+// email change code for the user will be overwritten.
+fn create(
+    connection: &PgConnection,
+    id: i32,
+    email: &str,
+) -> Result<EmailChangeCode, EmailChangeCodeErrorKind> {
+    let code = random_code();
+    let expiration_time =
+        match chrono::Local::now().checked_add_signed(chrono::Duration::minutes(30)) {
+            Some(t) => t,
+            None => return Err(EmailChangeCodeErrorKind::ExpirationTimeOverflow),
+        }
+        .naive_local();
+
+    // There can only be one email change code per user. Insert a new record or update an existing
+    // record.
+    diesel::insert_into(dsl::email_change_codes)
+        .values((
+            dsl::id.eq(id),
+            dsl::email.eq(email),
+            dsl::code.eq(code),
+            dsl::expiration_time.eq(expiration_time),
+            dsl::attempts.eq(0),
+        ))
+        .on_conflict(dsl::id)
+        .do_update()
+        .set((
+            dsl::email.eq(email),
+            dsl::code.eq(code),
+            dsl::expiration_time.eq(expiration_time),
+            dsl::attempts.eq(0),
+        ))
+        .returning((
+            dsl::id,
+            dsl::email,
+            dsl::code,
+            dsl::expiration_time,
+            dsl::attempts,
+        ))
+        .get_result(connection)
+        .map_err(EmailChangeCodeErrorKind::CreationFailed)
+}
+
+// Returns a random code between MIN_VALUE and MAX_VALUE.
+fn random_code() -> i32 {
+    thread_rng().gen_range(MIN_VALUE, MAX_VALUE)
+}
+
+// Increases the attempt counter.
+//
+// To prevent brute forcing the email change code we only allow a limited number of validation
+// attempts.
+fn increase_attempt_counter(
+    connection: &PgConnection,
+    email_change_code: EmailChangeCode,
+) -> Result<EmailChangeCode, EmailChangeCodeErrorKind> {
+    // If the number of attempts have already exceeded the limit previously, don't bother to
+    // increase the counter but exit early.
+    if email_change_code.attempts_exceeded() {
+        return Err(EmailChangeCodeErrorKind::MaxAttemptsExceeded);
+    }
+
+    let email_change_code =
+        diesel::update(dsl::email_change_codes.filter(dsl::id.eq(email_change_code.id)))
+            .set(dsl::attempts.eq(dsl::attempts + 1))
+            .returning((
+                dsl::id,
+                dsl::email,
+                dsl::code,
+                dsl::expiration_time,
+                dsl::attempts,
+            ))
+            .get_result::<EmailChangeCode>(connection)
+            .map_err(EmailChangeCodeErrorKind::UpdateFailed)?;
+
+    if email_change_code.attempts_exceeded() {
+        return Err(EmailChangeCodeErrorKind::MaxAttemptsExceeded);
+    }
+
+    Ok(email_change_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{establish_connection, get_database_url, user};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::get().
+    #[test]
+    fn test_get() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = user::create(&connection, "test@example.com", "mypass", &config).unwrap();
+            assert!(get_pending(&connection, &user).is_none());
+
+            // Invalid email addresses are rejected.
+            assert_eq!(
+                EmailChangeCodeErrorKind::InvalidEmail("invalid".to_string()),
+                get(&connection, &user, "invalid").unwrap_err()
+            );
+
+            let email_change_code = get(&connection, &user, "new@example.com").unwrap();
+            assert_eq!(user.id, email_change_code.id);
+            assert_eq!("new@example.com", email_change_code.email);
+            assert!(MIN_VALUE <= email_change_code.code);
+            assert!(email_change_code.code <= MAX_VALUE);
+            assert_eq!(0, email_change_code.attempts);
+            assert_eq!(
+                Some(email_change_code.clone()),
+                get_pending(&connection, &user)
+            );
+
+            // Requesting the same address again returns the same code.
+            let retrieved = get(&connection, &user, "new@example.com").unwrap();
+            assert_eq!(email_change_code.code, retrieved.code);
+            assert_eq!(1, retrieved.attempts);
+
+            // Requesting a different address counts as an attempt too.
+            for attempts in 2..6 {
+                let retrieved = get(&connection, &user, "other@example.com").unwrap();
+                assert_eq!("other@example.com", retrieved.email);
+                assert_eq!(attempts, retrieved.attempts);
+            }
+            assert_eq!(
+                EmailChangeCodeErrorKind::MaxAttemptsExceeded,
+                get(&connection, &user, "new@example.com").unwrap_err()
+            );
+
+            // When the code has expired a fresh one is generated.
+            expire_email_change_code(&connection, user.id);
+            assert!(get_pending(&connection, &user).is_none());
+            let fresh_email_change_code = get(&connection, &user, "new@example.com").unwrap();
+            assert_eq!(0, fresh_email_change_code.attempts);
+
+            Ok(())
+        });
+    }
+
+    // Tests super::change_email().
+    #[test]
+    fn test_change_email() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = user::create(&connection, "test@example.com", "mypass", &config).unwrap();
+
+            // Without an email change code an `Expired` error is returned.
+            assert_eq!(
+                EmailChangeCodeErrorKind::Expired,
+                change_email(&connection, user.clone(), 0).unwrap_err()
+            );
+
+            // A wrong code is rejected and does not change the email address.
+            let email_change_code = get(&connection, &user, "new@example.com").unwrap();
+            assert_eq!(
+                EmailChangeCodeErrorKind::InvalidCode,
+                change_email(&connection, user.clone(), email_change_code.code + 1).unwrap_err()
+            );
+            assert!(user::read(&connection, "test@example.com").is_ok());
+
+            // If the new address has been taken in the meantime the change fails.
+            let other_user =
+                user::create(&connection, "new@example.com", "mypass", &config).unwrap();
+            assert_eq!(
+                EmailChangeCodeErrorKind::EmailUpdateFailed(
+                    UserErrorKind::UserWithEmailAlreadyExists("new@example.com".to_string())
+                ),
+                change_email(&connection, user.clone(), email_change_code.code).unwrap_err()
+            );
+            user::delete(&connection, other_user.email.as_str()).unwrap();
+
+            // With the correct code the email address is changed and the code is removed.
+            let user = change_email(&connection, user, email_change_code.code).unwrap();
+            assert_eq!("new@example.com", user.email);
+            assert!(read(&connection, user.id).is_none());
+
+            Ok(())
+        });
+    }
+
+    // Expire the email change code for the given user by updating the expired time in the database.
+    fn expire_email_change_code(connection: &PgConnection, id: i32) {
+        diesel::update(dsl::email_change_codes.filter(dsl::id.eq(id)))
+            .set(dsl::expiration_time.eq(chrono::Local::now().naive_local()))
+            .execute(connection)
+            .unwrap();
+    }
+}
@@ -1,65 +1,122 @@
 use super::category::Category;
-use super::schema::expenses;
+use super::ledger::{self, Ledger};
 use super::schema::expenses::dsl;
+use super::schema::{expense_splits, expenses};
 use super::user::User;
 use chrono::Utc;
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
+use diesel::sql_types::Text;
+use diesel::{dsl::exists, select};
+use diesel_full_text_search::{ts_rank, TsQuery, TsVectorExtensions};
 use rust_decimal::Decimal;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::fmt;
 
-#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize)]
+#[derive(Associations, Clone, Debug, JsonSchema, PartialEq, Queryable, Serialize)]
 #[belongs_to(Category, foreign_key = "id")]
 #[belongs_to(User, foreign_key = "id")]
+#[belongs_to(Ledger)]
 pub struct Expense {
     pub id: i32,
     pub amount: Decimal,
     pub description: Option<String>,
     pub category_id: i32,
-    pub user_id: i32,
+    /// The user who recorded the expense. This is `None` if the user has been deleted.
+    pub user_id: Option<i32>,
     pub date: chrono::NaiveDate,
+    pub ledger_id: i32,
+}
+
+// The columns that make up an `Expense`. The search vector is only used for querying and is left
+// out.
+pub(crate) type Columns = (
+    dsl::id,
+    dsl::amount,
+    dsl::description,
+    dsl::category_id,
+    dsl::user_id,
+    dsl::date,
+    dsl::ledger_id,
+);
+pub(crate) const COLUMNS: Columns = (
+    dsl::id,
+    dsl::amount,
+    dsl::description,
+    dsl::category_id,
+    dsl::user_id,
+    dsl::date,
+    dsl::ledger_id,
+);
+
+sql_function! {
+    // Converts a search query into a tsquery. This function is defined in the database so that it
+    // uses the same text search configuration as the search vector of the expenses.
+    fn expense_search_query(query: Text) -> TsQuery;
 }
 
 // Possible errors thrown when handling expenses.
 #[derive(Debug, PartialEq)]
 pub enum ExpenseErrorKind {
-    // A category was passed that belongs to the wrong user.
-    CategoryHasWrongUser,
+    // The category does not belong to the ledger of the expense.
+    CategoryHasWrongLedger(i32),
     // An expense could not be created due to a database error.
     CreationFailed(diesel::result::Error),
     // An expense could not be deleted due to a database error.
     DeletionFailed(diesel::result::Error),
+    // The user does not have the required role in the ledger the category belongs to.
+    InsufficientPermissions(i32),
     // The amount should be greater than 0.
     InvalidAmount,
+    // The amount of the expense cannot be changed because it is shared among ledger members.
+    IsShared(i32),
     // An expense does not exist.
     NotFound(i32),
     // A database error occurred while reading expenses.
     ReadFailed(diesel::result::Error),
+    // An expense could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
 }
 
 impl fmt::Display for ExpenseErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &*self {
-            ExpenseErrorKind::CategoryHasWrongUser => write!(f, "Category is from the wrong user",),
+            ExpenseErrorKind::CategoryHasWrongLedger(ref id) => {
+                write!(f, "Category should be in ledger {}", id)
+            }
             ExpenseErrorKind::CreationFailed(ref err) => {
                 write!(f, "Database error when creating expense: {}", err)
             }
             ExpenseErrorKind::DeletionFailed(ref err) => {
                 write!(f, "Database error when deleting expense: {}", err)
             }
+            ExpenseErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to add expenses to ledger {}",
+                id
+            ),
             ExpenseErrorKind::InvalidAmount => {
                 write!(f, "Amount should be between 0.01 and 9999999.99")
             }
+            ExpenseErrorKind::IsShared(ref id) => write!(
+                f,
+                "The amount of expense {} cannot be changed while it is shared",
+                id
+            ),
             ExpenseErrorKind::NotFound(ref id) => write!(f, "Expense {} not found", id),
             ExpenseErrorKind::ReadFailed(ref err) => {
                 write!(f, "Database error when reading expense: {}", err)
             }
+            ExpenseErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when updating expense: {}", err)
+            }
         }
     }
 }
 
-/// Creates an expense.
+/// Creates an expense in the ledger of the given category. The user needs to be an editor or owner
+/// of the ledger.
 pub fn create(
     connection: &PgConnection,
     user: &User,
@@ -68,14 +125,18 @@ pub fn create(
     description: Option<&str>,
     date: Option<&chrono::NaiveDate>,
 ) -> Result<Expense, ExpenseErrorKind> {
-    // Check that the category belongs to the same user.
-    if category.user_id != user.id {
-        return Err(ExpenseErrorKind::CategoryHasWrongUser);
+    // Check that the user is allowed to add expenses to the ledger of the category.
+    match ledger::get_role(connection, category.ledger_id, user.id) {
+        Ok(Some(role)) if role.can_edit() => {}
+        Ok(_) => {
+            return Err(ExpenseErrorKind::InsufficientPermissions(
+                category.ledger_id,
+            ))
+        }
+        Err(e) => return Err(ExpenseErrorKind::ReadFailed(e)),
     }
 
-    if *amount <= Decimal::new(0, 2) || *amount > Decimal::new(999_999_999, 2) {
-        return Err(ExpenseErrorKind::InvalidAmount);
-    }
+    validate_amount(amount)?;
 
     diesel::insert_into(dsl::expenses)
         .values((
@@ -84,22 +145,19 @@ pub fn create(
             dsl::category_id.eq(category.id),
             dsl::user_id.eq(user.id),
             dsl::date.eq(date.unwrap_or(&Utc::now().naive_utc().date())),
+            dsl::ledger_id.eq(category.ledger_id),
         ))
-        .returning((
-            dsl::id,
-            dsl::amount,
-            dsl::description,
-            dsl::category_id,
-            dsl::user_id,
-            dsl::date,
-        ))
+        .returning(COLUMNS)
         .get_result(connection)
         .map_err(ExpenseErrorKind::CreationFailed)
 }
 
 /// Retrieves the expense with the given ID.
 pub fn read(connection: &PgConnection, id: i32) -> Option<Expense> {
-    let expense = dsl::expenses.find(id).first::<Expense>(connection);
+    let expense = dsl::expenses
+        .find(id)
+        .select(COLUMNS)
+        .first::<Expense>(connection);
 
     match expense {
         Ok(c) => Some(c),
@@ -107,6 +165,51 @@ pub fn read(connection: &PgConnection, id: i32) -> Option<Expense> {
     }
 }
 
+/// Updates the given expense. The user needs to be an editor or owner of the ledger, and the new
+/// category should be in the same ledger. The amount of a shared expense cannot be changed since
+/// this would invalidate the shares.
+pub fn update(
+    connection: &PgConnection,
+    user: &User,
+    expense: &Expense,
+    amount: &Decimal,
+    category: &Category,
+    description: Option<&str>,
+    date: &chrono::NaiveDate,
+) -> Result<Expense, ExpenseErrorKind> {
+    match ledger::get_role(connection, expense.ledger_id, user.id) {
+        Ok(Some(role)) if role.can_edit() => {}
+        Ok(_) => return Err(ExpenseErrorKind::InsufficientPermissions(expense.ledger_id)),
+        Err(e) => return Err(ExpenseErrorKind::ReadFailed(e)),
+    }
+
+    if category.ledger_id != expense.ledger_id {
+        return Err(ExpenseErrorKind::CategoryHasWrongLedger(expense.ledger_id));
+    }
+
+    validate_amount(amount)?;
+
+    if *amount != expense.amount {
+        let is_shared: bool = select(exists(expense_splits::table.find(expense.id)))
+            .get_result(connection)
+            .map_err(ExpenseErrorKind::ReadFailed)?;
+        if is_shared {
+            return Err(ExpenseErrorKind::IsShared(expense.id));
+        }
+    }
+
+    diesel::update(dsl::expenses.find(expense.id))
+        .set((
+            dsl::amount.eq(amount),
+            dsl::description.eq(description),
+            dsl::category_id.eq(category.id),
+            dsl::date.eq(date),
+        ))
+        .returning(COLUMNS)
+        .get_result(connection)
+        .map_err(ExpenseErrorKind::UpdateFailed)
+}
+
 /// Deletes the expense with the given ID.
 pub fn delete(connection: &PgConnection, id: i32) -> Result<(), ExpenseErrorKind> {
     let result = diesel::delete(dsl::expenses.filter(dsl::id.eq(id))).execute(connection);
@@ -129,13 +232,74 @@ pub fn list(
     let result = match user_id {
         Some(user_id) => dsl::expenses
             .filter(expenses::user_id.eq(&user_id))
+            .select(COLUMNS)
             .load::<Expense>(connection),
-        None => dsl::expenses.load::<Expense>(connection),
+        None => dsl::expenses.select(COLUMNS).load::<Expense>(connection),
     };
 
     result.map_err(ExpenseErrorKind::ReadFailed)
 }
 
+/// Returns a page of the expenses in the given ledger. The most recent expenses are returned
+/// first.
+pub fn list_by_ledger(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Expense>, ExpenseErrorKind> {
+    dsl::expenses
+        .select(COLUMNS)
+        .filter(dsl::ledger_id.eq(ledger.id))
+        .order((dsl::date.desc(), dsl::id.desc()))
+        .limit(limit)
+        .offset(offset)
+        .load::<Expense>(connection)
+        .map_err(ExpenseErrorKind::ReadFailed)
+}
+
+/// Counts the expenses in the given ledger.
+pub fn count_by_ledger(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<i64, ExpenseErrorKind> {
+    dsl::expenses
+        .filter(dsl::ledger_id.eq(ledger.id))
+        .select(diesel::dsl::count_star())
+        .first(connection)
+        .map_err(ExpenseErrorKind::ReadFailed)
+}
+
+/// Searches expenses by their description and the names of their category and parent categories,
+/// optionally filtered by ledger. The best matches are returned first.
+pub fn search(
+    connection: &PgConnection,
+    query: &str,
+    ledger: Option<&Ledger>,
+) -> Result<Vec<Expense>, ExpenseErrorKind> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut statement = dsl::expenses
+        .select(COLUMNS)
+        .filter(dsl::search_vector.matches(expense_search_query(query)))
+        .into_boxed();
+    if let Some(ledger) = ledger {
+        statement = statement.filter(dsl::ledger_id.eq(ledger.id));
+    }
+
+    statement
+        .order((
+            ts_rank(dsl::search_vector, expense_search_query(query)).desc(),
+            dsl::date.desc(),
+            dsl::id.desc(),
+        ))
+        .load::<Expense>(connection)
+        .map_err(ExpenseErrorKind::ReadFailed)
+}
+
 /// Counts all expenses, optionally filtered by user ID.
 pub fn count(connection: &PgConnection, user_id: Option<i32>) -> Result<i64, ExpenseErrorKind> {
     let result = match user_id {
@@ -151,10 +315,19 @@ pub fn count(connection: &PgConnection, user_id: Option<i32>) -> Result<i64, Exp
     result.map_err(ExpenseErrorKind::ReadFailed)
 }
 
+// Checks that the given amount is between 0.01 and 9999999.99.
+fn validate_amount(amount: &Decimal) -> Result<(), ExpenseErrorKind> {
+    if *amount <= Decimal::new(0, 2) || *amount > Decimal::new(999_999_999, 2) {
+        return Err(ExpenseErrorKind::InvalidAmount);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::db_test::*;
+    use crate::ledger::Role;
     use crate::{establish_connection, get_database_url};
     use app::AppConfig;
     use diesel::result::Error;
@@ -234,7 +407,7 @@ mod tests {
         });
     }
 
-    // Test that an error is returned when passing in a category from a different user.
+    // Test that an error is returned when passing in a category from a ledger the user cannot edit.
     #[test]
     fn test_create_with_invalid_category() {
         let connection = establish_connection(&get_database_url()).unwrap();
@@ -246,22 +419,33 @@ mod tests {
 
             // Create a different user that owns the category being passed in.
             let other_user = create_test_user(&connection, &config);
-            let other_user_cat =
-                crate::category::create(&connection, &other_user, "Utilities", None, None).unwrap();
+            let other_user_cat = create_test_category(&connection, &other_user);
+            let amount = Decimal::from_str("22.02").unwrap();
+
+            // Try creating an expense using a category belonging to a ledger of a different user.
+            // This should result in an error.
+            let result =
+                create(&connection, &user, &amount, &other_user_cat, None, None).unwrap_err();
+            assert_eq!(
+                ExpenseErrorKind::InsufficientPermissions(other_user_cat.ledger_id),
+                result
+            );
 
-            // Try creating an expense using a category belonging to a different user. This should
-            // result in an error.
-            let result = create(
-                &connection,
-                &user,
-                &Decimal::from_str("22.02").unwrap(),
-                &other_user_cat,
-                None,
-                None,
-            )
-            .unwrap_err();
+            // Viewers of the ledger are not allowed to add expenses either.
+            let ledger = ledger::read(&connection, other_user_cat.ledger_id, None).unwrap();
+            ledger::add_member(&connection, &ledger, &user, Role::Viewer).unwrap();
+            let result =
+                create(&connection, &user, &amount, &other_user_cat, None, None).unwrap_err();
+            assert_eq!(
+                ExpenseErrorKind::InsufficientPermissions(other_user_cat.ledger_id),
+                result
+            );
 
-            assert_eq!(ExpenseErrorKind::CategoryHasWrongUser, result);
+            // Editors are allowed to add expenses to the shared ledger.
+            ledger::set_role(&connection, &ledger, user.id, Role::Editor).unwrap();
+            let expense = create(&connection, &user, &amount, &other_user_cat, None, None).unwrap();
+            assert_eq!(Some(user.id), expense.user_id);
+            assert_eq!(ledger.id, expense.ledger_id);
 
             Ok(())
         });
@@ -367,6 +551,215 @@ mod tests {
         });
     }
 
+    // Tests super::search().
+    #[test]
+    fn test_search() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = ledger::get_default(&conn, &user).unwrap();
+            let health =
+                crate::category::create(&conn, &user, &ledger, "Health", None, None).unwrap();
+            let dentist =
+                crate::category::create(&conn, &user, &ledger, "Dentist", None, Some(&health))
+                    .unwrap();
+            let food = crate::category::create(&conn, &user, &ledger, "Food", None, None).unwrap();
+            let amount = Decimal::new(4500, 2);
+            let checkup = create(
+                &conn,
+                &user,
+                &amount,
+                &dentist,
+                Some("Yearly checkup"),
+                None,
+            )
+            .unwrap();
+            let toothpaste = create(
+                &conn,
+                &user,
+                &amount,
+                &food,
+                Some("Toothpaste for the dentists"),
+                None,
+            )
+            .unwrap();
+            let lunch = create(&conn, &user, &amount, &food, Some("Lunch"), None).unwrap();
+
+            // An empty query does not return any results.
+            assert!(search(&conn, "  ", None).unwrap().is_empty());
+
+            // Descriptions are searched using stemming. Matches in the description rank higher
+            // than matches in the category name.
+            assert_eq!(
+                vec![toothpaste.clone(), checkup.clone()],
+                search(&conn, "dentist", Some(&ledger)).unwrap()
+            );
+            assert_eq!(vec![lunch.clone()], search(&conn, "lunches", None).unwrap());
+
+            // The names of the parent categories are included.
+            assert_eq!(
+                vec![checkup.clone()],
+                search(&conn, "health checkup", Some(&ledger)).unwrap()
+            );
+
+            // Renaming a category updates the search results.
+            diesel::update(crate::schema::categories::table.find(health.id))
+                .set(crate::schema::categories::name.eq("Medical"))
+                .execute(&conn)?;
+            assert!(search(&conn, "health", Some(&ledger)).unwrap().is_empty());
+            assert_eq!(
+                vec![checkup],
+                search(&conn, "medical", Some(&ledger)).unwrap()
+            );
+
+            // Results can be limited to a ledger.
+            let other_ledger = create_test_ledger(&conn, &user);
+            assert!(search(&conn, "lunch", Some(&other_ledger))
+                .unwrap()
+                .is_empty());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::list_by_ledger() and super::count_by_ledger().
+    #[test]
+    fn test_list_by_ledger() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = ledger::get_default(&conn, &user).unwrap();
+            let cat = create_test_category(&conn, &user);
+            assert!(list_by_ledger(&conn, &ledger, 10, 0).unwrap().is_empty());
+            assert_eq!(0, count_by_ledger(&conn, &ledger).unwrap());
+
+            // Expenses are returned with the most recent ones first.
+            let amount = Decimal::new(1000, 2);
+            let dates = ["2020-03-01", "2020-01-01", "2020-02-01"];
+            let expenses: Vec<Expense> = dates
+                .iter()
+                .map(|date| {
+                    let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                    create(&conn, &user, &amount, &cat, None, Some(&date)).unwrap()
+                })
+                .collect();
+            assert_eq!(3, count_by_ledger(&conn, &ledger).unwrap());
+            assert_eq!(
+                vec![expenses[0].clone(), expenses[2].clone()],
+                list_by_ledger(&conn, &ledger, 2, 0).unwrap()
+            );
+            assert_eq!(
+                vec![expenses[1].clone()],
+                list_by_ledger(&conn, &ledger, 2, 2).unwrap()
+            );
+
+            // Expenses in other ledgers are not included.
+            let other_ledger = create_test_ledger(&conn, &user);
+            assert!(list_by_ledger(&conn, &other_ledger, 10, 0)
+                .unwrap()
+                .is_empty());
+            assert_eq!(0, count_by_ledger(&conn, &other_ledger).unwrap());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::update().
+    #[test]
+    fn test_update() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let cat = create_test_category(&conn, &user);
+            let other_cat = create_test_category(&conn, &user);
+            let expense = create_test_expense(&conn, &user, &cat);
+
+            let amount = Decimal::new(4250, 2);
+            let date = chrono::NaiveDate::from_ymd(2021, 4, 1);
+            let updated = update(
+                &conn,
+                &user,
+                &expense,
+                &amount,
+                &other_cat,
+                Some("Groceries"),
+                &date,
+            )
+            .unwrap();
+            assert_expense(
+                &updated,
+                Some(expense.id),
+                &amount,
+                Some("Groceries"),
+                other_cat.id,
+                user.id,
+                date,
+            );
+            assert_eq!(Some(updated.clone()), read(&conn, expense.id));
+
+            // The amount is validated.
+            let result = update(
+                &conn,
+                &user,
+                &updated,
+                &Decimal::new(0, 2),
+                &other_cat,
+                None,
+                &date,
+            );
+            assert_eq!(ExpenseErrorKind::InvalidAmount, result.unwrap_err());
+
+            // The category should be in the same ledger.
+            let other_ledger = create_test_ledger(&conn, &user);
+            let foreign_cat =
+                crate::category::create(&conn, &user, &other_ledger, "Travel", None, None).unwrap();
+            let result = update(&conn, &user, &updated, &amount, &foreign_cat, None, &date);
+            assert_eq!(
+                ExpenseErrorKind::CategoryHasWrongLedger(updated.ledger_id),
+                result.unwrap_err()
+            );
+
+            // Only editors and owners of the ledger can update expenses.
+            let other_user = create_test_user(&conn, &config);
+            let result = update(&conn, &other_user, &updated, &amount, &cat, None, &date);
+            assert_eq!(
+                ExpenseErrorKind::InsufficientPermissions(updated.ledger_id),
+                result.unwrap_err()
+            );
+
+            // The amount of a shared expense cannot be changed, but the other fields can.
+            crate::expense_share::split(
+                &conn,
+                &user,
+                &updated,
+                user.id,
+                crate::expense_share::SplitMethod::Equal,
+                &[(user.id, None)],
+            )
+            .unwrap();
+            let result = update(
+                &conn,
+                &user,
+                &updated,
+                &Decimal::new(1, 2),
+                &cat,
+                None,
+                &date,
+            );
+            assert_eq!(ExpenseErrorKind::IsShared(updated.id), result.unwrap_err());
+            let updated = update(&conn, &user, &updated, &amount, &cat, None, &date).unwrap();
+            assert_eq!(cat.id, updated.category_id);
+
+            Ok(())
+        });
+    }
+
     // Tests super::delete().
     #[test]
     fn test_delete() {
@@ -410,7 +803,7 @@ mod tests {
         description: Option<&str>,
         // The expected category ID.
         category_id: i32,
-        // The expected user ID of the category owner.
+        // The expected ID of the user that created the expense.
         user_id: i32,
         // The expected date.
         date: chrono::NaiveDate,
@@ -421,7 +814,7 @@ mod tests {
         assert_eq!(*amount, expense.amount);
         assert_eq!(description.map(|d| d.to_string()), expense.description);
         assert_eq!(category_id, expense.category_id);
-        assert_eq!(user_id, expense.user_id);
+        assert_eq!(Some(user_id), expense.user_id);
         assert_eq!(date, expense.date);
     }
 
@@ -0,0 +1,554 @@
+use super::expense::Expense;
+use super::ledger;
+use super::schema::{expense_shares, expense_splits};
+use super::user::User;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Write;
+use std::{fmt, str::FromStr};
+
+/// The way the amount of a shared expense is divided among the participants.
+#[derive(AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum SplitMethod {
+    // The amount is divided equally among the participants.
+    Equal,
+    // Every participant pays a percentage of the amount.
+    Percentage,
+    // Every participant pays an exact amount.
+    Exact,
+}
+
+impl SplitMethod {
+    /// Returns the machine name of the split method, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::expense_share::SplitMethod;
+    /// #
+    /// assert_eq!("equal", SplitMethod::Equal.as_str());
+    /// assert_eq!("percentage", SplitMethod::Percentage.as_str());
+    /// assert_eq!("exact", SplitMethod::Exact.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SplitMethod::Equal => "equal",
+            SplitMethod::Percentage => "percentage",
+            SplitMethod::Exact => "exact",
+        }
+    }
+}
+
+impl fmt::Display for SplitMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SplitMethod {
+    type Err = ExpenseShareErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "equal" => Ok(SplitMethod::Equal),
+            "percentage" => Ok(SplitMethod::Percentage),
+            "exact" => Ok(SplitMethod::Exact),
+            _ => Err(ExpenseShareErrorKind::InvalidMethod(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for SplitMethod {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for SplitMethod {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let method = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        method
+            .parse()
+            .map_err(|e: ExpenseShareErrorKind| e.to_string().into())
+    }
+}
+
+/// Records who paid a shared expense and how it is split.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct ExpenseSplit {
+    pub expense_id: i32,
+    /// The user who paid the expense. This is `None` if the user has been deleted.
+    pub paid_by: Option<i32>,
+    pub method: SplitMethod,
+}
+
+/// The part of a shared expense that is owed by one of the participants.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct ExpenseShare {
+    pub expense_id: i32,
+    /// The participant. This is `None` if the user has been deleted.
+    pub user_id: Option<i32>,
+    pub amount: Decimal,
+}
+
+// Possible errors thrown when handling shared expenses.
+#[derive(Debug, PartialEq)]
+pub enum ExpenseShareErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // A user has been passed more than once as a participant.
+    DuplicateParticipant(i32),
+    // The user does not have the required role in the ledger of the expense.
+    InsufficientPermissions(i32),
+    // The passed in split method does not exist.
+    InvalidMethod(String),
+    // The share of a participant is missing or negative.
+    InvalidShare(i32),
+    // An expense needs to be shared with at least one participant.
+    NoParticipants,
+    // The user is not a member of the ledger of the expense.
+    NotAMember(i32),
+    // The expense is not shared.
+    NotFound(i32),
+    // The shares do not add up to the expected total. Contains the actual and expected totals.
+    SharesDoNotAddUp(Decimal, Decimal),
+}
+
+impl fmt::Display for ExpenseShareErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExpenseShareErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            ExpenseShareErrorKind::DuplicateParticipant(ref id) => {
+                write!(f, "User {} participates more than once", id)
+            }
+            ExpenseShareErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to share expenses in ledger {}",
+                id
+            ),
+            ExpenseShareErrorKind::InvalidMethod(ref method) => {
+                write!(f, "Invalid split method: {}", method)
+            }
+            ExpenseShareErrorKind::InvalidShare(ref id) => {
+                write!(f, "The share of user {} is missing or negative", id)
+            }
+            ExpenseShareErrorKind::NoParticipants => {
+                write!(
+                    f,
+                    "An expense should be shared with at least one participant"
+                )
+            }
+            ExpenseShareErrorKind::NotAMember(ref id) => {
+                write!(f, "User {} is not a member of the ledger", id)
+            }
+            ExpenseShareErrorKind::NotFound(ref id) => write!(f, "Expense {} is not shared", id),
+            ExpenseShareErrorKind::SharesDoNotAddUp(ref actual, ref expected) => {
+                write!(f, "The shares add up to {} instead of {}", actual, expected)
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ExpenseShareErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        ExpenseShareErrorKind::DatabaseError(e)
+    }
+}
+
+/// Divides the given amount among the participants. Every participant is passed as a tuple of the
+/// user ID and an optional value. The value is ignored for equal splits, and contains the
+/// percentage or the exact amount for the other split methods. Cents that cannot be divided evenly
+/// are assigned to the first participants.
+///
+/// # Example
+///
+/// ```
+/// # use db::expense_share::{compute_shares, SplitMethod};
+/// # use rust_decimal::Decimal;
+/// #
+/// let amount = Decimal::new(1000, 2);
+///
+/// let shares = compute_shares(&amount, SplitMethod::Equal, &[(1, None), (2, None), (3, None)]);
+/// assert_eq!(
+///     Ok(vec![
+///         (1, Decimal::new(334, 2)),
+///         (2, Decimal::new(333, 2)),
+///         (3, Decimal::new(333, 2)),
+///     ]),
+///     shares
+/// );
+///
+/// let percentages = [(1, Some(Decimal::new(75, 0))), (2, Some(Decimal::new(25, 0)))];
+/// let shares = compute_shares(&amount, SplitMethod::Percentage, &percentages);
+/// assert_eq!(
+///     Ok(vec![(1, Decimal::new(750, 2)), (2, Decimal::new(250, 2))]),
+///     shares
+/// );
+/// ```
+pub fn compute_shares(
+    amount: &Decimal,
+    method: SplitMethod,
+    participants: &[(i32, Option<Decimal>)],
+) -> Result<Vec<(i32, Decimal)>, ExpenseShareErrorKind> {
+    if participants.is_empty() {
+        return Err(ExpenseShareErrorKind::NoParticipants);
+    }
+
+    let mut seen = HashSet::new();
+    for (user_id, _) in participants {
+        if !seen.insert(user_id) {
+            return Err(ExpenseShareErrorKind::DuplicateParticipant(*user_id));
+        }
+    }
+
+    // Returns the value of a participant, which is required for percentage and exact splits.
+    let value = |(user_id, value): &(i32, Option<Decimal>)| match value {
+        Some(value) if !value.is_sign_negative() => Ok(*value),
+        _ => Err(ExpenseShareErrorKind::InvalidShare(*user_id)),
+    };
+
+    let mut shares: Vec<(i32, Decimal)> = match method {
+        SplitMethod::Equal => {
+            let count = Decimal::from(participants.len() as u64);
+            let share = (amount / count).round_dp_with_strategy(2, RoundingStrategy::RoundDown);
+            participants.iter().map(|(id, _)| (*id, share)).collect()
+        }
+        SplitMethod::Percentage => {
+            let mut total = Decimal::zero();
+            let mut shares = vec![];
+            for participant in participants {
+                let percentage = value(participant)?;
+                total += percentage;
+                shares.push((
+                    participant.0,
+                    (amount * percentage / Decimal::new(100, 0))
+                        .round_dp_with_strategy(2, RoundingStrategy::RoundDown),
+                ));
+            }
+            if total != Decimal::new(100, 0) {
+                return Err(ExpenseShareErrorKind::SharesDoNotAddUp(
+                    total,
+                    Decimal::new(100, 0),
+                ));
+            }
+            shares
+        }
+        SplitMethod::Exact => {
+            let shares = participants
+                .iter()
+                .map(|participant| Ok((participant.0, value(participant)?)))
+                .collect::<Result<Vec<(i32, Decimal)>, ExpenseShareErrorKind>>()?;
+            let total: Decimal = shares.iter().map(|(_, share)| *share).sum();
+            if total != *amount {
+                return Err(ExpenseShareErrorKind::SharesDoNotAddUp(total, *amount));
+            }
+            shares
+        }
+    };
+
+    // Distribute the cents that have been lost while rounding down.
+    let cent = Decimal::new(1, 2);
+    let mut remainder = amount - shares.iter().map(|(_, share)| *share).sum::<Decimal>();
+    for (_, share) in shares.iter_mut() {
+        if remainder < cent {
+            break;
+        }
+        *share += cent;
+        remainder -= cent;
+    }
+
+    Ok(shares)
+}
+
+/// Marks the given expense as paid by the given user and shares it among the participants. Any
+/// previous split of the expense is replaced. The user, payer and participants need to be members
+/// of the ledger of the expense, and the user needs to be an editor or owner.
+pub fn split(
+    connection: &PgConnection,
+    user: &User,
+    expense: &Expense,
+    paid_by: i32,
+    method: SplitMethod,
+    participants: &[(i32, Option<Decimal>)],
+) -> Result<Vec<ExpenseShare>, ExpenseShareErrorKind> {
+    match ledger::get_role(connection, expense.ledger_id, user.id)? {
+        Some(role) if role.can_edit() => {}
+        _ => {
+            return Err(ExpenseShareErrorKind::InsufficientPermissions(
+                expense.ledger_id,
+            ))
+        }
+    }
+
+    for user_id in participants.iter().map(|(id, _)| id).chain(Some(&paid_by)) {
+        if ledger::get_role(connection, expense.ledger_id, *user_id)?.is_none() {
+            return Err(ExpenseShareErrorKind::NotAMember(*user_id));
+        }
+    }
+
+    let shares = compute_shares(&expense.amount, method, participants)?;
+
+    connection.transaction(|| {
+        diesel::delete(expense_splits::table.find(expense.id)).execute(connection)?;
+        diesel::insert_into(expense_splits::table)
+            .values((
+                expense_splits::expense_id.eq(expense.id),
+                expense_splits::paid_by.eq(paid_by),
+                expense_splits::method.eq(method),
+            ))
+            .execute(connection)?;
+
+        let values: Vec<_> = shares
+            .iter()
+            .map(|(user_id, amount)| {
+                (
+                    expense_shares::expense_id.eq(expense.id),
+                    expense_shares::user_id.eq(user_id),
+                    expense_shares::amount.eq(amount),
+                )
+            })
+            .collect();
+        Ok(diesel::insert_into(expense_shares::table)
+            .values(&values)
+            .returning((
+                expense_shares::expense_id,
+                expense_shares::user_id,
+                expense_shares::amount,
+            ))
+            .get_results(connection)?)
+    })
+}
+
+/// Retrieves the split and the shares of the expense with the given ID, if it is shared.
+pub fn read(
+    connection: &PgConnection,
+    expense_id: i32,
+) -> Option<(ExpenseSplit, Vec<ExpenseShare>)> {
+    let split = expense_splits::table
+        .find(expense_id)
+        .first::<ExpenseSplit>(connection)
+        .ok()?;
+    let shares = expense_shares::table
+        .filter(expense_shares::expense_id.eq(expense_id))
+        .select((
+            expense_shares::expense_id,
+            expense_shares::user_id,
+            expense_shares::amount,
+        ))
+        .order(expense_shares::user_id)
+        .load::<ExpenseShare>(connection)
+        .ok()?;
+
+    Some((split, shares))
+}
+
+/// Stops sharing the expense with the given ID.
+pub fn delete(connection: &PgConnection, expense_id: i32) -> Result<(), ExpenseShareErrorKind> {
+    let result = diesel::delete(expense_splits::table.find(expense_id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(ExpenseShareErrorKind::NotFound(expense_id));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::ledger::Role;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::compute_shares().
+    #[test]
+    fn test_compute_shares() {
+        let amount = Decimal::new(10000, 2);
+        let d = |value: i64| Some(Decimal::new(value, 2));
+
+        let test_cases = vec![
+            // An expense should be shared with at least one participant.
+            (
+                SplitMethod::Equal,
+                vec![],
+                Err(ExpenseShareErrorKind::NoParticipants),
+            ),
+            // Participants cannot be passed twice.
+            (
+                SplitMethod::Equal,
+                vec![(1, None), (1, None)],
+                Err(ExpenseShareErrorKind::DuplicateParticipant(1)),
+            ),
+            // Leftover cents are assigned to the first participants.
+            (
+                SplitMethod::Equal,
+                vec![(1, None), (2, None), (3, None)],
+                Ok(vec![
+                    (1, Decimal::new(3334, 2)),
+                    (2, Decimal::new(3333, 2)),
+                    (3, Decimal::new(3333, 2)),
+                ]),
+            ),
+            (
+                SplitMethod::Percentage,
+                vec![(1, d(3333)), (2, d(3333)), (3, d(3334))],
+                Ok(vec![
+                    (1, Decimal::new(3333, 2)),
+                    (2, Decimal::new(3333, 2)),
+                    (3, Decimal::new(3334, 2)),
+                ]),
+            ),
+            // Percentages should add up to 100.
+            (
+                SplitMethod::Percentage,
+                vec![(1, d(5000)), (2, d(4000))],
+                Err(ExpenseShareErrorKind::SharesDoNotAddUp(
+                    Decimal::new(9000, 2),
+                    Decimal::new(100, 0),
+                )),
+            ),
+            // Values are required for percentage and exact splits.
+            (
+                SplitMethod::Percentage,
+                vec![(1, d(10000)), (2, None)],
+                Err(ExpenseShareErrorKind::InvalidShare(2)),
+            ),
+            (
+                SplitMethod::Exact,
+                vec![(1, d(-100)), (2, d(10100))],
+                Err(ExpenseShareErrorKind::InvalidShare(1)),
+            ),
+            (
+                SplitMethod::Exact,
+                vec![(1, d(2550)), (2, d(7450))],
+                Ok(vec![(1, Decimal::new(2550, 2)), (2, Decimal::new(7450, 2))]),
+            ),
+            // Exact shares should add up to the amount.
+            (
+                SplitMethod::Exact,
+                vec![(1, d(2550)), (2, d(7000))],
+                Err(ExpenseShareErrorKind::SharesDoNotAddUp(
+                    Decimal::new(9550, 2),
+                    amount,
+                )),
+            ),
+        ];
+
+        for (method, participants, expected) in test_cases {
+            assert_eq!(expected, compute_shares(&amount, method, &participants));
+        }
+    }
+
+    // Tests super::split(), super::read() and super::delete().
+    #[test]
+    fn test_split() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let editor = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let stranger = create_test_user(&conn, &config);
+            let ledger = ledger::get_default(&conn, &owner).unwrap();
+            ledger::add_member(&conn, &ledger, &editor, Role::Editor).unwrap();
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+
+            let cat = create_test_category(&conn, &owner);
+            let amount = Decimal::new(9000, 2);
+            let expense = crate::expense::create(&conn, &owner, &amount, &cat, None, None).unwrap();
+            assert_eq!(None, read(&conn, expense.id));
+
+            // Viewers and strangers cannot share expenses.
+            let participants = [(owner.id, None), (editor.id, None), (viewer.id, None)];
+            for user in &[&viewer, &stranger] {
+                assert_eq!(
+                    Err(ExpenseShareErrorKind::InsufficientPermissions(ledger.id)),
+                    split(
+                        &conn,
+                        user,
+                        &expense,
+                        owner.id,
+                        SplitMethod::Equal,
+                        &participants
+                    )
+                );
+            }
+
+            // Only members can participate.
+            assert_eq!(
+                Err(ExpenseShareErrorKind::NotAMember(stranger.id)),
+                split(
+                    &conn,
+                    &editor,
+                    &expense,
+                    stranger.id,
+                    SplitMethod::Equal,
+                    &participants
+                )
+            );
+
+            let shares = split(
+                &conn,
+                &editor,
+                &expense,
+                owner.id,
+                SplitMethod::Equal,
+                &participants,
+            )
+            .unwrap();
+            assert_eq!(3, shares.len());
+            assert!(shares.iter().all(|s| s.amount == Decimal::new(3000, 2)));
+
+            // Splitting again replaces the previous split.
+            let participants = [(editor.id, Some(Decimal::new(9000, 2)))];
+            split(
+                &conn,
+                &owner,
+                &expense,
+                owner.id,
+                SplitMethod::Exact,
+                &participants,
+            )
+            .unwrap();
+            let (expense_split, shares) = read(&conn, expense.id).unwrap();
+            assert_eq!(
+                ExpenseSplit {
+                    expense_id: expense.id,
+                    paid_by: Some(owner.id),
+                    method: SplitMethod::Exact,
+                },
+                expense_split
+            );
+            assert_eq!(
+                vec![ExpenseShare {
+                    expense_id: expense.id,
+                    user_id: Some(editor.id),
+                    amount,
+                }],
+                shares
+            );
+
+            assert!(delete(&conn, expense.id).is_ok());
+            assert_eq!(None, read(&conn, expense.id));
+            assert_eq!(
+                Err(ExpenseShareErrorKind::NotFound(expense.id)),
+                delete(&conn, expense.id)
+            );
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,196 @@
+use super::api_token::{self, ApiToken, ApiTokenErrorKind};
+use super::category::{self, Categories, CategoryErrorKind};
+use super::expense::{self, Expense, ExpenseErrorKind};
+use super::ledger::{self, Ledger, LedgerErrorKind, Role};
+use super::two_factor::{self, TwoFactorErrorKind};
+use super::user::User;
+use super::user_identity::{self, UserIdentity, UserIdentityErrorKind};
+use super::user_preference::{self, UserPreferenceErrorKind, UserPreferences};
+use super::user_session::{self, UserSession, UserSessionErrorKind};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use serde::Serialize;
+use std::fmt;
+
+/// All data that is stored about a user, as it is offered for download to the user.
+#[derive(Debug, Serialize)]
+pub struct UserExport {
+    pub exported: NaiveDateTime,
+    pub account: AccountExport,
+    pub preferences: UserPreferences,
+    pub ledgers: Vec<LedgerExport>,
+    pub expenses: Vec<Expense>,
+    pub sessions: Vec<UserSession>,
+    pub api_tokens: Vec<ApiToken>,
+    pub identities: Vec<UserIdentity>,
+}
+
+/// The account details of a user. The password hash is not included.
+#[derive(Debug, Serialize)]
+pub struct AccountExport {
+    pub email: String,
+    pub created: NaiveDateTime,
+    pub activated: bool,
+    pub two_factor_enabled: bool,
+}
+
+/// A ledger the user is a member of, with the user's role and the tree of categories.
+#[derive(Debug, Serialize)]
+pub struct LedgerExport {
+    pub ledger: Ledger,
+    pub role: Role,
+    pub categories: Vec<Categories>,
+}
+
+// Possible errors thrown when exporting the data of a user.
+#[derive(Debug, PartialEq)]
+pub enum ExportErrorKind {
+    // The API tokens could not be read.
+    ApiTokensReadFailed(ApiTokenErrorKind),
+    // The categories could not be read.
+    CategoriesReadFailed(CategoryErrorKind),
+    // The expenses could not be read.
+    ExpensesReadFailed(ExpenseErrorKind),
+    // The linked identities could not be read.
+    IdentitiesReadFailed(UserIdentityErrorKind),
+    // The ledgers could not be read.
+    LedgersReadFailed(LedgerErrorKind),
+    // The preferences could not be read.
+    PreferencesReadFailed(UserPreferenceErrorKind),
+    // The sessions could not be read.
+    SessionsReadFailed(UserSessionErrorKind),
+    // The two-factor authentication status could not be read.
+    TwoFactorReadFailed(TwoFactorErrorKind),
+}
+
+impl fmt::Display for ExportErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExportErrorKind::ApiTokensReadFailed(ref err) => {
+                write!(f, "API tokens could not be exported: {}", err)
+            }
+            ExportErrorKind::CategoriesReadFailed(ref err) => {
+                write!(f, "Categories could not be exported: {}", err)
+            }
+            ExportErrorKind::ExpensesReadFailed(ref err) => {
+                write!(f, "Expenses could not be exported: {}", err)
+            }
+            ExportErrorKind::IdentitiesReadFailed(ref err) => {
+                write!(f, "Linked identities could not be exported: {}", err)
+            }
+            ExportErrorKind::LedgersReadFailed(ref err) => {
+                write!(f, "Ledgers could not be exported: {}", err)
+            }
+            ExportErrorKind::PreferencesReadFailed(ref err) => {
+                write!(f, "Preferences could not be exported: {}", err)
+            }
+            ExportErrorKind::SessionsReadFailed(ref err) => {
+                write!(f, "Sessions could not be exported: {}", err)
+            }
+            ExportErrorKind::TwoFactorReadFailed(ref err) => write!(
+                f,
+                "Two-factor authentication status could not be exported: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Collects all data that is stored about the given user.
+pub fn get(connection: &PgConnection, user: &User) -> Result<UserExport, ExportErrorKind> {
+    let account = AccountExport {
+        email: user.email.clone(),
+        created: user.created,
+        activated: user.activated,
+        two_factor_enabled: two_factor::is_enabled(connection, user)
+            .map_err(ExportErrorKind::TwoFactorReadFailed)?,
+    };
+
+    let ledgers = ledger::get_ledgers(connection, user)
+        .map_err(ExportErrorKind::LedgersReadFailed)?
+        .into_iter()
+        .map(|(ledger, role)| {
+            let categories = category::get_categories_tree(connection, &ledger)
+                .map_err(ExportErrorKind::CategoriesReadFailed)?
+                .children;
+            Ok(LedgerExport {
+                ledger,
+                role,
+                categories,
+            })
+        })
+        .collect::<Result<Vec<LedgerExport>, ExportErrorKind>>()?;
+
+    Ok(UserExport {
+        exported: chrono::Local::now().naive_local(),
+        account,
+        preferences: user_preference::read(connection, user)
+            .map_err(ExportErrorKind::PreferencesReadFailed)?,
+        ledgers,
+        expenses: expense::list(connection, Some(user.id))
+            .map_err(ExportErrorKind::ExpensesReadFailed)?,
+        sessions: user_session::list(connection, user)
+            .map_err(ExportErrorKind::SessionsReadFailed)?,
+        api_tokens: api_token::list(connection, user)
+            .map_err(ExportErrorKind::ApiTokensReadFailed)?,
+        identities: user_identity::list(connection, user)
+            .map_err(ExportErrorKind::IdentitiesReadFailed)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::prelude::*;
+    use diesel::result::Error;
+
+    // Tests super::get().
+    #[test]
+    fn test_get() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+
+            // A new user has no data apart from the account details.
+            let export = get(&conn, &user).unwrap();
+            assert_eq!(user.email, export.account.email);
+            assert!(!export.account.activated);
+            assert!(!export.account.two_factor_enabled);
+            assert_eq!(UserPreferences::defaults(user.id), export.preferences);
+            assert!(export.ledgers.is_empty());
+            assert!(export.expenses.is_empty());
+            assert!(export.identities.is_empty());
+
+            // Categories are exported as a tree in their ledger, expenses as a list.
+            let parent = create_test_category(&conn, &user);
+            let child = create_test_category_with_parent(&conn, &user, Some(&parent));
+            let expense = create_test_expense(&conn, &user, &child);
+
+            let export = get(&conn, &user).unwrap();
+            assert_eq!(1, export.ledgers.len());
+            assert_eq!(Role::Owner, export.ledgers[0].role);
+            let categories = &export.ledgers[0].categories;
+            assert_eq!(1, categories.len());
+            assert_eq!(Some(parent), categories[0].category);
+            assert_eq!(Some(child), categories[0].children[0].category);
+            assert_eq!(vec![expense], export.expenses);
+
+            // Identities that can be used to log in are included.
+            crate::user_identity::link(&conn, "https://sso.example.com", "1", Some(&user.email))
+                .unwrap();
+            let export = get(&conn, &user).unwrap();
+            assert_eq!(1, export.identities.len());
+            assert_eq!("https://sso.example.com", export.identities[0].issuer);
+
+            // The password hash is not part of the export.
+            let json = serde_json::to_string(&export).unwrap();
+            assert!(!json.contains(&user.password));
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,442 @@
+use super::category::Category;
+use super::ledger::Ledger;
+use super::schema::{budgets, categories, expenses, recurring_expenses};
+use chrono::{Datelike, NaiveDate};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The maximum number of complete months preceding the current month that are used to calculate
+/// the average monthly spending.
+pub const HISTORY_MONTHS: u32 = 3;
+
+/// The spending forecast of a ledger.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Forecast {
+    /// The first day of the month that is being forecast.
+    pub month: NaiveDate,
+    /// The number of months of history the averages are based on.
+    pub history_months: u32,
+    /// The forecast per category, ordered by the projected month end total, highest first.
+    pub categories: Vec<CategoryForecast>,
+    /// The total amount that has been spent so far this month.
+    pub spent: Decimal,
+    /// The average monthly spending over the history months.
+    pub average: Decimal,
+    /// The projected total spending at the end of the month.
+    pub month_end: Decimal,
+    /// The monthly total of the recurring expenses.
+    pub recurring: Decimal,
+    /// The projected total spending in the months following the current month.
+    pub next_months: Vec<MonthForecast>,
+}
+
+/// The spending forecast of a single category for the current month.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CategoryForecast {
+    pub category: Category,
+    pub spent: Decimal,
+    pub average: Decimal,
+    pub month_end: Decimal,
+    /// The monthly total of the recurring expenses in the category.
+    pub recurring: Decimal,
+    /// The monthly budget of the category, if one has been set.
+    pub budget: Option<Decimal>,
+    /// Whether the projected month end total exceeds the budget.
+    pub over_budget: bool,
+}
+
+impl Forecast {
+    /// Returns the categories of which the projected month end total exceeds the budget.
+    pub fn over_budget(&self) -> Vec<&CategoryForecast> {
+        self.categories.iter().filter(|c| c.over_budget).collect()
+    }
+}
+
+/// The projected spending in a future month.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct MonthForecast {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    pub amount: Decimal,
+}
+
+// Possible errors thrown when calculating forecasts.
+#[derive(Debug, PartialEq)]
+pub enum ForecastErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+}
+
+impl fmt::Display for ForecastErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ForecastErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ForecastErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        ForecastErrorKind::DatabaseError(e)
+    }
+}
+
+/// Projects the total spending at the end of the month, given the amount that has been spent so
+/// far and the average monthly spending. The remaining days of the month, excluding today, are
+/// expected to follow the average.
+///
+/// # Example
+///
+/// ```
+/// # use chrono::NaiveDate;
+/// # use db::forecast::project_month_end;
+/// # use rust_decimal::Decimal;
+/// #
+/// // Halfway through March 16 of the 31 days are left.
+/// let today = NaiveDate::from_ymd(2021, 3, 15);
+/// let month_end = project_month_end(Decimal::new(100, 0), Decimal::new(310, 0), today);
+/// assert_eq!(Decimal::new(26000, 2), month_end);
+///
+/// // On the last day of the month the forecast equals the amount spent.
+/// let today = NaiveDate::from_ymd(2021, 2, 28);
+/// let month_end = project_month_end(Decimal::new(100, 0), Decimal::new(310, 0), today);
+/// assert_eq!(Decimal::new(10000, 2), month_end);
+/// ```
+pub fn project_month_end(spent: Decimal, average: Decimal, today: NaiveDate) -> Decimal {
+    let days_in_month = days_in_month(today);
+    let days_left = days_in_month - today.day();
+    (spent + average * Decimal::from(days_left) / Decimal::from(days_in_month))
+        .round_dp_with_strategy(2, RoundingStrategy::RoundHalfUp)
+}
+
+/// Returns the spending forecast of the given ledger. The forecast for each category is based on
+/// the average spending during the complete months preceding the current one, up to
+/// `HISTORY_MONTHS`. Months before the first expense in the ledger are not taken into account.
+///
+/// Recurring expenses that are still due this month are added to the projection. They are part of
+/// the historic spending, so only the remainder of the average is spread over the days left. A
+/// category is over budget when its projected month end total exceeds its budget. The totals are
+/// projected for the given number of months following the current month.
+pub fn get_forecast(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    today: NaiveDate,
+    months: u32,
+) -> Result<Forecast, ForecastErrorKind> {
+    let month = add_months(today, 0);
+    let history_start = add_months(today, -(HISTORY_MONTHS as i32));
+
+    let first_expense: Option<NaiveDate> = expenses::table
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .select(diesel::dsl::min(expenses::date))
+        .first(connection)?;
+    let history_months = match first_expense {
+        Some(date) if date < month => {
+            (month_index(month) - month_index(date).max(month_index(history_start))) as u32
+        }
+        _ => 0,
+    };
+
+    let rows: Vec<(Category, NaiveDate, Decimal)> = expenses::table
+        .inner_join(categories::table)
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .filter(expenses::date.ge(history_start))
+        .filter(expenses::date.le(today))
+        .select((categories::all_columns, expenses::date, expenses::amount))
+        .load(connection)?;
+
+    let recurring: Vec<(Category, Decimal, i16)> = recurring_expenses::table
+        .inner_join(categories::table)
+        .filter(categories::ledger_id.eq(ledger.id))
+        .select((
+            categories::all_columns,
+            recurring_expenses::amount,
+            recurring_expenses::day_of_month,
+        ))
+        .load(connection)?;
+
+    let budget_rows: Vec<(Category, Decimal)> = budgets::table
+        .inner_join(categories::table)
+        .filter(categories::ledger_id.eq(ledger.id))
+        .select((categories::all_columns, budgets::amount))
+        .load(connection)?;
+
+    // Sum the amounts spent this month and during the history months per category.
+    let mut totals: HashMap<i32, CategoryTotals> = HashMap::new();
+    for (category, date, amount) in rows {
+        let entry = totals
+            .entry(category.id)
+            .or_insert_with(|| CategoryTotals::new(category));
+        if date >= month {
+            entry.spent += amount;
+        } else {
+            entry.history += amount;
+        }
+    }
+
+    // Sum the recurring expenses per category, and those that are still due this month. Expenses
+    // that fall on a day that does not exist in this month are due on the last day.
+    for (category, amount, day_of_month) in recurring {
+        let entry = totals
+            .entry(category.id)
+            .or_insert_with(|| CategoryTotals::new(category));
+        entry.recurring += amount;
+        if (day_of_month as u32).min(days_in_month(today)) > today.day() {
+            entry.upcoming += amount;
+        }
+    }
+
+    for (category, amount) in budget_rows {
+        totals
+            .entry(category.id)
+            .or_insert_with(|| CategoryTotals::new(category))
+            .budget = Some(amount);
+    }
+
+    let mut categories: Vec<CategoryForecast> = totals
+        .into_values()
+        .map(|totals| {
+            let average = if history_months > 0 {
+                (totals.history / Decimal::from(history_months))
+                    .round_dp_with_strategy(2, RoundingStrategy::RoundHalfUp)
+            } else {
+                Decimal::new(0, 2)
+            };
+            let variable = (average - totals.recurring).max(Decimal::new(0, 2));
+            let month_end = project_month_end(totals.spent + totals.upcoming, variable, today);
+            CategoryForecast {
+                category: totals.category,
+                spent: totals.spent,
+                average,
+                month_end,
+                recurring: totals.recurring,
+                budget: totals.budget,
+                over_budget: matches!(totals.budget, Some(budget) if month_end > budget),
+            }
+        })
+        .collect();
+    categories.sort_by(|a, b| {
+        b.month_end
+            .cmp(&a.month_end)
+            .then_with(|| a.category.name.cmp(&b.category.name))
+    });
+
+    let spent = categories.iter().map(|c| c.spent).sum();
+    let average = categories.iter().map(|c| c.average).sum();
+    let month_end = categories.iter().map(|c| c.month_end).sum();
+    let recurring = categories.iter().map(|c| c.recurring).sum();
+    // In the following months each category is expected to cost at least its recurring expenses.
+    let monthly: Decimal = categories.iter().map(|c| c.average.max(c.recurring)).sum();
+    let next_months = (1..=months as i32)
+        .map(|i| MonthForecast {
+            month: add_months(today, i),
+            amount: monthly,
+        })
+        .collect();
+
+    Ok(Forecast {
+        month,
+        history_months,
+        categories,
+        spent,
+        average,
+        month_end,
+        recurring,
+        next_months,
+    })
+}
+
+// The amounts of a single category that are used to calculate its forecast.
+struct CategoryTotals {
+    category: Category,
+    spent: Decimal,
+    history: Decimal,
+    recurring: Decimal,
+    upcoming: Decimal,
+    budget: Option<Decimal>,
+}
+
+impl CategoryTotals {
+    fn new(category: Category) -> Self {
+        CategoryTotals {
+            category,
+            spent: Decimal::new(0, 2),
+            history: Decimal::new(0, 2),
+            recurring: Decimal::new(0, 2),
+            upcoming: Decimal::new(0, 2),
+            budget: None,
+        }
+    }
+}
+
+// Returns the first day of the month that lies the given number of months from the given date.
+fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let index = month_index(date) + months;
+    NaiveDate::from_ymd(index.div_euclid(12), index.rem_euclid(12) as u32 + 1, 1)
+}
+
+// Returns the number of months since the start of the era. Used to calculate month differences.
+fn month_index(date: NaiveDate) -> i32 {
+    date.year() * 12 + date.month0() as i32
+}
+
+// Returns the number of days in the month of the given date.
+fn days_in_month(date: NaiveDate) -> u32 {
+    add_months(date, 1).pred().day()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+    use std::str::FromStr;
+
+    // Tests super::get_forecast().
+    #[test]
+    fn test_get_forecast() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = crate::ledger::get_default(&conn, &user).unwrap();
+            let food = create_test_category(&conn, &user);
+            let rent = create_test_category(&conn, &user);
+            let today = NaiveDate::from_ymd(2021, 4, 10);
+
+            // Without any expenses the forecast is empty.
+            let forecast = get_forecast(&conn, &ledger, today, 2).unwrap();
+            assert_eq!(NaiveDate::from_ymd(2021, 4, 1), forecast.month);
+            assert_eq!(0, forecast.history_months);
+            assert!(forecast.categories.is_empty());
+            assert_eq!(Decimal::zero(), forecast.month_end);
+            assert_eq!(
+                vec![
+                    MonthForecast {
+                        month: NaiveDate::from_ymd(2021, 5, 1),
+                        amount: Decimal::zero(),
+                    },
+                    MonthForecast {
+                        month: NaiveDate::from_ymd(2021, 6, 1),
+                        amount: Decimal::zero(),
+                    },
+                ],
+                forecast.next_months
+            );
+
+            // Add expenses for the past months. The expense in December lies outside the history
+            // period, and the one in the future has not happened yet, so both are ignored.
+            let expenses = vec![
+                (&rent, "1000.00", "2020-12-01"),
+                (&food, "150.00", "2021-02-03"),
+                (&rent, "900.00", "2021-02-01"),
+                (&food, "210.00", "2021-03-20"),
+                (&rent, "900.00", "2021-03-01"),
+                (&food, "40.00", "2021-04-02"),
+                (&rent, "900.00", "2021-04-01"),
+                (&food, "25.00", "2021-04-20"),
+            ];
+            for (category, amount, date) in expenses {
+                let amount = Decimal::from_str(amount).unwrap();
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                crate::expense::create(&conn, &user, &amount, category, None, Some(&date)).unwrap();
+            }
+
+            // The history starts in January, so 3 months are taken into account. 20 of the 30 days
+            // in April are left.
+            let forecast = get_forecast(&conn, &ledger, today, 1).unwrap();
+            assert_eq!(3, forecast.history_months);
+            assert_eq!(2, forecast.categories.len());
+
+            let rent_forecast = &forecast.categories[0];
+            assert_eq!(rent, rent_forecast.category);
+            assert_eq!(Decimal::new(90000, 2), rent_forecast.spent);
+            assert_eq!(Decimal::new(60000, 2), rent_forecast.average);
+            assert_eq!(Decimal::new(130000, 2), rent_forecast.month_end);
+
+            let food_forecast = &forecast.categories[1];
+            assert_eq!(food, food_forecast.category);
+            assert_eq!(Decimal::new(4000, 2), food_forecast.spent);
+            assert_eq!(Decimal::new(12000, 2), food_forecast.average);
+            assert_eq!(Decimal::new(12000, 2), food_forecast.month_end);
+
+            assert_eq!(Decimal::new(94000, 2), forecast.spent);
+            assert_eq!(Decimal::new(72000, 2), forecast.average);
+            assert_eq!(Decimal::new(142000, 2), forecast.month_end);
+            assert_eq!(Decimal::new(72000, 2), forecast.next_months[0].amount);
+            assert!(forecast.over_budget().is_empty());
+
+            // The rent is a recurring expense that has already been paid this month, so the
+            // average is not projected on top of it. The internet bill is due on the 31st, which
+            // is the last day of April. Only categories that exceed their budget are flagged.
+            let internet = create_test_category(&conn, &user);
+            let clothes = create_test_category(&conn, &user);
+            crate::budget::add_recurring_expense(
+                &conn,
+                &user,
+                &rent,
+                &Decimal::new(900, 0),
+                None,
+                1,
+            )
+            .unwrap();
+            let amount = Decimal::new(5000, 2);
+            crate::budget::add_recurring_expense(&conn, &user, &internet, &amount, None, 31)
+                .unwrap();
+            crate::budget::set_budget(&conn, &user, &food, &Decimal::new(100, 0)).unwrap();
+            crate::budget::set_budget(&conn, &user, &clothes, &Decimal::new(200, 0)).unwrap();
+            let forecast = get_forecast(&conn, &ledger, today, 1).unwrap();
+            assert_eq!(4, forecast.categories.len());
+
+            let rent_forecast = &forecast.categories[0];
+            assert_eq!(rent, rent_forecast.category);
+            assert_eq!(Decimal::new(90000, 2), rent_forecast.recurring);
+            assert_eq!(Decimal::new(90000, 2), rent_forecast.month_end);
+            assert_eq!(None, rent_forecast.budget);
+            assert!(!rent_forecast.over_budget);
+
+            let food_forecast = &forecast.categories[1];
+            assert_eq!(food, food_forecast.category);
+            assert_eq!(Decimal::new(12000, 2), food_forecast.month_end);
+            assert_eq!(Some(Decimal::new(10000, 2)), food_forecast.budget);
+            assert!(food_forecast.over_budget);
+
+            let internet_forecast = &forecast.categories[2];
+            assert_eq!(internet, internet_forecast.category);
+            assert_eq!(Decimal::zero(), internet_forecast.spent);
+            assert_eq!(amount, internet_forecast.month_end);
+
+            let clothes_forecast = &forecast.categories[3];
+            assert_eq!(clothes, clothes_forecast.category);
+            assert_eq!(Decimal::zero(), clothes_forecast.month_end);
+            assert!(!clothes_forecast.over_budget);
+
+            assert_eq!(vec![food_forecast], forecast.over_budget());
+            assert_eq!(Decimal::new(95000, 2), forecast.recurring);
+            assert_eq!(Decimal::new(107000, 2), forecast.month_end);
+            assert_eq!(Decimal::new(107000, 2), forecast.next_months[0].amount);
+
+            // When the ledger is only used since last month, only that month is taken into
+            // account.
+            let other_ledger = create_test_ledger(&conn, &user);
+            let category =
+                crate::category::create(&conn, &user, &other_ledger, "Fuel", None, None).unwrap();
+            let amount = Decimal::new(6000, 2);
+            let date = NaiveDate::from_ymd(2021, 3, 31);
+            crate::expense::create(&conn, &user, &amount, &category, None, Some(&date)).unwrap();
+            let forecast = get_forecast(&conn, &other_ledger, today, 1).unwrap();
+            assert_eq!(1, forecast.history_months);
+            assert_eq!(amount, forecast.average);
+            assert_eq!(Decimal::new(4000, 2), forecast.month_end);
+
+            Ok(())
+        });
+    }
+}
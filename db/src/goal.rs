@@ -0,0 +1,474 @@
+use super::ledger::{self, Ledger};
+use super::schema::{goal_contributions, goals, ledger_members};
+use super::user::User;
+use chrono::{Datelike, NaiveDate, Utc};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A savings goal, e.g. "Emergency fund 10000 by December".
+#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize)]
+#[belongs_to(Ledger)]
+pub struct Goal {
+    pub id: i32,
+    pub ledger_id: i32,
+    pub name: String,
+    pub target_amount: Decimal,
+    pub target_date: NaiveDate,
+    pub created: chrono::NaiveDateTime,
+}
+
+impl Goal {
+    /// Calculates the progress towards the goal, given the amount that has been saved so far. The
+    /// required monthly saving is spread over the remaining months, including the current one. It
+    /// is `None` if the target date has passed without reaching the goal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::NaiveDate;
+    /// # use db::goal::Goal;
+    /// # use rust_decimal::Decimal;
+    /// #
+    /// let goal = Goal {
+    ///     id: 1,
+    ///     ledger_id: 1,
+    ///     name: "Emergency fund".to_string(),
+    ///     target_amount: Decimal::new(10000, 0),
+    ///     target_date: NaiveDate::from_ymd(2021, 12, 31),
+    ///     created: NaiveDate::from_ymd(2021, 1, 1).and_hms(0, 0, 0),
+    /// };
+    ///
+    /// let progress = goal.progress(Decimal::new(2500, 0), NaiveDate::from_ymd(2021, 3, 15));
+    /// assert_eq!(Decimal::new(7500, 0), progress.remaining);
+    /// assert_eq!(25, progress.percentage);
+    /// assert_eq!(10, progress.months_left);
+    /// assert_eq!(Some(Decimal::new(75000, 2)), progress.monthly_saving);
+    ///
+    /// // The goal is overdue.
+    /// let progress = goal.progress(Decimal::new(2500, 0), NaiveDate::from_ymd(2022, 1, 1));
+    /// assert_eq!(None, progress.monthly_saving);
+    ///
+    /// // The goal has been reached.
+    /// let progress = goal.progress(Decimal::new(12000, 0), NaiveDate::from_ymd(2022, 1, 1));
+    /// assert_eq!(100, progress.percentage);
+    /// assert_eq!(Some(Decimal::new(0, 2)), progress.monthly_saving);
+    /// ```
+    pub fn progress(&self, saved: Decimal, today: NaiveDate) -> GoalProgress {
+        let remaining = (self.target_amount - saved).max(Decimal::zero());
+        let percentage = (saved * Decimal::new(100, 0) / self.target_amount)
+            .floor()
+            .max(Decimal::zero())
+            .min(Decimal::new(100, 0))
+            .to_u32()
+            .unwrap_or(0);
+
+        let months_left = if self.target_date < today {
+            0
+        } else {
+            let month_index = |date: NaiveDate| date.year() * 12 + date.month() as i32;
+            (month_index(self.target_date) - month_index(today) + 1) as u32
+        };
+
+        let monthly_saving = if remaining.is_zero() {
+            Some(Decimal::new(0, 2))
+        } else if self.target_date < today {
+            None
+        } else {
+            Some(
+                (remaining / Decimal::from(months_left))
+                    .round_dp_with_strategy(2, RoundingStrategy::RoundUp),
+            )
+        };
+
+        GoalProgress {
+            goal: self.clone(),
+            saved,
+            remaining,
+            percentage,
+            months_left,
+            monthly_saving,
+        }
+    }
+}
+
+/// The progress towards a savings goal.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub saved: Decimal,
+    pub remaining: Decimal,
+    pub percentage: u32,
+    pub months_left: u32,
+    pub monthly_saving: Option<Decimal>,
+}
+
+/// An amount that has been put aside for a savings goal. Negative amounts are withdrawals.
+#[derive(Associations, Clone, Debug, PartialEq, Queryable, Serialize)]
+#[belongs_to(Goal)]
+#[table_name = "goal_contributions"]
+pub struct Contribution {
+    pub id: i32,
+    pub goal_id: i32,
+    /// The user who made the contribution. This is `None` if the user has been deleted.
+    pub user_id: Option<i32>,
+    pub amount: Decimal,
+    pub description: Option<String>,
+    pub date: NaiveDate,
+}
+
+// Possible errors thrown when handling savings goals.
+#[derive(Debug, PartialEq)]
+pub enum GoalErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The user does not have the required role in the ledger.
+    InsufficientPermissions(i32),
+    // The amount is zero or out of range.
+    InvalidAmount,
+    // Some required data is missing.
+    MissingData(String),
+    // A goal does not exist.
+    NotFound(i32),
+    // The target date of a new goal lies in the past.
+    TargetDateInPast(NaiveDate),
+}
+
+impl fmt::Display for GoalErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GoalErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            GoalErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to manage goals in ledger {}",
+                id
+            ),
+            GoalErrorKind::InvalidAmount => {
+                write!(f, "Amount should be between 0.01 and 9999999.99")
+            }
+            GoalErrorKind::MissingData(ref err) => write!(f, "Missing data for field: {}", err),
+            GoalErrorKind::NotFound(ref id) => write!(f, "Goal {} not found", id),
+            GoalErrorKind::TargetDateInPast(ref date) => {
+                write!(f, "The target date {} lies in the past", date)
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for GoalErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        GoalErrorKind::DatabaseError(e)
+    }
+}
+
+/// Creates a savings goal in the given ledger. The user needs to be an editor or owner of the
+/// ledger.
+pub fn create(
+    connection: &PgConnection,
+    user: &User,
+    ledger: &Ledger,
+    name: &str,
+    target_amount: &Decimal,
+    target_date: &NaiveDate,
+) -> Result<Goal, GoalErrorKind> {
+    assert_can_edit(connection, ledger.id, user)?;
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(GoalErrorKind::MissingData("goal name".to_string()));
+    }
+    assert_valid_amount(target_amount)?;
+    if *target_date < Utc::now().naive_utc().date() {
+        return Err(GoalErrorKind::TargetDateInPast(*target_date));
+    }
+
+    Ok(diesel::insert_into(goals::table)
+        .values((
+            goals::ledger_id.eq(ledger.id),
+            goals::name.eq(name),
+            goals::target_amount.eq(target_amount),
+            goals::target_date.eq(target_date),
+            goals::created.eq(chrono::Local::now().naive_local()),
+        ))
+        .returning((
+            goals::id,
+            goals::ledger_id,
+            goals::name,
+            goals::target_amount,
+            goals::target_date,
+            goals::created,
+        ))
+        .get_result(connection)?)
+}
+
+/// Retrieves the goal with the given ID, with optional filter on the ledger members.
+pub fn read(connection: &PgConnection, id: i32, user_id: Option<i32>) -> Option<Goal> {
+    let goal = match user_id {
+        Some(user_id) => goals::table
+            .filter(goals::id.eq(id))
+            .filter(
+                goals::ledger_id.eq_any(
+                    ledger_members::table
+                        .filter(ledger_members::user_id.eq(user_id))
+                        .select(ledger_members::ledger_id),
+                ),
+            )
+            .first::<Goal>(connection),
+        None => goals::table.find(id).first::<Goal>(connection),
+    };
+
+    goal.ok()
+}
+
+/// Deletes the goal with the given ID, together with its contributions.
+pub fn delete(connection: &PgConnection, id: i32) -> Result<(), GoalErrorKind> {
+    let result = diesel::delete(goals::table.find(id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(GoalErrorKind::NotFound(id));
+    }
+
+    Ok(())
+}
+
+/// Returns the goals of the given ledger, ordered by target date.
+pub fn get_goals(connection: &PgConnection, ledger: &Ledger) -> Result<Vec<Goal>, GoalErrorKind> {
+    Ok(goals::table
+        .filter(goals::ledger_id.eq(ledger.id))
+        .order((goals::target_date, goals::id))
+        .load(connection)?)
+}
+
+/// Returns the progress of all goals in the given ledger, ordered by target date.
+pub fn get_progress(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    today: NaiveDate,
+) -> Result<Vec<GoalProgress>, GoalErrorKind> {
+    let goals = get_goals(connection, ledger)?;
+
+    let contributions: Vec<(i32, Decimal)> = goal_contributions::table
+        .inner_join(goals::table)
+        .filter(goals::ledger_id.eq(ledger.id))
+        .select((goal_contributions::goal_id, goal_contributions::amount))
+        .load(connection)?;
+    let mut saved: HashMap<i32, Decimal> = HashMap::new();
+    for (goal_id, amount) in contributions {
+        *saved.entry(goal_id).or_insert_with(|| Decimal::new(0, 2)) += amount;
+    }
+
+    Ok(goals
+        .iter()
+        .map(|goal| {
+            let amount = saved
+                .get(&goal.id)
+                .cloned()
+                .unwrap_or_else(|| Decimal::new(0, 2));
+            goal.progress(amount, today)
+        })
+        .collect())
+}
+
+/// Adds a contribution to the given goal. Negative amounts can be used to record withdrawals. The
+/// user needs to be an editor or owner of the ledger of the goal.
+pub fn add_contribution(
+    connection: &PgConnection,
+    user: &User,
+    goal: &Goal,
+    amount: &Decimal,
+    description: Option<&str>,
+    date: Option<&NaiveDate>,
+) -> Result<Contribution, GoalErrorKind> {
+    assert_can_edit(connection, goal.ledger_id, user)?;
+    assert_valid_amount(&amount.abs())?;
+
+    Ok(diesel::insert_into(goal_contributions::table)
+        .values((
+            goal_contributions::goal_id.eq(goal.id),
+            goal_contributions::user_id.eq(user.id),
+            goal_contributions::amount.eq(amount),
+            goal_contributions::description.eq(description),
+            goal_contributions::date.eq(date.unwrap_or(&Utc::now().naive_utc().date())),
+        ))
+        .returning((
+            goal_contributions::id,
+            goal_contributions::goal_id,
+            goal_contributions::user_id,
+            goal_contributions::amount,
+            goal_contributions::description,
+            goal_contributions::date,
+        ))
+        .get_result(connection)?)
+}
+
+/// Returns the contributions to the given goal, most recent first.
+pub fn get_contributions(
+    connection: &PgConnection,
+    goal: &Goal,
+) -> Result<Vec<Contribution>, GoalErrorKind> {
+    Ok(goal_contributions::table
+        .filter(goal_contributions::goal_id.eq(goal.id))
+        .order((
+            goal_contributions::date.desc(),
+            goal_contributions::id.desc(),
+        ))
+        .load(connection)?)
+}
+
+// Checks that the user is allowed to manage the goals of the given ledger.
+fn assert_can_edit(
+    connection: &PgConnection,
+    ledger_id: i32,
+    user: &User,
+) -> Result<(), GoalErrorKind> {
+    match ledger::get_role(connection, ledger_id, user.id)? {
+        Some(role) if role.can_edit() => Ok(()),
+        _ => Err(GoalErrorKind::InsufficientPermissions(ledger_id)),
+    }
+}
+
+// Checks that the amount is in the range that can be stored.
+fn assert_valid_amount(amount: &Decimal) -> Result<(), GoalErrorKind> {
+    if *amount <= Decimal::new(0, 2) || *amount > Decimal::new(999_999_999, 2) {
+        return Err(GoalErrorKind::InvalidAmount);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::ledger::Role;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::create(), super::read() and super::delete().
+    #[test]
+    fn test_crud() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let amount = Decimal::new(10000, 0);
+            let today = Utc::now().naive_utc().date();
+            let date = today + chrono::Duration::days(300);
+
+            assert_eq!(
+                Err(GoalErrorKind::InsufficientPermissions(ledger.id)),
+                create(&conn, &viewer, &ledger, "Emergency fund", &amount, &date)
+            );
+            assert_eq!(
+                Err(GoalErrorKind::MissingData("goal name".to_string())),
+                create(&conn, &user, &ledger, " ", &amount, &date)
+            );
+            assert_eq!(
+                Err(GoalErrorKind::InvalidAmount),
+                create(
+                    &conn,
+                    &user,
+                    &ledger,
+                    "Emergency fund",
+                    &Decimal::zero(),
+                    &date
+                )
+            );
+            let yesterday = today.pred();
+            assert_eq!(
+                Err(GoalErrorKind::TargetDateInPast(yesterday)),
+                create(&conn, &user, &ledger, "Emergency fund", &amount, &yesterday)
+            );
+
+            let goal = create(&conn, &user, &ledger, " Emergency fund ", &amount, &date).unwrap();
+            assert_eq!("Emergency fund", goal.name);
+            assert_eq!(Some(goal.clone()), read(&conn, goal.id, None));
+            assert_eq!(Some(goal.clone()), read(&conn, goal.id, Some(viewer.id)));
+            let stranger = create_test_user(&conn, &config);
+            assert_eq!(None, read(&conn, goal.id, Some(stranger.id)));
+            assert_eq!(vec![goal.clone()], get_goals(&conn, &ledger).unwrap());
+
+            assert!(delete(&conn, goal.id).is_ok());
+            assert_eq!(None, read(&conn, goal.id, None));
+            assert_eq!(
+                Err(GoalErrorKind::NotFound(goal.id)),
+                delete(&conn, goal.id)
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::add_contribution(), super::get_contributions() and super::get_progress().
+    #[test]
+    fn test_contributions() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let today = Utc::now().naive_utc().date();
+            let date = today + chrono::Duration::days(300);
+            let goal = create(
+                &conn,
+                &user,
+                &ledger,
+                "Holiday",
+                &Decimal::new(3000, 0),
+                &date,
+            )
+            .unwrap();
+            let other_goal =
+                create(&conn, &user, &ledger, "Bike", &Decimal::new(800, 0), &date).unwrap();
+
+            let amount = Decimal::new(50000, 2);
+            assert_eq!(
+                Err(GoalErrorKind::InsufficientPermissions(ledger.id)),
+                add_contribution(&conn, &viewer, &goal, &amount, None, None)
+            );
+            assert_eq!(
+                Err(GoalErrorKind::InvalidAmount),
+                add_contribution(&conn, &user, &goal, &Decimal::zero(), None, None)
+            );
+
+            let deposit =
+                add_contribution(&conn, &user, &goal, &amount, Some("Bonus"), None).unwrap();
+            assert_eq!(today, deposit.date);
+            let withdrawal = add_contribution(
+                &conn,
+                &user,
+                &goal,
+                &Decimal::new(-10000, 2),
+                None,
+                Some(&date),
+            )
+            .unwrap();
+            assert_eq!(
+                vec![withdrawal, deposit],
+                get_contributions(&conn, &goal).unwrap()
+            );
+
+            let progress = get_progress(&conn, &ledger, today).unwrap();
+            assert_eq!(2, progress.len());
+            let holiday = progress.iter().find(|p| p.goal == goal).unwrap();
+            assert_eq!(Decimal::new(40000, 2), holiday.saved);
+            assert_eq!(13, holiday.percentage);
+            let bike = progress.iter().find(|p| p.goal == other_goal).unwrap();
+            assert_eq!(Decimal::zero(), bike.saved);
+            assert_eq!(Decimal::new(800, 0), bike.remaining);
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,629 @@
+use super::schema::{ledger_members, ledgers, users};
+use super::user::User;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::io::Write;
+use std::{fmt, str::FromStr};
+
+// The name of the ledger that is created for users that are not a member of any ledger yet.
+pub const DEFAULT_LEDGER_NAME: &str = "Personal";
+
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct Ledger {
+    pub id: i32,
+    pub name: String,
+    pub created: chrono::NaiveDateTime,
+}
+
+/// The role of a member of a ledger. Roles are ordered by the permissions they grant, so a role
+/// can be compared against the minimum role required for an operation.
+#[derive(
+    AsExpression, Clone, Copy, Debug, Eq, FromSqlRow, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum Role {
+    // Can see the categories and expenses of the ledger.
+    Viewer,
+    // Can also add categories and expenses.
+    Editor,
+    // Can also manage the ledger members and invitations.
+    Owner,
+}
+
+impl Role {
+    /// Returns the machine name of the role, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::ledger::Role;
+    /// #
+    /// assert_eq!("owner", Role::Owner.as_str());
+    /// assert_eq!("editor", Role::Editor.as_str());
+    /// assert_eq!("viewer", Role::Viewer.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Editor => "editor",
+            Role::Viewer => "viewer",
+        }
+    }
+
+    /// Returns whether or not the role allows to add categories and expenses.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::ledger::Role;
+    /// #
+    /// assert!(Role::Owner.can_edit());
+    /// assert!(Role::Editor.can_edit());
+    /// assert!(!Role::Viewer.can_edit());
+    /// ```
+    pub fn can_edit(self) -> bool {
+        self >= Role::Editor
+    }
+
+    /// Returns whether or not the role allows to manage members and invitations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::ledger::Role;
+    /// #
+    /// assert!(Role::Owner.can_manage());
+    /// assert!(!Role::Editor.can_manage());
+    /// assert!(!Role::Viewer.can_manage());
+    /// ```
+    pub fn can_manage(self) -> bool {
+        self >= Role::Owner
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Role {
+    type Err = LedgerErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(Role::Owner),
+            "editor" => Ok(Role::Editor),
+            "viewer" => Ok(Role::Viewer),
+            _ => Err(LedgerErrorKind::InvalidRole(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Role {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Role {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let role = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        role.parse()
+            .map_err(|e: LedgerErrorKind| e.to_string().into())
+    }
+}
+
+// Possible errors thrown when handling ledgers.
+#[derive(Debug, PartialEq)]
+pub enum LedgerErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The user does not have the required role in the ledger.
+    InsufficientPermissions(i32),
+    // The passed in role does not exist.
+    InvalidRole(String),
+    // The last owner of a ledger cannot be removed or demoted.
+    LastOwner(i32),
+    // The user is already a member of the ledger.
+    MemberAlreadyExists(String),
+    // The user is not a member of the ledger.
+    MemberNotFound(i32),
+    // Some required data is missing.
+    MissingData(String),
+    // The ledger does not exist.
+    NotFound(i32),
+}
+
+impl fmt::Display for LedgerErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LedgerErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            LedgerErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to perform this operation on ledger {}",
+                id
+            ),
+            LedgerErrorKind::InvalidRole(ref role) => write!(f, "Invalid role: {}", role),
+            LedgerErrorKind::LastOwner(ref id) => {
+                write!(f, "Ledger {} needs to have at least one owner", id)
+            }
+            LedgerErrorKind::MemberAlreadyExists(ref email) => {
+                write!(f, "{} is already a member of the ledger", email)
+            }
+            LedgerErrorKind::MemberNotFound(ref id) => {
+                write!(f, "User {} is not a member of the ledger", id)
+            }
+            LedgerErrorKind::MissingData(ref err) => write!(f, "Missing data for field: {}", err),
+            LedgerErrorKind::NotFound(ref id) => write!(f, "Ledger {} not found", id),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for LedgerErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        LedgerErrorKind::DatabaseError(e)
+    }
+}
+
+/// Creates a ledger. The given user becomes its owner.
+pub fn create(
+    connection: &PgConnection,
+    owner: &User,
+    name: &str,
+) -> Result<Ledger, LedgerErrorKind> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(LedgerErrorKind::MissingData("ledger name".to_string()));
+    }
+
+    connection.transaction(|| {
+        let ledger: Ledger = diesel::insert_into(ledgers::table)
+            .values((
+                ledgers::name.eq(name),
+                ledgers::created.eq(chrono::Local::now().naive_local()),
+            ))
+            .returning((ledgers::id, ledgers::name, ledgers::created))
+            .get_result(connection)?;
+        add_member(connection, &ledger, owner, Role::Owner)?;
+        Ok(ledger)
+    })
+}
+
+/// Retrieves the ledger with the given ID, with optional member filter.
+pub fn read(connection: &PgConnection, id: i32, user_id: Option<i32>) -> Option<Ledger> {
+    let ledger = match user_id {
+        Some(user_id) => ledgers::table
+            .inner_join(ledger_members::table)
+            .filter(ledgers::id.eq(id))
+            .filter(ledger_members::user_id.eq(user_id))
+            .select((ledgers::id, ledgers::name, ledgers::created))
+            .first::<Ledger>(connection),
+        None => ledgers::table.find(id).first::<Ledger>(connection),
+    };
+
+    ledger.ok()
+}
+
+/// Deletes the ledger with the given ID, together with all its categories and expenses.
+pub fn delete(connection: &PgConnection, id: i32) -> Result<(), LedgerErrorKind> {
+    let result = diesel::delete(ledgers::table.filter(ledgers::id.eq(id))).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(LedgerErrorKind::NotFound(id));
+    }
+
+    Ok(())
+}
+
+/// Returns the ledgers the given user is a member of, together with the user's role. The ledgers
+/// are ordered by creation date.
+pub fn get_ledgers(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<Vec<(Ledger, Role)>, LedgerErrorKind> {
+    Ok(ledgers::table
+        .inner_join(ledger_members::table)
+        .filter(ledger_members::user_id.eq(user.id))
+        .select((
+            (ledgers::id, ledgers::name, ledgers::created),
+            ledger_members::role,
+        ))
+        .order((ledgers::created, ledgers::id))
+        .load(connection)?)
+}
+
+/// Returns the ledgers the given user shares with other members, ordered by creation date.
+pub fn get_shared_ledgers(connection: &PgConnection, user_id: i32) -> QueryResult<Vec<Ledger>> {
+    let own = ledger_members::table
+        .filter(ledger_members::user_id.eq(user_id))
+        .select(ledger_members::ledger_id);
+    let other_members = ledger_members::table
+        .filter(ledger_members::user_id.ne(user_id))
+        .select(ledger_members::ledger_id);
+
+    ledgers::table
+        .filter(ledgers::id.eq_any(own))
+        .filter(ledgers::id.eq_any(other_members))
+        .order((ledgers::created, ledgers::id))
+        .load(connection)
+}
+
+/// Returns the ledgers that are shared with other members and in which the given user is the last
+/// owner, ordered by creation date.
+pub fn get_last_owner_ledgers(connection: &PgConnection, user_id: i32) -> QueryResult<Vec<Ledger>> {
+    let owned = ledger_members::table
+        .filter(ledger_members::user_id.eq(user_id))
+        .filter(ledger_members::role.eq(Role::Owner))
+        .select(ledger_members::ledger_id);
+    let other_members = ledger_members::table
+        .filter(ledger_members::user_id.ne(user_id))
+        .select(ledger_members::ledger_id);
+    let other_owners = ledger_members::table
+        .filter(ledger_members::user_id.ne(user_id))
+        .filter(ledger_members::role.eq(Role::Owner))
+        .select(ledger_members::ledger_id);
+
+    ledgers::table
+        .filter(ledgers::id.eq_any(owned))
+        .filter(ledgers::id.eq_any(other_members))
+        .filter(ledgers::id.ne_all(other_owners))
+        .order((ledgers::created, ledgers::id))
+        .load(connection)
+}
+
+/// Returns the member who takes over the ownership of the ledger when the given user is deleted.
+/// This is the remaining member with the highest role, and the one with the oldest account if
+/// several members have that role. Returns `None` if the user is the only member.
+pub fn get_successor(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    user: &User,
+) -> QueryResult<Option<User>> {
+    let members: Vec<(User, Role)> = users::table
+        .inner_join(ledger_members::table)
+        .filter(ledger_members::ledger_id.eq(ledger.id))
+        .filter(ledger_members::user_id.ne(user.id))
+        .select((
+            (
+                users::id,
+                users::email,
+                users::password,
+                users::created,
+                users::activated,
+                users::admin,
+                users::disabled,
+            ),
+            ledger_members::role,
+        ))
+        .order((users::created, users::id))
+        .load(connection)?;
+
+    // Unlike `max_by_key()`, `min_by_key()` returns the first of several equal elements, which is
+    // the oldest account.
+    Ok(members
+        .into_iter()
+        .min_by_key(|(_, role)| Reverse(*role))
+        .map(|(member, _)| member))
+}
+
+/// Returns the default ledger for the given user. This is the oldest ledger owned by the user. If
+/// the user doesn't own a ledger yet a new one is created.
+pub fn get_default(connection: &PgConnection, user: &User) -> Result<Ledger, LedgerErrorKind> {
+    let ledger = ledgers::table
+        .inner_join(ledger_members::table)
+        .filter(ledger_members::user_id.eq(user.id))
+        .filter(ledger_members::role.eq(Role::Owner))
+        .select((ledgers::id, ledgers::name, ledgers::created))
+        .order((ledgers::created, ledgers::id))
+        .first::<Ledger>(connection)
+        .optional()?;
+
+    match ledger {
+        Some(ledger) => Ok(ledger),
+        None => create(connection, user, DEFAULT_LEDGER_NAME),
+    }
+}
+
+/// Returns the role of the given user in the given ledger, or `None` if the user is not a member.
+pub fn get_role(
+    connection: &PgConnection,
+    ledger_id: i32,
+    user_id: i32,
+) -> QueryResult<Option<Role>> {
+    ledger_members::table
+        .find((ledger_id, user_id))
+        .select(ledger_members::role)
+        .first(connection)
+        .optional()
+}
+
+/// Checks that the given user has at least the given role in the given ledger.
+pub fn assert_role(
+    connection: &PgConnection,
+    ledger_id: i32,
+    user_id: i32,
+    required_role: Role,
+) -> Result<Role, LedgerErrorKind> {
+    match get_role(connection, ledger_id, user_id)? {
+        Some(role) if role >= required_role => Ok(role),
+        _ => Err(LedgerErrorKind::InsufficientPermissions(ledger_id)),
+    }
+}
+
+/// Adds the given user to the ledger with the given role. No permission checks are done, this is
+/// the responsibility of the caller.
+pub fn add_member(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    user: &User,
+    role: Role,
+) -> Result<(), LedgerErrorKind> {
+    if get_role(connection, ledger.id, user.id)?.is_some() {
+        return Err(LedgerErrorKind::MemberAlreadyExists(user.email.clone()));
+    }
+
+    diesel::insert_into(ledger_members::table)
+        .values((
+            ledger_members::ledger_id.eq(ledger.id),
+            ledger_members::user_id.eq(user.id),
+            ledger_members::role.eq(role),
+        ))
+        .execute(connection)?;
+
+    Ok(())
+}
+
+/// Changes the role of a member of the ledger. A ledger always needs to keep at least one owner.
+pub fn set_role(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    user_id: i32,
+    role: Role,
+) -> Result<(), LedgerErrorKind> {
+    connection.transaction(|| {
+        let current_role = get_role(connection, ledger.id, user_id)?
+            .ok_or(LedgerErrorKind::MemberNotFound(user_id))?;
+        if current_role == Role::Owner && role != Role::Owner {
+            assert_not_last_owner(connection, ledger.id)?;
+        }
+
+        diesel::update(ledger_members::table.find((ledger.id, user_id)))
+            .set(ledger_members::role.eq(role))
+            .execute(connection)?;
+        Ok(())
+    })
+}
+
+/// Removes a member from the ledger. A ledger always needs to keep at least one owner.
+pub fn remove_member(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    user_id: i32,
+) -> Result<(), LedgerErrorKind> {
+    connection.transaction(|| {
+        let current_role = get_role(connection, ledger.id, user_id)?
+            .ok_or(LedgerErrorKind::MemberNotFound(user_id))?;
+        if current_role == Role::Owner {
+            assert_not_last_owner(connection, ledger.id)?;
+        }
+
+        diesel::delete(ledger_members::table.find((ledger.id, user_id))).execute(connection)?;
+        Ok(())
+    })
+}
+
+/// Returns the members of the given ledger with their roles, ordered by email address.
+pub fn get_members(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<(User, Role)>, LedgerErrorKind> {
+    Ok(users::table
+        .inner_join(ledger_members::table)
+        .filter(ledger_members::ledger_id.eq(ledger.id))
+        .select((
+            (
+                users::id,
+                users::email,
+                users::password,
+                users::created,
+                users::activated,
+                users::admin,
+                users::disabled,
+            ),
+            ledger_members::role,
+        ))
+        .order(users::email)
+        .load(connection)?)
+}
+
+// Checks that the ledger has more than one owner.
+fn assert_not_last_owner(connection: &PgConnection, ledger_id: i32) -> Result<(), LedgerErrorKind> {
+    let owner_count: i64 = ledger_members::table
+        .filter(ledger_members::ledger_id.eq(ledger_id))
+        .filter(ledger_members::role.eq(Role::Owner))
+        .count()
+        .get_result(connection)?;
+
+    if owner_count <= 1 {
+        return Err(LedgerErrorKind::LastOwner(ledger_id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::create().
+    #[test]
+    fn test_create() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+
+            // An empty name is not allowed.
+            assert_eq!(
+                Err(LedgerErrorKind::MissingData("ledger name".to_string())),
+                create(&conn, &user, "  ")
+            );
+
+            // The creator of the ledger becomes its owner.
+            let ledger = create(&conn, &user, " Household ").unwrap();
+            assert_eq!("Household", ledger.name);
+            assert_eq!(Ok(Some(Role::Owner)), get_role(&conn, ledger.id, user.id));
+            assert_eq!(Some(ledger.clone()), read(&conn, ledger.id, Some(user.id)));
+            assert_eq!(Some(ledger.clone()), read(&conn, ledger.id, None));
+
+            // Non-members cannot read the ledger.
+            let other_user = create_test_user(&conn, &config);
+            assert_eq!(None, read(&conn, ledger.id, Some(other_user.id)));
+
+            Ok(())
+        });
+    }
+
+    // Tests super::delete().
+    #[test]
+    fn test_delete() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &user);
+            let cat = crate::category::create(&conn, &user, &ledger, "Food", None, None).unwrap();
+            create_test_expense(&conn, &user, &cat);
+
+            // Deleting a ledger also deletes its categories and expenses.
+            assert!(delete(&conn, ledger.id).is_ok());
+            assert_eq!(None, read(&conn, ledger.id, None));
+            assert_eq!(None, crate::category::read(&conn, cat.id, None));
+            assert_eq!(0, crate::expense::count(&conn, Some(user.id)).unwrap());
+
+            // Deleting a non-existing ledger returns an error.
+            assert_eq!(
+                Err(LedgerErrorKind::NotFound(ledger.id)),
+                delete(&conn, ledger.id)
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::get_default().
+    #[test]
+    fn test_get_default() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            assert!(get_ledgers(&conn, &user).unwrap().is_empty());
+
+            // A personal ledger is created on first use, and is returned on subsequent calls.
+            let ledger = get_default(&conn, &user).unwrap();
+            assert_eq!(DEFAULT_LEDGER_NAME, ledger.name);
+            assert_eq!(ledger, get_default(&conn, &user).unwrap());
+            assert_eq!(
+                vec![(ledger.clone(), Role::Owner)],
+                get_ledgers(&conn, &user).unwrap()
+            );
+
+            // Ledgers that are shared with the user do not count as their default ledger.
+            let other_user = create_test_user(&conn, &config);
+            let shared_ledger = create_test_ledger(&conn, &other_user);
+            add_member(&conn, &shared_ledger, &user, Role::Editor).unwrap();
+            assert_eq!(ledger, get_default(&conn, &user).unwrap());
+            assert_eq!(
+                vec![(ledger, Role::Owner), (shared_ledger, Role::Editor)],
+                get_ledgers(&conn, &user).unwrap()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests the management of ledger members.
+    #[test]
+    fn test_members() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let member = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &owner);
+
+            assert_eq!(Ok(None), get_role(&conn, ledger.id, member.id));
+            assert_eq!(
+                Err(LedgerErrorKind::InsufficientPermissions(ledger.id)),
+                assert_role(&conn, ledger.id, member.id, Role::Viewer)
+            );
+
+            add_member(&conn, &ledger, &member, Role::Viewer).unwrap();
+            assert_eq!(
+                Err(LedgerErrorKind::MemberAlreadyExists(member.email.clone())),
+                add_member(&conn, &ledger, &member, Role::Editor)
+            );
+            assert_eq!(
+                Ok(Role::Viewer),
+                assert_role(&conn, ledger.id, member.id, Role::Viewer)
+            );
+            assert_eq!(
+                Err(LedgerErrorKind::InsufficientPermissions(ledger.id)),
+                assert_role(&conn, ledger.id, member.id, Role::Editor)
+            );
+
+            set_role(&conn, &ledger, member.id, Role::Editor).unwrap();
+            assert_eq!(
+                Ok(Some(Role::Editor)),
+                get_role(&conn, ledger.id, member.id)
+            );
+
+            let members = get_members(&conn, &ledger).unwrap();
+            assert_eq!(2, members.len());
+            assert!(members
+                .iter()
+                .any(|(u, r)| u.id == owner.id && *r == Role::Owner));
+            assert!(members
+                .iter()
+                .any(|(u, r)| u.id == member.id && *r == Role::Editor));
+
+            // The last owner cannot be demoted or removed.
+            assert_eq!(
+                Err(LedgerErrorKind::LastOwner(ledger.id)),
+                set_role(&conn, &ledger, owner.id, Role::Editor)
+            );
+            assert_eq!(
+                Err(LedgerErrorKind::LastOwner(ledger.id)),
+                remove_member(&conn, &ledger, owner.id)
+            );
+
+            // If there is another owner this is allowed.
+            set_role(&conn, &ledger, member.id, Role::Owner).unwrap();
+            assert!(remove_member(&conn, &ledger, owner.id).is_ok());
+            assert_eq!(None, read(&conn, ledger.id, Some(owner.id)));
+            assert_eq!(
+                Err(LedgerErrorKind::MemberNotFound(owner.id)),
+                remove_member(&conn, &ledger, owner.id)
+            );
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,421 @@
+use super::ledger::{self, Ledger, LedgerErrorKind, Role};
+use super::schema::ledger_invitations::dsl;
+use super::user::User;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::fmt;
+use validator::validate_email;
+
+// The number of days an invitation remains valid.
+const VALIDITY_DAYS: i64 = 7;
+
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct LedgerInvitation {
+    pub id: i32,
+    pub ledger_id: i32,
+    pub email: String,
+    pub role: Role,
+    pub invited_by: i32,
+    pub expiration_time: chrono::NaiveDateTime,
+}
+
+impl LedgerInvitation {
+    /// Returns whether or not the invitation is expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::ledger::Role;
+    /// # use db::ledger_invitation::LedgerInvitation;
+    /// #
+    /// let mut invitation = LedgerInvitation {
+    ///     id: 1,
+    ///     ledger_id: 1,
+    ///     email: "jane@example.com".to_string(),
+    ///     role: Role::Editor,
+    ///     invited_by: 1,
+    ///     expiration_time: chrono::Local::now().checked_add_signed(chrono::Duration::days(1)).unwrap().naive_local(),
+    /// };
+    /// assert_eq!(invitation.is_expired(), false);
+    /// #
+    /// # invitation.expiration_time = chrono::Local::now().checked_sub_signed(chrono::Duration::seconds(1)).unwrap().naive_local();
+    /// # assert_eq!(invitation.is_expired(), true);
+    /// ```
+    pub fn is_expired(&self) -> bool {
+        self.expiration_time.lt(&chrono::Local::now().naive_local())
+    }
+}
+
+// Possible errors thrown when handling ledger invitations.
+#[derive(Debug, PartialEq)]
+pub enum LedgerInvitationErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The expiration time overflowed. Not expected to occur before the end of the year 262143.
+    ExpirationTimeOverflow,
+    // The invitation has expired.
+    Expired,
+    // The passed in email address is not valid.
+    InvalidEmail(String),
+    // An error occurred while managing the ledger membership.
+    LedgerError(LedgerErrorKind),
+    // The invitation does not exist, or is not addressed to the user.
+    NotFound(i32),
+    // The user needs to be activated before they can accept an invitation.
+    UserNotActivated(String),
+}
+
+impl fmt::Display for LedgerInvitationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LedgerInvitationErrorKind::DatabaseError(ref err) => {
+                write!(f, "Database error: {}", err)
+            }
+            LedgerInvitationErrorKind::ExpirationTimeOverflow => {
+                write!(f, "Expiration time overflow")
+            }
+            LedgerInvitationErrorKind::Expired => write!(f, "The invitation has expired"),
+            LedgerInvitationErrorKind::InvalidEmail(ref email) => {
+                write!(f, "Invalid email address: {}", email)
+            }
+            LedgerInvitationErrorKind::LedgerError(ref err) => write!(f, "{}", err),
+            LedgerInvitationErrorKind::NotFound(ref id) => {
+                write!(f, "Invitation {} not found", id)
+            }
+            LedgerInvitationErrorKind::UserNotActivated(ref email) => write!(
+                f,
+                "The user with email {} needs to be activated before accepting invitations",
+                email
+            ),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for LedgerInvitationErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        LedgerInvitationErrorKind::DatabaseError(e)
+    }
+}
+
+impl From<LedgerErrorKind> for LedgerInvitationErrorKind {
+    fn from(e: LedgerErrorKind) -> Self {
+        LedgerInvitationErrorKind::LedgerError(e)
+    }
+}
+
+/// Invites the person with the given email address to join the ledger with the given role. Only
+/// owners of the ledger can invite new members. If the person was already invited the existing
+/// invitation is replaced.
+pub fn create(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    inviter: &User,
+    email: &str,
+    role: Role,
+) -> Result<LedgerInvitation, LedgerInvitationErrorKind> {
+    let email = email.trim();
+    if !validate_email(email) {
+        return Err(LedgerInvitationErrorKind::InvalidEmail(email.to_string()));
+    }
+
+    ledger::assert_role(connection, ledger.id, inviter.id, Role::Owner)?;
+
+    // Existing members cannot be invited again.
+    let is_member = ledger::get_members(connection, ledger)?
+        .iter()
+        .any(|(user, _)| user.email == email);
+    if is_member {
+        return Err(LedgerErrorKind::MemberAlreadyExists(email.to_string()).into());
+    }
+
+    let expiration_time =
+        match chrono::Local::now().checked_add_signed(chrono::Duration::days(VALIDITY_DAYS)) {
+            Some(t) => t,
+            None => return Err(LedgerInvitationErrorKind::ExpirationTimeOverflow),
+        }
+        .naive_local();
+
+    Ok(diesel::insert_into(dsl::ledger_invitations)
+        .values((
+            dsl::ledger_id.eq(ledger.id),
+            dsl::email.eq(email),
+            dsl::role.eq(role),
+            dsl::invited_by.eq(inviter.id),
+            dsl::expiration_time.eq(expiration_time),
+        ))
+        .on_conflict((dsl::ledger_id, dsl::email))
+        .do_update()
+        .set((
+            dsl::role.eq(role),
+            dsl::invited_by.eq(inviter.id),
+            dsl::expiration_time.eq(expiration_time),
+        ))
+        .returning((
+            dsl::id,
+            dsl::ledger_id,
+            dsl::email,
+            dsl::role,
+            dsl::invited_by,
+            dsl::expiration_time,
+        ))
+        .get_result(connection)?)
+}
+
+/// Retrieves the invitation with the given ID.
+pub fn read(connection: &PgConnection, id: i32) -> Option<LedgerInvitation> {
+    dsl::ledger_invitations
+        .find(id)
+        .first::<LedgerInvitation>(connection)
+        .ok()
+}
+
+/// Returns the pending invitations that are addressed to the given user, together with the
+/// ledgers they are for.
+pub fn get_pending(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<Vec<(LedgerInvitation, Ledger)>, LedgerInvitationErrorKind> {
+    use super::schema::ledgers;
+
+    Ok(dsl::ledger_invitations
+        .inner_join(ledgers::table)
+        .filter(dsl::email.eq(&user.email))
+        .filter(dsl::expiration_time.ge(chrono::Local::now().naive_local()))
+        .order(dsl::id)
+        .load(connection)?)
+}
+
+/// Returns the pending invitations for the given ledger.
+pub fn get_for_ledger(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<LedgerInvitation>, LedgerInvitationErrorKind> {
+    Ok(dsl::ledger_invitations
+        .filter(dsl::ledger_id.eq(ledger.id))
+        .filter(dsl::expiration_time.ge(chrono::Local::now().naive_local()))
+        .order(dsl::email)
+        .load(connection)?)
+}
+
+/// Accepts the invitation with the given ID on behalf of the given user. The user is added to the
+/// ledger and the invitation is removed.
+pub fn accept(
+    connection: &PgConnection,
+    user: &User,
+    id: i32,
+) -> Result<Ledger, LedgerInvitationErrorKind> {
+    let invitation = read_for_user(connection, user, id)?;
+    if invitation.is_expired() {
+        return Err(LedgerInvitationErrorKind::Expired);
+    }
+    if !user.activated {
+        return Err(LedgerInvitationErrorKind::UserNotActivated(
+            user.email.clone(),
+        ));
+    }
+
+    let ledger = ledger::read(connection, invitation.ledger_id, None)
+        .ok_or(LedgerErrorKind::NotFound(invitation.ledger_id))?;
+
+    connection.transaction(|| {
+        ledger::add_member(connection, &ledger, user, invitation.role)?;
+        delete(connection, invitation.id)?;
+        Ok(ledger)
+    })
+}
+
+/// Declines the invitation with the given ID on behalf of the given user.
+pub fn decline(
+    connection: &PgConnection,
+    user: &User,
+    id: i32,
+) -> Result<(), LedgerInvitationErrorKind> {
+    let invitation = read_for_user(connection, user, id)?;
+    delete(connection, invitation.id)
+}
+
+/// Deletes the invitation with the given ID.
+pub fn delete(connection: &PgConnection, id: i32) -> Result<(), LedgerInvitationErrorKind> {
+    let result = diesel::delete(dsl::ledger_invitations.find(id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(LedgerInvitationErrorKind::NotFound(id));
+    }
+
+    Ok(())
+}
+
+/// Purges all expired invitations.
+pub fn purge(connection: &PgConnection) -> Result<(), LedgerInvitationErrorKind> {
+    let expiration_time = chrono::Local::now().naive_local();
+    diesel::delete(dsl::ledger_invitations.filter(dsl::expiration_time.lt(expiration_time)))
+        .execute(connection)?;
+    Ok(())
+}
+
+// Retrieves the invitation with the given ID, ensuring that it is addressed to the given user.
+fn read_for_user(
+    connection: &PgConnection,
+    user: &User,
+    id: i32,
+) -> Result<LedgerInvitation, LedgerInvitationErrorKind> {
+    match read(connection, id) {
+        Some(invitation) if invitation.email == user.email => Ok(invitation),
+        _ => Err(LedgerInvitationErrorKind::NotFound(id)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::create().
+    #[test]
+    fn test_create() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let editor = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &owner);
+            ledger::add_member(&conn, &ledger, &editor, Role::Editor).unwrap();
+
+            // Invalid email addresses are rejected.
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::InvalidEmail(
+                    "invalid".to_string()
+                )),
+                create(&conn, &ledger, &owner, "invalid", Role::Viewer)
+            );
+
+            // Only owners can invite new members.
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::LedgerError(
+                    LedgerErrorKind::InsufficientPermissions(ledger.id)
+                )),
+                create(&conn, &ledger, &editor, "jane@example.com", Role::Viewer)
+            );
+
+            // Existing members cannot be invited.
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::LedgerError(
+                    LedgerErrorKind::MemberAlreadyExists(editor.email.clone())
+                )),
+                create(&conn, &ledger, &owner, &editor.email, Role::Viewer)
+            );
+
+            let invitation =
+                create(&conn, &ledger, &owner, "jane@example.com", Role::Viewer).unwrap();
+            assert_eq!(ledger.id, invitation.ledger_id);
+            assert_eq!("jane@example.com", invitation.email);
+            assert_eq!(Role::Viewer, invitation.role);
+            assert_eq!(owner.id, invitation.invited_by);
+            assert!(!invitation.is_expired());
+            assert_eq!(Some(invitation.clone()), read(&conn, invitation.id));
+
+            // Inviting the same person again updates the existing invitation.
+            let updated = create(&conn, &ledger, &owner, "jane@example.com", Role::Editor).unwrap();
+            assert_eq!(invitation.id, updated.id);
+            assert_eq!(Role::Editor, updated.role);
+            assert_eq!(vec![updated], get_for_ledger(&conn, &ledger).unwrap());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::accept() and super::decline().
+    #[test]
+    fn test_accept_and_decline() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let invitee = create_test_user(&conn, &config);
+            let other_user = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &owner);
+
+            let invitation = create(&conn, &ledger, &owner, &invitee.email, Role::Editor).unwrap();
+            assert_eq!(
+                vec![(invitation.clone(), ledger.clone())],
+                get_pending(&conn, &invitee).unwrap()
+            );
+            assert!(get_pending(&conn, &other_user).unwrap().is_empty());
+
+            // Invitations cannot be accepted by other users.
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::NotFound(invitation.id)),
+                accept(&conn, &other_user, invitation.id)
+            );
+
+            // Users need to be activated before they can accept an invitation.
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::UserNotActivated(
+                    invitee.email.clone()
+                )),
+                accept(&conn, &invitee, invitation.id)
+            );
+
+            let invitee = crate::user::activate(&conn, invitee).unwrap();
+            assert_eq!(Ok(ledger.clone()), accept(&conn, &invitee, invitation.id));
+            assert_eq!(
+                Ok(Some(Role::Editor)),
+                ledger::get_role(&conn, ledger.id, invitee.id)
+            );
+            assert_eq!(None, read(&conn, invitation.id));
+
+            // Declining removes the invitation without adding the user.
+            let invitation =
+                create(&conn, &ledger, &owner, &other_user.email, Role::Viewer).unwrap();
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::NotFound(invitation.id)),
+                decline(&conn, &invitee, invitation.id)
+            );
+            assert!(decline(&conn, &other_user, invitation.id).is_ok());
+            assert_eq!(None, read(&conn, invitation.id));
+            assert_eq!(Ok(None), ledger::get_role(&conn, ledger.id, other_user.id));
+
+            Ok(())
+        });
+    }
+
+    // Tests that expired invitations cannot be accepted and are purged.
+    #[test]
+    fn test_expired_invitations() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let invitee = crate::user::activate(&conn, create_test_user(&conn, &config)).unwrap();
+            let ledger = create_test_ledger(&conn, &owner);
+
+            let invitation = create(&conn, &ledger, &owner, &invitee.email, Role::Editor).unwrap();
+            let expiration_time = chrono::Local::now()
+                .checked_sub_signed(chrono::Duration::seconds(1))
+                .unwrap()
+                .naive_local();
+            diesel::update(dsl::ledger_invitations.find(invitation.id))
+                .set(dsl::expiration_time.eq(expiration_time))
+                .execute(&conn)
+                .unwrap();
+
+            assert!(get_pending(&conn, &invitee).unwrap().is_empty());
+            assert_eq!(
+                Err(LedgerInvitationErrorKind::Expired),
+                accept(&conn, &invitee, invitation.id)
+            );
+
+            purge(&conn).unwrap();
+            assert_eq!(None, read(&conn, invitation.id));
+
+            Ok(())
+        });
+    }
+}
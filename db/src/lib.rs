@@ -14,9 +14,28 @@ pub mod db_test;
 mod schema;
 
 pub mod activation_code;
+pub mod api_token;
+pub mod budget;
 pub mod category;
+pub mod email_change_code;
 pub mod expense;
+pub mod expense_share;
+pub mod export;
+pub mod forecast;
+pub mod goal;
+pub mod ledger;
+pub mod ledger_invitation;
+pub mod login_attempt;
+pub mod password_reset_code;
+pub mod registration_invitation;
+pub mod registration_notification;
+pub mod report;
+pub mod settlement;
+pub mod two_factor;
 pub mod user;
+pub mod user_identity;
+pub mod user_preference;
+pub mod user_session;
 
 // Type alias to make it easier to refer to the connection pool.
 pub type ConnectionPool = r2d2::Pool<ConnectionManager<PgConnection>>;
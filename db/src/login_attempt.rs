@@ -0,0 +1,414 @@
+use super::schema::login_attempts::dsl;
+use chrono::{Duration, NaiveDateTime};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::fmt;
+
+// The number of failed logins that are allowed for a single account before it gets locked.
+const FREE_ACCOUNT_ATTEMPTS: i16 = 5;
+
+// The number of failed logins that are allowed from a single IP address before it gets locked. This
+// is higher than the limit for accounts since many users can share an IP address.
+const FREE_IP_ATTEMPTS: i16 = 20;
+
+// The duration of the first lockout, in seconds. Every subsequent failed login doubles it.
+const BASE_LOCKOUT_SECONDS: i64 = 30;
+
+// The maximum duration of a lockout, in seconds.
+const MAX_LOCKOUT_SECONDS: i64 = 60 * 60;
+
+// The number of hours after the last failed login after which the attempt counter is reset.
+const RESET_AFTER_HOURS: i64 = 24;
+
+/// The scope in which failed logins are counted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    /// Failed logins for an email address.
+    Account,
+    /// Failed logins from an IP address.
+    Ip,
+}
+
+impl Scope {
+    /// Returns the machine name of the scope, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::login_attempt::Scope;
+    /// #
+    /// assert_eq!("account", Scope::Account.as_str());
+    /// assert_eq!("ip", Scope::Ip.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Account => "account",
+            Scope::Ip => "ip",
+        }
+    }
+
+    // Returns the number of failed logins that are allowed before a lockout.
+    fn free_attempts(self) -> i16 {
+        match self {
+            Scope::Account => FREE_ACCOUNT_ATTEMPTS,
+            Scope::Ip => FREE_IP_ATTEMPTS,
+        }
+    }
+}
+
+/// The failed logins for an email address or IP address.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct LoginAttempt {
+    pub scope: String,
+    pub identifier: String,
+    pub attempts: i16,
+    pub last_attempt_time: NaiveDateTime,
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+impl LoginAttempt {
+    /// Returns whether or not logins are currently locked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::login_attempt::LoginAttempt;
+    /// #
+    /// let mut login_attempt = LoginAttempt {
+    ///     scope: "account".to_string(),
+    ///     identifier: "jane@example.com".to_string(),
+    ///     attempts: 5,
+    ///     last_attempt_time: chrono::Local::now().naive_local(),
+    ///     locked_until: None,
+    /// };
+    /// assert_eq!(login_attempt.is_locked(), false);
+    ///
+    /// login_attempt.locked_until = Some(chrono::Local::now().checked_add_signed(chrono::Duration::seconds(30)).unwrap().naive_local());
+    /// assert_eq!(login_attempt.is_locked(), true);
+    /// #
+    /// # login_attempt.locked_until = Some(chrono::Local::now().checked_sub_signed(chrono::Duration::seconds(1)).unwrap().naive_local());
+    /// # assert_eq!(login_attempt.is_locked(), false);
+    /// ```
+    pub fn is_locked(&self) -> bool {
+        match self.locked_until {
+            Some(locked_until) => locked_until.gt(&chrono::Local::now().naive_local()),
+            None => false,
+        }
+    }
+
+    // Returns whether the last failed login is long enough ago for the attempt counter to be reset.
+    fn is_stale(&self) -> bool {
+        !self.is_locked()
+            && self.last_attempt_time + Duration::hours(RESET_AFTER_HOURS)
+                < chrono::Local::now().naive_local()
+    }
+}
+
+// Possible errors thrown when handling login attempts.
+#[derive(Debug, PartialEq)]
+pub enum LoginAttemptErrorKind {
+    // Login attempts could not be deleted due to a database error.
+    DeletionFailed(diesel::result::Error),
+    // Logins are locked until the given time because of too many failed attempts.
+    Locked(NaiveDateTime),
+    // Stale login attempts could not be purged due to a database error.
+    PurgingFailed(diesel::result::Error),
+    // Login attempts could not be read due to a database error.
+    ReadFailed(diesel::result::Error),
+    // A failed login could not be recorded due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for LoginAttemptErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoginAttemptErrorKind::DeletionFailed(ref err) => {
+                write!(f, "Database error when deleting login attempts: {}", err)
+            }
+            LoginAttemptErrorKind::Locked(ref locked_until) => {
+                // Round up so we never show "0 minutes".
+                let seconds = (*locked_until - chrono::Local::now().naive_local()).num_seconds();
+                let minutes = (seconds + 59) / 60;
+                write!(
+                    f,
+                    "Too many failed login attempts. Please try again in {} minute{}.",
+                    minutes.max(1),
+                    if minutes > 1 { "s" } else { "" }
+                )
+            }
+            LoginAttemptErrorKind::PurgingFailed(ref err) => {
+                write!(f, "Database error when purging login attempts: {}", err)
+            }
+            LoginAttemptErrorKind::ReadFailed(ref err) => {
+                write!(f, "Database error when reading login attempts: {}", err)
+            }
+            LoginAttemptErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when recording a failed login: {}", err)
+            }
+        }
+    }
+}
+
+/// Returns the duration of the lockout after the given number of failed logins, or `None` if the
+/// number of allowed attempts has not been reached yet. The lockout duration doubles with every
+/// failed attempt, up to a maximum of one hour.
+///
+/// # Example
+///
+/// ```
+/// # use db::login_attempt::get_lockout_duration;
+/// # use chrono::Duration;
+/// #
+/// assert_eq!(None, get_lockout_duration(4, 5));
+/// assert_eq!(Some(Duration::seconds(30)), get_lockout_duration(5, 5));
+/// assert_eq!(Some(Duration::seconds(60)), get_lockout_duration(6, 5));
+/// assert_eq!(Some(Duration::seconds(120)), get_lockout_duration(7, 5));
+/// assert_eq!(Some(Duration::hours(1)), get_lockout_duration(12, 5));
+/// assert_eq!(Some(Duration::hours(1)), get_lockout_duration(i16::MAX, 5));
+/// ```
+pub fn get_lockout_duration(attempts: i16, free_attempts: i16) -> Option<Duration> {
+    if attempts < free_attempts {
+        return None;
+    }
+    // Limit the exponent so the multiplication can't overflow.
+    let exponent = (attempts - free_attempts).min(16) as u32;
+    let seconds = (BASE_LOCKOUT_SECONDS * 2i64.pow(exponent)).min(MAX_LOCKOUT_SECONDS);
+    Some(Duration::seconds(seconds))
+}
+
+/// Checks whether logging in is allowed for the given email address from the given IP address.
+/// Returns a `Locked` error with the time until which the lockout lasts if either of them is
+/// locked.
+pub fn check(
+    connection: &PgConnection,
+    email: &str,
+    ip: Option<&str>,
+) -> Result<(), LoginAttemptErrorKind> {
+    let mut locked_until = None;
+    for (scope, identifier) in get_scopes(email, ip) {
+        if let Some(login_attempt) = read(connection, scope, identifier)? {
+            if login_attempt.is_locked() {
+                locked_until = locked_until.max(login_attempt.locked_until);
+            }
+        }
+    }
+    match locked_until {
+        Some(locked_until) => Err(LoginAttemptErrorKind::Locked(locked_until)),
+        None => Ok(()),
+    }
+}
+
+/// Records a failed login for the given email address and IP address. Once the number of allowed
+/// attempts has been exceeded, logins are locked for an exponentially increasing duration.
+pub fn register_failure(
+    connection: &PgConnection,
+    email: &str,
+    ip: Option<&str>,
+) -> Result<(), LoginAttemptErrorKind> {
+    for (scope, identifier) in get_scopes(email, ip) {
+        increase_attempt_counter(connection, scope, identifier)?;
+    }
+    Ok(())
+}
+
+/// Retrieves the failed logins for the given scope and identifier.
+pub fn read(
+    connection: &PgConnection,
+    scope: Scope,
+    identifier: &str,
+) -> Result<Option<LoginAttempt>, LoginAttemptErrorKind> {
+    dsl::login_attempts
+        .find((scope.as_str(), identifier))
+        .first::<LoginAttempt>(connection)
+        .optional()
+        .map_err(LoginAttemptErrorKind::ReadFailed)
+}
+
+/// Deletes the failed logins for the given scope and identifier, lifting any lockout. This is
+/// called after a successful login, and can be used by administrators to unlock an account.
+pub fn delete(
+    connection: &PgConnection,
+    scope: Scope,
+    identifier: &str,
+) -> Result<(), LoginAttemptErrorKind> {
+    diesel::delete(
+        dsl::login_attempts
+            .filter(dsl::scope.eq(scope.as_str()))
+            .filter(dsl::identifier.eq(identifier)),
+    )
+    .execute(connection)
+    .map_err(LoginAttemptErrorKind::DeletionFailed)?;
+    Ok(())
+}
+
+/// Purges login attempts that are no longer locked and are old enough for their counter to be
+/// reset.
+pub fn purge(connection: &PgConnection) -> Result<(), LoginAttemptErrorKind> {
+    let now = chrono::Local::now().naive_local();
+    diesel::delete(
+        dsl::login_attempts
+            .filter(dsl::last_attempt_time.lt(now - Duration::hours(RESET_AFTER_HOURS)))
+            .filter(dsl::locked_until.is_null().or(dsl::locked_until.lt(now))),
+    )
+    .execute(connection)
+    .map_err(LoginAttemptErrorKind::PurgingFailed)?;
+    Ok(())
+}
+
+// Returns the scopes and identifiers that are tracked for a login.
+fn get_scopes<'a>(email: &'a str, ip: Option<&'a str>) -> Vec<(Scope, &'a str)> {
+    let mut scopes = vec![(Scope::Account, email)];
+    if let Some(ip) = ip {
+        scopes.push((Scope::Ip, ip));
+    }
+    scopes
+}
+
+// Increases the failed login counter for the given scope and identifier, and locks logins if the
+// number of allowed attempts has been exceeded.
+fn increase_attempt_counter(
+    connection: &PgConnection,
+    scope: Scope,
+    identifier: &str,
+) -> Result<LoginAttempt, LoginAttemptErrorKind> {
+    let attempts = match read(connection, scope, identifier)? {
+        Some(login_attempt) if !login_attempt.is_stale() => {
+            login_attempt.attempts.saturating_add(1)
+        }
+        _ => 1,
+    };
+    let now = chrono::Local::now().naive_local();
+    let locked_until = get_lockout_duration(attempts, scope.free_attempts()).map(|d| now + d);
+
+    diesel::insert_into(dsl::login_attempts)
+        .values((
+            dsl::scope.eq(scope.as_str()),
+            dsl::identifier.eq(identifier),
+            dsl::attempts.eq(attempts),
+            dsl::last_attempt_time.eq(now),
+            dsl::locked_until.eq(locked_until),
+        ))
+        .on_conflict((dsl::scope, dsl::identifier))
+        .do_update()
+        .set((
+            dsl::attempts.eq(attempts),
+            dsl::last_attempt_time.eq(now),
+            dsl::locked_until.eq(locked_until),
+        ))
+        .get_result(connection)
+        .map_err(LoginAttemptErrorKind::UpdateFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{establish_connection, get_database_url};
+    use diesel::result::Error;
+
+    // Tests super::check() and super::register_failure().
+    #[test]
+    fn test_lockout() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        conn.test_transaction::<_, Error, _>(|| {
+            let email = "locked@example.com";
+            let ip_address = "192.0.2.1";
+            let ip = Some(ip_address);
+
+            // The allowed number of failed logins doesn't lock the account.
+            for _ in 1..FREE_ACCOUNT_ATTEMPTS {
+                assert_eq!(Ok(()), check(&conn, email, ip));
+                register_failure(&conn, email, ip).unwrap();
+            }
+            assert_eq!(Ok(()), check(&conn, email, ip));
+            let login_attempt = read(&conn, Scope::Account, email).unwrap().unwrap();
+            assert_eq!(FREE_ACCOUNT_ATTEMPTS - 1, login_attempt.attempts);
+            assert!(!login_attempt.is_locked());
+
+            // The next failed login locks the account.
+            register_failure(&conn, email, ip).unwrap();
+            let login_attempt = read(&conn, Scope::Account, email).unwrap().unwrap();
+            assert!(login_attempt.is_locked());
+            assert_eq!(
+                Err(LoginAttemptErrorKind::Locked(
+                    login_attempt.locked_until.unwrap()
+                )),
+                check(&conn, email, ip)
+            );
+
+            // The lockout applies to the account regardless of the IP address, and the IP address
+            // is not locked yet for other accounts.
+            assert!(check(&conn, email, None).is_err());
+            assert!(check(&conn, email, Some("192.0.2.2")).is_err());
+            assert_eq!(Ok(()), check(&conn, "other@example.com", ip));
+
+            // Every subsequent failure doubles the lockout.
+            register_failure(&conn, email, ip).unwrap();
+            let next_attempt = read(&conn, Scope::Account, email).unwrap().unwrap();
+            let lockout = next_attempt.locked_until.unwrap() - next_attempt.last_attempt_time;
+            assert_eq!(Duration::seconds(2 * BASE_LOCKOUT_SECONDS), lockout);
+
+            // Deleting the failed logins lifts the lockout.
+            delete(&conn, Scope::Account, email).unwrap();
+            assert_eq!(None, read(&conn, Scope::Account, email).unwrap());
+            assert_eq!(Ok(()), check(&conn, email, ip));
+
+            // The IP address is locked after it has been used for too many failed logins, even if
+            // they target different accounts.
+            let ip_attempts = read(&conn, Scope::Ip, ip_address)
+                .unwrap()
+                .unwrap()
+                .attempts;
+            for i in ip_attempts + 1..FREE_IP_ATTEMPTS {
+                register_failure(&conn, format!("user{}@example.com", i).as_str(), ip).unwrap();
+            }
+            assert_eq!(Ok(()), check(&conn, "other@example.com", ip));
+            register_failure(&conn, "other@example.com", ip).unwrap();
+            assert!(check(&conn, "new@example.com", ip).is_err());
+            assert_eq!(Ok(()), check(&conn, "new@example.com", None));
+
+            Ok(())
+        });
+    }
+
+    // Tests super::purge().
+    #[test]
+    fn test_purge() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        conn.test_transaction::<_, Error, _>(|| {
+            let long_ago = chrono::Local::now().naive_local() - Duration::days(2);
+            let in_the_future = chrono::Local::now().naive_local() + Duration::hours(1);
+            for (identifier, last_attempt_time, locked_until) in &[
+                ("recent", chrono::Local::now().naive_local(), None),
+                ("stale", long_ago, None),
+                ("stale-but-locked", long_ago, Some(in_the_future)),
+            ] {
+                diesel::insert_into(dsl::login_attempts)
+                    .values((
+                        dsl::scope.eq(Scope::Account.as_str()),
+                        dsl::identifier.eq(identifier),
+                        dsl::attempts.eq(1),
+                        dsl::last_attempt_time.eq(last_attempt_time),
+                        dsl::locked_until.eq(locked_until),
+                    ))
+                    .execute(&conn)?;
+            }
+
+            purge(&conn).unwrap();
+            assert!(read(&conn, Scope::Account, "recent").unwrap().is_some());
+            assert!(read(&conn, Scope::Account, "stale").unwrap().is_none());
+            assert!(read(&conn, Scope::Account, "stale-but-locked")
+                .unwrap()
+                .is_some());
+
+            // A stale counter starts again from scratch on the next failed login.
+            diesel::update(dsl::login_attempts.filter(dsl::identifier.eq("recent")))
+                .set((dsl::attempts.eq(4), dsl::last_attempt_time.eq(long_ago)))
+                .execute(&conn)?;
+            register_failure(&conn, "recent", None).unwrap();
+            let login_attempt = read(&conn, Scope::Account, "recent").unwrap().unwrap();
+            assert_eq!(1, login_attempt.attempts);
+
+            Ok(())
+        });
+    }
+}
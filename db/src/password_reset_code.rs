@@ -0,0 +1,442 @@
+use super::schema::password_reset_codes;
+use super::schema::password_reset_codes::dsl;
+use super::user::{User, UserErrorKind};
+use app::AppConfig;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::{thread_rng, Rng};
+use std::fmt;
+
+// The minimum and maximum values for a random password reset code.
+const MIN_VALUE: i32 = 100_000;
+const MAX_VALUE: i32 = 999_999;
+
+// The maximum number of password resets that can be attempted in 30 minutes.
+const MAX_ATTEMPTS: i16 = 5;
+
+#[derive(Associations, Clone, Debug, PartialEq, Queryable)]
+#[belongs_to(User, foreign_key = "id")]
+pub struct PasswordResetCode {
+    pub id: i32,
+    pub code: i32,
+    pub expiration_time: chrono::NaiveDateTime,
+    pub attempts: i16,
+}
+
+impl PasswordResetCode {
+    /// Returns whether or not the password reset code is expired.
+    pub fn is_expired(&self) -> bool {
+        self.expiration_time.lt(&chrono::Local::now().naive_local())
+    }
+
+    /// Returns whether or not the maximum number of attempts have been exceeded.
+    pub fn attempts_exceeded(&self) -> bool {
+        self.attempts.gt(&MAX_ATTEMPTS)
+    }
+
+    /// Checks whether the password reset code is valid. Will return an error if the code is expired
+    /// or has exceeded the maximum number of attempts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::password_reset_code::{PasswordResetCode, PasswordResetCodeErrorKind};
+    /// #
+    /// let mut password_reset_code = PasswordResetCode {
+    ///     id: 1,
+    ///     code: 123456,
+    ///     expiration_time: chrono::Local::now().checked_add_signed(chrono::Duration::minutes(30)).unwrap().naive_local(),
+    ///     attempts: 0,
+    /// };
+    ///
+    /// assert_eq!(Ok(()), password_reset_code.validate());
+    ///
+    /// password_reset_code.attempts = 6;
+    /// assert_eq!(Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded), password_reset_code.validate());
+    ///
+    /// password_reset_code.expiration_time = chrono::Local::now().checked_sub_signed(chrono::Duration::seconds(1)).unwrap().naive_local();
+    /// assert_eq!(Err(PasswordResetCodeErrorKind::Expired), password_reset_code.validate());
+    /// ```
+    pub fn validate(&self) -> Result<(), PasswordResetCodeErrorKind> {
+        if self.is_expired() {
+            return Err(PasswordResetCodeErrorKind::Expired);
+        }
+        if self.attempts_exceeded() {
+            return Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded);
+        }
+        Ok(())
+    }
+}
+
+// Possible errors thrown when handling password reset codes.
+#[derive(Debug, PartialEq)]
+pub enum PasswordResetCodeErrorKind {
+    // A new password reset code could not be created due to a database error.
+    CreationFailed(diesel::result::Error),
+    // A password reset code could not be deleted due to a database error.
+    DeletionFailed(diesel::result::Error),
+    // The expiration time overflowed. Not expected to occur before the end of the year 262143.
+    ExpirationTimeOverflow,
+    // The password reset code has expired.
+    Expired,
+    // The password reset code is invalid.
+    InvalidCode,
+    // The maximum number of attempts to retrieve or validate a password reset code has been
+    // exceeded.
+    MaxAttemptsExceeded,
+    // The new password could not be set.
+    PasswordUpdateFailed(UserErrorKind),
+    // Expired password reset codes could not be purged due to a database error.
+    PurgingFailed(diesel::result::Error),
+    // An existing password reset code could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for PasswordResetCodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PasswordResetCodeErrorKind::CreationFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when creating password reset code: {}",
+                    err
+                )
+            }
+            PasswordResetCodeErrorKind::DeletionFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when deleting password reset code: {}",
+                    err
+                )
+            }
+            PasswordResetCodeErrorKind::Expired => {
+                write!(f, "The password reset code has expired")
+            }
+            PasswordResetCodeErrorKind::ExpirationTimeOverflow => {
+                write!(f, "Expiration time overflow")
+            }
+            PasswordResetCodeErrorKind::InvalidCode => {
+                write!(f, "Invalid password reset code")
+            }
+            PasswordResetCodeErrorKind::MaxAttemptsExceeded => {
+                write!(f, "The maximum number of allowed attempts to retrieve or validate a password reset code has been exceeded. Please wait 30 minutes before requesting a new password reset code.")
+            }
+            PasswordResetCodeErrorKind::PasswordUpdateFailed(ref err) => {
+                write!(f, "Error when setting the new password: {}", err)
+            }
+            PasswordResetCodeErrorKind::PurgingFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when purging expired password reset codes: {}",
+                    err
+                )
+            }
+            PasswordResetCodeErrorKind::UpdateFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when updating password reset code: {}",
+                    err
+                )
+            }
+        }
+    }
+}
+
+/// Returns a password reset code for the given user.
+pub fn get(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<PasswordResetCode, PasswordResetCodeErrorKind> {
+    match read(connection, user.id) {
+        Some(c) => {
+            if c.is_expired() {
+                create(connection, user.id)
+            } else {
+                // If the password reset code already exists, increase the attempts counter before
+                // returning the code. This prevents an attacker flooding the user's inbox with
+                // password reset messages. Possibly returns a MaxAttemptsExceeded error.
+                increase_attempt_counter(connection, c)
+            }
+        }
+        None => create(connection, user.id),
+    }
+}
+
+/// Sets a new password for the given user if the given password reset code is valid. The password
+/// reset code is deleted afterwards so it cannot be reused.
+pub fn reset_password(
+    connection: &PgConnection,
+    user: User,
+    password_reset_code: i32,
+    password: &str,
+    config: &AppConfig,
+) -> Result<User, PasswordResetCodeErrorKind> {
+    match read(connection, user.id) {
+        Some(c) => {
+            c.validate()?;
+            if c.code == password_reset_code {
+                let user = super::user::set_password(connection, user, password, config)
+                    .map_err(PasswordResetCodeErrorKind::PasswordUpdateFailed)?;
+                delete(connection, &user)?;
+                return Ok(user);
+            }
+            increase_attempt_counter(connection, c)?;
+            Err(PasswordResetCodeErrorKind::InvalidCode)
+        }
+        // If no password reset code is present it has either expired and been purged, or it has
+        // never been requested. In both cases the user should request a new one.
+        None => Err(PasswordResetCodeErrorKind::Expired),
+    }
+}
+
+/// Purges all expired password reset codes.
+pub fn purge(connection: &PgConnection) -> Result<(), PasswordResetCodeErrorKind> {
+    let expiration_time = chrono::Local::now().naive_local();
+    diesel::delete(dsl::password_reset_codes.filter(dsl::expiration_time.lt(expiration_time)))
+        .execute(connection)
+        .map_err(PasswordResetCodeErrorKind::PurgingFailed)?;
+    Ok(())
+}
+
+/// Deletes the password reset code for the given user.
+pub fn delete(connection: &PgConnection, user: &User) -> Result<(), PasswordResetCodeErrorKind> {
+    diesel::delete(dsl::password_reset_codes.filter(dsl::id.eq(user.id)))
+        .execute(connection)
+        .map_err(PasswordResetCodeErrorKind::DeletionFailed)?;
+    Ok(())
+}
+
+// Retrieves the password reset code for the user with the given ID.
+//
+// Returns raw data from the database which may be stale. Use `get()` instead, this is guaranteed to
+// return a valid password reset code when possible, and has protection against brute force attacks.
+fn read(connection: &PgConnection, id: i32) -> Option<PasswordResetCode> {
+    dsl::password_reset_codes
+        .find(id)
+        .first::<PasswordResetCode>(connection)
+        .ok()
+}
+
+// Creates a password reset code for the user with the given ID, valid for 30 minutes. An existing
+// password reset code for the user will be overwritten.
+fn create(
+    connection: &PgConnection,
+    id: i32,
+) -> Result<PasswordResetCode, PasswordResetCodeErrorKind> {
+    let random_code = thread_rng().gen_range(MIN_VALUE, MAX_VALUE);
+    let expiration_time =
+        match chrono::Local::now().checked_add_signed(chrono::Duration::minutes(30)) {
+            Some(t) => t,
+            None => return Err(PasswordResetCodeErrorKind::ExpirationTimeOverflow),
+        }
+        .naive_local();
+
+    // There can only be one password reset code per user. Insert a new record or update an
+    // existing record.
+    diesel::insert_into(dsl::password_reset_codes)
+        .values((
+            dsl::id.eq(id),
+            dsl::code.eq(random_code),
+            dsl::expiration_time.eq(expiration_time),
+            dsl::attempts.eq(0),
+        ))
+        .on_conflict(dsl::id)
+        .do_update()
+        .set((
+            dsl::code.eq(random_code),
+            dsl::expiration_time.eq(expiration_time),
+            dsl::attempts.eq(0),
+        ))
+        .returning((dsl::id, dsl::code, dsl::expiration_time, dsl::attempts))
+        .get_result(connection)
+        .map_err(PasswordResetCodeErrorKind::CreationFailed)
+}
+
+// Increases the attempt counter.
+//
+// To prevent compromising a user account by brute forcing the password reset code we only allow a
+// limited number of validation attempts.
+fn increase_attempt_counter(
+    connection: &PgConnection,
+    password_reset_code: PasswordResetCode,
+) -> Result<PasswordResetCode, PasswordResetCodeErrorKind> {
+    // If the number of attempts have already exceeded the limit previously, don't bother to
+    // increase the counter but exit early.
+    if password_reset_code.attempts_exceeded() {
+        return Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded);
+    }
+
+    let password_reset_code =
+        diesel::update(dsl::password_reset_codes.filter(dsl::id.eq(password_reset_code.id)))
+            .set(dsl::attempts.eq(dsl::attempts + 1))
+            .returning((dsl::id, dsl::code, dsl::expiration_time, dsl::attempts))
+            .get_result::<PasswordResetCode>(connection)
+            .map_err(PasswordResetCodeErrorKind::UpdateFailed)?;
+
+    if password_reset_code.attempts_exceeded() {
+        return Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded);
+    }
+
+    Ok(password_reset_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{establish_connection, get_database_url, user};
+    use diesel::result::Error;
+
+    // Tests super::get().
+    #[test]
+    fn test_get() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = user::create(&connection, "test@example.com", "mypass", &config).unwrap();
+            assert!(read(&connection, user.id).is_none());
+
+            // Generate a password reset code. Activated users can reset their password too.
+            let user = user::activate(&connection, user).unwrap();
+            let password_reset_code = get(&connection, &user).unwrap();
+            assert_eq!(user.id, password_reset_code.id);
+            assert!(MIN_VALUE <= password_reset_code.code);
+            assert!(password_reset_code.code <= MAX_VALUE);
+            assert_eq!(0, password_reset_code.attempts);
+
+            // The same code can be retrieved 5 more times, after that an error is returned.
+            for attempts in 1..6 {
+                let retrieved = get(&connection, &user).unwrap();
+                assert_eq!(password_reset_code.code, retrieved.code);
+                assert_eq!(attempts, retrieved.attempts);
+            }
+            assert_eq!(
+                PasswordResetCodeErrorKind::MaxAttemptsExceeded,
+                get(&connection, &user).unwrap_err()
+            );
+
+            // When the code has expired a fresh one is generated.
+            expire_password_reset_code(&connection, user.id);
+            let fresh_password_reset_code = get(&connection, &user).unwrap();
+            assert_eq!(0, fresh_password_reset_code.attempts);
+            assert!(!fresh_password_reset_code.is_expired());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::reset_password().
+    #[test]
+    fn test_reset_password() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let email = "test@example.com";
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = user::create(&connection, email, "mypass", &config).unwrap();
+
+            // Without a password reset code an `Expired` error is returned.
+            assert_eq!(
+                PasswordResetCodeErrorKind::Expired,
+                reset_password(&connection, user.clone(), 0, "newpass", &config).unwrap_err()
+            );
+
+            // Wrong codes are rejected 5 times, after which the brute force protection kicks in and
+            // even the correct code is rejected.
+            let password_reset_code = get(&connection, &user).unwrap();
+            let wrong_code = password_reset_code.code + 1;
+            for _i in 0..5 {
+                assert_eq!(
+                    PasswordResetCodeErrorKind::InvalidCode,
+                    reset_password(&connection, user.clone(), wrong_code, "newpass", &config)
+                        .unwrap_err()
+                );
+            }
+            assert_eq!(
+                PasswordResetCodeErrorKind::MaxAttemptsExceeded,
+                reset_password(&connection, user.clone(), wrong_code, "newpass", &config)
+                    .unwrap_err()
+            );
+            assert_eq!(
+                PasswordResetCodeErrorKind::MaxAttemptsExceeded,
+                reset_password(
+                    &connection,
+                    user.clone(),
+                    password_reset_code.code,
+                    "newpass",
+                    &config
+                )
+                .unwrap_err()
+            );
+            assert!(user::verify_password(&connection, email, "mypass", &config).is_ok());
+
+            // An expired code is rejected.
+            expire_password_reset_code(&connection, user.id);
+            assert_eq!(
+                PasswordResetCodeErrorKind::Expired,
+                reset_password(
+                    &connection,
+                    user.clone(),
+                    password_reset_code.code,
+                    "newpass",
+                    &config
+                )
+                .unwrap_err()
+            );
+
+            // With a fresh code the password is changed and the code can not be used again.
+            let fresh_password_reset_code = get(&connection, &user).unwrap();
+            reset_password(
+                &connection,
+                user.clone(),
+                fresh_password_reset_code.code,
+                "newpass",
+                &config,
+            )
+            .unwrap();
+            assert!(user::verify_password(&connection, email, "newpass", &config).is_ok());
+            assert!(read(&connection, user.id).is_none());
+            assert_eq!(
+                PasswordResetCodeErrorKind::Expired,
+                reset_password(
+                    &connection,
+                    user,
+                    fresh_password_reset_code.code,
+                    "otherpass",
+                    &config
+                )
+                .unwrap_err()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::purge().
+    #[test]
+    fn test_purge() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user1 = user::create(&connection, "test1@example.com", "mypass", &config).unwrap();
+            let user2 = user::create(&connection, "test2@example.com", "mypass", &config).unwrap();
+            get(&connection, &user1).unwrap();
+            get(&connection, &user2).unwrap();
+            expire_password_reset_code(&connection, user2.id);
+
+            // Only the expired password reset code is purged.
+            purge(&connection).unwrap();
+            assert!(read(&connection, user1.id).is_some());
+            assert!(read(&connection, user2.id).is_none());
+
+            Ok(())
+        });
+    }
+
+    // Expire the password reset code for the given user by updating the expired time in the
+    // database.
+    fn expire_password_reset_code(connection: &PgConnection, id: i32) {
+        diesel::update(dsl::password_reset_codes.filter(dsl::id.eq(id)))
+            .set(dsl::expiration_time.eq(chrono::Local::now().naive_local()))
+            .execute(connection)
+            .unwrap();
+    }
+}
@@ -0,0 +1,395 @@
+use super::schema::registration_invitations::dsl;
+use super::user::{self, User, UserErrorKind};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use std::fmt;
+use validator::validate_email;
+
+// The number of days an invitation remains valid.
+const VALIDITY_DAYS: i64 = 14;
+
+// The characters that are used in invitation codes. Characters that are easily confused, such as 0
+// and o, are left out since the code is typed in by hand.
+const CODE_CHARACTERS: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// The number of characters in an invitation code, not counting the separators.
+const CODE_LENGTH: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct RegistrationInvitation {
+    pub id: i32,
+    pub email: String,
+    pub code: String,
+    pub invited_by: Option<i32>,
+    pub expiration_time: chrono::NaiveDateTime,
+}
+
+impl RegistrationInvitation {
+    /// Returns whether or not the invitation is expired.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::registration_invitation::RegistrationInvitation;
+    /// #
+    /// let mut invitation = RegistrationInvitation {
+    ///     id: 1,
+    ///     email: "jane@example.com".to_string(),
+    ///     code: "abcd-efgh-jkmn".to_string(),
+    ///     invited_by: None,
+    ///     expiration_time: chrono::Local::now().checked_add_signed(chrono::Duration::days(1)).unwrap().naive_local(),
+    /// };
+    /// assert_eq!(invitation.is_expired(), false);
+    /// #
+    /// # invitation.expiration_time = chrono::Local::now().checked_sub_signed(chrono::Duration::seconds(1)).unwrap().naive_local();
+    /// # assert_eq!(invitation.is_expired(), true);
+    /// ```
+    pub fn is_expired(&self) -> bool {
+        self.expiration_time.lt(&chrono::Local::now().naive_local())
+    }
+}
+
+// Possible errors thrown when handling registration invitations.
+#[derive(Debug, PartialEq)]
+pub enum RegistrationInvitationErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The expiration time overflowed. Not expected to occur before the end of the year 262143.
+    ExpirationTimeOverflow,
+    // The invitation has expired.
+    Expired,
+    // The passed in email address is not valid.
+    InvalidEmail(String),
+    // The invitation code is not valid for the email address.
+    InvalidCode,
+    // There is no invitation for the email address.
+    NotFound(String),
+    // An account already exists for the email address, so there is no need for an invitation.
+    UserAlreadyExists(String),
+    // The user could not be read due to a database error.
+    UserReadFailed(UserErrorKind),
+}
+
+impl fmt::Display for RegistrationInvitationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistrationInvitationErrorKind::DatabaseError(ref err) => {
+                write!(f, "Database error: {}", err)
+            }
+            RegistrationInvitationErrorKind::ExpirationTimeOverflow => {
+                write!(f, "Expiration time overflow")
+            }
+            RegistrationInvitationErrorKind::Expired => write!(f, "The invitation has expired"),
+            RegistrationInvitationErrorKind::InvalidEmail(ref email) => {
+                write!(f, "Invalid email address: {}", email)
+            }
+            RegistrationInvitationErrorKind::InvalidCode => {
+                write!(f, "The invitation code is not valid for this email address")
+            }
+            RegistrationInvitationErrorKind::NotFound(ref email) => {
+                write!(f, "There is no invitation for {}", email)
+            }
+            RegistrationInvitationErrorKind::UserAlreadyExists(ref email) => {
+                write!(f, "An account already exists for {}", email)
+            }
+            RegistrationInvitationErrorKind::UserReadFailed(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for RegistrationInvitationErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        RegistrationInvitationErrorKind::DatabaseError(e)
+    }
+}
+
+/// Invites the person with the given email address to create an account. If the person was already
+/// invited the existing invitation is replaced by a new one with a new code. Invitations created on
+/// the command line don't have an inviter.
+pub fn create(
+    connection: &PgConnection,
+    email: &str,
+    inviter: Option<&User>,
+) -> Result<RegistrationInvitation, RegistrationInvitationErrorKind> {
+    let email = email.trim();
+    if !validate_email(email) {
+        return Err(RegistrationInvitationErrorKind::InvalidEmail(
+            email.to_string(),
+        ));
+    }
+
+    match user::read(connection, email) {
+        Ok(_) => {
+            return Err(RegistrationInvitationErrorKind::UserAlreadyExists(
+                email.to_string(),
+            ))
+        }
+        Err(UserErrorKind::UserNotFound(_)) => {}
+        Err(e) => return Err(RegistrationInvitationErrorKind::UserReadFailed(e)),
+    }
+
+    let expiration_time =
+        match chrono::Local::now().checked_add_signed(chrono::Duration::days(VALIDITY_DAYS)) {
+            Some(t) => t,
+            None => return Err(RegistrationInvitationErrorKind::ExpirationTimeOverflow),
+        }
+        .naive_local();
+    let code = generate_code();
+    let invited_by = inviter.map(|u| u.id);
+
+    Ok(diesel::insert_into(dsl::registration_invitations)
+        .values((
+            dsl::email.eq(email),
+            dsl::code.eq(&code),
+            dsl::invited_by.eq(invited_by),
+            dsl::expiration_time.eq(expiration_time),
+        ))
+        .on_conflict(dsl::email)
+        .do_update()
+        .set((
+            dsl::code.eq(&code),
+            dsl::invited_by.eq(invited_by),
+            dsl::expiration_time.eq(expiration_time),
+        ))
+        .returning((
+            dsl::id,
+            dsl::email,
+            dsl::code,
+            dsl::invited_by,
+            dsl::expiration_time,
+        ))
+        .get_result(connection)?)
+}
+
+/// Retrieves the invitation for the given email address.
+pub fn read(connection: &PgConnection, email: &str) -> Option<RegistrationInvitation> {
+    dsl::registration_invitations
+        .filter(dsl::email.eq(email.trim()))
+        .first::<RegistrationInvitation>(connection)
+        .ok()
+}
+
+/// Returns the invitations that have not expired, ordered by email address.
+pub fn list(
+    connection: &PgConnection,
+) -> Result<Vec<RegistrationInvitation>, RegistrationInvitationErrorKind> {
+    Ok(dsl::registration_invitations
+        .filter(dsl::expiration_time.ge(chrono::Local::now().naive_local()))
+        .order(dsl::email)
+        .load(connection)?)
+}
+
+/// Checks that the given code is valid for the invitation that was sent to the given email address.
+/// The code is case insensitive, and separators are optional.
+pub fn validate(
+    connection: &PgConnection,
+    email: &str,
+    code: &str,
+) -> Result<RegistrationInvitation, RegistrationInvitationErrorKind> {
+    // Don't disclose whether or not an invitation has been sent to the email address.
+    let invitation = read(connection, email).ok_or(RegistrationInvitationErrorKind::InvalidCode)?;
+    if normalize_code(code) != normalize_code(invitation.code.as_str()) {
+        return Err(RegistrationInvitationErrorKind::InvalidCode);
+    }
+    if invitation.is_expired() {
+        return Err(RegistrationInvitationErrorKind::Expired);
+    }
+    Ok(invitation)
+}
+
+/// Deletes the invitation for the given email address.
+pub fn delete(
+    connection: &PgConnection,
+    email: &str,
+) -> Result<(), RegistrationInvitationErrorKind> {
+    let email = email.trim();
+    let result = diesel::delete(dsl::registration_invitations.filter(dsl::email.eq(email)))
+        .execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(RegistrationInvitationErrorKind::NotFound(email.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Purges all expired invitations.
+pub fn purge(connection: &PgConnection) -> Result<(), RegistrationInvitationErrorKind> {
+    let expiration_time = chrono::Local::now().naive_local();
+    diesel::delete(dsl::registration_invitations.filter(dsl::expiration_time.lt(expiration_time)))
+        .execute(connection)?;
+    Ok(())
+}
+
+// Returns a new random invitation code, in groups of 4 characters separated by dashes.
+fn generate_code() -> String {
+    let mut rng = thread_rng();
+    let characters: Vec<char> = (0..CODE_LENGTH)
+        .map(|_| CODE_CHARACTERS[rng.gen_range(0, CODE_CHARACTERS.len())] as char)
+        .collect();
+    characters
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Normalizes an invitation code for comparison, removing separators and whitespace.
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::create().
+    #[test]
+    fn test_create() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let admin = create_test_user(&conn, &config);
+
+            // Invalid email addresses are rejected.
+            assert_eq!(
+                RegistrationInvitationErrorKind::InvalidEmail("invalid".to_string()),
+                create(&conn, "invalid", Some(&admin)).unwrap_err()
+            );
+
+            // People who already have an account don't need to be invited.
+            assert_eq!(
+                RegistrationInvitationErrorKind::UserAlreadyExists(admin.email.clone()),
+                create(&conn, admin.email.as_str(), None).unwrap_err()
+            );
+
+            let invitation = create(&conn, " jane@example.com ", Some(&admin)).unwrap();
+            assert_eq!("jane@example.com", invitation.email);
+            assert_eq!(Some(admin.id), invitation.invited_by);
+            assert!(!invitation.is_expired());
+            assert!(regex_is_match(invitation.code.as_str()));
+
+            // Inviting the same person again replaces the code.
+            let new_invitation = create(&conn, "jane@example.com", None).unwrap();
+            assert_eq!(invitation.id, new_invitation.id);
+            assert_eq!(None, new_invitation.invited_by);
+            assert_ne!(invitation.code, new_invitation.code);
+            assert_eq!(Some(new_invitation), read(&conn, "jane@example.com"));
+
+            Ok(())
+        });
+    }
+
+    // Tests super::validate().
+    #[test]
+    fn test_validate() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let invitation = create(&conn, "jane@example.com", None).unwrap();
+            create(&conn, "john@example.com", None).unwrap();
+
+            // The code is case insensitive and the separators are optional.
+            let code = invitation.code.as_str();
+            assert!(validate(&conn, "jane@example.com", code).is_ok());
+            assert!(validate(&conn, "jane@example.com", &code.to_uppercase()).is_ok());
+            assert!(validate(&conn, "jane@example.com", &code.replace("-", " ")).is_ok());
+            assert!(validate(&conn, "jane@example.com", &code.replace("-", "")).is_ok());
+
+            // The code is only valid for the email address the invitation was sent to.
+            assert_eq!(
+                RegistrationInvitationErrorKind::InvalidCode,
+                validate(&conn, "john@example.com", code).unwrap_err()
+            );
+            assert_eq!(
+                RegistrationInvitationErrorKind::InvalidCode,
+                validate(&conn, "unknown@example.com", code).unwrap_err()
+            );
+            assert_eq!(
+                RegistrationInvitationErrorKind::InvalidCode,
+                validate(&conn, "jane@example.com", "").unwrap_err()
+            );
+
+            // Expired invitations are not valid.
+            expire(&conn, "jane@example.com");
+            assert_eq!(
+                RegistrationInvitationErrorKind::Expired,
+                validate(&conn, "jane@example.com", code).unwrap_err()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::list(), super::delete() and super::purge().
+    #[test]
+    fn test_list_delete_purge() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            // Start from a clean slate.
+            diesel::delete(dsl::registration_invitations).execute(&conn)?;
+
+            create(&conn, "john@example.com", None).unwrap();
+            create(&conn, "jane@example.com", None).unwrap();
+            create(&conn, "expired@example.com", None).unwrap();
+            expire(&conn, "expired@example.com");
+
+            // Expired invitations are not listed.
+            let emails = |conn: &PgConnection| -> Vec<String> {
+                list(conn).unwrap().into_iter().map(|i| i.email).collect()
+            };
+            assert_eq!(vec!["jane@example.com", "john@example.com"], emails(&conn));
+
+            assert!(delete(&conn, "john@example.com").is_ok());
+            assert_eq!(vec!["jane@example.com"], emails(&conn));
+            assert_eq!(
+                RegistrationInvitationErrorKind::NotFound("john@example.com".to_string()),
+                delete(&conn, "john@example.com").unwrap_err()
+            );
+
+            // Purging removes the expired invitations only.
+            purge(&conn).unwrap();
+            assert!(read(&conn, "expired@example.com").is_none());
+            assert!(read(&conn, "jane@example.com").is_some());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::generate_code().
+    #[test]
+    fn test_generate_code() {
+        for _ in 0..100 {
+            assert!(regex_is_match(generate_code().as_str()));
+        }
+    }
+
+    // Sets the expiration time of the invitation for the given email address in the past.
+    fn expire(conn: &PgConnection, email: &str) {
+        let expiration_time = chrono::Local::now().naive_local() - chrono::Duration::seconds(1);
+        diesel::update(dsl::registration_invitations.filter(dsl::email.eq(email)))
+            .set(dsl::expiration_time.eq(expiration_time))
+            .execute(conn)
+            .unwrap();
+    }
+
+    // Checks that the given string is formatted as an invitation code.
+    fn regex_is_match(code: &str) -> bool {
+        code.len() == 14
+            && code.split('-').all(|group| {
+                group.len() == 4 && group.bytes().all(|c| CODE_CHARACTERS.contains(&c))
+            })
+    }
+}
@@ -0,0 +1,108 @@
+use super::schema::registration_notifications::dsl;
+use super::user::User;
+use chrono::Duration;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use std::fmt;
+
+// The number of minutes that need to pass before the owner of an account is notified again about
+// an attempt to register with their email address.
+const INTERVAL_MINUTES: i64 = 30;
+
+// Possible errors thrown when handling registration notifications.
+#[derive(Debug, PartialEq)]
+pub enum RegistrationNotificationErrorKind {
+    // The time of the last notification could not be read due to a database error.
+    ReadFailed(diesel::result::Error),
+    // The time of the last notification could not be recorded due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for RegistrationNotificationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegistrationNotificationErrorKind::ReadFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when reading registration notification: {}",
+                    err
+                )
+            }
+            RegistrationNotificationErrorKind::UpdateFailed(ref err) => {
+                write!(
+                    f,
+                    "Database error when recording registration notification: {}",
+                    err
+                )
+            }
+        }
+    }
+}
+
+/// Returns whether the given user can be notified about an attempt to register with their email
+/// address, and if so records the time of the notification.
+///
+/// Only one notification is allowed every 30 minutes, so that the registration form cannot be used
+/// to flood the inbox of the account owner.
+pub fn claim(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<bool, RegistrationNotificationErrorKind> {
+    let now = chrono::Local::now().naive_local();
+    let last_sent = dsl::registration_notifications
+        .find(user.id)
+        .select(dsl::sent_time)
+        .first::<chrono::NaiveDateTime>(connection)
+        .optional()
+        .map_err(RegistrationNotificationErrorKind::ReadFailed)?;
+    if let Some(last_sent) = last_sent {
+        if last_sent + Duration::minutes(INTERVAL_MINUTES) > now {
+            return Ok(false);
+        }
+    }
+
+    diesel::insert_into(dsl::registration_notifications)
+        .values((dsl::user_id.eq(user.id), dsl::sent_time.eq(now)))
+        .on_conflict(dsl::user_id)
+        .do_update()
+        .set(dsl::sent_time.eq(now))
+        .execute(connection)
+        .map_err(RegistrationNotificationErrorKind::UpdateFailed)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::create_test_user;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::claim().
+    #[test]
+    fn test_claim() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+
+            // The first notification is allowed, subsequent ones are throttled.
+            assert_eq!(Ok(true), claim(&conn, &user));
+            assert_eq!(Ok(false), claim(&conn, &user));
+            assert_eq!(Ok(false), claim(&conn, &user));
+
+            // Once the interval has passed a new notification is allowed.
+            let earlier =
+                chrono::Local::now().naive_local() - Duration::minutes(INTERVAL_MINUTES + 1);
+            diesel::update(dsl::registration_notifications.find(user.id))
+                .set(dsl::sent_time.eq(earlier))
+                .execute(&conn)
+                .unwrap();
+            assert_eq!(Ok(true), claim(&conn, &user));
+            assert_eq!(Ok(false), claim(&conn, &user));
+
+            Ok(())
+        });
+    }
+}
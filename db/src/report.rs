@@ -0,0 +1,1043 @@
+use super::category::Category;
+use super::expense::{self, Expense};
+use super::ledger::Ledger;
+use super::schema::{categories, expenses};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use diesel::dsl::sql;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Numeric};
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The total amount and number of expenses in a period.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Total {
+    pub amount: Decimal,
+    pub count: i64,
+}
+
+/// The total amount spent in a category during a period.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CategoryTotal {
+    pub category: Category,
+    pub amount: Decimal,
+}
+
+/// The total amount spent during a single interval of a time series.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct IntervalTotal {
+    /// The first day of the interval.
+    pub start: NaiveDate,
+    pub amount: Decimal,
+}
+
+/// The maximum length of a report period in days. Longer periods are rejected, since a time series
+/// contains an entry for every interval in the period.
+pub const MAX_PERIOD_DAYS: i64 = 3660;
+
+/// A period between two dates, inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Period {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Period {
+    /// Returns the period that is shifted by the given number of months. If the day of the month
+    /// does not exist in the target month, the last day of that month is used. Returns `None` if
+    /// the shifted period lies outside the range of supported dates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::NaiveDate;
+    /// # use db::report::Period;
+    /// #
+    /// let period = Period {
+    ///     start: NaiveDate::from_ymd(2021, 3, 1),
+    ///     end: NaiveDate::from_ymd(2021, 3, 31),
+    /// };
+    ///
+    /// // The previous month.
+    /// let previous = period.shift(-1).unwrap();
+    /// assert_eq!(NaiveDate::from_ymd(2021, 2, 1), previous.start);
+    /// assert_eq!(NaiveDate::from_ymd(2021, 2, 28), previous.end);
+    ///
+    /// // The same month last year.
+    /// let previous = period.shift(-12).unwrap();
+    /// assert_eq!(NaiveDate::from_ymd(2020, 3, 1), previous.start);
+    /// assert_eq!(NaiveDate::from_ymd(2020, 3, 31), previous.end);
+    ///
+    /// // There is no year before the earliest supported date.
+    /// let period = Period {
+    ///     start: chrono::naive::MIN_DATE,
+    ///     end: chrono::naive::MIN_DATE,
+    /// };
+    /// assert_eq!(None, period.shift(-12));
+    /// ```
+    pub fn shift(&self, months: i32) -> Option<Period> {
+        let shift = |date: NaiveDate| {
+            let index = (date.year() * 12 + date.month0() as i32).checked_add(months)?;
+            let (year, month) = (index.div_euclid(12), index.rem_euclid(12) as u32 + 1);
+            (1..=date.day())
+                .rev()
+                .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        };
+        Some(Period {
+            start: shift(self.start)?,
+            end: shift(self.end)?,
+        })
+    }
+
+    /// Checks that the start date does not lie after the end date, and that the period is not
+    /// longer than `MAX_PERIOD_DAYS`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::NaiveDate;
+    /// # use db::report::{Period, ReportErrorKind};
+    /// #
+    /// let period = Period {
+    ///     start: NaiveDate::from_ymd(2021, 3, 1),
+    ///     end: NaiveDate::from_ymd(2021, 3, 31),
+    /// };
+    /// assert_eq!(Ok(()), period.validate());
+    ///
+    /// let period = Period {
+    ///     start: NaiveDate::from_ymd(1900, 1, 1),
+    ///     end: NaiveDate::from_ymd(2021, 3, 31),
+    /// };
+    /// assert_eq!(
+    ///     Err(ReportErrorKind::PeriodTooLong(period.start, period.end)),
+    ///     period.validate()
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), ReportErrorKind> {
+        if self.start > self.end {
+            return Err(ReportErrorKind::InvalidPeriod(self.start, self.end));
+        }
+        if (self.end - self.start).num_days() >= MAX_PERIOD_DAYS {
+            return Err(ReportErrorKind::PeriodTooLong(self.start, self.end));
+        }
+        Ok(())
+    }
+}
+
+/// The spending in a category during two periods.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CategoryComparison {
+    pub category: Category,
+    pub previous: Decimal,
+    pub current: Decimal,
+    pub change: Decimal,
+    /// The relative change in percent. This is `None` if nothing was spent in the previous period.
+    pub percentage: Option<Decimal>,
+}
+
+/// A comparison of the spending in two periods.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Comparison {
+    pub previous_period: Period,
+    pub current_period: Period,
+    pub previous: Decimal,
+    pub current: Decimal,
+    pub change: Decimal,
+    pub percentage: Option<Decimal>,
+    /// The categories, ordered by the size of the absolute change, largest first.
+    pub categories: Vec<CategoryComparison>,
+    /// The ID of the category with the largest absolute change.
+    pub largest_change: Option<i32>,
+    /// The ID of the category with the largest relative change.
+    pub largest_percentage: Option<i32>,
+}
+
+/// The intervals by which the expenses in a time series are grouped. The day on which weeks start
+/// depends on the preferences of the user.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Interval {
+    Day,
+    Week,
+    Month,
+}
+
+impl Interval {
+    /// Returns the machine name of the interval, as it is used by PostgreSQL's `date_trunc()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::report::Interval;
+    /// #
+    /// assert_eq!("day", Interval::Day.as_str());
+    /// assert_eq!("week", Interval::Week.as_str());
+    /// assert_eq!("month", Interval::Month.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Interval::Day => "day",
+            Interval::Week => "week",
+            Interval::Month => "month",
+        }
+    }
+
+    /// Returns the first day of the interval that contains the given date. Weeks start on the given
+    /// day of the week. Returns `None` if that day lies before the earliest supported date.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::{NaiveDate, Weekday};
+    /// # use db::report::Interval;
+    /// #
+    /// let date = NaiveDate::from_ymd(2021, 3, 18);
+    /// let truncate = |interval: Interval, week_start| interval.truncate(date, week_start).unwrap();
+    /// assert_eq!(date, truncate(Interval::Day, Weekday::Mon));
+    /// assert_eq!(NaiveDate::from_ymd(2021, 3, 15), truncate(Interval::Week, Weekday::Mon));
+    /// assert_eq!(NaiveDate::from_ymd(2021, 3, 14), truncate(Interval::Week, Weekday::Sun));
+    /// assert_eq!(NaiveDate::from_ymd(2021, 3, 1), truncate(Interval::Month, Weekday::Mon));
+    ///
+    /// // The week containing the earliest supported date starts before it.
+    /// assert_eq!(None, Interval::Week.truncate(chrono::naive::MIN_DATE, Weekday::Mon));
+    /// ```
+    pub fn truncate(self, date: NaiveDate, week_start: Weekday) -> Option<NaiveDate> {
+        match self {
+            Interval::Day => Some(date),
+            Interval::Week => {
+                date.checked_sub_signed(Duration::days(days_since(date.weekday(), week_start)))
+            }
+            Interval::Month => date.with_day(1),
+        }
+    }
+
+    // Returns the SQL expression that returns the first day of the interval containing the date of
+    // an expense.
+    fn sql_truncate(self, week_start: Weekday) -> String {
+        match self {
+            // PostgreSQL's `date_trunc()` only supports weeks that start on Monday.
+            Interval::Week => format!(
+                "(expenses.date - ((EXTRACT(DOW FROM expenses.date)::int + 7 - {}) % 7))::date",
+                week_start.num_days_from_sunday()
+            ),
+            _ => format!("date_trunc('{}', expenses.date)::date", self.as_str()),
+        }
+    }
+
+    // Returns the first day of the interval following the one that starts on the given date, or
+    // `None` if it lies after the latest supported date.
+    fn next(self, start: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Interval::Day => start.checked_add_signed(Duration::days(1)),
+            Interval::Week => start.checked_add_signed(Duration::weeks(1)),
+            Interval::Month => match start.month() {
+                12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1),
+                month => NaiveDate::from_ymd_opt(start.year(), month + 1, 1),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Interval {
+    type Err = ReportErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "day" => Ok(Interval::Day),
+            "week" => Ok(Interval::Week),
+            "month" => Ok(Interval::Month),
+            _ => Err(ReportErrorKind::InvalidInterval(s.to_string())),
+        }
+    }
+}
+
+// Returns the number of days that passed since the most recent given weekday, counting from the
+// given day. Returns 0 if the day is the given weekday.
+fn days_since(day: Weekday, weekday: Weekday) -> i64 {
+    ((day.num_days_from_sunday() + 7 - weekday.num_days_from_sunday()) % 7) as i64
+}
+
+// Possible errors thrown when generating reports.
+#[derive(Debug, PartialEq)]
+pub enum ReportErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The interval of a time series is not known.
+    InvalidInterval(String),
+    // The start date of a period lies after the end date, or the period reaches beyond the range of
+    // supported dates.
+    InvalidPeriod(NaiveDate, NaiveDate),
+    // A period is longer than `MAX_PERIOD_DAYS`.
+    PeriodTooLong(NaiveDate, NaiveDate),
+}
+
+impl fmt::Display for ReportErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReportErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            ReportErrorKind::InvalidInterval(ref interval) => write!(
+                f,
+                "Invalid interval {}, expected 'day', 'week' or 'month'",
+                interval
+            ),
+            ReportErrorKind::InvalidPeriod(ref start, ref end) if start > end => write!(
+                f,
+                "The start date {} lies after the end date {}",
+                start, end
+            ),
+            ReportErrorKind::InvalidPeriod(ref start, ref end) => write!(
+                f,
+                "The period from {} to {} lies outside the range of supported dates",
+                start, end
+            ),
+            ReportErrorKind::PeriodTooLong(ref start, ref end) => write!(
+                f,
+                "The period from {} to {} is longer than {} days",
+                start, end, MAX_PERIOD_DAYS
+            ),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ReportErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        ReportErrorKind::DatabaseError(e)
+    }
+}
+
+/// Returns the total amount and number of expenses in the given ledger between the start and end
+/// dates, inclusive.
+pub fn get_total(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    start: &NaiveDate,
+    end: &NaiveDate,
+) -> Result<Total, ReportErrorKind> {
+    // Diesel does not support selecting multiple aggregates in a single query, so this is done
+    // using SQL literals.
+    let (amount, count): (Decimal, i64) = expenses::table
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .filter(expenses::date.between(start, end))
+        .select(sql::<(Numeric, BigInt)>(
+            "COALESCE(SUM(expenses.amount), 0.00), COUNT(*)",
+        ))
+        .first(connection)?;
+
+    Ok(Total { amount, count })
+}
+
+/// Returns the total amount spent per category in the given ledger between the start and end
+/// dates, inclusive. The categories with the highest totals are returned first. Categories without
+/// expenses in the period are omitted.
+pub fn get_category_totals(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    start: &NaiveDate,
+    end: &NaiveDate,
+    limit: Option<i64>,
+) -> Result<Vec<CategoryTotal>, ReportErrorKind> {
+    let mut statement = expenses::table
+        .inner_join(categories::table)
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .filter(expenses::date.between(start, end))
+        .group_by(categories::id)
+        .select((
+            categories::all_columns,
+            sql::<Numeric>("SUM(expenses.amount)"),
+        ))
+        .order((
+            sql::<Numeric>("SUM(expenses.amount)").desc(),
+            categories::name,
+        ))
+        .into_boxed();
+    if let Some(limit) = limit {
+        statement = statement.limit(limit);
+    }
+
+    Ok(statement
+        .load::<(Category, Decimal)>(connection)?
+        .into_iter()
+        .map(|(category, amount)| CategoryTotal { category, amount })
+        .collect())
+}
+
+/// Returns the total amount spent per category in the given ledger between the start and end
+/// dates, inclusive, rolled up through the category tree. If a parent category is given, the
+/// amounts are grouped by its direct children, otherwise by the root categories. Expenses that are
+/// booked on the parent category itself are listed under the parent. The categories with the
+/// highest totals are returned first.
+pub fn get_category_breakdown(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    start: &NaiveDate,
+    end: &NaiveDate,
+    parent: Option<&Category>,
+) -> Result<Vec<CategoryTotal>, ReportErrorKind> {
+    let totals = get_category_totals(connection, ledger, start, end, None)?;
+    let categories: HashMap<i32, Category> = categories::table
+        .filter(categories::ledger_id.eq(ledger.id))
+        .load::<Category>(connection)?
+        .into_iter()
+        .map(|category| (category.id, category))
+        .collect();
+
+    // Walk up the tree from each category until reaching a direct child of the parent.
+    let parent_id = parent.map(|p| p.id);
+    let mut breakdown: HashMap<i32, Decimal> = HashMap::new();
+    for total in totals {
+        let mut category = &total.category;
+        while category.parent_id != parent_id && Some(category.id) != parent_id {
+            match category.parent_id.and_then(|id| categories.get(&id)) {
+                Some(c) => category = c,
+                // The category is not part of the requested subtree.
+                None => break,
+            }
+        }
+        if category.parent_id == parent_id || Some(category.id) == parent_id {
+            *breakdown
+                .entry(category.id)
+                .or_insert_with(|| Decimal::new(0, 2)) += total.amount;
+        }
+    }
+
+    let mut breakdown: Vec<CategoryTotal> = breakdown
+        .into_iter()
+        .filter_map(|(id, amount)| {
+            categories.get(&id).map(|category| CategoryTotal {
+                category: category.clone(),
+                amount,
+            })
+        })
+        .collect();
+    breakdown.sort_by(|a, b| {
+        b.amount
+            .cmp(&a.amount)
+            .then_with(|| a.category.name.cmp(&b.category.name))
+    });
+
+    Ok(breakdown)
+}
+
+/// Returns the relative change between two amounts in percent, rounded to one decimal. Returns
+/// `None` if the previous amount is zero.
+///
+/// # Example
+///
+/// ```
+/// # use db::report::percentage_change;
+/// # use rust_decimal::Decimal;
+/// #
+/// let change = percentage_change(Decimal::new(200, 0), Decimal::new(246, 0));
+/// assert_eq!(Some(Decimal::new(230, 1)), change);
+///
+/// let change = percentage_change(Decimal::new(300, 0), Decimal::new(100, 0));
+/// assert_eq!(Some(Decimal::new(-667, 1)), change);
+///
+/// let change = percentage_change(Decimal::new(100, 0), Decimal::new(200, 0));
+/// assert_eq!("100.0", change.unwrap().to_string());
+///
+/// assert_eq!(None, percentage_change(Decimal::new(0, 2), Decimal::new(100, 0)));
+/// ```
+pub fn percentage_change(previous: Decimal, current: Decimal) -> Option<Decimal> {
+    if previous.is_zero() {
+        return None;
+    }
+    let mut percentage = ((current - previous) * Decimal::new(100, 0) / previous).round_dp(1);
+    percentage.rescale(1);
+    Some(percentage)
+}
+
+/// Compares the spending in the given ledger during two periods per category, rolled up through
+/// the category tree like in `get_category_breakdown()`. Categories that have expenses in either
+/// period are included. Both periods should be valid, see `Period::validate()`.
+pub fn compare(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    previous_period: &Period,
+    current_period: &Period,
+    parent: Option<&Category>,
+) -> Result<Comparison, ReportErrorKind> {
+    previous_period.validate()?;
+    current_period.validate()?;
+
+    let previous_totals = get_category_breakdown(
+        connection,
+        ledger,
+        &previous_period.start,
+        &previous_period.end,
+        parent,
+    )?;
+    let current_totals = get_category_breakdown(
+        connection,
+        ledger,
+        &current_period.start,
+        &current_period.end,
+        parent,
+    )?;
+
+    // Combine the totals of both periods per category.
+    let mut totals: HashMap<i32, (Category, Decimal, Decimal)> = HashMap::new();
+    for total in previous_totals {
+        totals.insert(
+            total.category.id,
+            (total.category, total.amount, Decimal::new(0, 2)),
+        );
+    }
+    for total in current_totals {
+        totals
+            .entry(total.category.id)
+            .or_insert_with(|| (total.category, Decimal::new(0, 2), Decimal::new(0, 2)))
+            .2 = total.amount;
+    }
+
+    let mut categories: Vec<CategoryComparison> = totals
+        .into_iter()
+        .map(|(_, (category, previous, current))| CategoryComparison {
+            category,
+            previous,
+            current,
+            change: current - previous,
+            percentage: percentage_change(previous, current),
+        })
+        .collect();
+    categories.sort_by(|a, b| {
+        b.change
+            .abs()
+            .cmp(&a.change.abs())
+            .then_with(|| a.category.name.cmp(&b.category.name))
+    });
+
+    let largest_change = categories
+        .first()
+        .filter(|c| !c.change.is_zero())
+        .map(|c| c.category.id);
+    let largest_percentage = categories
+        .iter()
+        .filter_map(|c| c.percentage.map(|p| (c.category.id, p.abs())))
+        .filter(|(_, p)| !p.is_zero())
+        .fold(
+            None,
+            |largest: Option<(i32, Decimal)>, (id, p)| match largest {
+                Some((_, l)) if l >= p => largest,
+                _ => Some((id, p)),
+            },
+        )
+        .map(|(id, _)| id);
+
+    let previous: Decimal = categories.iter().map(|c| c.previous).sum();
+    let current: Decimal = categories.iter().map(|c| c.current).sum();
+
+    Ok(Comparison {
+        previous_period: *previous_period,
+        current_period: *current_period,
+        previous,
+        current,
+        change: current - previous,
+        percentage: percentage_change(previous, current),
+        categories,
+        largest_change,
+        largest_percentage,
+    })
+}
+
+/// Returns the total amount spent in the given ledger per interval, between the start and end
+/// dates, inclusive. Weeks start on the given day of the week. Intervals without expenses are
+/// included with an amount of zero.
+pub fn get_time_series(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    start: &NaiveDate,
+    end: &NaiveDate,
+    interval: Interval,
+    week_start: Weekday,
+) -> Result<Vec<IntervalTotal>, ReportErrorKind> {
+    Period {
+        start: *start,
+        end: *end,
+    }
+    .validate()?;
+
+    // Determine the intervals before querying, so that periods which reach beyond the range of
+    // supported dates are rejected.
+    let invalid_period = || ReportErrorKind::InvalidPeriod(*start, *end);
+    let mut intervals = vec![];
+    let mut date = interval
+        .truncate(*start, week_start)
+        .ok_or_else(invalid_period)?;
+    while date <= *end {
+        intervals.push(date);
+        date = interval.next(date).ok_or_else(invalid_period)?;
+    }
+
+    // The interval and the first day of the week are taken from a fixed set of values so it is safe
+    // to include them in the query.
+    let interval_start = interval.sql_truncate(week_start);
+    let totals: HashMap<NaiveDate, Decimal> = expenses::table
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .filter(expenses::date.between(start, end))
+        .group_by(sql::<Date>(&interval_start))
+        .select((
+            sql::<Date>(&interval_start),
+            sql::<Numeric>("SUM(expenses.amount)"),
+        ))
+        .load::<(NaiveDate, Decimal)>(connection)?
+        .into_iter()
+        .collect();
+
+    Ok(intervals
+        .into_iter()
+        .map(|start| IntervalTotal {
+            start,
+            amount: totals
+                .get(&start)
+                .cloned()
+                .unwrap_or_else(|| Decimal::new(0, 2)),
+        })
+        .collect())
+}
+
+/// Returns the most recent expenses in the given ledger, together with their categories.
+pub fn get_latest_expenses(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    limit: i64,
+) -> Result<Vec<(Expense, Category)>, ReportErrorKind> {
+    Ok(expenses::table
+        .inner_join(categories::table)
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .select((expense::COLUMNS, categories::all_columns))
+        .order((expenses::date.desc(), expenses::id.desc()))
+        .limit(limit)
+        .load(connection)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use chrono::naive::{MAX_DATE, MIN_DATE};
+    use diesel::result::Error;
+    use std::str::FromStr;
+
+    // Tests super::get_total(), super::get_category_totals() and super::get_latest_expenses().
+    #[test]
+    fn test_totals() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = crate::ledger::get_default(&conn, &user).unwrap();
+            let food = crate::category::create(&conn, &user, &ledger, "Food", None, None).unwrap();
+            let fuel = crate::category::create(&conn, &user, &ledger, "Fuel", None, None).unwrap();
+            let rent = crate::category::create(&conn, &user, &ledger, "Rent", None, None).unwrap();
+            let start = NaiveDate::from_ymd(2021, 3, 1);
+            let end = NaiveDate::from_ymd(2021, 3, 31);
+
+            // Without any expenses the totals are empty.
+            let total = get_total(&conn, &ledger, &start, &end).unwrap();
+            assert_eq!(Decimal::new(0, 2), total.amount);
+            assert_eq!(0, total.count);
+            assert!(get_category_totals(&conn, &ledger, &start, &end, None)
+                .unwrap()
+                .is_empty());
+            assert!(get_latest_expenses(&conn, &ledger, 5).unwrap().is_empty());
+
+            let mut expenses = vec![];
+            for (category, amount, date) in [
+                (&rent, "900.00", "2021-02-28"),
+                (&food, "25.50", "2021-03-01"),
+                (&fuel, "60.00", "2021-03-12"),
+                (&food, "40.00", "2021-03-31"),
+                (&rent, "900.00", "2021-04-01"),
+            ]
+            .iter()
+            {
+                let amount = Decimal::from_str(amount).unwrap();
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                expenses.push(
+                    crate::expense::create(&conn, &user, &amount, category, None, Some(&date))
+                        .unwrap(),
+                );
+            }
+
+            // Only the expenses within the period are counted.
+            let total = get_total(&conn, &ledger, &start, &end).unwrap();
+            assert_eq!(Decimal::new(12550, 2), total.amount);
+            assert_eq!(3, total.count);
+
+            let totals = get_category_totals(&conn, &ledger, &start, &end, None).unwrap();
+            assert_eq!(
+                vec![
+                    CategoryTotal {
+                        category: food.clone(),
+                        amount: Decimal::new(6550, 2),
+                    },
+                    CategoryTotal {
+                        category: fuel.clone(),
+                        amount: Decimal::new(6000, 2),
+                    },
+                ],
+                totals
+            );
+            let totals = get_category_totals(&conn, &ledger, &start, &end, Some(1)).unwrap();
+            assert_eq!(1, totals.len());
+            assert_eq!(food, totals[0].category);
+
+            // The latest expenses are returned first.
+            let latest = get_latest_expenses(&conn, &ledger, 2).unwrap();
+            assert_eq!(
+                vec![(expenses[4].clone(), rent), (expenses[3].clone(), food)],
+                latest
+            );
+
+            // Expenses in other ledgers are not included.
+            let other_ledger = create_test_ledger(&conn, &user);
+            assert_eq!(
+                0,
+                get_total(&conn, &other_ledger, &start, &end).unwrap().count
+            );
+            assert!(get_latest_expenses(&conn, &other_ledger, 5)
+                .unwrap()
+                .is_empty());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::get_category_breakdown().
+    #[test]
+    fn test_get_category_breakdown() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = crate::ledger::get_default(&conn, &user).unwrap();
+            let create_category = |name: &str, parent: Option<&Category>| {
+                crate::category::create(&conn, &user, &ledger, name, None, parent).unwrap()
+            };
+            let food = create_category("Food", None);
+            let groceries = create_category("Groceries", Some(&food));
+            let vegetables = create_category("Vegetables", Some(&groceries));
+            let restaurants = create_category("Restaurants", Some(&food));
+            let housing = create_category("Housing", None);
+            let start = NaiveDate::from_ymd(2021, 3, 1);
+            let end = NaiveDate::from_ymd(2021, 3, 31);
+
+            for (category, amount, date) in [
+                (&food, "5.00", "2021-03-01"),
+                (&groceries, "20.00", "2021-03-02"),
+                (&vegetables, "12.50", "2021-03-03"),
+                (&restaurants, "45.00", "2021-03-04"),
+                (&housing, "900.00", "2021-03-05"),
+                (&restaurants, "100.00", "2021-04-01"),
+            ]
+            .iter()
+            {
+                let amount = Decimal::from_str(amount).unwrap();
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                crate::expense::create(&conn, &user, &amount, category, None, Some(&date)).unwrap();
+            }
+
+            // Without a parent the amounts are rolled up into the root categories.
+            assert_eq!(
+                vec![
+                    CategoryTotal {
+                        category: housing.clone(),
+                        amount: Decimal::new(90000, 2),
+                    },
+                    CategoryTotal {
+                        category: food.clone(),
+                        amount: Decimal::new(8250, 2),
+                    },
+                ],
+                get_category_breakdown(&conn, &ledger, &start, &end, None).unwrap()
+            );
+
+            // With a parent the amounts are rolled up into its direct children. Expenses on the
+            // parent itself are listed separately.
+            assert_eq!(
+                vec![
+                    CategoryTotal {
+                        category: restaurants.clone(),
+                        amount: Decimal::new(4500, 2),
+                    },
+                    CategoryTotal {
+                        category: groceries.clone(),
+                        amount: Decimal::new(3250, 2),
+                    },
+                    CategoryTotal {
+                        category: food.clone(),
+                        amount: Decimal::new(500, 2),
+                    },
+                ],
+                get_category_breakdown(&conn, &ledger, &start, &end, Some(&food)).unwrap()
+            );
+
+            // A category without children only contains its own expenses.
+            assert_eq!(
+                vec![CategoryTotal {
+                    category: vegetables.clone(),
+                    amount: Decimal::new(1250, 2),
+                }],
+                get_category_breakdown(&conn, &ledger, &start, &end, Some(&vegetables)).unwrap()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::compare().
+    #[test]
+    fn test_compare() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = crate::ledger::get_default(&conn, &user).unwrap();
+            let create_category = |name: &str, parent: Option<&Category>| {
+                crate::category::create(&conn, &user, &ledger, name, None, parent).unwrap()
+            };
+            let food = create_category("Food", None);
+            let groceries = create_category("Groceries", Some(&food));
+            let restaurants = create_category("Restaurants", Some(&food));
+            let housing = create_category("Housing", None);
+            let travel = create_category("Travel", None);
+
+            for (category, amount, date) in [
+                (&groceries, "100.00", "2020-03-10"),
+                (&restaurants, "100.00", "2020-03-20"),
+                (&housing, "900.00", "2020-03-01"),
+                (&groceries, "110.00", "2021-03-10"),
+                (&restaurants, "136.00", "2021-03-20"),
+                (&housing, "900.00", "2021-03-01"),
+                (&travel, "50.00", "2021-03-15"),
+            ]
+            .iter()
+            {
+                let amount = Decimal::from_str(amount).unwrap();
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                crate::expense::create(&conn, &user, &amount, category, None, Some(&date)).unwrap();
+            }
+
+            let current = Period {
+                start: NaiveDate::from_ymd(2021, 3, 1),
+                end: NaiveDate::from_ymd(2021, 3, 31),
+            };
+            let previous = current.shift(-12).unwrap();
+
+            // The root categories are compared. Categories without expenses in the previous
+            // period don't have a percentage.
+            let comparison = compare(&conn, &ledger, &previous, &current, None).unwrap();
+            assert_eq!(Decimal::new(110000, 2), comparison.previous);
+            assert_eq!(Decimal::new(119600, 2), comparison.current);
+            assert_eq!(Decimal::new(9600, 2), comparison.change);
+            assert_eq!(Some(Decimal::new(87, 1)), comparison.percentage);
+            let categories: Vec<(i32, Decimal, Option<Decimal>)> = comparison
+                .categories
+                .iter()
+                .map(|c| (c.category.id, c.change, c.percentage))
+                .collect();
+            assert_eq!(
+                vec![
+                    (travel.id, Decimal::new(5000, 2), None),
+                    (food.id, Decimal::new(4600, 2), Some(Decimal::new(230, 1))),
+                    (housing.id, Decimal::new(0, 2), Some(Decimal::new(0, 1))),
+                ],
+                categories
+            );
+            assert_eq!(Some(travel.id), comparison.largest_change);
+            assert_eq!(Some(food.id), comparison.largest_percentage);
+
+            // The subcategories of a category can be compared.
+            let comparison = compare(&conn, &ledger, &previous, &current, Some(&food)).unwrap();
+            assert_eq!(Decimal::new(20000, 2), comparison.previous);
+            assert_eq!(Decimal::new(24600, 2), comparison.current);
+            assert_eq!(Some(restaurants.id), comparison.largest_change);
+            assert_eq!(Some(restaurants.id), comparison.largest_percentage);
+            assert_eq!(
+                Some(Decimal::new(360, 1)),
+                comparison.categories[0].percentage
+            );
+
+            // The periods should be valid.
+            let invalid = Period {
+                start: current.end,
+                end: current.start,
+            };
+            assert_eq!(
+                ReportErrorKind::InvalidPeriod(current.end, current.start),
+                compare(&conn, &ledger, &previous, &invalid, None).unwrap_err()
+            );
+
+            // Neither period can be longer than the maximum number of days.
+            let long = Period {
+                start: previous.end - Duration::days(MAX_PERIOD_DAYS),
+                end: previous.end,
+            };
+            assert_eq!(
+                ReportErrorKind::PeriodTooLong(long.start, long.end),
+                compare(&conn, &ledger, &long, &current, None).unwrap_err()
+            );
+            assert_eq!(
+                ReportErrorKind::PeriodTooLong(long.start, long.end),
+                compare(&conn, &ledger, &previous, &long, None).unwrap_err()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::get_time_series().
+    #[test]
+    fn test_get_time_series() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let ledger = crate::ledger::get_default(&conn, &user).unwrap();
+            let category = create_test_category(&conn, &user);
+
+            for (amount, date) in [
+                ("10.00", "2021-02-27"),
+                ("20.00", "2021-03-01"),
+                ("5.00", "2021-03-01"),
+                ("7.50", "2021-03-03"),
+                ("40.00", "2021-03-15"),
+            ]
+            .iter()
+            {
+                let amount = Decimal::from_str(amount).unwrap();
+                let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+                crate::expense::create(&conn, &user, &amount, &category, None, Some(&date))
+                    .unwrap();
+            }
+
+            let totals = |start: NaiveDate, end: NaiveDate, interval: Interval| {
+                get_time_series(&conn, &ledger, &start, &end, interval, Weekday::Mon)
+                    .unwrap()
+                    .into_iter()
+                    .map(|t| (t.start, t.amount))
+                    .collect::<Vec<(NaiveDate, Decimal)>>()
+            };
+            let date = |day: u32| NaiveDate::from_ymd(2021, 3, day);
+
+            // Days without expenses are included.
+            assert_eq!(
+                vec![
+                    (date(1), Decimal::new(2500, 2)),
+                    (date(2), Decimal::new(0, 2)),
+                    (date(3), Decimal::new(750, 2)),
+                ],
+                totals(date(1), date(3), Interval::Day)
+            );
+
+            // Weeks start on Monday. The first week starts before the period, but only the
+            // expenses within the period are counted.
+            assert_eq!(
+                vec![
+                    (date(1), Decimal::new(750, 2)),
+                    (date(8), Decimal::new(0, 2)),
+                    (date(15), Decimal::new(4000, 2)),
+                ],
+                totals(date(2), date(15), Interval::Week)
+            );
+
+            // Weeks can also start on another day.
+            assert_eq!(
+                vec![
+                    (NaiveDate::from_ymd(2021, 2, 28), Decimal::new(750, 2)),
+                    (date(7), Decimal::new(0, 2)),
+                    (date(14), Decimal::new(4000, 2)),
+                ],
+                get_time_series(
+                    &conn,
+                    &ledger,
+                    &date(2),
+                    &date(15),
+                    Interval::Week,
+                    Weekday::Sun
+                )
+                .unwrap()
+                .into_iter()
+                .map(|t| (t.start, t.amount))
+                .collect::<Vec<(NaiveDate, Decimal)>>()
+            );
+
+            assert_eq!(
+                vec![
+                    (NaiveDate::from_ymd(2021, 2, 1), Decimal::new(1000, 2)),
+                    (date(1), Decimal::new(7250, 2)),
+                    (NaiveDate::from_ymd(2021, 4, 1), Decimal::new(0, 2)),
+                ],
+                totals(
+                    NaiveDate::from_ymd(2021, 2, 1),
+                    NaiveDate::from_ymd(2021, 4, 30),
+                    Interval::Month
+                )
+            );
+
+            // The start date cannot lie after the end date.
+            assert_eq!(
+                ReportErrorKind::InvalidPeriod(date(2), date(1)),
+                get_time_series(
+                    &conn,
+                    &ledger,
+                    &date(2),
+                    &date(1),
+                    Interval::Day,
+                    Weekday::Mon
+                )
+                .unwrap_err()
+            );
+
+            // Periods cannot be longer than the maximum number of days.
+            let start = date(1) - Duration::days(MAX_PERIOD_DAYS);
+            assert_eq!(
+                ReportErrorKind::PeriodTooLong(start, date(1)),
+                get_time_series(
+                    &conn,
+                    &ledger,
+                    &start,
+                    &date(1),
+                    Interval::Month,
+                    Weekday::Mon
+                )
+                .unwrap_err()
+            );
+
+            // Periods that reach beyond the range of supported dates are rejected.
+            let (start, end) = (MIN_DATE, MIN_DATE + Duration::days(1));
+            assert_eq!(
+                ReportErrorKind::InvalidPeriod(start, end),
+                get_time_series(&conn, &ledger, &start, &end, Interval::Week, Weekday::Mon)
+                    .unwrap_err()
+            );
+            let (start, end) = (MAX_DATE - Duration::days(1), MAX_DATE);
+            for interval in &[Interval::Day, Interval::Week, Interval::Month] {
+                assert_eq!(
+                    ReportErrorKind::InvalidPeriod(start, end),
+                    get_time_series(&conn, &ledger, &start, &end, *interval, Weekday::Mon)
+                        .unwrap_err()
+                );
+            }
+
+            Ok(())
+        });
+    }
+}
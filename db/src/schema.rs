@@ -7,27 +7,237 @@ table! {
     }
 }
 
+table! {
+    api_tokens (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Varchar,
+        token_hash -> Varchar,
+        scope -> Varchar,
+        created -> Timestamp,
+        expiration_time -> Nullable<Timestamp>,
+        last_used -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    budgets (category_id) {
+        category_id -> Int4,
+        amount -> Numeric,
+    }
+}
+
 table! {
     categories (id) {
         id -> Int4,
         name -> Varchar,
         description -> Nullable<Varchar>,
-        user_id -> Int4,
         parent_id -> Nullable<Int4>,
+        ledger_id -> Int4,
     }
 }
 
 table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::TsVector;
+
     expenses (id) {
         id -> Int4,
         amount -> Numeric,
         description -> Nullable<Varchar>,
         category_id -> Int4,
+        user_id -> Nullable<Int4>,
+        date -> Date,
+        ledger_id -> Int4,
+        search_vector -> TsVector,
+    }
+}
+
+table! {
+    email_change_codes (id) {
+        id -> Int4,
+        email -> Varchar,
+        code -> Int4,
+        expiration_time -> Timestamp,
+        attempts -> Int2,
+    }
+}
+
+table! {
+    expense_shares (id) {
+        expense_id -> Int4,
+        user_id -> Nullable<Int4>,
+        amount -> Numeric,
+        id -> Int4,
+    }
+}
+
+table! {
+    expense_splits (expense_id) {
+        expense_id -> Int4,
+        paid_by -> Nullable<Int4>,
+        method -> Varchar,
+    }
+}
+
+table! {
+    goal_contributions (id) {
+        id -> Int4,
+        goal_id -> Int4,
+        user_id -> Nullable<Int4>,
+        amount -> Numeric,
+        description -> Nullable<Varchar>,
+        date -> Date,
+    }
+}
+
+table! {
+    goals (id) {
+        id -> Int4,
+        ledger_id -> Int4,
+        name -> Varchar,
+        target_amount -> Numeric,
+        target_date -> Date,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    ledger_invitations (id) {
+        id -> Int4,
+        ledger_id -> Int4,
+        email -> Varchar,
+        role -> Varchar,
+        invited_by -> Int4,
+        expiration_time -> Timestamp,
+    }
+}
+
+table! {
+    ledger_members (ledger_id, user_id) {
+        ledger_id -> Int4,
+        user_id -> Int4,
+        role -> Varchar,
+    }
+}
+
+table! {
+    ledgers (id) {
+        id -> Int4,
+        name -> Varchar,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    login_attempts (scope, identifier) {
+        scope -> Varchar,
+        identifier -> Varchar,
+        attempts -> Int2,
+        last_attempt_time -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    password_reset_codes (id) {
+        id -> Int4,
+        code -> Int4,
+        expiration_time -> Timestamp,
+        attempts -> Int2,
+    }
+}
+
+table! {
+    recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        code_hash -> Varchar,
+    }
+}
+
+table! {
+    recurring_expenses (id) {
+        id -> Int4,
+        category_id -> Int4,
+        amount -> Numeric,
+        description -> Nullable<Varchar>,
+        day_of_month -> Int2,
+    }
+}
+
+table! {
+    registration_invitations (id) {
+        id -> Int4,
+        email -> Varchar,
+        code -> Varchar,
+        invited_by -> Nullable<Int4>,
+        expiration_time -> Timestamp,
+    }
+}
+
+table! {
+    registration_notifications (user_id) {
         user_id -> Int4,
+        sent_time -> Timestamp,
+    }
+}
+
+table! {
+    settlements (id) {
+        id -> Int4,
+        ledger_id -> Int4,
+        payer_id -> Nullable<Int4>,
+        payee_id -> Nullable<Int4>,
+        amount -> Numeric,
         date -> Date,
     }
 }
 
+table! {
+    totp_secrets (id) {
+        id -> Int4,
+        secret -> Varchar,
+        enabled -> Bool,
+        last_used_step -> Nullable<Int8>,
+        failed_attempts -> Int2,
+    }
+}
+
+table! {
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        issuer -> Varchar,
+        subject -> Varchar,
+        created -> Timestamp,
+        last_login -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_preferences (user_id) {
+        user_id -> Int4,
+        locale -> Varchar,
+        currency -> Varchar,
+        date_format -> Varchar,
+        first_day_of_week -> Varchar,
+        language -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    user_sessions (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token_hash -> Varchar,
+        created -> Timestamp,
+        last_seen -> Timestamp,
+        ip_address -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+    }
+}
+
 table! {
     users (id) {
         id -> Int4,
@@ -35,12 +245,65 @@ table! {
         password -> Varchar,
         created -> Timestamp,
         activated -> Bool,
+        admin -> Bool,
+        disabled -> Bool,
     }
 }
 
 joinable!(activation_codes -> users (id));
-joinable!(categories -> users (user_id));
+joinable!(api_tokens -> users (user_id));
+joinable!(budgets -> categories (category_id));
+joinable!(categories -> ledgers (ledger_id));
+joinable!(email_change_codes -> users (id));
+joinable!(expense_shares -> expense_splits (expense_id));
+joinable!(expense_shares -> users (user_id));
+joinable!(expense_splits -> expenses (expense_id));
+joinable!(expense_splits -> users (paid_by));
 joinable!(expenses -> categories (category_id));
+joinable!(expenses -> ledgers (ledger_id));
 joinable!(expenses -> users (user_id));
+joinable!(goal_contributions -> goals (goal_id));
+joinable!(goal_contributions -> users (user_id));
+joinable!(goals -> ledgers (ledger_id));
+joinable!(ledger_invitations -> ledgers (ledger_id));
+joinable!(ledger_invitations -> users (invited_by));
+joinable!(ledger_members -> ledgers (ledger_id));
+joinable!(ledger_members -> users (user_id));
+joinable!(password_reset_codes -> users (id));
+joinable!(recovery_codes -> users (user_id));
+joinable!(recurring_expenses -> categories (category_id));
+joinable!(registration_invitations -> users (invited_by));
+joinable!(registration_notifications -> users (user_id));
+joinable!(settlements -> ledgers (ledger_id));
+joinable!(totp_secrets -> users (id));
+joinable!(user_identities -> users (user_id));
+joinable!(user_preferences -> users (user_id));
+joinable!(user_sessions -> users (user_id));
 
-allow_tables_to_appear_in_same_query!(activation_codes, categories, expenses, users,);
+allow_tables_to_appear_in_same_query!(
+    activation_codes,
+    api_tokens,
+    budgets,
+    categories,
+    email_change_codes,
+    expense_shares,
+    expense_splits,
+    expenses,
+    goal_contributions,
+    goals,
+    ledger_invitations,
+    ledger_members,
+    ledgers,
+    login_attempts,
+    password_reset_codes,
+    recovery_codes,
+    recurring_expenses,
+    registration_invitations,
+    registration_notifications,
+    settlements,
+    totp_secrets,
+    user_identities,
+    user_preferences,
+    user_sessions,
+    users,
+);
@@ -0,0 +1,473 @@
+use super::ledger::{self, Ledger};
+use super::schema::{expense_shares, expense_splits, expenses, ledger_members, settlements, users};
+use super::user::User;
+use chrono::Utc;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A payment between two members of a ledger to settle their debts.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct Settlement {
+    pub id: i32,
+    pub ledger_id: i32,
+    /// The user who made the payment. This is `None` if the user has been deleted.
+    pub payer_id: Option<i32>,
+    /// The user who received the payment. This is `None` if the user has been deleted.
+    pub payee_id: Option<i32>,
+    pub amount: Decimal,
+    pub date: chrono::NaiveDate,
+}
+
+/// The balance of a user in a ledger. A positive amount means the user is owed money, a negative
+/// amount means the user owes money to the other members.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Balance {
+    pub user_id: i32,
+    pub email: String,
+    pub amount: Decimal,
+}
+
+/// An amount that is owed by one user to another.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Debt {
+    pub debtor_id: i32,
+    pub debtor: String,
+    pub creditor_id: i32,
+    pub creditor: String,
+    pub amount: Decimal,
+}
+
+// Possible errors thrown when handling settlements.
+#[derive(Debug, PartialEq)]
+pub enum SettlementErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The user does not have the required role in the ledger.
+    InsufficientPermissions(i32),
+    // The amount should be greater than 0.
+    InvalidAmount,
+    // The user is not a member of the ledger.
+    NotAMember(i32),
+    // A settlement does not exist.
+    NotFound(i32),
+    // The payer and payee of a settlement should be different users.
+    SamePayerAndPayee,
+}
+
+impl fmt::Display for SettlementErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SettlementErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            SettlementErrorKind::InsufficientPermissions(ref id) => write!(
+                f,
+                "Insufficient permissions to record settlements in ledger {}",
+                id
+            ),
+            SettlementErrorKind::InvalidAmount => {
+                write!(f, "Amount should be between 0.01 and 9999999.99")
+            }
+            SettlementErrorKind::NotAMember(ref id) => {
+                write!(f, "User {} is not a member of the ledger", id)
+            }
+            SettlementErrorKind::NotFound(ref id) => write!(f, "Settlement {} not found", id),
+            SettlementErrorKind::SamePayerAndPayee => {
+                write!(f, "The payer and the payee should be different users")
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for SettlementErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        SettlementErrorKind::DatabaseError(e)
+    }
+}
+
+/// Records a payment from the payer to the payee in the given ledger. The user needs to be an
+/// editor or owner of the ledger, and the payer and payee need to be members.
+pub fn create(
+    connection: &PgConnection,
+    user: &User,
+    ledger: &Ledger,
+    payer_id: i32,
+    payee_id: i32,
+    amount: &Decimal,
+    date: Option<&chrono::NaiveDate>,
+) -> Result<Settlement, SettlementErrorKind> {
+    match ledger::get_role(connection, ledger.id, user.id)? {
+        Some(role) if role.can_edit() => {}
+        _ => return Err(SettlementErrorKind::InsufficientPermissions(ledger.id)),
+    }
+
+    if payer_id == payee_id {
+        return Err(SettlementErrorKind::SamePayerAndPayee);
+    }
+
+    for user_id in &[payer_id, payee_id] {
+        if ledger::get_role(connection, ledger.id, *user_id)?.is_none() {
+            return Err(SettlementErrorKind::NotAMember(*user_id));
+        }
+    }
+
+    if *amount <= Decimal::new(0, 2) || *amount > Decimal::new(999_999_999, 2) {
+        return Err(SettlementErrorKind::InvalidAmount);
+    }
+
+    Ok(diesel::insert_into(settlements::table)
+        .values((
+            settlements::ledger_id.eq(ledger.id),
+            settlements::payer_id.eq(payer_id),
+            settlements::payee_id.eq(payee_id),
+            settlements::amount.eq(amount),
+            settlements::date.eq(date.unwrap_or(&Utc::now().naive_utc().date())),
+        ))
+        .returning((
+            settlements::id,
+            settlements::ledger_id,
+            settlements::payer_id,
+            settlements::payee_id,
+            settlements::amount,
+            settlements::date,
+        ))
+        .get_result(connection)?)
+}
+
+/// Retrieves the settlement with the given ID.
+pub fn read(connection: &PgConnection, id: i32) -> Option<Settlement> {
+    settlements::table
+        .find(id)
+        .first::<Settlement>(connection)
+        .ok()
+}
+
+/// Deletes the settlement with the given ID.
+pub fn delete(connection: &PgConnection, id: i32) -> Result<(), SettlementErrorKind> {
+    let result = diesel::delete(settlements::table.find(id)).execute(connection)?;
+
+    // Throw an error if nothing was deleted.
+    if result == 0 {
+        return Err(SettlementErrorKind::NotFound(id));
+    }
+
+    Ok(())
+}
+
+/// Returns the settlements of the given ledger, most recent first.
+pub fn list(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<Settlement>, SettlementErrorKind> {
+    Ok(settlements::table
+        .filter(settlements::ledger_id.eq(ledger.id))
+        .order((settlements::date.desc(), settlements::id.desc()))
+        .load(connection)?)
+}
+
+/// Returns the balances of the members of the given ledger, ordered by email address. The balances
+/// are calculated from the shared expenses and the settlements. Former members are included if
+/// their balance is not settled. Deleted users are left out.
+pub fn get_balances(
+    connection: &PgConnection,
+    ledger: &Ledger,
+) -> Result<Vec<Balance>, SettlementErrorKind> {
+    // The amounts of deleted users are collected under `None`.
+    let mut amounts: BTreeMap<Option<i32>, Decimal> = BTreeMap::new();
+
+    // Every member starts with an empty balance.
+    let member_ids: Vec<i32> = ledger_members::table
+        .filter(ledger_members::ledger_id.eq(ledger.id))
+        .select(ledger_members::user_id)
+        .load(connection)?;
+    for user_id in member_ids {
+        amounts.insert(Some(user_id), Decimal::zero());
+    }
+
+    // The payer of a shared expense is owed the full amount.
+    let paid: Vec<(Option<i32>, Decimal)> = expense_splits::table
+        .inner_join(expenses::table)
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .select((expense_splits::paid_by, expenses::amount))
+        .load(connection)?;
+    for (user_id, amount) in paid {
+        *amounts.entry(user_id).or_insert_with(Decimal::zero) += amount;
+    }
+
+    // The participants owe their share.
+    let owed: Vec<(Option<i32>, Decimal)> = expense_shares::table
+        .inner_join(expense_splits::table.inner_join(expenses::table))
+        .filter(expenses::ledger_id.eq(ledger.id))
+        .select((expense_shares::user_id, expense_shares::amount))
+        .load(connection)?;
+    for (user_id, amount) in owed {
+        *amounts.entry(user_id).or_insert_with(Decimal::zero) -= amount;
+    }
+
+    // Settlements move money from the payer to the payee.
+    let settled: Vec<(Option<i32>, Option<i32>, Decimal)> = settlements::table
+        .filter(settlements::ledger_id.eq(ledger.id))
+        .select((
+            settlements::payer_id,
+            settlements::payee_id,
+            settlements::amount,
+        ))
+        .load(connection)?;
+    for (payer_id, payee_id, amount) in settled {
+        *amounts.entry(payer_id).or_insert_with(Decimal::zero) += amount;
+        *amounts.entry(payee_id).or_insert_with(Decimal::zero) -= amount;
+    }
+
+    let user_ids: Vec<i32> = amounts.keys().flatten().cloned().collect();
+    let emails: Vec<(i32, String)> = users::table
+        .filter(users::id.eq_any(&user_ids))
+        .select((users::id, users::email))
+        .order(users::email)
+        .load(connection)?;
+
+    Ok(emails
+        .into_iter()
+        .map(|(user_id, email)| Balance {
+            user_id,
+            email,
+            amount: amounts[&Some(user_id)],
+        })
+        .collect())
+}
+
+/// Returns who owes whom to settle the given balances. The debts are simplified so that the
+/// number of payments is kept low: the largest debtor always pays the largest creditor first.
+///
+/// # Example
+///
+/// ```
+/// # use db::settlement::{get_debts, Balance};
+/// # use rust_decimal::Decimal;
+/// #
+/// let balance = |user_id, email: &str, amount| Balance {
+///     user_id,
+///     email: email.to_string(),
+///     amount: Decimal::new(amount, 2),
+/// };
+/// let balances = [
+///     balance(1, "alice@example.com", 6000),
+///     balance(2, "bob@example.com", -4000),
+///     balance(3, "carol@example.com", -2000),
+/// ];
+///
+/// let debts = get_debts(&balances);
+/// assert_eq!(2, debts.len());
+/// assert_eq!("bob@example.com", debts[0].debtor);
+/// assert_eq!("alice@example.com", debts[0].creditor);
+/// assert_eq!(Decimal::new(4000, 2), debts[0].amount);
+/// assert_eq!("carol@example.com", debts[1].debtor);
+/// assert_eq!(Decimal::new(2000, 2), debts[1].amount);
+/// ```
+pub fn get_debts(balances: &[Balance]) -> Vec<Debt> {
+    // Sort the creditors and debtors by the size of their balance, largest first.
+    let mut creditors: Vec<Balance> = balances
+        .iter()
+        .filter(|b| b.amount.is_sign_positive() && !b.amount.is_zero())
+        .cloned()
+        .collect();
+    creditors.sort_by_key(|b| std::cmp::Reverse(b.amount));
+    let mut debtors: Vec<Balance> = balances
+        .iter()
+        .filter(|b| b.amount.is_sign_negative() && !b.amount.is_zero())
+        .cloned()
+        .collect();
+    debtors.sort_by_key(|b| b.amount);
+
+    let mut debts = vec![];
+    let (mut c, mut d) = (0, 0);
+    while c < creditors.len() && d < debtors.len() {
+        let amount = creditors[c].amount.min(-debtors[d].amount);
+        debts.push(Debt {
+            debtor_id: debtors[d].user_id,
+            debtor: debtors[d].email.clone(),
+            creditor_id: creditors[c].user_id,
+            creditor: creditors[c].email.clone(),
+            amount,
+        });
+        creditors[c].amount -= amount;
+        debtors[d].amount += amount;
+        if creditors[c].amount.is_zero() {
+            c += 1;
+        }
+        if debtors[d].amount.is_zero() {
+            d += 1;
+        }
+    }
+
+    debts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::expense_share::{self, SplitMethod};
+    use crate::ledger::Role;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::create(), super::read(), super::list() and super::delete().
+    #[test]
+    fn test_crud() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let owner = create_test_user(&conn, &config);
+            let viewer = create_test_user(&conn, &config);
+            let stranger = create_test_user(&conn, &config);
+            let ledger = create_test_ledger(&conn, &owner);
+            ledger::add_member(&conn, &ledger, &viewer, Role::Viewer).unwrap();
+            let amount = Decimal::new(1250, 2);
+
+            // Viewers cannot record settlements.
+            assert_eq!(
+                Err(SettlementErrorKind::InsufficientPermissions(ledger.id)),
+                create(&conn, &viewer, &ledger, viewer.id, owner.id, &amount, None)
+            );
+            assert_eq!(
+                Err(SettlementErrorKind::SamePayerAndPayee),
+                create(&conn, &owner, &ledger, owner.id, owner.id, &amount, None)
+            );
+            assert_eq!(
+                Err(SettlementErrorKind::NotAMember(stranger.id)),
+                create(&conn, &owner, &ledger, stranger.id, owner.id, &amount, None)
+            );
+            assert_eq!(
+                Err(SettlementErrorKind::InvalidAmount),
+                create(
+                    &conn,
+                    &owner,
+                    &ledger,
+                    viewer.id,
+                    owner.id,
+                    &Decimal::zero(),
+                    None
+                )
+            );
+
+            let date = chrono::NaiveDate::from_ymd(2021, 2, 21);
+            let settlement = create(
+                &conn,
+                &owner,
+                &ledger,
+                viewer.id,
+                owner.id,
+                &amount,
+                Some(&date),
+            )
+            .unwrap();
+            assert_eq!(Some(viewer.id), settlement.payer_id);
+            assert_eq!(Some(owner.id), settlement.payee_id);
+            assert_eq!(amount, settlement.amount);
+            assert_eq!(date, settlement.date);
+            assert_eq!(Some(settlement.clone()), read(&conn, settlement.id));
+            assert_eq!(vec![settlement.clone()], list(&conn, &ledger).unwrap());
+
+            assert!(delete(&conn, settlement.id).is_ok());
+            assert_eq!(None, read(&conn, settlement.id));
+            assert_eq!(
+                Err(SettlementErrorKind::NotFound(settlement.id)),
+                delete(&conn, settlement.id)
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::get_balances() and super::get_debts().
+    #[test]
+    fn test_get_balances() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let alice = create_test_user(&conn, &config);
+            let bob = create_test_user(&conn, &config);
+            let carol = create_test_user(&conn, &config);
+            let ledger = ledger::get_default(&conn, &alice).unwrap();
+            ledger::add_member(&conn, &ledger, &bob, Role::Editor).unwrap();
+            ledger::add_member(&conn, &ledger, &carol, Role::Editor).unwrap();
+            let balance = |user: &User| {
+                get_balances(&conn, &ledger)
+                    .unwrap()
+                    .into_iter()
+                    .find(|b| b.user_id == user.id)
+                    .unwrap()
+                    .amount
+            };
+
+            // Expenses that are not shared do not affect the balances.
+            let cat = create_test_category(&conn, &alice);
+            create_test_expense(&conn, &alice, &cat);
+            assert_eq!(3, get_balances(&conn, &ledger).unwrap().len());
+            assert!(get_debts(&get_balances(&conn, &ledger).unwrap()).is_empty());
+
+            // Alice pays 90.00 for the three of them.
+            let amount = Decimal::new(9000, 2);
+            let expense = crate::expense::create(&conn, &alice, &amount, &cat, None, None).unwrap();
+            let participants = [(alice.id, None), (bob.id, None), (carol.id, None)];
+            expense_share::split(
+                &conn,
+                &alice,
+                &expense,
+                alice.id,
+                SplitMethod::Equal,
+                &participants,
+            )
+            .unwrap();
+            assert_eq!(Decimal::new(6000, 2), balance(&alice));
+            assert_eq!(Decimal::new(-3000, 2), balance(&bob));
+            assert_eq!(Decimal::new(-3000, 2), balance(&carol));
+
+            // Bob pays 30.00 for Carol only.
+            let amount = Decimal::new(3000, 2);
+            let expense = crate::expense::create(&conn, &bob, &amount, &cat, None, None).unwrap();
+            expense_share::split(
+                &conn,
+                &bob,
+                &expense,
+                bob.id,
+                SplitMethod::Equal,
+                &[(carol.id, None)],
+            )
+            .unwrap();
+            assert_eq!(Decimal::zero(), balance(&bob));
+            assert_eq!(Decimal::new(-6000, 2), balance(&carol));
+
+            let debts = get_debts(&get_balances(&conn, &ledger).unwrap());
+            assert_eq!(1, debts.len());
+            assert_eq!(carol.id, debts[0].debtor_id);
+            assert_eq!(alice.id, debts[0].creditor_id);
+            assert_eq!(Decimal::new(6000, 2), debts[0].amount);
+
+            // Carol settles part of her debt.
+            create(
+                &conn,
+                &carol,
+                &ledger,
+                carol.id,
+                alice.id,
+                &Decimal::new(4000, 2),
+                None,
+            )
+            .unwrap();
+            assert_eq!(Decimal::new(2000, 2), balance(&alice));
+            assert_eq!(Decimal::new(-2000, 2), balance(&carol));
+
+            // Former members remain in the balances until their debts are settled.
+            ledger::remove_member(&conn, &ledger, carol.id).unwrap();
+            assert_eq!(Decimal::new(-2000, 2), balance(&carol));
+
+            Ok(())
+        });
+    }
+}
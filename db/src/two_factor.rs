@@ -0,0 +1,640 @@
+use super::schema::{recovery_codes, totp_secrets};
+use super::user::{hash_password, verify_hash, HashVerification, User};
+use app::AppConfig;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use hmac::{Hmac, Mac, NewMac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{thread_rng, Rng};
+use sha1::Sha1;
+use std::fmt;
+
+/// The number of seconds during which a TOTP code is valid.
+pub const TIME_STEP: i64 = 30;
+
+/// The number of recovery codes that are generated when two-factor authentication is enabled.
+pub const RECOVERY_CODES: usize = 10;
+
+/// The number of incorrect codes that can be entered in the second login step before the password
+/// has to be entered again.
+pub const MAX_ATTEMPTS: i16 = 5;
+
+// The number of digits in a TOTP code.
+const DIGITS: u32 = 6;
+
+// The number of time steps before and after the current one for which codes are still accepted, to
+// allow for clock drift between the server and the authenticator app.
+const ALLOWED_DRIFT: i64 = 1;
+
+// The characters used in recovery codes. Characters that are easily confused are left out.
+const RECOVERY_CODE_CHARACTERS: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// The number of characters in a recovery code, not counting the dash in the middle.
+const RECOVERY_CODE_LENGTH: usize = 10;
+
+/// The TOTP secret of a user. Two-factor authentication is only enabled once the user has confirmed
+/// the secret by entering a valid code.
+#[derive(Clone, Debug, PartialEq, Queryable)]
+pub struct TotpSecret {
+    pub id: i32,
+    /// The shared secret, base32 encoded.
+    pub secret: String,
+    pub enabled: bool,
+    /// The time step of the last code that was used to log in. Codes can only be used once.
+    pub last_used_step: Option<i64>,
+    /// The number of incorrect codes entered since the password was last verified.
+    pub failed_attempts: i16,
+}
+
+// Possible errors thrown when handling two-factor authentication.
+#[derive(Debug, PartialEq)]
+pub enum TwoFactorErrorKind {
+    // Two-factor authentication is already enabled for the user.
+    AlreadyEnabled,
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The TOTP code or recovery code is not valid.
+    InvalidCode,
+    // The stored TOTP secret could not be decoded.
+    InvalidSecret,
+    // Too many incorrect codes have been entered since the password was last verified.
+    MaxAttemptsExceeded,
+    // Two-factor authentication has not been set up for the user.
+    NotEnabled,
+    // A recovery code could not be hashed.
+    RecoveryCodeHashFailed(argonautica::Error),
+}
+
+impl fmt::Display for TwoFactorErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TwoFactorErrorKind::AlreadyEnabled => {
+                write!(f, "Two-factor authentication is already enabled")
+            }
+            TwoFactorErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            TwoFactorErrorKind::InvalidCode => write!(f, "Invalid authentication code"),
+            TwoFactorErrorKind::InvalidSecret => write!(f, "Invalid two-factor secret"),
+            TwoFactorErrorKind::MaxAttemptsExceeded => {
+                write!(f, "Too many incorrect authentication codes")
+            }
+            TwoFactorErrorKind::NotEnabled => {
+                write!(f, "Two-factor authentication has not been set up")
+            }
+            TwoFactorErrorKind::RecoveryCodeHashFailed(ref err) => {
+                write!(f, "Recovery code hashing error: {}", err)
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for TwoFactorErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        TwoFactorErrorKind::DatabaseError(e)
+    }
+}
+
+/// Generates the TOTP code for the given secret and time step, as defined in RFC 6238.
+///
+/// # Example
+///
+/// ```
+/// # use db::two_factor::generate_code;
+/// #
+/// // Test vectors from RFC 6238, truncated to 6 digits.
+/// let secret = b"12345678901234567890";
+/// assert_eq!(287_082, generate_code(secret, 59 / 30));
+/// assert_eq!(81_804, generate_code(secret, 1_111_111_109 / 30));
+/// assert_eq!(5_924, generate_code(secret, 1_234_567_890 / 30));
+/// ```
+pub fn generate_code(secret: &[u8], step: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_varkey(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation, as defined in RFC 4226.
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    value % 10u32.pow(DIGITS)
+}
+
+/// Returns the otpauth URI for the given account and secret. Authenticator apps can be set up by
+/// scanning this URI in a QR code.
+///
+/// # Example
+///
+/// ```
+/// # use db::two_factor::get_otpauth_uri;
+/// #
+/// assert_eq!(
+///     "otpauth://totp/firetrack:jane%40example%2Ecom?secret=JBSWY3DPEHPK3PXP&issuer=firetrack&digits=6&period=30",
+///     get_otpauth_uri("jane@example.com", "JBSWY3DPEHPK3PXP")
+/// );
+/// ```
+pub fn get_otpauth_uri(email: &str, secret: &str) -> String {
+    let issuer = utf8_percent_encode(app::APPLICATION_NAME, NON_ALPHANUMERIC);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&digits={}&period={}",
+        issuer,
+        utf8_percent_encode(email, NON_ALPHANUMERIC),
+        secret,
+        issuer,
+        DIGITS,
+        TIME_STEP
+    )
+}
+
+/// Starts the two-factor authentication setup for the given user by generating a new TOTP secret.
+/// Two-factor authentication is enabled after the secret has been confirmed using
+/// `confirm_enrollment()`.
+pub fn start_enrollment(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<TotpSecret, TwoFactorErrorKind> {
+    if is_enabled(connection, user)? {
+        return Err(TwoFactorErrorKind::AlreadyEnabled);
+    }
+
+    let bytes: [u8; 20] = thread_rng().gen();
+    let secret = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes);
+
+    let totp_secret = diesel::insert_into(totp_secrets::table)
+        .values((
+            totp_secrets::id.eq(user.id),
+            totp_secrets::secret.eq(&secret),
+            totp_secrets::enabled.eq(false),
+        ))
+        .on_conflict(totp_secrets::id)
+        .do_update()
+        .set((
+            totp_secrets::secret.eq(&secret),
+            totp_secrets::last_used_step.eq(None::<i64>),
+            totp_secrets::failed_attempts.eq(0),
+        ))
+        .get_result(connection)?;
+    Ok(totp_secret)
+}
+
+/// Enables two-factor authentication for the given user if the code matches the TOTP secret that
+/// was generated by `start_enrollment()`. Returns a fresh set of recovery codes. These are only
+/// stored as hashes, so they should be shown to the user right away.
+pub fn confirm_enrollment(
+    connection: &PgConnection,
+    user: &User,
+    code: &str,
+    config: &AppConfig,
+) -> Result<Vec<String>, TwoFactorErrorKind> {
+    confirm_enrollment_at(
+        connection,
+        user,
+        code,
+        chrono::Utc::now().timestamp(),
+        config,
+    )
+}
+
+/// Returns whether two-factor authentication is enabled for the given user.
+pub fn is_enabled(connection: &PgConnection, user: &User) -> Result<bool, TwoFactorErrorKind> {
+    Ok(matches!(read(connection, user.id)?, Some(s) if s.enabled))
+}
+
+/// Verifies the code that is entered in the second login step. This is either a TOTP code, or one
+/// of the recovery codes. Each TOTP code and recovery code can only be used once.
+///
+/// Incorrect codes are counted. Once `MAX_ATTEMPTS` incorrect codes have been entered no more codes
+/// are accepted until the counter is reset with `reset_attempts()` after the password has been
+/// verified again.
+pub fn verify(
+    connection: &PgConnection,
+    user: &User,
+    code: &str,
+    config: &AppConfig,
+) -> Result<(), TwoFactorErrorKind> {
+    verify_at(
+        connection,
+        user,
+        code,
+        chrono::Utc::now().timestamp(),
+        config,
+    )
+}
+
+/// Resets the counter of incorrect codes for the given user. This is called when the password has
+/// been verified in the first login step.
+pub fn reset_attempts(connection: &PgConnection, user: &User) -> Result<(), TwoFactorErrorKind> {
+    diesel::update(totp_secrets::table.filter(totp_secrets::id.eq(user.id)))
+        .set(totp_secrets::failed_attempts.eq(0))
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Replaces the recovery codes of the given user with a fresh set. Returns the new codes.
+pub fn generate_recovery_codes(
+    connection: &PgConnection,
+    user: &User,
+    config: &AppConfig,
+) -> Result<Vec<String>, TwoFactorErrorKind> {
+    let mut rng = thread_rng();
+    let codes: Vec<String> = (0..RECOVERY_CODES)
+        .map(|_| {
+            let code: String = (0..RECOVERY_CODE_LENGTH)
+                .map(|_| {
+                    let index = rng.gen_range(0, RECOVERY_CODE_CHARACTERS.len());
+                    RECOVERY_CODE_CHARACTERS[index] as char
+                })
+                .collect();
+            format!(
+                "{}-{}",
+                &code[..RECOVERY_CODE_LENGTH / 2],
+                &code[RECOVERY_CODE_LENGTH / 2..]
+            )
+        })
+        .collect();
+
+    let mut hashes = vec![];
+    for code in &codes {
+        let hash = hash_password(
+            normalize_recovery_code(code).as_str(),
+            config.secret_key(),
+            config.hasher_memory_size(),
+            config.hasher_iterations(),
+        )
+        .map_err(TwoFactorErrorKind::RecoveryCodeHashFailed)?;
+        hashes.push((
+            recovery_codes::user_id.eq(user.id),
+            recovery_codes::code_hash.eq(hash),
+        ));
+    }
+
+    connection.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user.id)))
+            .execute(connection)?;
+        diesel::insert_into(recovery_codes::table)
+            .values(&hashes)
+            .execute(connection)
+    })?;
+
+    Ok(codes)
+}
+
+/// Returns the number of unused recovery codes of the given user.
+pub fn count_recovery_codes(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<i64, TwoFactorErrorKind> {
+    Ok(recovery_codes::table
+        .filter(recovery_codes::user_id.eq(user.id))
+        .count()
+        .get_result(connection)?)
+}
+
+/// Disables two-factor authentication for the given user. The TOTP secret and recovery codes are
+/// deleted.
+pub fn disable(connection: &PgConnection, user: &User) -> Result<(), TwoFactorErrorKind> {
+    connection.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(recovery_codes::table.filter(recovery_codes::user_id.eq(user.id)))
+            .execute(connection)?;
+        diesel::delete(totp_secrets::table.filter(totp_secrets::id.eq(user.id))).execute(connection)
+    })?;
+    Ok(())
+}
+
+/// Retrieves the TOTP secret of the user with the given ID, if there is one.
+pub fn read(connection: &PgConnection, id: i32) -> Result<Option<TotpSecret>, TwoFactorErrorKind> {
+    Ok(totp_secrets::table
+        .find(id)
+        .first::<TotpSecret>(connection)
+        .optional()?)
+}
+
+// Enables two-factor authentication if the code is valid at the given Unix timestamp.
+fn confirm_enrollment_at(
+    connection: &PgConnection,
+    user: &User,
+    code: &str,
+    timestamp: i64,
+    config: &AppConfig,
+) -> Result<Vec<String>, TwoFactorErrorKind> {
+    let totp_secret = read(connection, user.id)?.ok_or(TwoFactorErrorKind::NotEnabled)?;
+    if totp_secret.enabled {
+        return Err(TwoFactorErrorKind::AlreadyEnabled);
+    }
+    let step = find_step(&totp_secret, code, timestamp)?;
+
+    diesel::update(totp_secrets::table.filter(totp_secrets::id.eq(user.id)))
+        .set((
+            totp_secrets::enabled.eq(true),
+            totp_secrets::last_used_step.eq(step),
+        ))
+        .execute(connection)?;
+    generate_recovery_codes(connection, user, config)
+}
+
+// Verifies the code entered in the second login step at the given Unix timestamp.
+fn verify_at(
+    connection: &PgConnection,
+    user: &User,
+    code: &str,
+    timestamp: i64,
+    config: &AppConfig,
+) -> Result<(), TwoFactorErrorKind> {
+    let totp_secret = match read(connection, user.id)? {
+        Some(s) if s.enabled => s,
+        _ => return Err(TwoFactorErrorKind::NotEnabled),
+    };
+    if totp_secret.failed_attempts >= MAX_ATTEMPTS {
+        return Err(TwoFactorErrorKind::MaxAttemptsExceeded);
+    }
+
+    match check_code(connection, user, &totp_secret, code, timestamp, config) {
+        Err(TwoFactorErrorKind::InvalidCode) => {
+            // Count the incorrect code. The counter is stored in the database so it cannot be
+            // reset by the client.
+            let failed_attempts: i16 =
+                diesel::update(totp_secrets::table.filter(totp_secrets::id.eq(user.id)))
+                    .set(totp_secrets::failed_attempts.eq(totp_secrets::failed_attempts + 1))
+                    .returning(totp_secrets::failed_attempts)
+                    .get_result(connection)?;
+            if failed_attempts >= MAX_ATTEMPTS {
+                return Err(TwoFactorErrorKind::MaxAttemptsExceeded);
+            }
+            Err(TwoFactorErrorKind::InvalidCode)
+        }
+        Ok(()) => reset_attempts(connection, user),
+        Err(e) => Err(e),
+    }
+}
+
+// Checks the TOTP code or recovery code entered in the second login step at the given Unix
+// timestamp. A code that is accepted is marked as used.
+fn check_code(
+    connection: &PgConnection,
+    user: &User,
+    totp_secret: &TotpSecret,
+    code: &str,
+    timestamp: i64,
+    config: &AppConfig,
+) -> Result<(), TwoFactorErrorKind> {
+    // A TOTP code consists of digits only, recovery codes always contain letters.
+    let code = code.trim();
+    if code.chars().all(|c| c.is_ascii_digit()) {
+        let step = find_step(totp_secret, code, timestamp)?;
+
+        // Reject codes that have already been used, so an intercepted code cannot be replayed.
+        if matches!(totp_secret.last_used_step, Some(last) if step <= last) {
+            return Err(TwoFactorErrorKind::InvalidCode);
+        }
+        diesel::update(totp_secrets::table.filter(totp_secrets::id.eq(user.id)))
+            .set(totp_secrets::last_used_step.eq(step))
+            .execute(connection)?;
+        return Ok(());
+    }
+
+    let code = normalize_recovery_code(code);
+    let hashes: Vec<(i32, String)> = recovery_codes::table
+        .filter(recovery_codes::user_id.eq(user.id))
+        .select((recovery_codes::id, recovery_codes::code_hash))
+        .load(connection)?;
+    for (id, hash) in hashes {
+        if verify_hash(hash.as_str(), code.as_str(), config) != HashVerification::Invalid {
+            // Recovery codes can only be used once.
+            diesel::delete(recovery_codes::table.filter(recovery_codes::id.eq(id)))
+                .execute(connection)?;
+            return Ok(());
+        }
+    }
+    Err(TwoFactorErrorKind::InvalidCode)
+}
+
+// Returns the time step for which the given TOTP code is valid, allowing for clock drift.
+fn find_step(
+    totp_secret: &TotpSecret,
+    code: &str,
+    timestamp: i64,
+) -> Result<i64, TwoFactorErrorKind> {
+    let secret = base32::decode(
+        base32::Alphabet::RFC4648 { padding: false },
+        totp_secret.secret.as_str(),
+    )
+    .ok_or(TwoFactorErrorKind::InvalidSecret)?;
+    let code: u32 = code
+        .trim()
+        .parse()
+        .map_err(|_| TwoFactorErrorKind::InvalidCode)?;
+
+    let current_step = timestamp / TIME_STEP;
+    (current_step - ALLOWED_DRIFT..=current_step + ALLOWED_DRIFT)
+        .find(|step| generate_code(&secret, *step as u64) == code)
+        .ok_or(TwoFactorErrorKind::InvalidCode)
+}
+
+// Strips the dash and whitespace from a recovery code and converts it to lowercase, so users can
+// enter it in any format.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::create_test_user;
+    use crate::{establish_connection, get_database_url};
+    use diesel::result::Error;
+
+    // A fixed point in time used in the tests.
+    const TIMESTAMP: i64 = 1_616_930_000;
+
+    // Tests super::start_enrollment() and super::confirm_enrollment().
+    #[test]
+    fn test_enrollment() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            assert!(!is_enabled(&conn, &user).unwrap());
+            assert_eq!(
+                TwoFactorErrorKind::NotEnabled,
+                confirm_enrollment_at(&conn, &user, "123456", TIMESTAMP, &config).unwrap_err()
+            );
+
+            // Starting the enrollment generates a secret, but does not enable two-factor
+            // authentication yet. Starting again replaces the secret.
+            let first = start_enrollment(&conn, &user).unwrap();
+            let totp_secret = start_enrollment(&conn, &user).unwrap();
+            assert_ne!(first.secret, totp_secret.secret);
+            assert_eq!(32, totp_secret.secret.len());
+            assert!(!totp_secret.enabled);
+            assert!(!is_enabled(&conn, &user).unwrap());
+
+            // A wrong code is rejected.
+            let code = get_code(&totp_secret, TIMESTAMP);
+            let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                confirm_enrollment_at(&conn, &user, &wrong_code, TIMESTAMP, &config).unwrap_err()
+            );
+
+            // The correct code enables two-factor authentication and returns recovery codes.
+            let recovery_codes =
+                confirm_enrollment_at(&conn, &user, &code, TIMESTAMP, &config).unwrap();
+            assert!(is_enabled(&conn, &user).unwrap());
+            assert_eq!(RECOVERY_CODES, recovery_codes.len());
+            for recovery_code in &recovery_codes {
+                assert_eq!(RECOVERY_CODE_LENGTH + 1, recovery_code.len());
+            }
+            assert_eq!(
+                RECOVERY_CODES as i64,
+                count_recovery_codes(&conn, &user).unwrap()
+            );
+
+            // Once enabled the enrollment cannot be restarted.
+            assert_eq!(
+                TwoFactorErrorKind::AlreadyEnabled,
+                start_enrollment(&conn, &user).unwrap_err()
+            );
+
+            // Disabling removes the secret and the recovery codes.
+            disable(&conn, &user).unwrap();
+            assert!(!is_enabled(&conn, &user).unwrap());
+            assert_eq!(0, count_recovery_codes(&conn, &user).unwrap());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::verify().
+    #[test]
+    fn test_verify() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let totp_secret = start_enrollment(&conn, &user).unwrap();
+            assert_eq!(
+                TwoFactorErrorKind::NotEnabled,
+                verify_at(&conn, &user, "123456", TIMESTAMP, &config).unwrap_err()
+            );
+            let code = get_code(&totp_secret, TIMESTAMP);
+            let recovery_codes =
+                confirm_enrollment_at(&conn, &user, &code, TIMESTAMP, &config).unwrap();
+
+            // The code that was used to confirm the enrollment cannot be used again.
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, &code, TIMESTAMP, &config).unwrap_err()
+            );
+
+            // A code from the previous time step is still accepted to allow for clock drift, but
+            // older codes are not.
+            let later = TIMESTAMP + 5 * TIME_STEP;
+            let old_code = get_code(&totp_secret, later - 2 * TIME_STEP);
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, &old_code, later, &config).unwrap_err()
+            );
+            let previous_code = get_code(&totp_secret, later - TIME_STEP);
+            verify_at(&conn, &user, &previous_code, later, &config).unwrap();
+
+            // Once a code has been used, codes from the same or earlier time steps are rejected.
+            let current_code = get_code(&totp_secret, later);
+            verify_at(&conn, &user, &current_code, later, &config).unwrap();
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, &previous_code, later, &config).unwrap_err()
+            );
+
+            // Recovery codes are accepted in any case and with or without dash, but only once.
+            let recovery_code = recovery_codes[0].replace("-", " ").to_uppercase();
+            verify_at(&conn, &user, &recovery_code, later, &config).unwrap();
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, &recovery_codes[0], later, &config).unwrap_err()
+            );
+            assert_eq!(
+                RECOVERY_CODES as i64 - 1,
+                count_recovery_codes(&conn, &user).unwrap()
+            );
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, "abcde-fghjk", later, &config).unwrap_err()
+            );
+
+            // Generating new recovery codes invalidates the old ones.
+            generate_recovery_codes(&conn, &user, &config).unwrap();
+            assert_eq!(
+                TwoFactorErrorKind::InvalidCode,
+                verify_at(&conn, &user, &recovery_codes[1], later, &config).unwrap_err()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::verify() and super::reset_attempts() with too many incorrect codes.
+    #[test]
+    fn test_max_attempts() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let totp_secret = start_enrollment(&conn, &user).unwrap();
+            let code = get_code(&totp_secret, TIMESTAMP);
+            confirm_enrollment_at(&conn, &user, &code, TIMESTAMP, &config).unwrap();
+
+            // A correct code resets the counter.
+            let later = TIMESTAMP + 5 * TIME_STEP;
+            for _ in 1..MAX_ATTEMPTS {
+                assert_eq!(
+                    TwoFactorErrorKind::InvalidCode,
+                    verify_at(&conn, &user, "000000", later, &config).unwrap_err()
+                );
+            }
+            verify_at(&conn, &user, &get_code(&totp_secret, later), later, &config).unwrap();
+            assert_eq!(0, read(&conn, user.id).unwrap().unwrap().failed_attempts);
+
+            // After too many incorrect codes even a correct code is refused.
+            let later = later + TIME_STEP;
+            for _ in 1..MAX_ATTEMPTS {
+                assert_eq!(
+                    TwoFactorErrorKind::InvalidCode,
+                    verify_at(&conn, &user, "000000", later, &config).unwrap_err()
+                );
+            }
+            assert_eq!(
+                TwoFactorErrorKind::MaxAttemptsExceeded,
+                verify_at(&conn, &user, "000000", later, &config).unwrap_err()
+            );
+            let code = get_code(&totp_secret, later);
+            assert_eq!(
+                TwoFactorErrorKind::MaxAttemptsExceeded,
+                verify_at(&conn, &user, &code, later, &config).unwrap_err()
+            );
+
+            // Once the password has been verified again codes are accepted.
+            reset_attempts(&conn, &user).unwrap();
+            verify_at(&conn, &user, &code, later, &config).unwrap();
+
+            Ok(())
+        });
+    }
+
+    // Returns the TOTP code for the given secret at the given timestamp.
+    fn get_code(totp_secret: &TotpSecret, timestamp: i64) -> String {
+        let secret = base32::decode(
+            base32::Alphabet::RFC4648 { padding: false },
+            totp_secret.secret.as_str(),
+        )
+        .unwrap();
+        format!(
+            "{:06}",
+            generate_code(&secret, (timestamp / TIME_STEP) as u64)
+        )
+    }
+}
@@ -1,8 +1,8 @@
 // Todo: Add a function for updating a user.
-use super::schema::users;
+use super::schema::{ledger_members, ledgers, users};
 use app::AppConfig;
-use argonautica::Hasher;
-use diesel::pg::PgConnection;
+use argonautica::{Hasher, Verifier};
+use diesel::pg::{Pg, PgConnection};
 use diesel::prelude::*;
 use std::fmt;
 use validator::validate_email;
@@ -14,6 +14,10 @@ pub struct User {
     pub password: String,
     pub created: chrono::NaiveDateTime,
     pub activated: bool,
+    pub admin: bool,
+    /// Disabled users cannot log in, and their sessions and API tokens are no longer accepted. This
+    /// is independent of the activation, which only verifies the email address.
+    pub disabled: bool,
 }
 
 // Possible errors being thrown when dealing with users.
@@ -21,13 +25,21 @@ pub struct User {
 pub enum UserErrorKind {
     // A user could not be activated due to a database error.
     ActivationFailed(diesel::result::Error),
+    // The admin flag of a user could not be updated due to a database error.
+    AdminUpdateFailed(diesel::result::Error),
+    // A user could not be disabled or enabled due to a database error.
+    DisabledUpdateFailed(diesel::result::Error),
     // The password is not correct.
     IncorrectPassword(String),
+    // The email address of a user could not be updated due to a database error.
+    EmailUpdateFailed(diesel::result::Error),
     // The passed in email address is not valid.
     InvalidEmail(String),
     // The user password could not be hashed. This is usually due to a requirement not being met,
     // such as a missing password.
     PasswordHashFailed(argonautica::Error),
+    // The password of a user could not be updated due to a database error.
+    PasswordUpdateFailed(diesel::result::Error),
     // A new user could not be created due to a database error.
     UserCreationFailed(diesel::result::Error),
     // A user could not be deleted due to a database error.
@@ -47,13 +59,25 @@ impl fmt::Display for UserErrorKind {
             UserErrorKind::ActivationFailed(ref err) => {
                 write!(f, "Database error when activating user: {}", err)
             }
+            UserErrorKind::AdminUpdateFailed(ref err) => {
+                write!(f, "Database error when updating admin flag: {}", err)
+            }
+            UserErrorKind::DisabledUpdateFailed(ref err) => {
+                write!(f, "Database error when updating disabled flag: {}", err)
+            }
             UserErrorKind::IncorrectPassword(ref email) => {
                 write!(f, "Wrong password for email address: {}", email)
             }
+            UserErrorKind::EmailUpdateFailed(ref err) => {
+                write!(f, "Database error when updating email address: {}", err)
+            }
             UserErrorKind::InvalidEmail(ref email) => write!(f, "Invalid email address: {}", email),
             UserErrorKind::PasswordHashFailed(ref err) => {
                 write!(f, "Password hashing error: {}", err)
             }
+            UserErrorKind::PasswordUpdateFailed(ref err) => {
+                write!(f, "Database error when updating password: {}", err)
+            }
             UserErrorKind::UserCreationFailed(ref err) => {
                 write!(f, "Database error when creating user: {}", err)
             }
@@ -102,6 +126,7 @@ pub fn create(
             users::password.eq(hashed_password),
             users::created.eq(chrono::Local::now().naive_local()),
             users::activated.eq(false),
+            users::admin.eq(false),
         ))
         .returning((
             users::id,
@@ -109,17 +134,48 @@ pub fn create(
             users::password,
             users::created,
             users::activated,
+            users::admin,
+            users::disabled,
         ))
         .get_result(connection)
         .map_err(UserErrorKind::UserCreationFailed)
 }
 
-/// Deletes the user with the given email.
+/// Deletes the user with the given email. Ledgers which have the user as their only member are
+/// deleted as well. The expenses, settlements and goal contributions of the user in shared ledgers
+/// are kept. If the user is the last owner of a shared ledger, the ownership is passed on to the
+/// member returned by `ledger::get_successor()`.
 pub fn delete(connection: &PgConnection, email: &str) -> Result<(), UserErrorKind> {
-    user_exists(connection, email)?;
+    let user = read(connection, email)?;
+
+    connection
+        .transaction(|| {
+            for ledger in crate::ledger::get_last_owner_ledgers(connection, user.id)? {
+                if let Some(successor) = crate::ledger::get_successor(connection, &ledger, &user)? {
+                    diesel::update(ledger_members::table.find((ledger.id, successor.id)))
+                        .set(ledger_members::role.eq(crate::ledger::Role::Owner))
+                        .execute(connection)?;
+                }
+            }
+
+            let ledger_ids: Vec<i32> = ledger_members::table
+                .filter(ledger_members::user_id.eq(user.id))
+                .select(ledger_members::ledger_id)
+                .load(connection)?;
+            let shared_ledger_ids: Vec<i32> = ledger_members::table
+                .filter(ledger_members::ledger_id.eq_any(&ledger_ids))
+                .filter(ledger_members::user_id.ne(user.id))
+                .select(ledger_members::ledger_id)
+                .load(connection)?;
+            diesel::delete(
+                ledgers::table
+                    .filter(ledgers::id.eq_any(&ledger_ids))
+                    .filter(ledgers::id.ne_all(&shared_ledger_ids)),
+            )
+            .execute(connection)?;
 
-    diesel::delete(users::table.filter(users::email.eq(email)))
-        .execute(connection)
+            diesel::delete(users::table.filter(users::id.eq(user.id))).execute(connection)
+        })
         .map_err(UserErrorKind::UserDeletionFailed)?;
     Ok(())
 }
@@ -139,7 +195,7 @@ fn user_exists(connection: &PgConnection, email: &str) -> Result<(), UserErrorKi
 }
 
 // Performs an Argon2 hash of the password.
-fn hash_password(
+pub(crate) fn hash_password(
     password: &str,
     secret: &str,
     memory_size: u32,
@@ -165,7 +221,124 @@ pub fn read(connection: &PgConnection, email: &str) -> Result<User, UserErrorKin
     }
 }
 
+/// Returns a page of users, ordered by email address. If a search string is given, only users whose
+/// email address contains it are returned. The search is case insensitive.
+pub fn list(
+    connection: &PgConnection,
+    search: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<User>, UserErrorKind> {
+    search_query(search)
+        .order(users::email)
+        .limit(limit)
+        .offset(offset)
+        .load::<User>(connection)
+        .map_err(UserErrorKind::UserReadFailed)
+}
+
+/// Counts the users whose email address contains the given search string, or all users if no
+/// search string is given.
+pub fn count(connection: &PgConnection, search: Option<&str>) -> Result<i64, UserErrorKind> {
+    search_query(search)
+        .count()
+        .get_result(connection)
+        .map_err(UserErrorKind::UserReadFailed)
+}
+
+// Returns a query that selects the users whose email address contains the given search string.
+fn search_query(search: Option<&str>) -> users::BoxedQuery<'static, Pg> {
+    let mut query = users::table.into_boxed();
+    if let Some(search) = search {
+        // Escape the wildcards so they are matched literally.
+        let pattern = search
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query = query.filter(users::email.ilike(format!("%{}%", pattern)));
+    }
+    query
+}
+
+// The result of verifying a password against a stored hash.
+#[derive(Debug, PartialEq)]
+pub(crate) enum HashVerification {
+    // The password does not match the hash.
+    Invalid,
+    // The password matches, and the hash has been created with the current secret key and hasher
+    // parameters.
+    Valid,
+    // The password matches, but the hash has been created with a previous secret key or with
+    // outdated hasher parameters.
+    Outdated,
+}
+
+// Verifies the password against the given hash. Hashes that have been created with one of the
+// previous secret keys are accepted too, so that the secret key can be rotated.
+pub(crate) fn verify_hash(hash: &str, password: &str, config: &AppConfig) -> HashVerification {
+    let is_valid = |secret: &str| {
+        Verifier::default()
+            .with_hash(hash)
+            .with_password(password)
+            .with_secret_key(secret)
+            .verify()
+            .unwrap_or(false)
+    };
+
+    if is_valid(config.secret_key()) {
+        if hash_is_outdated(
+            hash,
+            config.hasher_memory_size(),
+            config.hasher_iterations(),
+        ) {
+            return HashVerification::Outdated;
+        }
+        return HashVerification::Valid;
+    }
+    if config
+        .previous_secret_keys()
+        .iter()
+        .any(|secret| is_valid(secret.as_str()))
+    {
+        return HashVerification::Outdated;
+    }
+    HashVerification::Invalid
+}
+
+/// Returns whether the given Argon2 hash has been created with a different memory size or number
+/// of iterations than the given ones. Hashes of which the parameters cannot be determined are
+/// considered to be outdated.
+///
+/// # Example
+///
+/// ```
+/// # use db::user::hash_is_outdated;
+/// #
+/// let hash = "$argon2id$v=19$m=512,t=1,p=1$c29tZXNhbHQ$aGFzaA";
+/// assert_eq!(hash_is_outdated(hash, 512, 1), false);
+/// assert_eq!(hash_is_outdated(hash, 512, 2), true);
+/// assert_eq!(hash_is_outdated(hash, 4096, 1), true);
+/// assert_eq!(hash_is_outdated("invalid", 512, 1), true);
+/// ```
+pub fn hash_is_outdated(hash: &str, memory_size: u32, iterations: u32) -> bool {
+    let parameters = match hash.split('$').find(|s| s.starts_with("m=")) {
+        Some(parameters) => parameters,
+        None => return true,
+    };
+    let get_parameter = |name: &str| {
+        parameters
+            .split(',')
+            .filter_map(|p| p.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| value.parse::<u32>().ok())
+    };
+    get_parameter("m") != Some(memory_size) || get_parameter("t") != Some(iterations)
+}
+
 /// Verifies that the given email and password are valid. Returns the user if they match.
+///
+/// If the password hash has been created with a previous secret key or with outdated hasher
+/// parameters, the password is transparently rehashed using the current configuration.
 pub fn verify_password(
     connection: &PgConnection,
     email: &str,
@@ -174,10 +347,20 @@ pub fn verify_password(
 ) -> Result<User, UserErrorKind> {
     let user = read(connection, email)?;
 
-    if asserts::hashed_password_is_valid(user.password.as_str(), password, config.secret_key()) {
-        Ok(user)
-    } else {
-        Err(UserErrorKind::IncorrectPassword(email.to_string()))
+    match verify_hash(user.password.as_str(), password, config) {
+        HashVerification::Valid => Ok(user),
+        HashVerification::Outdated => {
+            // A failure to rehash should not prevent the user from logging in, the password will
+            // be rehashed on a next login.
+            match set_password(connection, user.clone(), password, config) {
+                Ok(user) => Ok(user),
+                Err(e) => {
+                    warn!("Password of user {} could not be rehashed: {}", email, e);
+                    Ok(user)
+                }
+            }
+        }
+        HashVerification::Invalid => Err(UserErrorKind::IncorrectPassword(email.to_string())),
     }
 }
 
@@ -198,12 +381,138 @@ pub fn activate(connection: &PgConnection, user: User) -> Result<User, UserError
             users::password,
             users::created,
             users::activated,
+            users::admin,
+            users::disabled,
         ))
         .get_result::<User>(connection)
         .map_err(UserErrorKind::ActivationFailed)?;
     Ok(user)
 }
 
+/// Disables or enables the given user. Disabled users cannot log in, and their sessions and API
+/// tokens are rejected.
+pub fn set_disabled(
+    connection: &PgConnection,
+    user: User,
+    disabled: bool,
+) -> Result<User, UserErrorKind> {
+    diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::disabled.eq(disabled))
+        .returning((
+            users::id,
+            users::email,
+            users::password,
+            users::created,
+            users::activated,
+            users::admin,
+            users::disabled,
+        ))
+        .get_result::<User>(connection)
+        .map_err(UserErrorKind::DisabledUpdateFailed)
+}
+
+/// Grants or revokes administrator privileges for the given user.
+pub fn set_admin(
+    connection: &PgConnection,
+    user: User,
+    admin: bool,
+) -> Result<User, UserErrorKind> {
+    diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::admin.eq(admin))
+        .returning((
+            users::id,
+            users::email,
+            users::password,
+            users::created,
+            users::activated,
+            users::admin,
+            users::disabled,
+        ))
+        .get_result::<User>(connection)
+        .map_err(UserErrorKind::AdminUpdateFailed)
+}
+
+/// Sets a new password for the given user.
+pub fn set_password(
+    connection: &PgConnection,
+    user: User,
+    password: &str,
+    config: &AppConfig,
+) -> Result<User, UserErrorKind> {
+    let hashed_password = hash_password(
+        password,
+        config.secret_key(),
+        config.hasher_memory_size(),
+        config.hasher_iterations(),
+    )
+    .map_err(UserErrorKind::PasswordHashFailed)?;
+
+    diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::password.eq(hashed_password))
+        .returning((
+            users::id,
+            users::email,
+            users::password,
+            users::created,
+            users::activated,
+            users::admin,
+            users::disabled,
+        ))
+        .get_result::<User>(connection)
+        .map_err(UserErrorKind::PasswordUpdateFailed)
+}
+
+/// Removes the password of the given user, so that it is no longer possible to log in with a
+/// password. A new password can be chosen using the password reset form.
+pub fn remove_password(connection: &PgConnection, user: User) -> Result<User, UserErrorKind> {
+    // An empty string is not a valid hash, so no password will ever match it.
+    diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::password.eq(""))
+        .returning((
+            users::id,
+            users::email,
+            users::password,
+            users::created,
+            users::activated,
+            users::admin,
+            users::disabled,
+        ))
+        .get_result::<User>(connection)
+        .map_err(UserErrorKind::PasswordUpdateFailed)
+}
+
+/// Changes the email address of the given user.
+///
+/// Note that this does not verify that the user owns the new email address. Use
+/// `db::email_change_code::change_email()` for this.
+pub fn set_email(
+    connection: &PgConnection,
+    user: User,
+    email: &str,
+) -> Result<User, UserErrorKind> {
+    if !validate_email(email) {
+        return Err(UserErrorKind::InvalidEmail(email.to_string()));
+    }
+
+    if user_exists(connection, email).is_ok() {
+        return Err(UserErrorKind::UserWithEmailAlreadyExists(email.to_string()));
+    }
+
+    diesel::update(users::table.filter(users::id.eq(user.id)))
+        .set(users::email.eq(email))
+        .returning((
+            users::id,
+            users::email,
+            users::password,
+            users::created,
+            users::activated,
+            users::admin,
+            users::disabled,
+        ))
+        .get_result::<User>(connection)
+        .map_err(UserErrorKind::EmailUpdateFailed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::asserts::*;
@@ -332,6 +641,23 @@ mod tests {
             assert!(result.is_ok());
             assert!(user_exists(&connection, email).is_err());
 
+            // Deleting a user deletes the ledgers they are the only member of, but keeps the
+            // ledgers that are shared with other users.
+            let user = create(&connection, email, password, &config).unwrap();
+            let other_user = create(&connection, "other@example.com", password, &config).unwrap();
+            let personal_ledger = crate::ledger::get_default(&connection, &user).unwrap();
+            let shared_ledger = crate::ledger::get_default(&connection, &other_user).unwrap();
+            crate::ledger::add_member(
+                &connection,
+                &shared_ledger,
+                &user,
+                crate::ledger::Role::Editor,
+            )
+            .unwrap();
+            delete(&connection, email).unwrap();
+            assert!(crate::ledger::read(&connection, personal_ledger.id, None).is_none());
+            assert!(crate::ledger::read(&connection, shared_ledger.id, None).is_some());
+
             // Deleting a non-existing user should result in a UserNotFound error.
             let non_existing_email = "non-existing@example.com";
             let delete_non_existing_user = delete(&connection, non_existing_email).unwrap_err();
@@ -344,6 +670,134 @@ mod tests {
         });
     }
 
+    // Tests delete() for the members of a shared ledger.
+    #[test]
+    fn test_delete_shared_ledger_member() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            use crate::db_test::*;
+            use crate::expense_share::SplitMethod;
+            use crate::ledger::{get_role, Role};
+            use rust_decimal::Decimal;
+
+            // Create a ledger that is shared by an owner, a viewer and an editor. The editor
+            // records an expense that is shared with the owner, a payment and a contribution to a
+            // savings goal.
+            let owner = create_test_user(&connection, &config);
+            let viewer = create_test_user(&connection, &config);
+            let editor = create_test_user(&connection, &config);
+            let ledger = crate::ledger::get_default(&connection, &owner).unwrap();
+            crate::ledger::add_member(&connection, &ledger, &viewer, Role::Viewer).unwrap();
+            crate::ledger::add_member(&connection, &ledger, &editor, Role::Editor).unwrap();
+            let category = create_test_category(&connection, &owner);
+            let expense = crate::expense::create(
+                &connection,
+                &editor,
+                &Decimal::new(5000, 2),
+                &category,
+                None,
+                None,
+            )
+            .unwrap();
+            let participants = [(owner.id, None), (editor.id, None)];
+            crate::expense_share::split(
+                &connection,
+                &editor,
+                &expense,
+                editor.id,
+                SplitMethod::Equal,
+                &participants,
+            )
+            .unwrap();
+            let settlement = crate::settlement::create(
+                &connection,
+                &editor,
+                &ledger,
+                owner.id,
+                editor.id,
+                &Decimal::new(1000, 2),
+                None,
+            )
+            .unwrap();
+            let goal = crate::goal::create(
+                &connection,
+                &owner,
+                &ledger,
+                "Holiday",
+                &Decimal::new(100000, 2),
+                &chrono::NaiveDate::from_ymd(2030, 1, 1),
+            )
+            .unwrap();
+            crate::goal::add_contribution(
+                &connection,
+                &editor,
+                &goal,
+                &Decimal::new(2000, 2),
+                None,
+                None,
+            )
+            .unwrap();
+
+            // When the last owner is deleted, the member with the highest role becomes the owner.
+            assert_eq!(
+                vec![ledger.clone()],
+                crate::ledger::get_last_owner_ledgers(&connection, owner.id).unwrap()
+            );
+            assert_eq!(
+                Some(editor.id),
+                crate::ledger::get_successor(&connection, &ledger, &owner)
+                    .unwrap()
+                    .map(|u| u.id)
+            );
+            delete(&connection, &owner.email).unwrap();
+            assert!(crate::ledger::read(&connection, ledger.id, None).is_some());
+            assert_eq!(
+                Ok(Some(Role::Owner)),
+                get_role(&connection, ledger.id, editor.id)
+            );
+            assert_eq!(
+                Ok(Some(Role::Viewer)),
+                get_role(&connection, ledger.id, viewer.id)
+            );
+
+            // The data of deleted members is kept without a reference to them.
+            delete(&connection, &editor.email).unwrap();
+            assert_eq!(
+                Ok(Some(Role::Owner)),
+                get_role(&connection, ledger.id, viewer.id)
+            );
+            let expense = crate::expense::read(&connection, expense.id).unwrap();
+            assert_eq!(None, expense.user_id);
+            let (split, shares) = crate::expense_share::read(&connection, expense.id).unwrap();
+            assert_eq!(None, split.paid_by);
+            assert_eq!(
+                vec![None, None],
+                shares.iter().map(|s| s.user_id).collect::<Vec<_>>()
+            );
+            let settlement = crate::settlement::read(&connection, settlement.id).unwrap();
+            assert_eq!((None, None), (settlement.payer_id, settlement.payee_id));
+            let contributions = crate::goal::get_contributions(&connection, &goal).unwrap();
+            assert_eq!(
+                vec![None],
+                contributions.iter().map(|c| c.user_id).collect::<Vec<_>>()
+            );
+
+            // Deleted members are left out of the balances.
+            let balances = crate::settlement::get_balances(&connection, &ledger).unwrap();
+            assert_eq!(
+                vec![viewer.id],
+                balances.iter().map(|b| b.user_id).collect::<Vec<_>>()
+            );
+
+            // The last member is deleted together with the ledger.
+            delete(&connection, &viewer.email).unwrap();
+            assert!(crate::ledger::read(&connection, ledger.id, None).is_none());
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_read() {
         let connection = establish_connection(&get_database_url()).unwrap();
@@ -384,6 +838,59 @@ mod tests {
         });
     }
 
+    // Tests super::list().
+    #[test]
+    fn test_list() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let emails = [
+                "list-b@example.com",
+                "list-a@example.com",
+                "list_c@example.com",
+            ];
+            for email in &emails {
+                create(&connection, email, "mypass", &config).unwrap();
+            }
+            let get_emails = |search: Option<&str>| -> Vec<String> {
+                list(&connection, search, i64::MAX, 0)
+                    .unwrap()
+                    .into_iter()
+                    .map(|u| u.email)
+                    .collect()
+            };
+
+            // Without a search string all users are returned, ordered by email address.
+            let all = get_emails(None);
+            let positions: Vec<usize> = ["list-a@example.com", "list-b@example.com"]
+                .iter()
+                .map(|email| all.iter().position(|e| e == email).unwrap())
+                .collect();
+            assert!(positions[0] < positions[1]);
+            assert!(all.contains(&"list_c@example.com".to_string()));
+
+            // The search is case insensitive and matches any part of the email address.
+            assert_eq!(
+                vec!["list-a@example.com", "list-b@example.com"],
+                get_emails(Some("LIST-"))
+            );
+            assert_eq!(vec!["list-a@example.com"], get_emails(Some("t-a@")));
+            assert_eq!(Ok(2), count(&connection, Some("LIST-")));
+
+            // The results can be paged.
+            let page = list(&connection, Some("list"), 1, 1).unwrap();
+            assert_eq!(1, page.len());
+            assert_eq!("list-b@example.com", page[0].email);
+            assert_eq!(Ok(3), count(&connection, Some("list")));
+
+            // Wildcards are matched literally.
+            assert_eq!(vec!["list_c@example.com"], get_emails(Some("list_")));
+            assert!(get_emails(Some("list%a")).is_empty());
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_activate() {
         let connection = establish_connection(&get_database_url()).unwrap();
@@ -406,6 +913,30 @@ mod tests {
         });
     }
 
+    // Tests super::set_disabled().
+    #[test]
+    fn test_set_disabled() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = create(&connection, "test@example.com", "mypass", &config).unwrap();
+            let user = activate(&connection, user).unwrap();
+            assert!(!user.disabled);
+
+            // Disabling a user doesn't affect the activation.
+            let user = set_disabled(&connection, user, true).unwrap();
+            assert!(user.disabled);
+            assert!(user.activated);
+            assert!(read(&connection, "test@example.com").unwrap().disabled);
+
+            let user = set_disabled(&connection, user, false).unwrap();
+            assert!(!user.disabled);
+            assert!(!read(&connection, "test@example.com").unwrap().disabled);
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_verify_password() {
         let connection = establish_connection(&get_database_url()).unwrap();
@@ -457,6 +988,137 @@ mod tests {
             Ok(())
         });
     }
+
+    // Tests that super::verify_password() rehashes outdated passwords.
+    #[test]
+    fn test_verify_password_rehash() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let mut config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = create(&connection, "test@example.com", "mypass", &config).unwrap();
+
+            // The hash is left alone when it is up to date.
+            let verified = verify_password(&connection, &user.email, "mypass", &config).unwrap();
+            assert_eq!(user.password, verified.password);
+
+            // When the hasher parameters change the password is rehashed on the next login.
+            config.set_hasher_iterations(2);
+            let verified = verify_password(&connection, &user.email, "mypass", &config).unwrap();
+            assert_ne!(user.password, verified.password);
+            assert!(!hash_is_outdated(&verified.password, 512, 2));
+            assert_eq!(
+                read(&connection, &user.email).unwrap().password,
+                verified.password
+            );
+
+            // When the secret key is rotated, passwords hashed with the previous key are still
+            // accepted and are rehashed with the new key.
+            config.set_secret_keys("new_secret".to_string(), vec!["my_secret".to_string()]);
+            let verified = verify_password(&connection, &user.email, "mypass", &config).unwrap();
+            assert!(hashed_password_is_valid(
+                &verified.password,
+                "mypass",
+                "new_secret"
+            ));
+
+            // After the rehash the previous key is no longer needed.
+            config.set_secret_keys("new_secret".to_string(), vec![]);
+            assert!(verify_password(&connection, &user.email, "mypass", &config).is_ok());
+
+            // A wrong password is rejected and does not change the hash.
+            config.set_hasher_iterations(3);
+            assert_eq!(
+                UserErrorKind::IncorrectPassword(user.email.clone()),
+                verify_password(&connection, &user.email, "wrong", &config).unwrap_err()
+            );
+            assert_eq!(
+                read(&connection, &user.email).unwrap().password,
+                verified.password
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests super::set_password().
+    #[test]
+    fn test_set_password() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let email = "test@example.com";
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = create(&connection, email, "mypass", &config).unwrap();
+
+            // After setting a new password only the new password is accepted.
+            let user = set_password(&connection, user, "newpass", &config).unwrap();
+            assert!(asserts::hashed_password_is_valid(
+                user.password.as_str(),
+                "newpass",
+                config.secret_key()
+            ));
+            assert!(verify_password(&connection, email, "newpass", &config).is_ok());
+            assert_eq!(
+                UserErrorKind::IncorrectPassword(email.to_string()),
+                verify_password(&connection, email, "mypass", &config).unwrap_err()
+            );
+
+            // An empty password cannot be hashed.
+            assert!(set_password(&connection, user, "", &config).is_err());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::set_email().
+    #[test]
+    fn test_set_email() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            let user = create(&connection, "test@example.com", "mypass", &config).unwrap();
+            create(&connection, "other@example.com", "mypass", &config).unwrap();
+
+            // Invalid email addresses and addresses of other users are rejected.
+            assert_eq!(
+                UserErrorKind::InvalidEmail("invalid".to_string()),
+                set_email(&connection, user.clone(), "invalid").unwrap_err()
+            );
+            assert_eq!(
+                UserErrorKind::UserWithEmailAlreadyExists("other@example.com".to_string()),
+                set_email(&connection, user.clone(), "other@example.com").unwrap_err()
+            );
+
+            // After changing the email address the user can log in with the new address.
+            let user = set_email(&connection, user, "new@example.com").unwrap();
+            assert_eq!("new@example.com", user.email);
+            assert!(verify_password(&connection, "new@example.com", "mypass", &config).is_ok());
+            assert!(read(&connection, "test@example.com").is_err());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::set_admin().
+    #[test]
+    fn test_set_admin() {
+        let connection = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        connection.test_transaction::<_, Error, _>(|| {
+            // New users are not administrators.
+            let user = create(&connection, "test@example.com", "mypass", &config).unwrap();
+            assert!(!user.admin);
+
+            let user = set_admin(&connection, user, true).unwrap();
+            assert!(user.admin);
+            assert!(read(&connection, "test@example.com").unwrap().admin);
+
+            let user = set_admin(&connection, user, false).unwrap();
+            assert!(!user.admin);
+            assert!(!read(&connection, "test@example.com").unwrap().admin);
+
+            Ok(())
+        });
+    }
 }
 
 /// Reusable assertions.
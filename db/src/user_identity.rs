@@ -0,0 +1,268 @@
+use super::schema::user_identities::dsl;
+use super::schema::{api_tokens, user_identities, user_sessions, users};
+use super::user::{self, User, UserErrorKind};
+use chrono::NaiveDateTime;
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::fmt;
+
+/// An account at an OpenID Connect identity provider that can be used to log in. The account is
+/// identified by the issuer URL of the provider together with the subject identifier that the
+/// provider has assigned to it.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct UserIdentity {
+    pub id: i32,
+    pub user_id: i32,
+    pub issuer: String,
+    pub subject: String,
+    pub created: NaiveDateTime,
+    pub last_login: Option<NaiveDateTime>,
+}
+
+// Possible errors thrown when handling user identities.
+#[derive(Debug, PartialEq)]
+pub enum UserIdentityErrorKind {
+    // The sessions or API tokens of an account that is not activated yet could not be removed due
+    // to a database error.
+    AccountResetFailed(diesel::result::Error),
+    // A new identity could not be linked due to a database error.
+    CreationFailed(diesel::result::Error),
+    // The identity is not linked yet, and the identity provider has not verified the email address.
+    EmailNotVerified,
+    // The password of an account that is not activated yet could not be removed.
+    PasswordRemovalFailed(UserErrorKind),
+    // Identities could not be read due to a database error.
+    ReadFailed(diesel::result::Error),
+    // An identity could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
+    // The user the identity belongs to could not be read.
+    UserReadFailed(UserErrorKind),
+    // There is no account with the email address of the identity.
+    UserNotFound(String),
+}
+
+impl fmt::Display for UserIdentityErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UserIdentityErrorKind::AccountResetFailed(ref err) => {
+                write!(f, "Database error when resetting account: {}", err)
+            }
+            UserIdentityErrorKind::CreationFailed(ref err) => {
+                write!(f, "Database error when linking identity: {}", err)
+            }
+            UserIdentityErrorKind::EmailNotVerified => write!(
+                f,
+                "The identity provider has not verified your email address"
+            ),
+            UserIdentityErrorKind::PasswordRemovalFailed(ref err) => {
+                write!(f, "The password could not be removed: {}", err)
+            }
+            UserIdentityErrorKind::ReadFailed(ref err) => {
+                write!(f, "Database error when reading identity: {}", err)
+            }
+            UserIdentityErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when updating identity: {}", err)
+            }
+            UserIdentityErrorKind::UserReadFailed(ref err) => {
+                write!(f, "User of the identity could not be read: {}", err)
+            }
+            UserIdentityErrorKind::UserNotFound(ref email) => {
+                write!(f, "There is no account with the email address {}", email)
+            }
+        }
+    }
+}
+
+/// Returns the user that can log in with the identity that has the given issuer and subject, and
+/// updates the last login time of the identity.
+///
+/// An identity that is not known yet is linked to the account that uses the given email address.
+/// Since this grants access to the account, it is only done if the identity provider has verified
+/// that the email address belongs to the user. Pass `None` if the email address is missing or has
+/// not been verified. Once linked, the identity keeps its account, even when the email address
+/// changes on either side.
+///
+/// An account that has not been activated yet might have been registered by someone who does not
+/// own the email address. When an identity is linked to such an account, its password, sessions and
+/// API tokens are removed, so that only the owner of the verified email address has access to it.
+pub fn link(
+    connection: &PgConnection,
+    issuer: &str,
+    subject: &str,
+    verified_email: Option<&str>,
+) -> Result<(UserIdentity, User), UserIdentityErrorKind> {
+    let now = chrono::Local::now().naive_local();
+    let result = user_identities::table
+        .inner_join(users::table)
+        .filter(dsl::issuer.eq(issuer))
+        .filter(dsl::subject.eq(subject))
+        .first::<(UserIdentity, User)>(connection)
+        .optional()
+        .map_err(UserIdentityErrorKind::ReadFailed)?;
+
+    if let Some((user_identity, user)) = result {
+        let user_identity = diesel::update(dsl::user_identities.find(user_identity.id))
+            .set(dsl::last_login.eq(now))
+            .get_result::<UserIdentity>(connection)
+            .map_err(UserIdentityErrorKind::UpdateFailed)?;
+        return Ok((user_identity, user));
+    }
+
+    let email = verified_email.ok_or(UserIdentityErrorKind::EmailNotVerified)?;
+    let user = match user::read(connection, email) {
+        Ok(user) => user,
+        Err(UserErrorKind::UserNotFound(email)) => {
+            return Err(UserIdentityErrorKind::UserNotFound(email))
+        }
+        Err(e) => return Err(UserIdentityErrorKind::UserReadFailed(e)),
+    };
+    let user = if user.activated {
+        user
+    } else {
+        reset_account(connection, user)?
+    };
+    let user_identity = diesel::insert_into(user_identities::table)
+        .values((
+            dsl::user_id.eq(user.id),
+            dsl::issuer.eq(issuer),
+            dsl::subject.eq(subject),
+            dsl::last_login.eq(now),
+        ))
+        .get_result::<UserIdentity>(connection)
+        .map_err(UserIdentityErrorKind::CreationFailed)?;
+    Ok((user_identity, user))
+}
+
+// Removes the password, sessions and API tokens of the given user.
+fn reset_account(connection: &PgConnection, user: User) -> Result<User, UserIdentityErrorKind> {
+    diesel::delete(user_sessions::table.filter(user_sessions::user_id.eq(user.id)))
+        .execute(connection)
+        .and_then(|_| {
+            diesel::delete(api_tokens::table.filter(api_tokens::user_id.eq(user.id)))
+                .execute(connection)
+        })
+        .map_err(UserIdentityErrorKind::AccountResetFailed)?;
+    user::remove_password(connection, user).map_err(UserIdentityErrorKind::PasswordRemovalFailed)
+}
+
+/// Returns the identities that are linked to the given user, oldest first.
+pub fn list(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<Vec<UserIdentity>, UserIdentityErrorKind> {
+    dsl::user_identities
+        .filter(dsl::user_id.eq(user.id))
+        .order(dsl::created.asc())
+        .load(connection)
+        .map_err(UserIdentityErrorKind::ReadFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::link().
+    #[test]
+    fn test_link() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let other_user = create_test_user(&conn, &config);
+            let issuer = "https://sso.example.com";
+            assert!(list(&conn, &user).unwrap().is_empty());
+
+            // An unknown identity cannot be linked if the email address is not verified.
+            assert_eq!(
+                UserIdentityErrorKind::EmailNotVerified,
+                link(&conn, issuer, "1", None).unwrap_err()
+            );
+
+            // An unknown identity cannot be linked if there is no account for the email address.
+            assert_eq!(
+                UserIdentityErrorKind::UserNotFound("unknown@example.com".to_string()),
+                link(&conn, issuer, "1", Some("unknown@example.com")).unwrap_err()
+            );
+            assert!(list(&conn, &user).unwrap().is_empty());
+
+            // An unknown identity is linked to the account with the verified email address. The
+            // account is activated, so its password is kept.
+            let user = user::activate(&conn, user).unwrap();
+            let (user_identity, linked_user) =
+                link(&conn, issuer, "1", Some(user.email.as_str())).unwrap();
+            assert_eq!(user.password, linked_user.password);
+            assert_eq!(user.id, linked_user.id);
+            assert_eq!(user.id, user_identity.user_id);
+            assert_eq!(issuer, user_identity.issuer);
+            assert_eq!("1", user_identity.subject);
+            assert!(user_identity.last_login.is_some());
+            assert_eq!(vec![user_identity.clone()], list(&conn, &user).unwrap());
+
+            // A linked identity keeps its account, regardless of the email address.
+            let (linked_identity, linked_user) =
+                link(&conn, issuer, "1", Some(other_user.email.as_str())).unwrap();
+            assert_eq!(user.id, linked_user.id);
+            assert_eq!(user_identity.id, linked_identity.id);
+            let (_, linked_user) = link(&conn, issuer, "1", None).unwrap();
+            assert_eq!(user.id, linked_user.id);
+
+            // The same subject at another identity provider is a different identity.
+            let (other_identity, linked_user) = link(
+                &conn,
+                "https://other.example.com",
+                "1",
+                Some(other_user.email.as_str()),
+            )
+            .unwrap();
+            assert_eq!(other_user.id, linked_user.id);
+            assert_ne!(user_identity.id, other_identity.id);
+            assert_eq!(vec![other_identity], list(&conn, &other_user).unwrap());
+            assert_eq!(1, list(&conn, &user).unwrap().len());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::link() for an account that has not been activated yet.
+    #[test]
+    fn test_link_not_activated() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            // Someone registers an account with the email address of somebody else, and logs in
+            // with the password they have chosen.
+            let user = create_test_user(&conn, &config);
+            assert!(!user.activated);
+            crate::user_session::create(&conn, &user, None, None).unwrap();
+            crate::api_token::create(
+                &conn,
+                &user,
+                "Test token",
+                crate::api_token::Scope::Write,
+                None,
+            )
+            .unwrap();
+
+            // When the owner of the email address logs in through the identity provider, the
+            // password, sessions and API tokens are removed.
+            let (_, linked_user) = link(
+                &conn,
+                "https://sso.example.com",
+                "1",
+                Some(user.email.as_str()),
+            )
+            .unwrap();
+            assert_eq!(user.id, linked_user.id);
+            assert!(user::verify_password(&conn, user.email.as_str(), "letmein", &config).is_err());
+            assert!(crate::user_session::list(&conn, &user).unwrap().is_empty());
+            assert!(crate::api_token::list(&conn, &user).unwrap().is_empty());
+
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,863 @@
+use super::schema::user_preferences;
+use super::user::User;
+use chrono::{NaiveDate, Weekday};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgConnection};
+use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::{fmt, str::FromStr};
+
+/// The personal preferences of a user, which determine the language of the interface and how
+/// amounts and dates are shown and entered.
+#[derive(Clone, Debug, Deserialize, PartialEq, Queryable, Serialize)]
+pub struct UserPreferences {
+    pub user_id: i32,
+    pub locale: Locale,
+    pub currency: Currency,
+    pub date_format: DateFormat,
+    pub first_day_of_week: FirstDayOfWeek,
+    // The language of the interface. If not set, the language requested by the browser is used.
+    pub language: Option<Language>,
+}
+
+impl UserPreferences {
+    /// Returns the default preferences for the user with the given ID. These are used until the
+    /// user saves their own preferences.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::*;
+    /// #
+    /// let preferences = UserPreferences::defaults(1);
+    /// assert_eq!(Locale::En, preferences.locale);
+    /// assert_eq!(Currency::Eur, preferences.currency);
+    /// assert_eq!(DateFormat::Iso, preferences.date_format);
+    /// assert_eq!(FirstDayOfWeek::Monday, preferences.first_day_of_week);
+    /// assert_eq!(None, preferences.language);
+    /// ```
+    pub fn defaults(user_id: i32) -> UserPreferences {
+        UserPreferences {
+            user_id,
+            locale: Locale::En,
+            currency: Currency::Eur,
+            date_format: DateFormat::Iso,
+            first_day_of_week: FirstDayOfWeek::Monday,
+            language: None,
+        }
+    }
+
+    /// Formats the given amount in the currency and the number format of the user.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::*;
+    /// # use rust_decimal::Decimal;
+    /// #
+    /// let mut preferences = UserPreferences::defaults(1);
+    /// assert_eq!("€1,234.50", preferences.format_amount(Decimal::new(12345, 1)));
+    /// assert_eq!("-€0.99", preferences.format_amount(Decimal::new(-99, 2)));
+    ///
+    /// preferences.locale = Locale::De;
+    /// assert_eq!("1.234,50 €", preferences.format_amount(Decimal::new(12345, 1)));
+    ///
+    /// preferences.locale = Locale::Nl;
+    /// preferences.currency = Currency::Chf;
+    /// assert_eq!("CHF 1.234,50", preferences.format_amount(Decimal::new(12345, 1)));
+    /// ```
+    pub fn format_amount(&self, amount: Decimal) -> String {
+        let sign = if amount.round_dp(2) < Decimal::new(0, 0) {
+            "-"
+        } else {
+            ""
+        };
+        let number = self.locale.format_number(amount.abs());
+        let symbol = self.currency.symbol();
+
+        // Currency codes that are used as a symbol are always separated from the number.
+        let separator = if self.locale.separates_symbol() || symbol.chars().count() > 1 {
+            " "
+        } else {
+            ""
+        };
+        if self.locale.symbol_first() {
+            format!("{}{}{}{}", sign, symbol, separator, number)
+        } else {
+            format!("{}{} {}", sign, number, symbol)
+        }
+    }
+
+    /// Parses an amount that was entered by the user. The decimal separator of the user's locale is
+    /// accepted, as well as a point. Thousands separators are not allowed since they are ambiguous.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::*;
+    /// # use rust_decimal::Decimal;
+    /// #
+    /// let mut preferences = UserPreferences::defaults(1);
+    /// assert_eq!(Ok(Decimal::new(1250, 2)), preferences.parse_amount("12.50"));
+    /// assert!(preferences.parse_amount("12,50").is_err());
+    ///
+    /// preferences.locale = Locale::Fr;
+    /// assert_eq!(Ok(Decimal::new(1250, 2)), preferences.parse_amount("12,50"));
+    /// assert_eq!(Ok(Decimal::new(1250, 2)), preferences.parse_amount("12.50"));
+    /// ```
+    pub fn parse_amount(&self, amount: &str) -> Result<Decimal, UserPreferenceErrorKind> {
+        let normalized = amount.replace(self.locale.decimal_separator(), ".");
+        Decimal::from_str(normalized.as_str())
+            .map_err(|_| UserPreferenceErrorKind::InvalidAmount(amount.to_string()))
+    }
+
+    /// Formats the given date in the date format of the user.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::NaiveDate;
+    /// # use db::user_preference::*;
+    /// #
+    /// let mut preferences = UserPreferences::defaults(1);
+    /// assert_eq!("2021-05-16", preferences.format_date(NaiveDate::from_ymd(2021, 5, 16)));
+    ///
+    /// preferences.date_format = DateFormat::MonthDayYear;
+    /// assert_eq!("05/16/2021", preferences.format_date(NaiveDate::from_ymd(2021, 5, 16)));
+    /// ```
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format.pattern()).to_string()
+    }
+
+    /// Parses a date that was entered by the user in their date format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use chrono::NaiveDate;
+    /// # use db::user_preference::*;
+    /// #
+    /// let mut preferences = UserPreferences::defaults(1);
+    /// preferences.date_format = DateFormat::DayMonthYearDotted;
+    /// assert_eq!(Ok(NaiveDate::from_ymd(2021, 5, 16)), preferences.parse_date("16.05.2021"));
+    /// assert!(preferences.parse_date("2021-05-16").is_err());
+    /// ```
+    pub fn parse_date(&self, date: &str) -> Result<NaiveDate, UserPreferenceErrorKind> {
+        NaiveDate::parse_from_str(date, self.date_format.pattern())
+            .map_err(|_| UserPreferenceErrorKind::InvalidDate(date.to_string()))
+    }
+}
+
+/// The locale of the user, which determines the number format.
+#[derive(AsExpression, Clone, Copy, Debug, Deserialize, FromSqlRow, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum Locale {
+    // English.
+    En,
+    // Dutch.
+    Nl,
+    // French.
+    Fr,
+    // German.
+    De,
+}
+
+impl Locale {
+    /// All supported locales, in the order they are offered to the user.
+    pub const ALL: [Locale; 4] = [Locale::En, Locale::Nl, Locale::Fr, Locale::De];
+
+    /// Returns the language code of the locale, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::Locale;
+    /// #
+    /// assert_eq!("en", Locale::En.as_str());
+    /// assert_eq!("nl", Locale::Nl.as_str());
+    /// assert_eq!("fr", Locale::Fr.as_str());
+    /// assert_eq!("de", Locale::De.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Nl => "nl",
+            Locale::Fr => "fr",
+            Locale::De => "de",
+        }
+    }
+
+    /// Returns the name of the language, in the language itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::En => "English",
+            Locale::Nl => "Nederlands",
+            Locale::Fr => "Français",
+            Locale::De => "Deutsch",
+        }
+    }
+
+    /// Returns the character that separates the integer part of a number from the fraction.
+    pub fn decimal_separator(self) -> char {
+        match self {
+            Locale::En => '.',
+            Locale::Nl | Locale::Fr | Locale::De => ',',
+        }
+    }
+
+    /// Returns the character that separates groups of thousands.
+    pub fn group_separator(self) -> char {
+        match self {
+            Locale::En => ',',
+            Locale::Nl | Locale::De => '.',
+            // A non-breaking space, so that large amounts are not wrapped.
+            Locale::Fr => '\u{a0}',
+        }
+    }
+
+    /// Formats a number with two decimals, using the separators of the locale.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::Locale;
+    /// # use rust_decimal::Decimal;
+    /// #
+    /// assert_eq!("1,234,567.89", Locale::En.format_number(Decimal::new(123456789, 2)));
+    /// assert_eq!("1.234.567,89", Locale::Nl.format_number(Decimal::new(123456789, 2)));
+    /// assert_eq!("149,90", Locale::De.format_number(Decimal::new(1499, 1)));
+    /// assert_eq!("-0.01", Locale::En.format_number(Decimal::new(-1, 2)));
+    /// ```
+    pub fn format_number(self, number: Decimal) -> String {
+        let formatted = format!("{:.2}", number.abs().round_dp(2));
+        let (integer, fraction) = formatted.split_at(formatted.len() - 3);
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push(self.group_separator());
+            }
+            grouped.push(digit);
+        }
+
+        let sign = if number.round_dp(2) < Decimal::new(0, 0) {
+            "-"
+        } else {
+            ""
+        };
+        format!(
+            "{}{}{}{}",
+            sign,
+            grouped,
+            self.decimal_separator(),
+            &fraction[1..]
+        )
+    }
+
+    // Returns whether the currency symbol is placed before the amount.
+    fn symbol_first(self) -> bool {
+        match self {
+            Locale::En | Locale::Nl => true,
+            Locale::Fr | Locale::De => false,
+        }
+    }
+
+    // Returns whether the currency symbol is separated from the amount by a space.
+    fn separates_symbol(self) -> bool {
+        match self {
+            Locale::En => false,
+            Locale::Nl | Locale::Fr | Locale::De => true,
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Locale {
+    type Err = UserPreferenceErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Locale::En),
+            "nl" => Ok(Locale::Nl),
+            "fr" => Ok(Locale::Fr),
+            "de" => Ok(Locale::De),
+            _ => Err(UserPreferenceErrorKind::InvalidLocale(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Locale {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Locale {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let locale = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        locale
+            .parse()
+            .map_err(|e: UserPreferenceErrorKind| e.to_string().into())
+    }
+}
+
+/// The currency in which amounts are shown.
+#[derive(AsExpression, Clone, Copy, Debug, Deserialize, FromSqlRow, PartialEq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+#[sql_type = "Varchar"]
+pub enum Currency {
+    // Euro.
+    Eur,
+    // US dollar.
+    Usd,
+    // Pound sterling.
+    Gbp,
+    // Swiss franc.
+    Chf,
+}
+
+impl Currency {
+    /// All supported currencies, in the order they are offered to the user.
+    pub const ALL: [Currency; 4] = [Currency::Eur, Currency::Usd, Currency::Gbp, Currency::Chf];
+
+    /// Returns the ISO 4217 code of the currency, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::Currency;
+    /// #
+    /// assert_eq!("EUR", Currency::Eur.as_str());
+    /// assert_eq!("USD", Currency::Usd.as_str());
+    /// assert_eq!("GBP", Currency::Gbp.as_str());
+    /// assert_eq!("CHF", Currency::Chf.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Currency::Eur => "EUR",
+            Currency::Usd => "USD",
+            Currency::Gbp => "GBP",
+            Currency::Chf => "CHF",
+        }
+    }
+
+    /// Returns the symbol that is shown next to amounts.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Currency::Eur => "€",
+            Currency::Usd => "$",
+            Currency::Gbp => "£",
+            Currency::Chf => "CHF",
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = UserPreferenceErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "EUR" => Ok(Currency::Eur),
+            "USD" => Ok(Currency::Usd),
+            "GBP" => Ok(Currency::Gbp),
+            "CHF" => Ok(Currency::Chf),
+            _ => Err(UserPreferenceErrorKind::InvalidCurrency(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Currency {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Currency {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let currency = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        currency
+            .parse()
+            .map_err(|e: UserPreferenceErrorKind| e.to_string().into())
+    }
+}
+
+/// The format in which dates are shown and entered.
+#[derive(AsExpression, Clone, Copy, Debug, Deserialize, FromSqlRow, PartialEq, Serialize)]
+#[sql_type = "Varchar"]
+pub enum DateFormat {
+    // 2021-05-16
+    #[serde(rename = "iso")]
+    Iso,
+    // 16/05/2021
+    #[serde(rename = "dmy")]
+    DayMonthYear,
+    // 16.05.2021
+    #[serde(rename = "dmy-dotted")]
+    DayMonthYearDotted,
+    // 05/16/2021
+    #[serde(rename = "mdy")]
+    MonthDayYear,
+}
+
+impl DateFormat {
+    /// All supported date formats, in the order they are offered to the user.
+    pub const ALL: [DateFormat; 4] = [
+        DateFormat::Iso,
+        DateFormat::DayMonthYear,
+        DateFormat::DayMonthYearDotted,
+        DateFormat::MonthDayYear,
+    ];
+
+    /// Returns the machine name of the date format, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::DateFormat;
+    /// #
+    /// assert_eq!("iso", DateFormat::Iso.as_str());
+    /// assert_eq!("dmy", DateFormat::DayMonthYear.as_str());
+    /// assert_eq!("dmy-dotted", DateFormat::DayMonthYearDotted.as_str());
+    /// assert_eq!("mdy", DateFormat::MonthDayYear.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DateFormat::Iso => "iso",
+            DateFormat::DayMonthYear => "dmy",
+            DateFormat::DayMonthYearDotted => "dmy-dotted",
+            DateFormat::MonthDayYear => "mdy",
+        }
+    }
+
+    /// Returns the strftime pattern of the date format.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            DateFormat::Iso => "%Y-%m-%d",
+            DateFormat::DayMonthYear => "%d/%m/%Y",
+            DateFormat::DayMonthYearDotted => "%d.%m.%Y",
+            DateFormat::MonthDayYear => "%m/%d/%Y",
+        }
+    }
+
+    /// Returns a human readable description of the date format. This is also understood by the
+    /// date picker in the browser.
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            DateFormat::Iso => "YYYY-MM-DD",
+            DateFormat::DayMonthYear => "DD/MM/YYYY",
+            DateFormat::DayMonthYearDotted => "DD.MM.YYYY",
+            DateFormat::MonthDayYear => "MM/DD/YYYY",
+        }
+    }
+}
+
+impl fmt::Display for DateFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = UserPreferenceErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iso" => Ok(DateFormat::Iso),
+            "dmy" => Ok(DateFormat::DayMonthYear),
+            "dmy-dotted" => Ok(DateFormat::DayMonthYearDotted),
+            "mdy" => Ok(DateFormat::MonthDayYear),
+            _ => Err(UserPreferenceErrorKind::InvalidDateFormat(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for DateFormat {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for DateFormat {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let date_format = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        date_format
+            .parse()
+            .map_err(|e: UserPreferenceErrorKind| e.to_string().into())
+    }
+}
+
+/// The day on which weeks start in calendars and reports.
+#[derive(AsExpression, Clone, Copy, Debug, Deserialize, FromSqlRow, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum FirstDayOfWeek {
+    Monday,
+    Saturday,
+    Sunday,
+}
+
+impl FirstDayOfWeek {
+    /// All supported first days of the week, in the order they are offered to the user.
+    pub const ALL: [FirstDayOfWeek; 3] = [
+        FirstDayOfWeek::Monday,
+        FirstDayOfWeek::Saturday,
+        FirstDayOfWeek::Sunday,
+    ];
+
+    /// Returns the machine name of the day, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::FirstDayOfWeek;
+    /// #
+    /// assert_eq!("monday", FirstDayOfWeek::Monday.as_str());
+    /// assert_eq!("saturday", FirstDayOfWeek::Saturday.as_str());
+    /// assert_eq!("sunday", FirstDayOfWeek::Sunday.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FirstDayOfWeek::Monday => "monday",
+            FirstDayOfWeek::Saturday => "saturday",
+            FirstDayOfWeek::Sunday => "sunday",
+        }
+    }
+
+    /// Returns the day as a chrono weekday.
+    pub fn weekday(self) -> Weekday {
+        match self {
+            FirstDayOfWeek::Monday => Weekday::Mon,
+            FirstDayOfWeek::Saturday => Weekday::Sat,
+            FirstDayOfWeek::Sunday => Weekday::Sun,
+        }
+    }
+}
+
+impl fmt::Display for FirstDayOfWeek {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for FirstDayOfWeek {
+    type Err = UserPreferenceErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monday" => Ok(FirstDayOfWeek::Monday),
+            "saturday" => Ok(FirstDayOfWeek::Saturday),
+            "sunday" => Ok(FirstDayOfWeek::Sunday),
+            _ => Err(UserPreferenceErrorKind::InvalidFirstDayOfWeek(
+                s.to_string(),
+            )),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for FirstDayOfWeek {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for FirstDayOfWeek {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let day = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        day.parse()
+            .map_err(|e: UserPreferenceErrorKind| e.to_string().into())
+    }
+}
+
+/// A language in which the interface can be shown. A translation catalog exists for every language.
+#[derive(
+    AsExpression, Clone, Copy, Debug, Deserialize, Eq, FromSqlRow, Hash, PartialEq, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[sql_type = "Varchar"]
+pub enum Language {
+    // English.
+    En,
+    // Dutch.
+    Nl,
+}
+
+impl Language {
+    /// All supported languages, in the order they are offered to the user.
+    pub const ALL: [Language; 2] = [Language::En, Language::Nl];
+
+    /// Returns the language code, as it is stored in the database.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_preference::Language;
+    /// #
+    /// assert_eq!("en", Language::En.as_str());
+    /// assert_eq!("nl", Language::Nl.as_str());
+    /// ```
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Nl => "nl",
+        }
+    }
+
+    /// Returns the name of the language, in the language itself.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::Nl => "Nederlands",
+        }
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Language {
+    type Err = UserPreferenceErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Language::En),
+            "nl" => Ok(Language::Nl),
+            _ => Err(UserPreferenceErrorKind::InvalidLanguage(s.to_string())),
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for Language {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for Language {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let language = <String as FromSql<Varchar, Pg>>::from_sql(bytes)?;
+        language
+            .parse()
+            .map_err(|e: UserPreferenceErrorKind| e.to_string().into())
+    }
+}
+
+// Possible errors thrown when handling user preferences.
+#[derive(Debug, PartialEq)]
+pub enum UserPreferenceErrorKind {
+    // A database error occurred.
+    DatabaseError(diesel::result::Error),
+    // The passed in amount could not be parsed.
+    InvalidAmount(String),
+    // The passed in currency is not supported.
+    InvalidCurrency(String),
+    // The passed in date could not be parsed.
+    InvalidDate(String),
+    // The passed in date format is not supported.
+    InvalidDateFormat(String),
+    // The passed in first day of the week is not supported.
+    InvalidFirstDayOfWeek(String),
+    // The passed in language is not supported.
+    InvalidLanguage(String),
+    // The passed in locale is not supported.
+    InvalidLocale(String),
+}
+
+impl fmt::Display for UserPreferenceErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UserPreferenceErrorKind::DatabaseError(ref err) => write!(f, "Database error: {}", err),
+            UserPreferenceErrorKind::InvalidAmount(ref amount) => {
+                write!(f, "Invalid amount: {}", amount)
+            }
+            UserPreferenceErrorKind::InvalidCurrency(ref currency) => {
+                write!(f, "Unsupported currency: {}", currency)
+            }
+            UserPreferenceErrorKind::InvalidDate(ref date) => write!(f, "Invalid date: {}", date),
+            UserPreferenceErrorKind::InvalidDateFormat(ref date_format) => {
+                write!(f, "Unsupported date format: {}", date_format)
+            }
+            UserPreferenceErrorKind::InvalidFirstDayOfWeek(ref day) => {
+                write!(f, "Weeks cannot start on {}", day)
+            }
+            UserPreferenceErrorKind::InvalidLanguage(ref language) => {
+                write!(f, "Unsupported language: {}", language)
+            }
+            UserPreferenceErrorKind::InvalidLocale(ref locale) => {
+                write!(f, "Unsupported locale: {}", locale)
+            }
+        }
+    }
+}
+
+impl From<diesel::result::Error> for UserPreferenceErrorKind {
+    fn from(e: diesel::result::Error) -> Self {
+        UserPreferenceErrorKind::DatabaseError(e)
+    }
+}
+
+/// Returns the preferences of the given user. The defaults are returned if the user has not saved
+/// any preferences yet.
+pub fn read(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<UserPreferences, UserPreferenceErrorKind> {
+    Ok(user_preferences::table
+        .find(user.id)
+        .first::<UserPreferences>(connection)
+        .optional()?
+        .unwrap_or_else(|| UserPreferences::defaults(user.id)))
+}
+
+/// Saves the given preferences.
+pub fn update(
+    connection: &PgConnection,
+    preferences: &UserPreferences,
+) -> Result<UserPreferences, UserPreferenceErrorKind> {
+    let values = (
+        user_preferences::locale.eq(preferences.locale),
+        user_preferences::currency.eq(preferences.currency),
+        user_preferences::date_format.eq(preferences.date_format),
+        user_preferences::first_day_of_week.eq(preferences.first_day_of_week),
+        user_preferences::language.eq(preferences.language),
+    );
+    Ok(diesel::insert_into(user_preferences::table)
+        .values((user_preferences::user_id.eq(preferences.user_id), values))
+        .on_conflict(user_preferences::user_id)
+        .do_update()
+        .set(values)
+        .get_result(connection)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::*;
+    use crate::{establish_connection, get_database_url};
+    use app::AppConfig;
+    use diesel::result::Error;
+
+    // Tests super::read() and super::update().
+    #[test]
+    fn test_read_update() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+
+            // The defaults are returned as long as no preferences are saved.
+            assert_eq!(
+                UserPreferences::defaults(user.id),
+                read(&conn, &user).unwrap()
+            );
+
+            let preferences = UserPreferences {
+                user_id: user.id,
+                locale: Locale::Fr,
+                currency: Currency::Chf,
+                date_format: DateFormat::DayMonthYearDotted,
+                first_day_of_week: FirstDayOfWeek::Sunday,
+                language: Some(Language::Nl),
+            };
+            assert_eq!(preferences, update(&conn, &preferences).unwrap());
+            assert_eq!(preferences, read(&conn, &user).unwrap());
+
+            // Saving again overwrites the existing preferences.
+            let preferences = UserPreferences {
+                currency: Currency::Usd,
+                language: None,
+                ..preferences
+            };
+            assert_eq!(preferences, update(&conn, &preferences).unwrap());
+            assert_eq!(preferences, read(&conn, &user).unwrap());
+
+            // The preferences of other users are not affected.
+            let other_user = create_test_user(&conn, &config);
+            assert_eq!(
+                UserPreferences::defaults(other_user.id),
+                read(&conn, &other_user).unwrap()
+            );
+
+            Ok(())
+        });
+    }
+
+    // Tests that the machine names of the preferences can be parsed again.
+    #[test]
+    fn test_from_str() {
+        for locale in Locale::ALL.iter() {
+            assert_eq!(Ok(*locale), locale.as_str().parse());
+        }
+        for currency in Currency::ALL.iter() {
+            assert_eq!(Ok(*currency), currency.as_str().parse());
+        }
+        for date_format in DateFormat::ALL.iter() {
+            assert_eq!(Ok(*date_format), date_format.as_str().parse());
+        }
+        for day in FirstDayOfWeek::ALL.iter() {
+            assert_eq!(Ok(*day), day.as_str().parse());
+        }
+        for language in Language::ALL.iter() {
+            assert_eq!(Ok(*language), language.as_str().parse());
+        }
+        assert_eq!(
+            Err(UserPreferenceErrorKind::InvalidCurrency("XYZ".to_string())),
+            "XYZ".parse::<Currency>()
+        );
+    }
+
+    // Tests super::UserPreferences::format_amount() for all locales.
+    #[test]
+    fn test_format_amount() {
+        let amount = Decimal::new(123_456, 2);
+        let cases = [
+            (Locale::En, Currency::Eur, "€1,234.56"),
+            (Locale::En, Currency::Usd, "$1,234.56"),
+            (Locale::En, Currency::Chf, "CHF 1,234.56"),
+            (Locale::Nl, Currency::Eur, "€ 1.234,56"),
+            (Locale::Fr, Currency::Eur, "1\u{a0}234,56 €"),
+            (Locale::De, Currency::Gbp, "1.234,56 £"),
+        ];
+        for (locale, currency, expected) in cases.iter() {
+            let preferences = UserPreferences {
+                locale: *locale,
+                currency: *currency,
+                ..UserPreferences::defaults(1)
+            };
+            assert_eq!(*expected, preferences.format_amount(amount));
+        }
+
+        let preferences = UserPreferences::defaults(1);
+        assert_eq!("€0.00", preferences.format_amount(Decimal::new(0, 0)));
+        assert_eq!("€0.00", preferences.format_amount(Decimal::new(-1, 3)));
+        assert_eq!("€100.00", preferences.format_amount(Decimal::new(100, 0)));
+        assert_eq!(
+            "-€1,000.00",
+            preferences.format_amount(Decimal::new(-1000, 0))
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use super::schema::user_sessions::dsl;
+use super::schema::{user_sessions, users};
+use super::user::User;
+use app::AppConfig;
+use chrono::{Duration, NaiveDateTime};
+use diesel::pg::PgConnection;
+use diesel::prelude::*;
+use rand::{thread_rng, Rng};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+// The number of random bytes in a session or API token.
+const TOKEN_LENGTH: usize = 32;
+
+// The maximum length of the stored user agent string.
+const MAX_USER_AGENT_LENGTH: usize = 255;
+
+// The number of seconds after which the last seen time of a session is updated. This avoids writing
+// to the database on every request.
+const LAST_SEEN_UPDATE_INTERVAL: i64 = 60;
+
+/// A session of a logged in user. The session token is only stored as a hash, so a session cannot
+/// be hijacked using the data in the database.
+#[derive(Clone, Debug, PartialEq, Queryable, Serialize)]
+pub struct UserSession {
+    pub id: i32,
+    pub user_id: i32,
+    #[serde(skip)]
+    pub token_hash: String,
+    pub created: NaiveDateTime,
+    pub last_seen: NaiveDateTime,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl UserSession {
+    /// Returns whether or not the session has expired, given the idle timeout and the absolute
+    /// timeout in minutes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use db::user_session::UserSession;
+    /// # use chrono::{Duration, Local};
+    /// #
+    /// let now = Local::now().naive_local();
+    /// let mut user_session = UserSession {
+    ///     id: 1,
+    ///     user_id: 1,
+    ///     token_hash: "".to_string(),
+    ///     created: now - Duration::minutes(90),
+    ///     last_seen: now - Duration::minutes(20),
+    ///     ip_address: None,
+    ///     user_agent: None,
+    /// };
+    /// assert_eq!(user_session.is_expired(30, 120), false);
+    ///
+    /// // The session expires when it has been idle for too long.
+    /// assert_eq!(user_session.is_expired(15, 120), true);
+    ///
+    /// // The session expires when it is too old, even if it is in use.
+    /// assert_eq!(user_session.is_expired(30, 60), true);
+    /// ```
+    pub fn is_expired(&self, idle_timeout: u32, absolute_timeout: u32) -> bool {
+        let now = chrono::Local::now().naive_local();
+        self.last_seen + Duration::minutes(idle_timeout.into()) < now
+            || self.created + Duration::minutes(absolute_timeout.into()) < now
+    }
+}
+
+// Possible errors thrown when handling user sessions.
+#[derive(Debug, PartialEq)]
+pub enum UserSessionErrorKind {
+    // A new session could not be created due to a database error.
+    CreationFailed(diesel::result::Error),
+    // A session could not be deleted due to a database error.
+    DeletionFailed(diesel::result::Error),
+    // The session does not exist or belongs to another user.
+    NotFound(i32),
+    // Expired sessions could not be purged due to a database error.
+    PurgingFailed(diesel::result::Error),
+    // Sessions could not be read due to a database error.
+    ReadFailed(diesel::result::Error),
+    // A session could not be updated due to a database error.
+    UpdateFailed(diesel::result::Error),
+}
+
+impl fmt::Display for UserSessionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UserSessionErrorKind::CreationFailed(ref err) => {
+                write!(f, "Database error when creating session: {}", err)
+            }
+            UserSessionErrorKind::DeletionFailed(ref err) => {
+                write!(f, "Database error when deleting session: {}", err)
+            }
+            UserSessionErrorKind::NotFound(id) => write!(f, "Session {} not found", id),
+            UserSessionErrorKind::PurgingFailed(ref err) => {
+                write!(f, "Database error when purging expired sessions: {}", err)
+            }
+            UserSessionErrorKind::ReadFailed(ref err) => {
+                write!(f, "Database error when reading session: {}", err)
+            }
+            UserSessionErrorKind::UpdateFailed(ref err) => {
+                write!(f, "Database error when updating session: {}", err)
+            }
+        }
+    }
+}
+
+/// Starts a new session for the given user. Returns the session together with the session token
+/// that identifies it. The token is not stored and cannot be retrieved later.
+pub fn create(
+    connection: &PgConnection,
+    user: &User,
+    ip_address: Option<&str>,
+    user_agent: Option<&str>,
+) -> Result<(UserSession, String), UserSessionErrorKind> {
+    let token = generate_token();
+    let user_agent =
+        user_agent.map(|ua| ua.chars().take(MAX_USER_AGENT_LENGTH).collect::<String>());
+
+    let user_session = diesel::insert_into(dsl::user_sessions)
+        .values((
+            dsl::user_id.eq(user.id),
+            dsl::token_hash.eq(hash_token(token.as_str())),
+            dsl::ip_address.eq(ip_address),
+            dsl::user_agent.eq(user_agent),
+        ))
+        .get_result(connection)
+        .map_err(UserSessionErrorKind::CreationFailed)?;
+    Ok((user_session, token))
+}
+
+/// Returns the session with the given token together with its user. Returns `None` if there is no
+/// such session, if it has expired or if the user has been disabled. Expired sessions are deleted,
+/// and the last seen time of active sessions is updated.
+pub fn authenticate(
+    connection: &PgConnection,
+    token: &str,
+    config: &AppConfig,
+) -> Result<Option<(UserSession, User)>, UserSessionErrorKind> {
+    let result = user_sessions::table
+        .inner_join(users::table)
+        .filter(dsl::token_hash.eq(hash_token(token)))
+        .filter(users::disabled.eq(false))
+        .first::<(UserSession, User)>(connection)
+        .optional()
+        .map_err(UserSessionErrorKind::ReadFailed)?;
+
+    let (mut user_session, user) = match result {
+        Some(result) => result,
+        None => return Ok(None),
+    };
+
+    if user_session.is_expired(
+        config.session_idle_timeout(),
+        config.session_absolute_timeout(),
+    ) {
+        delete_by_token(connection, token)?;
+        return Ok(None);
+    }
+
+    let now = chrono::Local::now().naive_local();
+    if user_session.last_seen + Duration::seconds(LAST_SEEN_UPDATE_INTERVAL) < now {
+        user_session = diesel::update(dsl::user_sessions.find(user_session.id))
+            .set(dsl::last_seen.eq(now))
+            .get_result(connection)
+            .map_err(UserSessionErrorKind::UpdateFailed)?;
+    }
+
+    Ok(Some((user_session, user)))
+}
+
+/// Returns the sessions of the given user, most recently used first.
+pub fn list(
+    connection: &PgConnection,
+    user: &User,
+) -> Result<Vec<UserSession>, UserSessionErrorKind> {
+    dsl::user_sessions
+        .filter(dsl::user_id.eq(user.id))
+        .order(dsl::last_seen.desc())
+        .load(connection)
+        .map_err(UserSessionErrorKind::ReadFailed)
+}
+
+/// Ends the session with the given ID. Only sessions of the given user can be ended.
+pub fn delete(connection: &PgConnection, user: &User, id: i32) -> Result<(), UserSessionErrorKind> {
+    let count = diesel::delete(
+        dsl::user_sessions
+            .filter(dsl::id.eq(id))
+            .filter(dsl::user_id.eq(user.id)),
+    )
+    .execute(connection)
+    .map_err(UserSessionErrorKind::DeletionFailed)?;
+    match count {
+        0 => Err(UserSessionErrorKind::NotFound(id)),
+        _ => Ok(()),
+    }
+}
+
+/// Ends the session with the given token.
+pub fn delete_by_token(connection: &PgConnection, token: &str) -> Result<(), UserSessionErrorKind> {
+    diesel::delete(dsl::user_sessions.filter(dsl::token_hash.eq(hash_token(token))))
+        .execute(connection)
+        .map_err(UserSessionErrorKind::DeletionFailed)?;
+    Ok(())
+}
+
+/// Ends all sessions of the given user, except for the session with the given ID if passed. Returns
+/// the number of sessions that were ended.
+pub fn delete_all(
+    connection: &PgConnection,
+    user: &User,
+    except: Option<i32>,
+) -> Result<usize, UserSessionErrorKind> {
+    let query = dsl::user_sessions.filter(dsl::user_id.eq(user.id));
+    match except {
+        Some(id) => diesel::delete(query.filter(dsl::id.ne(id))).execute(connection),
+        None => diesel::delete(query).execute(connection),
+    }
+    .map_err(UserSessionErrorKind::DeletionFailed)
+}
+
+/// Purges expired sessions.
+pub fn purge(connection: &PgConnection, config: &AppConfig) -> Result<(), UserSessionErrorKind> {
+    let now = chrono::Local::now().naive_local();
+    let idle_limit = now - Duration::minutes(config.session_idle_timeout().into());
+    let absolute_limit = now - Duration::minutes(config.session_absolute_timeout().into());
+    diesel::delete(
+        dsl::user_sessions.filter(
+            dsl::last_seen
+                .lt(idle_limit)
+                .or(dsl::created.lt(absolute_limit)),
+        ),
+    )
+    .execute(connection)
+    .map_err(UserSessionErrorKind::PurgingFailed)?;
+    Ok(())
+}
+
+// Returns a new random token, hex encoded.
+pub(crate) fn generate_token() -> String {
+    let bytes: [u8; TOKEN_LENGTH] = thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Returns the hash of a random token, as it is stored in the database. Tokens have enough entropy
+// that a fast hash is sufficient.
+pub(crate) fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_test::create_test_user;
+    use crate::{establish_connection, get_database_url};
+    use diesel::result::Error;
+
+    // Tests super::create() and super::authenticate().
+    #[test]
+    fn test_authenticate() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let (user_session, token) =
+                create(&conn, &user, Some("192.0.2.1"), Some("Mozilla/5.0")).unwrap();
+            assert_eq!(TOKEN_LENGTH * 2, token.len());
+            assert_ne!(token, user_session.token_hash);
+            assert_eq!(Some("192.0.2.1".to_string()), user_session.ip_address);
+            assert_eq!(Some("Mozilla/5.0".to_string()), user_session.user_agent);
+
+            // The session can be retrieved with the token.
+            let (authenticated, session_user) =
+                authenticate(&conn, &token, &config).unwrap().unwrap();
+            assert_eq!(user_session, authenticated);
+            assert_eq!(user.id, session_user.id);
+            assert!(authenticate(&conn, "invalid", &config).unwrap().is_none());
+
+            // The sessions of disabled users are rejected.
+            let user = crate::user::set_disabled(&conn, user, true).unwrap();
+            assert!(authenticate(&conn, &token, &config).unwrap().is_none());
+            let user = crate::user::set_disabled(&conn, user, false).unwrap();
+            assert!(authenticate(&conn, &token, &config).unwrap().is_some());
+
+            // The last seen time is updated when the session is used after a while.
+            let a_while_ago = chrono::Local::now().naive_local() - Duration::minutes(5);
+            diesel::update(dsl::user_sessions.find(user_session.id))
+                .set(dsl::last_seen.eq(a_while_ago))
+                .execute(&conn)?;
+            let (authenticated, _) = authenticate(&conn, &token, &config).unwrap().unwrap();
+            assert!(authenticated.last_seen > a_while_ago);
+
+            // An idle session expires and is deleted.
+            let idle = chrono::Local::now().naive_local()
+                - Duration::minutes(config.session_idle_timeout() as i64 + 1);
+            diesel::update(dsl::user_sessions.find(user_session.id))
+                .set(dsl::last_seen.eq(idle))
+                .execute(&conn)?;
+            assert!(authenticate(&conn, &token, &config).unwrap().is_none());
+            assert!(list(&conn, &user).unwrap().is_empty());
+
+            // A session expires after the absolute timeout, even if it is in use.
+            let (user_session, token) = create(&conn, &user, None, None).unwrap();
+            let old = chrono::Local::now().naive_local()
+                - Duration::minutes(config.session_absolute_timeout() as i64 + 1);
+            diesel::update(dsl::user_sessions.find(user_session.id))
+                .set(dsl::created.eq(old))
+                .execute(&conn)?;
+            assert!(authenticate(&conn, &token, &config).unwrap().is_none());
+
+            Ok(())
+        });
+    }
+
+    // Tests super::delete(), super::delete_by_token() and super::delete_all().
+    #[test]
+    fn test_delete() {
+        let conn = establish_connection(&get_database_url()).unwrap();
+        let config = AppConfig::from_test_defaults();
+        conn.test_transaction::<_, Error, _>(|| {
+            let user = create_test_user(&conn, &config);
+            let other_user = create_test_user(&conn, &config);
+            let sessions: Vec<(UserSession, String)> = (0..4)
+                .map(|_| create(&conn, &user, None, None).unwrap())
+                .collect();
+            let (other_session, _) = create(&conn, &other_user, None, None).unwrap();
+            assert_eq!(4, list(&conn, &user).unwrap().len());
+
+            // Sessions of other users cannot be deleted.
+            assert_eq!(
+                Err(UserSessionErrorKind::NotFound(other_session.id)),
+                delete(&conn, &user, other_session.id)
+            );
+
+            delete(&conn, &user, sessions[0].0.id).unwrap();
+            assert!(authenticate(&conn, &sessions[0].1, &config)
+                .unwrap()
+                .is_none());
+            delete_by_token(&conn, &sessions[1].1).unwrap();
+            assert_eq!(2, list(&conn, &user).unwrap().len());
+
+            // All sessions except the current one can be ended.
+            assert_eq!(1, delete_all(&conn, &user, Some(sessions[2].0.id)).unwrap());
+            assert!(authenticate(&conn, &sessions[2].1, &config)
+                .unwrap()
+                .is_some());
+            assert_eq!(1, delete_all(&conn, &user, None).unwrap());
+            assert!(list(&conn, &user).unwrap().is_empty());
+
+            // The sessions of the other user are not affected.
+            assert_eq!(1, list(&conn, &other_user).unwrap().len());
+
+            Ok(())
+        });
+    }
+}
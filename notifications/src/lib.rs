@@ -3,9 +3,14 @@ extern crate log;
 
 use app::AppConfig;
 use db::activation_code::{ActivationCode, ActivationCodeErrorKind};
+use db::email_change_code::{EmailChangeCode, EmailChangeCodeErrorKind};
+use db::ledger::Ledger;
+use db::ledger_invitation::LedgerInvitation;
+use db::password_reset_code::{PasswordResetCode, PasswordResetCodeErrorKind};
+use db::registration_invitation::RegistrationInvitation;
 use db::user::User;
 use mailgun_v3::email::{async_impl::send_with_request_builder, Message, MessageBody};
-use mailgun_v3::{Credentials, EmailAddress};
+use mailgun_v3::{Credentials, EmailAddress, ReqError};
 use reqwest::RequestBuilder;
 use std::fmt;
 
@@ -15,37 +20,154 @@ const MAILGUN_API_ENDPOINT_URI: &str = "messages";
 // Errors that might occur when handling notifications.
 #[derive(Debug, PartialEq)]
 pub enum NotificationErrorKind {
+    // The account deletion notification could not be delivered due to a Mailgun error.
+    AccountDeletionNotificationNotDelivered(String),
     // The activation notification could not be delivered due to a Mailgun error.
     ActivationNotificationNotDelivered(String),
+    // The email change notification could not be delivered due to a Mailgun error.
+    EmailChangeNotificationNotDelivered(String),
+    // The ledger invitation could not be sent because the invitation has expired.
+    ExpiredLedgerInvitation(i32),
+    // The passed registration invitation has expired.
+    ExpiredRegistrationInvitation(i32),
     // The activation notification could not be sent because the notification code is not valid.
     InvalidActivationCode(ActivationCodeErrorKind),
+    // The email change notification could not be sent because the email change code is not valid.
+    InvalidEmailChangeCode(EmailChangeCodeErrorKind),
+    // The password reset notification could not be sent because the password reset code is not
+    // valid.
+    InvalidPasswordResetCode(PasswordResetCodeErrorKind),
+    // The ledger invitation notification could not be delivered due to a Mailgun error.
+    LedgerInvitationNotDelivered(String),
+    // The password reset notification could not be delivered due to a Mailgun error.
+    PasswordResetNotificationNotDelivered(String),
+    // The notification about a registration attempt could not be delivered due to a Mailgun error.
+    RegistrationAttemptNotificationNotDelivered(String),
+    // The registration invitation could not be delivered due to a Mailgun error.
+    RegistrationInvitationNotDelivered(String),
     // The user ID in the passed activation code did not match that from the passed user.
     WrongActivationCodeUser(i32, i32),
+    // The user ID in the passed email change code did not match that from the passed user.
+    WrongEmailChangeCodeUser(i32, i32),
+    // The ledger ID in the passed invitation did not match that from the passed ledger.
+    WrongLedgerInvitationLedger(i32, i32),
+    // The user ID in the passed password reset code did not match that from the passed user.
+    WrongPasswordResetCodeUser(i32, i32),
 }
 
 impl fmt::Display for NotificationErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            NotificationErrorKind::AccountDeletionNotificationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver account deletion notification: {}",
+                err
+            ),
             NotificationErrorKind::ActivationNotificationNotDelivered(ref err) => write!(
                 f,
                 "Mailgun error when attempting to deliver activation notification: {}",
                 err
             ),
+            NotificationErrorKind::EmailChangeNotificationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver email change notification: {}",
+                err
+            ),
+            NotificationErrorKind::ExpiredLedgerInvitation(ref id) => write!(
+                f,
+                "Ledger invitation mail could not be delivered because invitation {} has expired",
+                id
+            ),
+            NotificationErrorKind::ExpiredRegistrationInvitation(ref id) => write!(
+                f,
+                "Registration invitation mail could not be delivered because invitation {} has expired",
+                id
+            ),
             NotificationErrorKind::InvalidActivationCode(ref err) => write!(
                 f,
                 "Activation mail could not be delivered due to an invalid activation code: {}",
                 err
             ),
+            NotificationErrorKind::InvalidEmailChangeCode(ref err) => write!(
+                f,
+                "Email change mail could not be delivered due to an invalid email change code: {}",
+                err
+            ),
+            NotificationErrorKind::InvalidPasswordResetCode(ref err) => write!(
+                f,
+                "Password reset mail could not be delivered due to an invalid password reset code: {}",
+                err
+            ),
+            NotificationErrorKind::LedgerInvitationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver ledger invitation notification: {}",
+                err
+            ),
+            NotificationErrorKind::PasswordResetNotificationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver password reset notification: {}",
+                err
+            ),
+            NotificationErrorKind::RegistrationAttemptNotificationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver registration attempt notification: {}",
+                err
+            ),
+            NotificationErrorKind::RegistrationInvitationNotDelivered(ref err) => write!(
+                f,
+                "Mailgun error when attempting to deliver registration invitation: {}",
+                err
+            ),
             NotificationErrorKind::WrongActivationCodeUser(ref user_id, ref activation_id) => write!(
                 f,
                 "Activation mail could not be delivered because the activation code is for the user with ID {} but the passed user ID is {}",
                 activation_id,
                 user_id
             ),
+            NotificationErrorKind::WrongEmailChangeCodeUser(ref user_id, ref code_user_id) => write!(
+                f,
+                "Email change mail could not be delivered because the email change code is for the user with ID {} but the passed user ID is {}",
+                code_user_id,
+                user_id
+            ),
+            NotificationErrorKind::WrongLedgerInvitationLedger(ref ledger_id, ref invitation_ledger_id) => write!(
+                f,
+                "Ledger invitation mail could not be delivered because the invitation is for the ledger with ID {} but the passed ledger ID is {}",
+                invitation_ledger_id,
+                ledger_id
+            ),
+            NotificationErrorKind::WrongPasswordResetCodeUser(ref user_id, ref code_user_id) => write!(
+                f,
+                "Password reset mail could not be delivered because the password reset code is for the user with ID {} but the passed user ID is {}",
+                code_user_id,
+                user_id
+            ),
         }
     }
 }
 
+// Confirms to the given user that their account and all of its data have been deleted.
+pub async fn account_deletion(
+    user: &User,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    let subject = format!("Your {} account has been deleted", app::APPLICATION_NAME);
+    let body_text = format!(
+        "Your {} account {} and all of its data have been deleted at your request.\n\nIf you did not delete your account, please contact us as soon as possible.",
+        app::APPLICATION_NAME,
+        user.email,
+    );
+    send(user.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver account deletion notification: {:?}",
+                err
+            );
+            NotificationErrorKind::AccountDeletionNotificationNotDelivered(err.to_string())
+        })
+}
+
 // Sends a activation mail containing the given activation code to the given user.
 pub async fn activate(
     user: &User,
@@ -65,6 +187,214 @@ pub async fn activate(
         ));
     }
 
+    let subject = format!("Activation code for {}", app::APPLICATION_NAME);
+    let body_text = format!("Activation code: {}", activation_code.code);
+    send(user.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver activation notification: {:?}",
+                err
+            );
+            NotificationErrorKind::ActivationNotificationNotDelivered(err.to_string())
+        })
+}
+
+// Sends a mail containing the given email change code to the new email address of the given user.
+pub async fn email_change(
+    user: &User,
+    email_change_code: &EmailChangeCode,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    // Sanity check: ensure that the email change code is valid.
+    email_change_code
+        .validate()
+        .map_err(NotificationErrorKind::InvalidEmailChangeCode)?;
+
+    // Sanity check: the user ID should match the one from the email change code.
+    if user.id != email_change_code.id {
+        return Err(NotificationErrorKind::WrongEmailChangeCodeUser(
+            user.id,
+            email_change_code.id,
+        ));
+    }
+
+    let subject = format!(
+        "Confirm your new email address for {}",
+        app::APPLICATION_NAME
+    );
+    let body_text = format!(
+        "You have requested to change the email address of your {} account from {} to this address. Enter the following code on the account settings page to confirm the change: {}\n\nThe code expires in 30 minutes. If you did not request this change you can safely ignore this email.",
+        app::APPLICATION_NAME,
+        user.email,
+        email_change_code.code,
+    );
+    send(email_change_code.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver email change notification: {:?}",
+                err
+            );
+            NotificationErrorKind::EmailChangeNotificationNotDelivered(err.to_string())
+        })
+}
+
+// Sends a mail inviting the recipient of the given ledger invitation to join the ledger.
+pub async fn ledger_invitation(
+    invitation: &LedgerInvitation,
+    ledger: &Ledger,
+    inviter: &User,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    // Sanity check: ensure that the invitation is still valid.
+    if invitation.is_expired() {
+        return Err(NotificationErrorKind::ExpiredLedgerInvitation(
+            invitation.id,
+        ));
+    }
+
+    // Sanity check: the ledger ID should match the one from the invitation.
+    if ledger.id != invitation.ledger_id {
+        return Err(NotificationErrorKind::WrongLedgerInvitationLedger(
+            ledger.id,
+            invitation.ledger_id,
+        ));
+    }
+
+    let subject = format!(
+        "Invitation to the {} ledger on {}",
+        ledger.name,
+        app::APPLICATION_NAME
+    );
+    let body_text = format!(
+        "{} has invited you to join the {} ledger on {} as {}.\n\nLog in with {} and visit the Ledgers page to accept the invitation. If you don't have an account yet, please sign up first using this email address.\n\nThe invitation expires on {}.",
+        inviter.email,
+        ledger.name,
+        app::APPLICATION_NAME,
+        invitation.role,
+        invitation.email,
+        invitation.expiration_time.format("%Y-%m-%d"),
+    );
+    send(invitation.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver ledger invitation notification: {:?}",
+                err
+            );
+            NotificationErrorKind::LedgerInvitationNotDelivered(err.to_string())
+        })
+}
+
+// Sends a mail containing the given password reset code to the given user.
+pub async fn password_reset(
+    user: &User,
+    password_reset_code: &PasswordResetCode,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    // Sanity check: ensure that the password reset code is valid.
+    password_reset_code
+        .validate()
+        .map_err(NotificationErrorKind::InvalidPasswordResetCode)?;
+
+    // Sanity check: the user ID should match the one from the password reset code.
+    if user.id != password_reset_code.id {
+        return Err(NotificationErrorKind::WrongPasswordResetCodeUser(
+            user.id,
+            password_reset_code.id,
+        ));
+    }
+
+    let subject = format!("Password reset code for {}", app::APPLICATION_NAME);
+    let body_text = format!(
+        "Someone has requested to reset the password of your {} account. If this was you, enter the following code to choose a new password: {}\n\nThe code expires in 30 minutes. If you did not request a password reset you can safely ignore this email.",
+        app::APPLICATION_NAME,
+        password_reset_code.code,
+    );
+    send(user.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver password reset notification: {:?}",
+                err
+            );
+            NotificationErrorKind::PasswordResetNotificationNotDelivered(err.to_string())
+        })
+}
+
+// Informs the given user that someone tried to register a new account using their email address.
+pub async fn registration_attempt(
+    user: &User,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    let subject = format!("Registration attempt on {}", app::APPLICATION_NAME);
+    let body_text = format!(
+        "Someone tried to sign up for {} using your email address, but you already have an account.\n\nIf this was you, you can log in with your existing account. If you have forgotten your password you can reset it from the login page. If this wasn't you, you can safely ignore this email.",
+        app::APPLICATION_NAME,
+    );
+    send(user.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver registration attempt notification: {:?}",
+                err
+            );
+            NotificationErrorKind::RegistrationAttemptNotificationNotDelivered(err.to_string())
+        })
+}
+
+// Sends a mail inviting the recipient of the given registration invitation to create an account.
+// The inviter is omitted for invitations that were created on the command line.
+pub async fn registration_invitation(
+    invitation: &RegistrationInvitation,
+    inviter: Option<&User>,
+    config: &AppConfig,
+) -> Result<(), NotificationErrorKind> {
+    // Sanity check: ensure that the invitation is still valid.
+    if invitation.is_expired() {
+        return Err(NotificationErrorKind::ExpiredRegistrationInvitation(
+            invitation.id,
+        ));
+    }
+
+    let subject = format!("Invitation to join {}", app::APPLICATION_NAME);
+    let introduction = match inviter {
+        Some(inviter) => format!(
+            "{} has invited you to create an account on {}.",
+            inviter.email,
+            app::APPLICATION_NAME
+        ),
+        None => format!(
+            "You have been invited to create an account on {}.",
+            app::APPLICATION_NAME
+        ),
+    };
+    let body_text = format!(
+        "{}\n\nSign up using the email address {} and enter the following invitation code: {}\n\nThe invitation expires on {}.",
+        introduction,
+        invitation.email,
+        invitation.code,
+        invitation.expiration_time.format("%Y-%m-%d"),
+    );
+    send(invitation.email.as_str(), subject, body_text, config)
+        .await
+        .map_err(|err| {
+            error!(
+                "Mailgun error when attempting to deliver registration invitation: {:?}",
+                err
+            );
+            NotificationErrorKind::RegistrationInvitationNotDelivered(err.to_string())
+        })
+}
+
+// Sends a plain text mail to the given recipient.
+async fn send(
+    recipient: &str,
+    subject: String,
+    body_text: String,
+    config: &AppConfig,
+) -> Result<(), ReqError> {
     let sender = EmailAddress::name_address(
         // Todo: Make sender name configurable.
         "Firetrack team",
@@ -75,27 +405,18 @@ pub async fn activate(
         )
         .as_str(),
     );
-    let recipient = EmailAddress::address(user.email.as_str());
-    let body_text = format!("Activation code: {}", activation_code.code);
+    let recipient = EmailAddress::address(recipient);
     let body = MessageBody::Text(body_text);
     let message = Message {
         to: vec![recipient],
-        subject: format!("Activation code for {}", app::APPLICATION_NAME),
+        subject,
         body,
         ..Default::default()
     };
 
     let credentials = Credentials::new(config.mailgun_api_key(), config.mailgun_user_domain());
     let request_builder = get_request_builder(config);
-    send_with_request_builder(request_builder, &credentials, &sender, message)
-        .await
-        .map_err(|err| {
-            error!(
-                "Mailgun error when attempting to deliver activation notification: {:?}",
-                err
-            );
-            NotificationErrorKind::ActivationNotificationNotDelivered(err.to_string())
-        })?;
+    send_with_request_builder(request_builder, &credentials, &sender, message).await?;
     Ok(())
 }
 
@@ -305,17 +626,350 @@ mod tests {
         );
     }
 
+    #[actix_rt::test]
+    // Tests sending email change notifications.
+    async fn test_email_change() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let user = get_user();
+        let email_change_code = EmailChangeCode {
+            id: user.id,
+            email: "new-address@example.com".to_string(),
+            code: 234_567,
+            expiration_time: chrono::Local::now()
+                .checked_add_signed(chrono::Duration::minutes(30))
+                .unwrap()
+                .naive_local(),
+            attempts: 0,
+        };
+
+        // The mail is sent to the new email address.
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!(
+                        "Confirm your new email address for {}",
+                        app::APPLICATION_NAME
+                    ),
+                ),
+                Matcher::UrlEncoded("to".to_string(), email_change_code.email.clone()),
+                Matcher::Regex(format!(
+                    "text=.*confirm\\+the\\+change%3A\\+{}",
+                    email_change_code.code
+                )),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(email_change(&user, &email_change_code, &config)
+            .await
+            .is_ok());
+
+        // An error is returned when the email change code is for a different user.
+        let other_user = User {
+            id: user.id + 1,
+            ..get_user()
+        };
+        assert_eq!(
+            NotificationErrorKind::WrongEmailChangeCodeUser(other_user.id, user.id),
+            email_change(&other_user, &email_change_code, &config)
+                .await
+                .unwrap_err()
+        );
+    }
+
+    #[actix_rt::test]
+    // Tests sending ledger invitation notifications.
+    async fn test_ledger_invitation() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let inviter = get_user();
+        let ledger = get_ledger();
+        let invitation = get_ledger_invitation();
+
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!(
+                        "Invitation to the {} ledger on {}",
+                        ledger.name,
+                        app::APPLICATION_NAME
+                    ),
+                ),
+                Matcher::UrlEncoded("to".to_string(), invitation.email.clone()),
+                Matcher::Regex(format!(
+                    "text={}\\+has\\+invited\\+you",
+                    inviter.email.replace("@", "%40")
+                )),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(ledger_invitation(&invitation, &ledger, &inviter, &config)
+            .await
+            .is_ok());
+
+        // An error is returned when the invitation is for a different ledger.
+        let other_ledger = Ledger {
+            id: ledger.id + 1,
+            ..get_ledger()
+        };
+        assert_eq!(
+            NotificationErrorKind::WrongLedgerInvitationLedger(other_ledger.id, ledger.id),
+            ledger_invitation(&invitation, &other_ledger, &inviter, &config)
+                .await
+                .unwrap_err()
+        );
+
+        // An error is returned when the invitation has expired.
+        let expired_invitation = LedgerInvitation {
+            expiration_time: chrono::Local::now()
+                .checked_sub_signed(chrono::Duration::minutes(1))
+                .unwrap()
+                .naive_local(),
+            ..get_ledger_invitation()
+        };
+        assert_eq!(
+            NotificationErrorKind::ExpiredLedgerInvitation(expired_invitation.id),
+            ledger_invitation(&expired_invitation, &ledger, &inviter, &config)
+                .await
+                .unwrap_err()
+        );
+    }
+
+    #[actix_rt::test]
+    // Tests sending password reset notifications.
+    async fn test_password_reset() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let user = get_user();
+        let password_reset_code = get_password_reset_code();
+
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!("Password reset code for {}", app::APPLICATION_NAME),
+                ),
+                Matcher::UrlEncoded("to".to_string(), user.email.clone()),
+                Matcher::Regex(format!(
+                    "text=.*code\\+to\\+choose\\+a\\+new\\+password%3A\\+{}",
+                    password_reset_code.code
+                )),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(password_reset(&user, &password_reset_code, &config)
+            .await
+            .is_ok());
+
+        // An error is returned when the password reset code is for a different user.
+        let other_user = User {
+            id: user.id + 1,
+            ..get_user()
+        };
+        assert_eq!(
+            NotificationErrorKind::WrongPasswordResetCodeUser(other_user.id, user.id),
+            password_reset(&other_user, &password_reset_code, &config)
+                .await
+                .unwrap_err()
+        );
+
+        // An error is returned when the password reset code has expired.
+        let expired_password_reset_code = PasswordResetCode {
+            expiration_time: chrono::Local::now()
+                .checked_sub_signed(chrono::Duration::minutes(1))
+                .unwrap()
+                .naive_local(),
+            ..get_password_reset_code()
+        };
+        assert_eq!(
+            NotificationErrorKind::InvalidPasswordResetCode(PasswordResetCodeErrorKind::Expired),
+            password_reset(&user, &expired_password_reset_code, &config)
+                .await
+                .unwrap_err()
+        );
+    }
+
+    #[actix_rt::test]
+    // Tests sending notifications about registration attempts.
+    async fn test_registration_attempt() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let user = get_user();
+
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!("Registration attempt on {}", app::APPLICATION_NAME),
+                ),
+                Matcher::UrlEncoded("to".to_string(), user.email.clone()),
+                Matcher::Regex("text=Someone\\+tried\\+to\\+sign\\+up".to_string()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(registration_attempt(&user, &config).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    // Tests sending registration invitations.
+    async fn test_registration_invitation() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let inviter = get_user();
+        let invitation = get_registration_invitation();
+
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!("Invitation to join {}", app::APPLICATION_NAME),
+                ),
+                Matcher::UrlEncoded("to".to_string(), invitation.email.clone()),
+                Matcher::Regex(format!("text=.*invitation\\+code%3A\\+{}", invitation.code)),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(
+            registration_invitation(&invitation, Some(&inviter), &config)
+                .await
+                .is_ok()
+        );
+        assert!(registration_invitation(&invitation, None, &config)
+            .await
+            .is_ok());
+
+        // An error is returned when the invitation has expired.
+        let expired_invitation = RegistrationInvitation {
+            expiration_time: chrono::Local::now()
+                .checked_sub_signed(chrono::Duration::minutes(1))
+                .unwrap()
+                .naive_local(),
+            ..get_registration_invitation()
+        };
+        assert_eq!(
+            NotificationErrorKind::ExpiredRegistrationInvitation(expired_invitation.id),
+            registration_invitation(&expired_invitation, Some(&inviter), &config)
+                .await
+                .unwrap_err()
+        );
+    }
+
+    #[actix_rt::test]
+    // Tests sending account deletion notifications.
+    async fn test_account_deletion() {
+        use mockito::Matcher;
+
+        let config = AppConfig::from_test_defaults();
+        let user = get_user();
+
+        let uri = get_mailgun_uri(&config);
+        let _m = mockito::mock("POST", uri.as_str())
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded(
+                    "subject".to_string(),
+                    format!("Your {} account has been deleted", app::APPLICATION_NAME),
+                ),
+                Matcher::UrlEncoded("to".to_string(), user.email.clone()),
+                Matcher::Regex("text=Your\\+firetrack\\+account".to_string()),
+            ]))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"id": "<0123456789abcdef@example.com>", "message": "Queued. Thank you."}"#,
+            )
+            .create();
+
+        assert!(account_deletion(&user, &config).await.is_ok());
+    }
+
     // Returns a test user.
     fn get_user() -> User {
         User {
             id: 1,
             activated: false,
+            admin: false,
+            disabled: false,
             email: "testuser@example.com".to_string(),
             created: chrono::Local::now().naive_local(),
             password: "123456".to_string(),
         }
     }
 
+    // Returns a test ledger.
+    fn get_ledger() -> Ledger {
+        Ledger {
+            id: 1,
+            name: "Household".to_string(),
+            created: chrono::Local::now().naive_local(),
+        }
+    }
+
+    // Returns a test ledger invitation.
+    fn get_ledger_invitation() -> LedgerInvitation {
+        LedgerInvitation {
+            id: 1,
+            ledger_id: 1,
+            email: "invitee@example.com".to_string(),
+            role: db::ledger::Role::Editor,
+            invited_by: 1,
+            expiration_time: chrono::Local::now()
+                .checked_add_signed(chrono::Duration::days(7))
+                .unwrap()
+                .naive_local(),
+        }
+    }
+
+    // Returns a test registration invitation.
+    fn get_registration_invitation() -> RegistrationInvitation {
+        RegistrationInvitation {
+            id: 1,
+            email: "invitee@example.com".to_string(),
+            code: "abcd-efgh-jkmn".to_string(),
+            invited_by: Some(1),
+            expiration_time: chrono::Local::now()
+                .checked_add_signed(chrono::Duration::days(14))
+                .unwrap()
+                .naive_local(),
+        }
+    }
+
     // Returns a test activation code.
     fn get_activation_code() -> ActivationCode {
         ActivationCode {
@@ -328,4 +982,17 @@ mod tests {
             attempts: 0,
         }
     }
+
+    // Returns a test password reset code.
+    fn get_password_reset_code() -> PasswordResetCode {
+        PasswordResetCode {
+            id: 1,
+            code: 654_321,
+            expiration_time: chrono::Local::now()
+                .checked_add_signed(chrono::Duration::minutes(30))
+                .unwrap()
+                .naive_local(),
+            attempts: 0,
+        }
+    }
 }
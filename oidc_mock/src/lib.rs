@@ -0,0 +1,302 @@
+#[macro_use]
+extern crate log;
+
+use app::AppConfig;
+use hmac::{Hmac, Mac, NewMac};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use rand::{thread_rng, Rng};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::{form_urlencoded, Url};
+
+// The number of seconds an ID token is valid.
+const ID_TOKEN_LIFETIME: u64 = 300;
+
+// The state of the mock identity provider: the configuration of the client, and the authorization
+// codes that have been issued but not yet exchanged for tokens.
+struct Provider {
+    config: AppConfig,
+    codes: Mutex<HashMap<String, Authorization>>,
+}
+
+// A login that has been approved in the authorization endpoint.
+struct Authorization {
+    redirect_uri: String,
+    nonce: Option<String>,
+    code_challenge: String,
+    email: String,
+    email_verified: bool,
+}
+
+// Starts the mock server on the port as configured in the application. The issuer URL in the
+// configuration should point to this server.
+pub async fn serve(config: AppConfig) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr = SocketAddr::from(([127, 0, 0, 1], config.oidc_mock_server_port()));
+    let provider = Arc::new(Provider {
+        config,
+        codes: Mutex::new(HashMap::new()),
+    });
+    let service = make_service_fn(move |_conn| {
+        let provider = provider.clone();
+        async move { Ok::<_, hyper::Error>(service_fn(move |req| handle(provider.clone(), req))) }
+    });
+    Server::bind(&addr).serve(service).await?;
+    Ok(())
+}
+
+// Routes the request to the endpoint that handles it.
+async fn handle(
+    provider: Arc<Provider>,
+    req: Request<Body>,
+) -> Result<Response<Body>, hyper::Error> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/.well-known/openid-configuration") => discovery(&provider),
+        (&Method::GET, "/authorize") => authorize(&provider, req.uri().query().unwrap_or("")),
+        (&Method::POST, "/token") => {
+            let body = hyper::body::to_bytes(req.into_body()).await?;
+            token(&provider, &body)
+        }
+        _ => text_response(StatusCode::NOT_FOUND, "Not found."),
+    };
+    Ok(response)
+}
+
+// Returns the provider metadata, which tells the client where to find the endpoints.
+fn discovery(provider: &Provider) -> Response<Body> {
+    let issuer = provider.config.oidc_issuer_url();
+    json_response(
+        StatusCode::OK,
+        json!({
+            "issuer": issuer,
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "response_types_supported": ["code"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["HS256"],
+            "scopes_supported": ["openid", "email"],
+            "token_endpoint_auth_methods_supported": ["client_secret_post"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": ["iss", "sub", "aud", "exp", "iat", "nonce", "email", "email_verified"],
+        }),
+    )
+}
+
+// Mocks the authorization endpoint. Since there are no passwords, this shows a form in which any
+// email address can be entered. Passing the `email` parameter in the query string skips the form,
+// which allows to log in without a browser. The email address is considered verified unless
+// `email_verified=false` is passed.
+fn authorize(provider: &Provider, query: &str) -> Response<Body> {
+    let params: HashMap<String, String> = form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let param = |name: &str| params.get(name).map(String::as_str).unwrap_or("");
+
+    // Only the registered client can log in, and PKCE is required.
+    if param("client_id") != provider.config.oidc_client_id() {
+        return text_response(StatusCode::BAD_REQUEST, "Unknown client.");
+    }
+    if param("redirect_uri") != provider.config.oidc_redirect_url() {
+        return text_response(StatusCode::BAD_REQUEST, "Unregistered redirect URI.");
+    }
+    let redirect = |params: &[(&str, &str)]| {
+        let mut url = Url::parse(param("redirect_uri")).unwrap();
+        url.query_pairs_mut().extend_pairs(params);
+        if !param("state").is_empty() {
+            url.query_pairs_mut().append_pair("state", param("state"));
+        }
+        Response::builder()
+            .status(StatusCode::FOUND)
+            .header(header::LOCATION, url.as_str())
+            .body(Body::empty())
+            .unwrap()
+    };
+    if param("response_type") != "code"
+        || !param("scope").split(' ').any(|scope| scope == "openid")
+        || param("code_challenge").is_empty()
+        || param("code_challenge_method") != "S256"
+    {
+        return redirect(&[("error", "invalid_request")]);
+    }
+
+    if param("action") == "cancel" {
+        return redirect(&[("error", "access_denied")]);
+    }
+    if param("email").is_empty() {
+        return login_form(&params);
+    }
+
+    let code = random_string();
+    provider.codes.lock().unwrap().insert(
+        code.clone(),
+        Authorization {
+            redirect_uri: param("redirect_uri").to_string(),
+            nonce: params.get("nonce").cloned(),
+            code_challenge: param("code_challenge").to_string(),
+            email: param("email").to_string(),
+            email_verified: param("email_verified") != "false",
+        },
+    );
+    info!("Issued authorization code for {}", param("email"));
+    redirect(&[("code", code.as_str())])
+}
+
+// Returns the login form of the authorization endpoint. The parameters of the authorization request
+// are passed on in hidden fields.
+fn login_form(params: &HashMap<String, String>) -> Response<Body> {
+    let hidden_fields: String = params
+        .iter()
+        .filter(|(name, _)| !["action", "email", "email_verified"].contains(&name.as_str()))
+        .map(|(name, value)| {
+            format!(
+                r#"<input type="hidden" name="{}" value="{}">"#,
+                escape_html(name),
+                escape_html(value)
+            )
+        })
+        .collect();
+    let html = format!(
+        r#"<!doctype html>
+<html>
+<head><title>Mock identity provider</title></head>
+<body>
+<h1>Mock identity provider</h1>
+<form method="get" action="/authorize">
+{}
+<label for="email">Email address</label>
+<input type="email" name="email" id="email" required>
+<label><input type="checkbox" name="email_verified" value="false"> The email address is not verified</label>
+<button type="submit" name="action" value="login">Log in</button>
+<button type="submit" name="action" value="cancel" formnovalidate>Cancel</button>
+</form>
+</body>
+</html>"#,
+        hidden_fields
+    );
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/html")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+// Mocks the token endpoint, which exchanges an authorization code for an ID token. The client
+// authenticates with its client secret, and proves that it started the login by passing the PKCE
+// code verifier.
+fn token(provider: &Provider, body: &[u8]) -> Response<Body> {
+    let params: HashMap<String, String> = form_urlencoded::parse(body).into_owned().collect();
+    let param = |name: &str| params.get(name).map(String::as_str).unwrap_or("");
+    let error = |status: StatusCode, error: &str| {
+        warn!("Token request rejected: {}", error);
+        json_response(status, json!({ "error": error }))
+    };
+
+    let config = &provider.config;
+    if param("client_id") != config.oidc_client_id()
+        || param("client_secret") != config.oidc_client_secret()
+    {
+        return error(StatusCode::UNAUTHORIZED, "invalid_client");
+    }
+    if param("grant_type") != "authorization_code" {
+        return error(StatusCode::BAD_REQUEST, "unsupported_grant_type");
+    }
+
+    // Authorization codes can only be used once.
+    let authorization = match provider.codes.lock().unwrap().remove(param("code")) {
+        Some(authorization) => authorization,
+        None => return error(StatusCode::BAD_REQUEST, "invalid_grant"),
+    };
+    let code_challenge = base64::encode_config(
+        Sha256::digest(param("code_verifier").as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    );
+    if param("redirect_uri") != authorization.redirect_uri
+        || code_challenge != authorization.code_challenge
+    {
+        return error(StatusCode::BAD_REQUEST, "invalid_grant");
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut claims = json!({
+        "iss": config.oidc_issuer_url(),
+        // The subject is derived from the email address so it is stable across logins.
+        "sub": base64::encode_config(
+            Sha256::digest(authorization.email.as_bytes()),
+            base64::URL_SAFE_NO_PAD,
+        ),
+        "aud": config.oidc_client_id(),
+        "exp": now + ID_TOKEN_LIFETIME,
+        "iat": now,
+        "email": authorization.email,
+        "email_verified": authorization.email_verified,
+    });
+    if let Some(nonce) = authorization.nonce {
+        claims["nonce"] = json!(nonce);
+    }
+
+    json_response(
+        StatusCode::OK,
+        json!({
+            "access_token": random_string(),
+            "token_type": "Bearer",
+            "expires_in": ID_TOKEN_LIFETIME,
+            "id_token": sign(&claims, config.oidc_client_secret()),
+        }),
+    )
+}
+
+// Returns a JSON Web Token containing the given claims, signed with the client secret.
+fn sign(claims: &Value, secret: &str) -> String {
+    let encode = |value: &Value| {
+        base64::encode_config(value.to_string().as_bytes(), base64::URL_SAFE_NO_PAD)
+    };
+    let payload = format!(
+        "{}.{}",
+        encode(&json!({"alg": "HS256", "typ": "JWT"})),
+        encode(claims)
+    );
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    let signature = base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD);
+    format!("{}.{}", payload, signature)
+}
+
+// Returns a random string that can be used as an authorization code or access token.
+fn random_string() -> String {
+    let bytes: [u8; 16] = thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Escapes a string for use in an HTML attribute.
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Returns a response with the given JSON body.
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+// Returns a response with the given plain text body.
+fn text_response(status: StatusCode, body: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(Body::from(body))
+        .unwrap()
+}
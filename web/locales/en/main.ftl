@@ -0,0 +1,424 @@
+## Page layout.
+
+logo-alt = Firetrack logo
+nav-home = Home
+nav-account = Account
+nav-log-out = Log out
+nav-sign-up = Sign up
+nav-log-in = Log in
+nav-expenses = Expenses
+nav-balances = Balances
+nav-goals = Goals
+nav-budgets = Budgets
+nav-reports = Reports
+nav-ledgers = Ledgers
+
+## Common.
+
+common-name = Name
+common-total = Total
+common-created = Created
+common-expires = Expires
+common-never = Never
+common-revoke = Revoke
+common-invite = Invite
+common-cancel = Cancel
+common-current-password = Current password
+common-from = From
+common-to = to
+
+## Error pages.
+
+error-not-found-title = Page not found
+error-not-found-message = Sorry, this page does not exist
+error-not-found-explanation = We can't seem to find the page you're looking for.
+error-access-denied-title = Access denied
+error-access-denied-message = Please log in and try again
+error-suggestions = You can try to <a href="/user/login">log in</a> or <a href="/">go to the homepage</a>.
+
+## User forms.
+
+user-email = Email address
+user-email-invalid = Please enter a valid email address.
+user-email-help = We'll never share your email with anyone else.
+user-password = Password
+user-password-invalid = Please enter a password.
+user-invitation-code = Invitation code
+user-invitation-code-invalid = This invitation code is not valid for this email address, or it has expired.
+user-invitation-code-help = Registration is by invitation only. Use the email address the invitation was sent to.
+
+## Login.
+
+login-title = Log in
+login-invalid = Incorrect email address or password. Please try again.
+login-with-provider = Log in with { $provider }
+login-forgot-password = Forgot your password?
+login-account-activated = Your account has been activated. You can now log in.
+login-account-deleted = Your account and all of its data have been deleted.
+login-account-disabled = This account has been disabled. Please contact an administrator.
+login-password-changed = Your password has been changed. You can now log in.
+login-two-factor-failed = Too many incorrect authentication codes. Please log in again.
+
+## Two-factor authentication.
+
+two-factor-title = Two-factor authentication
+two-factor-code = Enter your authentication code
+two-factor-code-help = Enter the 6-digit code from your authenticator app. If you have lost access to the app you can enter one of your recovery codes.
+two-factor-code-incorrect = Incorrect authentication code. Please try again.
+two-factor-submit = Log in
+
+## Registration.
+
+register-title = Sign up
+register-closed = Registration is closed. New accounts cannot be created at this time.
+
+## Account activation.
+
+activate-title = Activate account
+activate-code = Enter your activation code
+activate-code-help = Please enter the activation code which was sent to your email.
+activate-code-invalid = The activation code is not valid.
+activate-code-format = Please enter a 6-digit number
+activate-code-expired = The activation code has expired. Please request a new activation code and try again.
+activate-code-incorrect = Incorrect activation code. Please try again.
+activate-max-attempts = You have exceeded the maximum number of activation attempts. Please try again later.
+activate-submit = Activate
+activate-resend-question = Didn't receive a code, or has it expired?
+activate-resend = Resend activation code
+activation-code-sent = A new activation code has been sent to { $email }.
+activation-code-max-requests = You have requested too many activation codes. Please wait 30 minutes and try again.
+
+## Password reset.
+
+password-reset-title = Reset password
+password-reset-email-help = We will send a code to this address that you can use to choose a new password.
+password-reset-submit = Send code
+password-reset-confirm-title = Choose a new password
+password-reset-confirm-sent = If an account exists for { $email }, we have sent it a code to reset the password.
+password-reset-code = Enter your code
+password-reset-code-help = Didn't receive a code? <a href="/user/password-reset">Request a new one</a>.
+password-reset-code-format = Please enter a 6-digit number.
+password-reset-code-incorrect = Incorrect or expired code. A code is valid for 30 minutes and can only be tried a limited number of times.
+password-reset-new-password = New password
+password-reset-confirm-submit = Change password
+
+## Expenses.
+
+expenses-title = Expenses
+expense-add-title = Add expense
+expense-search-placeholder = Search expenses in { $ledger }
+expense-search-label = Search expenses
+expense-search-empty = No expenses found matching "{ $query }".
+expense-date = Date
+expense-category = Category
+expense-description = Description
+expense-description-placeholder = Optional
+expense-amount = Amount
+expense-paid-by = Paid by
+expense-split-equally = Split equally among all members
+expense-form-title = Expense
+expense-add-submit = Add
+expense-amount-required = Please enter an amount.
+expense-amount-format = Amount should be in the format '{ $example }'.
+expense-amount-invalid = Please enter a valid amount.
+expense-amount-too-small = Amount should be 0.01 or greater.
+expense-amount-too-large = Amount should be 9999999.99 or smaller.
+expense-category-required = Please choose a category.
+expense-category-invalid = Invalid category ID.
+expense-category-unknown = Unknown category.
+expense-date-required = Please pick a date.
+expense-date-format = Date should be in the format { $format }.
+expense-date-invalid = Please enter a valid date.
+expense-added = Successfully added { $amount } expense to the { $category } category.
+expense-added-shared = Successfully added { $amount } expense to the { $category } category. It is shared among { $members } members.
+
+## Preferences.
+
+preferences-title = Preferences
+preferences-language = Language
+preferences-language-browser = Same as the browser
+preferences-locale = Number format
+preferences-currency = Currency
+preferences-date-format = Date format
+preferences-first-day-of-week = First day of the week
+preferences-first-day-of-week-help = Used when reporting on your spending per week.
+preferences-submit = Save preferences
+preferences-saved = Your preferences have been saved.
+day-monday = Monday
+day-saturday = Saturday
+day-sunday = Sunday
+month-1 = January
+month-2 = February
+month-3 = March
+month-4 = April
+month-5 = May
+month-6 = June
+month-7 = July
+month-8 = August
+month-9 = September
+month-10 = October
+month-11 = November
+month-12 = December
+
+## Dashboard.
+
+dashboard-title = Dashboard
+dashboard-spent-in = Spent in { $month }
+dashboard-expense-count = { $count ->
+        [one] { $count } expense
+       *[other] { $count } expenses
+    } in { $ledger }
+dashboard-forecast = Expected by the end of the month
+dashboard-above-average = Above the monthly average of { $average }
+dashboard-within-average = Within the monthly average of { $average }
+dashboard-no-history = No spending history yet
+dashboard-view-budgets = View budgets
+dashboard-top-categories = Top categories
+dashboard-nothing-spent = Nothing has been spent this month.
+dashboard-view-reports = View reports
+dashboard-view-goals = View goals
+dashboard-latest-expenses = Latest expenses
+dashboard-no-expenses = No expenses have been added yet.
+dashboard-view-all-expenses = View all expenses
+
+## Balances.
+
+balances-title = Balances
+balance-debts = Who owes whom
+balance-debt = { $debtor } owes { $creditor } { $amount }
+balance-settle = Settle
+balance-settled = All balances are settled.
+balance-payments = Payments
+balance-payment = { $payer } paid { $payee }
+balance-payer = Payer
+balance-paid = paid
+balance-payee = Payee
+balance-deleted-user = A deleted user
+balance-record-payment = Record payment
+
+## Savings goals.
+
+goals-title = Savings goals
+goal-delete = Delete goal
+goal-progress = { $saved } of { $target } by { $date }
+goal-reached = The goal has been reached.
+goal-monthly-saving = Save { $amount } per month during the next { $months } { $months ->
+        [one] month
+       *[other] months
+    } to reach this goal.
+goal-target-date-passed = The target date has passed, { $amount } is still missing.
+goal-contribute = Contribute
+goal-new = New goal
+goal-name-placeholder = Emergency fund
+goal-target-amount = Target amount
+goal-target-date = Target date
+goal-create = Create goal
+
+## Budgets.
+
+budgets-title = Budgets
+budget-none = No budgets have been set yet. A budget warns you when a category is expected to cost more than planned.
+budget-set = Set budget
+budget-remove = Remove budget
+budget-saved = The budget of { $category } has been set to { $amount }.
+budget-removed = The budget of { $category } has been removed.
+budget-over = <strong>{ $category }</strong> is expected to cost { $amount } this month, which exceeds its budget of { $budget }.
+budget-recurring-expenses = Recurring expenses
+budget-recurring-none = No recurring expenses have been added yet. Recurring expenses such as the rent are included in the forecast.
+budget-day-of-month = Day of the month
+budget-recurring-add = Add recurring expense
+budget-recurring-added = Added a recurring expense of { $amount } to { $category }.
+budget-recurring-delete = Delete recurring expense
+budget-recurring-deleted = The recurring expense has been deleted.
+
+## Ledgers.
+
+ledgers-title = Ledgers
+ledger-invitations = Invitations
+ledger-invited-to-join = You have been invited to join the <strong>{ $ledger }</strong> ledger as { $role }.
+ledger-accept = Accept
+ledger-decline = Decline
+ledger-yours = Your ledgers
+ledger-current = Current
+ledger-switch = Switch
+ledger-name = Ledger name
+ledger-create = Create ledger
+ledger-members = Members of { $ledger }
+ledger-leave = Leave
+ledger-remove = Remove
+ledger-invited = Invited
+ledger-role = Role
+ledger-role-owner = owner
+ledger-role-editor = editor
+ledger-role-viewer = viewer
+ledger-created = Created the { $ledger } ledger.
+ledger-selected = You are now working in the { $ledger } ledger.
+ledger-invitation-sent = An invitation has been sent to { $email }.
+ledger-joined = You have joined the { $ledger } ledger.
+ledger-invitation-declined = The invitation has been declined.
+ledger-left = You have left the { $ledger } ledger.
+ledger-member-removed = The member has been removed.
+
+## Reports.
+
+reports-title = Reports
+report-per = per
+report-interval-day = day
+report-interval-week = week
+report-interval-month = month
+report-show = Show
+report-spending-over-time = Spending over time
+report-spent = Spent
+report-spending-by-category = Spending by category
+report-all-categories = All categories
+report-parent-category = Back to the parent category
+report-forecast = Forecast for { $month } { $year }
+report-no-history = There is no spending history yet. The forecast becomes available after the first complete month.
+report-history = Based on the average spending of the last { $months } { $months ->
+        [one] month
+       *[other] months
+    }.
+report-monthly-average = Monthly average
+report-month-end = Month end
+report-budget = Budget
+report-next-months = Next months
+report-compare-title = Compare periods
+report-compared-to = compared to
+report-compare = Compare
+report-previous-month = Previous month
+report-previous-year = Previous year
+report-changed-most = <strong>{ $category }</strong> changed the most:
+report-down = <strong>{ $category }</strong> is down { $percentage }% compared to the previous period.
+report-up = <strong>{ $category }</strong> is up { $percentage }% compared to the previous period.
+report-change = Change
+report-no-expenses = There are no expenses in either period.
+
+## Account settings.
+
+account-title = Account settings
+account-logged-in-as = You are logged in as <strong>{ $email }</strong>.
+account-email-change-pending = A confirmation code has been sent to <strong>{ $email }</strong>. Your email address will be changed once the code has been confirmed. The code is valid until { $time }.
+account-confirmation-code = Confirmation code
+account-confirm = Confirm
+account-email-change-cancel = Cancel the change
+account-new-email = New email address
+account-change-email = Change email address
+account-change-password = Change password
+account-two-factor-enabled = Two-factor authentication is <strong>enabled</strong>. You have { $count } unused recovery codes left.
+account-two-factor-disable = Disable two-factor authentication
+account-two-factor-intro = Protect your account by requiring a code from an authenticator app when you log in.
+account-two-factor-setup = Set up two-factor authentication
+account-preferences-intro = Choose how amounts and dates are shown, and on which day your week starts.
+account-preferences-change = Change preferences
+account-sessions = Sessions
+account-sessions-intro = Review the devices on which you are logged in, and log out of the ones you don't use anymore.
+account-sessions-manage = Manage sessions
+account-api-tokens-intro = Create personal API tokens to access your data from scripts and other applications.
+account-api-tokens-manage = Manage API tokens
+account-administration = Administration
+account-administration-intro = You are an administrator. You can activate, disable and delete user accounts, and invite people to create an account.
+account-manage-users = Manage users
+account-your-data = Your data
+account-export = You can <a href="/account/export">download all your data</a> as a JSON file, including your categories and expenses.
+account-delete-intro = Deleting your account permanently removes it together with all of its data. Ledgers that are shared with other members are kept for them. Download your data first if you want to keep a copy.
+account-delete = Delete my account
+account-delete-shared-ledgers = You share these ledgers with other members. They are kept, together with the expenses and payments you recorded in them:
+account-delete-successor = { $email } becomes the owner
+account-password-incorrect = The current password is incorrect.
+account-password-required = Please enter a new password.
+account-password-changed = Your password has been changed.
+account-email-unchanged = This is already your email address.
+account-email-code-sent = A confirmation code has been sent to { $email }. Enter it below to confirm the change.
+account-code-format = Please enter a 6-digit number.
+account-email-changed = Your email address has been changed to { $email }.
+account-email-change-cancelled = The change of email address has been cancelled.
+account-two-factor-code-invalid = The code is not valid. Please try again.
+account-two-factor-enabled-alert = Two-factor authentication has been enabled.
+account-two-factor-disabled-alert = Two-factor authentication has been disabled.
+
+## Two-factor authentication setup.
+
+two-factor-setup-recovery-codes = Store these recovery codes in a safe place. Each code can be used once to log in if you lose access to your authenticator app. They will not be shown again.
+two-factor-setup-done = Done
+two-factor-setup-scan = Scan this QR code with your authenticator app.
+two-factor-setup-key = If you can't scan the code, enter this key instead:
+two-factor-setup-code = Enter the 6-digit code from the app
+two-factor-setup-submit = Enable two-factor authentication
+
+## Sessions.
+
+sessions-title = Active sessions
+sessions-intro = These are the devices on which you are currently logged in. Revoke any session you don't recognize.
+session-device = Device
+session-ip-address = IP address
+session-logged-in = Logged in
+session-last-seen = Last seen
+session-unknown-device = Unknown device
+session-unknown = Unknown
+session-this-device = This device
+sessions-revoke-all = Log out everywhere
+session-revoked = The session has been revoked.
+
+## API tokens.
+
+api-tokens-title = API tokens
+api-token-new = Your new API token
+api-token-usage = Pass the token in the <code>Authorization: Bearer</code> header of your API requests.
+api-token-access = Access
+api-token-last-used = Last used
+api-token-scope-read = read
+api-token-scope-write = write
+api-tokens-empty = You don't have any API tokens yet.
+api-token-create-title = Create a token
+api-token-name-placeholder = e.g. Phone shortcut
+api-token-read-only = Read only
+api-token-read-write = Read and write
+api-token-expiration = Expiration
+api-token-expires-days = { $days } days
+api-token-expires-year = 1 year
+api-token-create = Create token
+api-token-created = The API token { $name } has been created. Copy it now, it will not be shown again.
+api-token-days-invalid = Please enter a valid number of days.
+api-token-revoked = The API token has been revoked.
+
+## Administration.
+
+admin-users-title = Users
+admin-search-placeholder = Search users by email address
+admin-search-label = Search users
+admin-registered = Registered
+admin-status = Status
+admin-activation-code = Activation code
+admin-administrator = Administrator
+admin-active = Active
+admin-not-activated = Not activated
+admin-disabled = Disabled
+admin-code-none = None
+admin-code-expired = Expired on { $time }
+admin-code-attempts-exceeded = Too many attempts ({ $attempts })
+admin-code-valid = Valid until { $time }, { $attempts } { $attempts ->
+        [one] attempt
+       *[other] attempts
+    }
+admin-activate = Activate
+admin-enable = Enable
+admin-disable = Disable
+admin-delete = Delete
+admin-no-users = No users found.
+admin-pages = Pages
+admin-page = Page { $page } of { $pages }
+admin-previous-page = Previous
+admin-next-page = Next
+admin-account-activated = The account of { $email } has been activated.
+admin-account-disabled = The account of { $email } has been disabled.
+admin-account-enabled = The account of { $email } has been enabled.
+admin-account-deleted = The account of { $email } has been deleted.
+admin-cannot-disable-self = You cannot disable your own account.
+admin-cannot-delete-self = You cannot delete your own account here. Use the account settings instead.
+admin-invitations-title = Invitations
+admin-registration-mode = Registration is currently { $mode }. Invitation codes are only required when registration is invite-only.
+admin-registration-mode-open = open
+admin-registration-mode-closed = closed
+admin-invitations-empty = There are no pending invitations.
+admin-invitation-sent = An invitation has been sent to { $email }.
+admin-invitation-revoked = The invitation for { $email } has been revoked.
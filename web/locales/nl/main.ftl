@@ -0,0 +1,424 @@
+## Page layout.
+
+logo-alt = Firetrack-logo
+nav-home = Home
+nav-account = Account
+nav-log-out = Afmelden
+nav-sign-up = Registreren
+nav-log-in = Aanmelden
+nav-expenses = Uitgaven
+nav-balances = Saldi
+nav-goals = Doelen
+nav-budgets = Budgetten
+nav-reports = Rapporten
+nav-ledgers = Kasboeken
+
+## Common.
+
+common-name = Naam
+common-total = Totaal
+common-created = Aangemaakt
+common-expires = Verloopt
+common-never = Nooit
+common-revoke = Intrekken
+common-invite = Uitnodigen
+common-cancel = Annuleren
+common-current-password = Huidig wachtwoord
+common-from = Van
+common-to = tot
+
+## Error pages.
+
+error-not-found-title = Pagina niet gevonden
+error-not-found-message = Sorry, deze pagina bestaat niet
+error-not-found-explanation = We kunnen de pagina die je zoekt niet vinden.
+error-access-denied-title = Geen toegang
+error-access-denied-message = Meld je aan en probeer het opnieuw
+error-suggestions = Je kan proberen om <a href="/user/login">aan te melden</a> of <a href="/">naar de startpagina te gaan</a>.
+
+## User forms.
+
+user-email = E-mailadres
+user-email-invalid = Vul een geldig e-mailadres in.
+user-email-help = We delen je e-mailadres nooit met anderen.
+user-password = Wachtwoord
+user-password-invalid = Vul een wachtwoord in.
+user-invitation-code = Uitnodigingscode
+user-invitation-code-invalid = Deze uitnodigingscode is niet geldig voor dit e-mailadres, of ze is verlopen.
+user-invitation-code-help = Registreren kan enkel op uitnodiging. Gebruik het e-mailadres waar de uitnodiging naartoe is gestuurd.
+
+## Login.
+
+login-title = Aanmelden
+login-invalid = Onjuist e-mailadres of wachtwoord. Probeer het opnieuw.
+login-with-provider = Aanmelden met { $provider }
+login-forgot-password = Wachtwoord vergeten?
+login-account-activated = Je account is geactiveerd. Je kan je nu aanmelden.
+login-account-deleted = Je account en al je gegevens zijn verwijderd.
+login-account-disabled = Dit account is uitgeschakeld. Neem contact op met een beheerder.
+login-password-changed = Je wachtwoord is gewijzigd. Je kan je nu aanmelden.
+login-two-factor-failed = Te veel onjuiste authenticatiecodes. Meld je opnieuw aan.
+
+## Two-factor authentication.
+
+two-factor-title = Tweestapsverificatie
+two-factor-code = Vul je authenticatiecode in
+two-factor-code-help = Vul de 6-cijferige code uit je authenticator-app in. Als je geen toegang meer hebt tot de app kan je een van je herstelcodes invullen.
+two-factor-code-incorrect = Onjuiste authenticatiecode. Probeer het opnieuw.
+two-factor-submit = Aanmelden
+
+## Registration.
+
+register-title = Registreren
+register-closed = Registreren is gesloten. Er kunnen momenteel geen nieuwe accounts aangemaakt worden.
+
+## Account activation.
+
+activate-title = Account activeren
+activate-code = Vul je activatiecode in
+activate-code-help = Vul de activatiecode in die naar je e-mailadres is gestuurd.
+activate-code-invalid = De activatiecode is niet geldig.
+activate-code-format = Vul een getal van 6 cijfers in
+activate-code-expired = De activatiecode is verlopen. Vraag een nieuwe activatiecode aan en probeer het opnieuw.
+activate-code-incorrect = Onjuiste activatiecode. Probeer het opnieuw.
+activate-max-attempts = Je hebt het maximum aantal activatiepogingen overschreden. Probeer het later opnieuw.
+activate-submit = Activeren
+activate-resend-question = Geen code ontvangen, of is ze verlopen?
+activate-resend = Activatiecode opnieuw versturen
+activation-code-sent = Er is een nieuwe activatiecode verstuurd naar { $email }.
+activation-code-max-requests = Je hebt te veel activatiecodes aangevraagd. Wacht 30 minuten en probeer het opnieuw.
+
+## Password reset.
+
+password-reset-title = Wachtwoord herstellen
+password-reset-email-help = We sturen een code naar dit adres waarmee je een nieuw wachtwoord kan kiezen.
+password-reset-submit = Code versturen
+password-reset-confirm-title = Kies een nieuw wachtwoord
+password-reset-confirm-sent = Als er een account bestaat voor { $email }, dan hebben we er een code naartoe gestuurd om het wachtwoord te herstellen.
+password-reset-code = Vul je code in
+password-reset-code-help = Geen code ontvangen? <a href="/user/password-reset">Vraag een nieuwe aan</a>.
+password-reset-code-format = Vul een getal van 6 cijfers in.
+password-reset-code-incorrect = Onjuiste of verlopen code. Een code is 30 minuten geldig en kan maar een beperkt aantal keer geprobeerd worden.
+password-reset-new-password = Nieuw wachtwoord
+password-reset-confirm-submit = Wachtwoord wijzigen
+
+## Expenses.
+
+expenses-title = Uitgaven
+expense-add-title = Uitgave toevoegen
+expense-search-placeholder = Zoek uitgaven in { $ledger }
+expense-search-label = Uitgaven zoeken
+expense-search-empty = Er zijn geen uitgaven gevonden voor "{ $query }".
+expense-date = Datum
+expense-category = Categorie
+expense-description = Omschrijving
+expense-description-placeholder = Optioneel
+expense-amount = Bedrag
+expense-paid-by = Betaald door
+expense-split-equally = Gelijk verdelen over alle leden
+expense-form-title = Uitgave
+expense-add-submit = Toevoegen
+expense-amount-required = Vul een bedrag in.
+expense-amount-format = Het bedrag moet in de vorm '{ $example }' zijn.
+expense-amount-invalid = Vul een geldig bedrag in.
+expense-amount-too-small = Het bedrag moet minstens 0,01 zijn.
+expense-amount-too-large = Het bedrag mag maximaal 9999999,99 zijn.
+expense-category-required = Kies een categorie.
+expense-category-invalid = Ongeldige categorie.
+expense-category-unknown = Onbekende categorie.
+expense-date-required = Kies een datum.
+expense-date-format = De datum moet in de vorm { $format } zijn.
+expense-date-invalid = Vul een geldige datum in.
+expense-added = Uitgave van { $amount } toegevoegd aan de categorie { $category }.
+expense-added-shared = Uitgave van { $amount } toegevoegd aan de categorie { $category }. Ze wordt gedeeld door { $members } leden.
+
+## Preferences.
+
+preferences-title = Voorkeuren
+preferences-language = Taal
+preferences-language-browser = Zelfde als de browser
+preferences-locale = Getalnotatie
+preferences-currency = Munteenheid
+preferences-date-format = Datumnotatie
+preferences-first-day-of-week = Eerste dag van de week
+preferences-first-day-of-week-help = Wordt gebruikt in rapporten over je uitgaven per week.
+preferences-submit = Voorkeuren opslaan
+preferences-saved = Je voorkeuren zijn opgeslagen.
+day-monday = Maandag
+day-saturday = Zaterdag
+day-sunday = Zondag
+month-1 = januari
+month-2 = februari
+month-3 = maart
+month-4 = april
+month-5 = mei
+month-6 = juni
+month-7 = juli
+month-8 = augustus
+month-9 = september
+month-10 = oktober
+month-11 = november
+month-12 = december
+
+## Dashboard.
+
+dashboard-title = Dashboard
+dashboard-spent-in = Uitgegeven in { $month }
+dashboard-expense-count = { $count ->
+        [one] { $count } uitgave
+       *[other] { $count } uitgaven
+    } in { $ledger }
+dashboard-forecast = Verwacht tegen het einde van de maand
+dashboard-above-average = Boven het maandgemiddelde van { $average }
+dashboard-within-average = Binnen het maandgemiddelde van { $average }
+dashboard-no-history = Nog geen uitgavengeschiedenis
+dashboard-view-budgets = Budgetten bekijken
+dashboard-top-categories = Topcategorieën
+dashboard-nothing-spent = Er is deze maand nog niets uitgegeven.
+dashboard-view-reports = Rapporten bekijken
+dashboard-view-goals = Doelen bekijken
+dashboard-latest-expenses = Laatste uitgaven
+dashboard-no-expenses = Er zijn nog geen uitgaven toegevoegd.
+dashboard-view-all-expenses = Alle uitgaven bekijken
+
+## Balances.
+
+balances-title = Saldi
+balance-debts = Wie is wie iets verschuldigd
+balance-debt = { $debtor } is { $creditor } { $amount } verschuldigd
+balance-settle = Vereffenen
+balance-settled = Alle saldi zijn vereffend.
+balance-payments = Betalingen
+balance-payment = { $payer } betaalde { $payee }
+balance-payer = Betaler
+balance-paid = betaalde
+balance-payee = Ontvanger
+balance-deleted-user = Een verwijderde gebruiker
+balance-record-payment = Betaling registreren
+
+## Savings goals.
+
+goals-title = Spaardoelen
+goal-delete = Doel verwijderen
+goal-progress = { $saved } van { $target } tegen { $date }
+goal-reached = Het doel is bereikt.
+goal-monthly-saving = Spaar { $amount } per maand { $months ->
+        [one] tijdens de volgende maand
+       *[other] tijdens de volgende { $months } maanden
+    } om dit doel te bereiken.
+goal-target-date-passed = De streefdatum is verstreken, er ontbreekt nog { $amount }.
+goal-contribute = Bijdragen
+goal-new = Nieuw doel
+goal-name-placeholder = Noodfonds
+goal-target-amount = Streefbedrag
+goal-target-date = Streefdatum
+goal-create = Doel aanmaken
+
+## Budgets.
+
+budgets-title = Budgetten
+budget-none = Er zijn nog geen budgetten ingesteld. Een budget waarschuwt je wanneer een categorie naar verwachting meer kost dan gepland.
+budget-set = Budget instellen
+budget-remove = Budget verwijderen
+budget-saved = Het budget van { $category } is ingesteld op { $amount }.
+budget-removed = Het budget van { $category } is verwijderd.
+budget-over = <strong>{ $category }</strong> kost deze maand naar verwachting { $amount }, meer dan het budget van { $budget }.
+budget-recurring-expenses = Terugkerende uitgaven
+budget-recurring-none = Er zijn nog geen terugkerende uitgaven toegevoegd. Terugkerende uitgaven zoals de huur worden meegenomen in de prognose.
+budget-day-of-month = Dag van de maand
+budget-recurring-add = Terugkerende uitgave toevoegen
+budget-recurring-added = Terugkerende uitgave van { $amount } toegevoegd aan { $category }.
+budget-recurring-delete = Terugkerende uitgave verwijderen
+budget-recurring-deleted = De terugkerende uitgave is verwijderd.
+
+## Ledgers.
+
+ledgers-title = Kasboeken
+ledger-invitations = Uitnodigingen
+ledger-invited-to-join = Je bent uitgenodigd voor het kasboek <strong>{ $ledger }</strong> als { $role }.
+ledger-accept = Aanvaarden
+ledger-decline = Weigeren
+ledger-yours = Jouw kasboeken
+ledger-current = Huidig
+ledger-switch = Wisselen
+ledger-name = Naam van het kasboek
+ledger-create = Kasboek aanmaken
+ledger-members = Leden van { $ledger }
+ledger-leave = Verlaten
+ledger-remove = Verwijderen
+ledger-invited = Uitgenodigd
+ledger-role = Rol
+ledger-role-owner = eigenaar
+ledger-role-editor = bewerker
+ledger-role-viewer = lezer
+ledger-created = Het kasboek { $ledger } is aangemaakt.
+ledger-selected = Je werkt nu in het kasboek { $ledger }.
+ledger-invitation-sent = Er is een uitnodiging verstuurd naar { $email }.
+ledger-joined = Je bent lid geworden van het kasboek { $ledger }.
+ledger-invitation-declined = De uitnodiging is geweigerd.
+ledger-left = Je hebt het kasboek { $ledger } verlaten.
+ledger-member-removed = Het lid is verwijderd.
+
+## Reports.
+
+reports-title = Rapporten
+report-per = per
+report-interval-day = dag
+report-interval-week = week
+report-interval-month = maand
+report-show = Tonen
+report-spending-over-time = Uitgaven doorheen de tijd
+report-spent = Uitgegeven
+report-spending-by-category = Uitgaven per categorie
+report-all-categories = Alle categorieën
+report-parent-category = Terug naar de bovenliggende categorie
+report-forecast = Prognose voor { $month } { $year }
+report-no-history = Er is nog geen uitgavengeschiedenis. De prognose is beschikbaar na de eerste volledige maand.
+report-history = Gebaseerd op de gemiddelde uitgaven van { $months ->
+        [one] de voorbije maand
+       *[other] de voorbije { $months } maanden
+    }.
+report-monthly-average = Maandgemiddelde
+report-month-end = Einde van de maand
+report-budget = Budget
+report-next-months = Volgende maanden
+report-compare-title = Periodes vergelijken
+report-compared-to = vergeleken met
+report-compare = Vergelijken
+report-previous-month = Vorige maand
+report-previous-year = Vorig jaar
+report-changed-most = <strong>{ $category }</strong> veranderde het meest:
+report-down = <strong>{ $category }</strong> is { $percentage }% gedaald ten opzichte van de vorige periode.
+report-up = <strong>{ $category }</strong> is { $percentage }% gestegen ten opzichte van de vorige periode.
+report-change = Verschil
+report-no-expenses = Er zijn in geen van beide periodes uitgaven.
+
+## Account settings.
+
+account-title = Accountinstellingen
+account-logged-in-as = Je bent aangemeld als <strong>{ $email }</strong>.
+account-email-change-pending = Er is een bevestigingscode verstuurd naar <strong>{ $email }</strong>. Je e-mailadres wordt gewijzigd zodra de code bevestigd is. De code is geldig tot { $time }.
+account-confirmation-code = Bevestigingscode
+account-confirm = Bevestigen
+account-email-change-cancel = De wijziging annuleren
+account-new-email = Nieuw e-mailadres
+account-change-email = E-mailadres wijzigen
+account-change-password = Wachtwoord wijzigen
+account-two-factor-enabled = Tweestapsverificatie is <strong>ingeschakeld</strong>. Je hebt nog { $count } ongebruikte herstelcodes.
+account-two-factor-disable = Tweestapsverificatie uitschakelen
+account-two-factor-intro = Bescherm je account door bij het aanmelden een code uit een authenticator-app te vragen.
+account-two-factor-setup = Tweestapsverificatie instellen
+account-preferences-intro = Kies hoe bedragen en datums getoond worden, en op welke dag je week begint.
+account-preferences-change = Voorkeuren wijzigen
+account-sessions = Sessies
+account-sessions-intro = Bekijk de toestellen waarop je aangemeld bent, en meld je af op de toestellen die je niet meer gebruikt.
+account-sessions-manage = Sessies beheren
+account-api-tokens-intro = Maak persoonlijke API-tokens aan om je gegevens te gebruiken in scripts en andere toepassingen.
+account-api-tokens-manage = API-tokens beheren
+account-administration = Beheer
+account-administration-intro = Je bent een beheerder. Je kan gebruikersaccounts activeren, uitschakelen en verwijderen, en mensen uitnodigen om een account aan te maken.
+account-manage-users = Gebruikers beheren
+account-your-data = Je gegevens
+account-export = Je kan <a href="/account/export">al je gegevens downloaden</a> als JSON-bestand, met inbegrip van je categorieën en uitgaven.
+account-delete-intro = Als je je account verwijdert, worden het account en al zijn gegevens definitief verwijderd. Kasboeken die gedeeld zijn met andere leden blijven voor hen bewaard. Download eerst je gegevens als je een kopie wil bijhouden.
+account-delete = Mijn account verwijderen
+account-delete-shared-ledgers = Je deelt deze kasboeken met andere leden. Ze blijven bewaard, samen met de uitgaven en betalingen die je erin hebt geregistreerd:
+account-delete-successor = { $email } wordt de eigenaar
+account-password-incorrect = Het huidige wachtwoord is onjuist.
+account-password-required = Vul een nieuw wachtwoord in.
+account-password-changed = Je wachtwoord is gewijzigd.
+account-email-unchanged = Dit is al je e-mailadres.
+account-email-code-sent = Er is een bevestigingscode verstuurd naar { $email }. Vul ze hieronder in om de wijziging te bevestigen.
+account-code-format = Vul een getal van 6 cijfers in.
+account-email-changed = Je e-mailadres is gewijzigd naar { $email }.
+account-email-change-cancelled = De wijziging van je e-mailadres is geannuleerd.
+account-two-factor-code-invalid = De code is niet geldig. Probeer het opnieuw.
+account-two-factor-enabled-alert = Tweestapsverificatie is ingeschakeld.
+account-two-factor-disabled-alert = Tweestapsverificatie is uitgeschakeld.
+
+## Two-factor authentication setup.
+
+two-factor-setup-recovery-codes = Bewaar deze herstelcodes op een veilige plaats. Elke code kan één keer gebruikt worden om aan te melden als je geen toegang meer hebt tot je authenticator-app. Ze worden niet opnieuw getoond.
+two-factor-setup-done = Klaar
+two-factor-setup-scan = Scan deze QR-code met je authenticator-app.
+two-factor-setup-key = Als je de code niet kan scannen, vul dan deze sleutel in:
+two-factor-setup-code = Vul de 6-cijferige code uit de app in
+two-factor-setup-submit = Tweestapsverificatie inschakelen
+
+## Sessions.
+
+sessions-title = Actieve sessies
+sessions-intro = Dit zijn de toestellen waarop je momenteel aangemeld bent. Trek elke sessie in die je niet herkent.
+session-device = Toestel
+session-ip-address = IP-adres
+session-logged-in = Aangemeld
+session-last-seen = Laatst gezien
+session-unknown-device = Onbekend toestel
+session-unknown = Onbekend
+session-this-device = Dit toestel
+sessions-revoke-all = Overal afmelden
+session-revoked = De sessie is ingetrokken.
+
+## API tokens.
+
+api-tokens-title = API-tokens
+api-token-new = Je nieuwe API-token
+api-token-usage = Geef de token mee in de <code>Authorization: Bearer</code>-header van je API-verzoeken.
+api-token-access = Toegang
+api-token-last-used = Laatst gebruikt
+api-token-scope-read = lezen
+api-token-scope-write = schrijven
+api-tokens-empty = Je hebt nog geen API-tokens.
+api-token-create-title = Een token aanmaken
+api-token-name-placeholder = bv. Snelkoppeling op gsm
+api-token-read-only = Enkel lezen
+api-token-read-write = Lezen en schrijven
+api-token-expiration = Vervaldatum
+api-token-expires-days = { $days } dagen
+api-token-expires-year = 1 jaar
+api-token-create = Token aanmaken
+api-token-created = De API-token { $name } is aangemaakt. Kopieer hem nu, hij wordt niet opnieuw getoond.
+api-token-days-invalid = Vul een geldig aantal dagen in.
+api-token-revoked = De API-token is ingetrokken.
+
+## Administration.
+
+admin-users-title = Gebruikers
+admin-search-placeholder = Zoek gebruikers op e-mailadres
+admin-search-label = Gebruikers zoeken
+admin-registered = Geregistreerd
+admin-status = Status
+admin-activation-code = Activatiecode
+admin-administrator = Beheerder
+admin-active = Actief
+admin-not-activated = Niet geactiveerd
+admin-disabled = Uitgeschakeld
+admin-code-none = Geen
+admin-code-expired = Verlopen op { $time }
+admin-code-attempts-exceeded = Te veel pogingen ({ $attempts })
+admin-code-valid = Geldig tot { $time }, { $attempts } { $attempts ->
+        [one] poging
+       *[other] pogingen
+    }
+admin-activate = Activeren
+admin-enable = Inschakelen
+admin-disable = Uitschakelen
+admin-delete = Verwijderen
+admin-no-users = Geen gebruikers gevonden.
+admin-pages = Pagina's
+admin-page = Pagina { $page } van { $pages }
+admin-previous-page = Vorige
+admin-next-page = Volgende
+admin-account-activated = Het account van { $email } is geactiveerd.
+admin-account-disabled = Het account van { $email } is uitgeschakeld.
+admin-account-enabled = Het account van { $email } is ingeschakeld.
+admin-account-deleted = Het account van { $email } is verwijderd.
+admin-cannot-disable-self = Je kan je eigen account niet uitschakelen.
+admin-cannot-delete-self = Je kan je eigen account hier niet verwijderen. Gebruik daarvoor de accountinstellingen.
+admin-invitations-title = Uitnodigingen
+admin-registration-mode = Registreren is momenteel { $mode }. Uitnodigingscodes zijn enkel nodig als registreren alleen op uitnodiging kan.
+admin-registration-mode-open = open
+admin-registration-mode-closed = gesloten
+admin-invitations-empty = Er zijn geen openstaande uitnodigingen.
+admin-invitation-sent = Er is een uitnodiging verstuurd naar { $email }.
+admin-invitation-revoked = De uitnodiging voor { $email } is ingetrokken.
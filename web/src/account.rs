@@ -0,0 +1,734 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use crate::identity::get_current_session_id;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use app::AppConfig;
+use db::api_token::Scope;
+use db::ledger::Ledger;
+use db::two_factor::{TotpSecret, TwoFactorErrorKind};
+use db::user::User;
+use db::user_preference::{
+    Currency, DateFormat, FirstDayOfWeek, Language, Locale, UserPreferences,
+};
+use rust_decimal::Decimal;
+
+// The POST data of the form to change the password.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordForm {
+    current_password: String,
+    password: String,
+}
+
+// The POST data of the form to change the email address.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EmailForm {
+    email: String,
+    current_password: String,
+}
+
+// The POST data of the form to confirm a change of email address.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EmailConfirmForm {
+    code: String,
+}
+
+// The POST data of the form to confirm the two-factor authentication setup.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TwoFactorConfirmForm {
+    code: String,
+}
+
+// The POST data of the form to disable two-factor authentication.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TwoFactorDisableForm {
+    current_password: String,
+}
+
+// The POST data of the form to create an API token. The expiration is given in days, an empty
+// value means the token does not expire.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ApiTokenForm {
+    name: String,
+    scope: String,
+    expires_in: String,
+}
+
+// The POST data of the form to revoke an API token.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ApiTokenRevokeForm {
+    id: i32,
+}
+
+// The POST data of the form to delete the account.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeleteForm {
+    current_password: String,
+}
+
+// The POST data of the form to change the preferences of the user.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PreferencesForm {
+    locale: String,
+    currency: String,
+    date_format: String,
+    first_day_of_week: String,
+    // The language of the interface. Empty to use the language requested by the browser.
+    #[serde(default)]
+    language: String,
+}
+
+#[cfg(test)]
+impl PreferencesForm {
+    pub fn new(
+        locale: &str,
+        currency: &str,
+        date_format: &str,
+        first_day_of_week: &str,
+    ) -> PreferencesForm {
+        PreferencesForm {
+            locale: locale.to_string(),
+            currency: currency.to_string(),
+            date_format: date_format.to_string(),
+            first_day_of_week: first_day_of_week.to_string(),
+            language: "".to_string(),
+        }
+    }
+
+    pub fn with_language(mut self, language: &str) -> PreferencesForm {
+        self.language = language.to_string();
+        self
+    }
+}
+
+// An option in one of the dropdowns of the preferences form, as a value and a label.
+#[derive(Serialize)]
+struct PreferenceOption {
+    value: &'static str,
+    label: String,
+}
+
+// A ledger that is shared with other members, as listed on the form to delete the account. The
+// successor is the email address of the member that becomes the owner, if the user is the last
+// owner.
+#[derive(Serialize)]
+struct SharedLedger {
+    ledger: Ledger,
+    successor: Option<String>,
+}
+
+// The POST data of the form to revoke a session.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SessionRevokeForm {
+    id: i32,
+}
+
+// Request handler for the account settings page.
+pub async fn overview_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_overview(id, pool, template, vec![])
+}
+
+// Request handler for downloading all data that is stored about the user as a JSON file.
+pub async fn export_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let export = db::export::get(&connection, &user).map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .header(
+            "content-disposition",
+            format!(
+                "attachment; filename=\"{}-export.json\"",
+                app::APPLICATION_NAME
+            ),
+        )
+        .json(export))
+}
+
+// Submit handler for the form to delete the account. The current password is required. The
+// account and all of its data are deleted and a confirmation is sent by email. Shared ledgers are
+// kept for the other members.
+pub async fn delete_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<DeleteForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    if db::user::verify_password(&connection, &user.email, &input.current_password, &config)
+        .is_err()
+    {
+        // Release the connection, rendering the overview acquires its own.
+        drop(connection);
+        let alert = Alert::danger(template.translate("account-password-incorrect"));
+        return render_overview(id, pool, template, vec![alert]);
+    }
+
+    db::user::delete(&connection, &user.email).map_err(error::ErrorInternalServerError)?;
+
+    // The account is already gone at this point, so a failure to deliver the confirmation should
+    // not be reported to the user. Delivery errors are logged.
+    notifications::account_deletion(&user, &config).await.ok();
+
+    // Set a flag on the session and redirect to the login page using a HTTP 303 redirect which will
+    // issue a GET request.
+    id.forget();
+    session
+        .set("account_deleted", true)
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .header("location", "/user/login")
+        .finish())
+}
+
+// Submit handler for the form to change the password. The current password is required. All other
+// sessions of the user are ended, so that anyone who knew the old password is logged out.
+pub async fn password_submit(
+    req: HttpRequest,
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<PasswordForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = if input.password.is_empty() {
+        Alert::danger(template.translate("account-password-required"))
+    } else {
+        match db::user::verify_password(&connection, &user.email, &input.current_password, &config)
+        {
+            Err(_) => Alert::danger(template.translate("account-password-incorrect")),
+            Ok(user) => match db::user::set_password(&connection, user, &input.password, &config) {
+                Ok(user) => {
+                    db::user_session::delete_all(&connection, &user, get_current_session_id(&req))
+                        .map_err(error::ErrorInternalServerError)?;
+                    Alert::success(template.translate("account-password-changed"))
+                }
+                Err(e) => Alert::danger(e),
+            },
+        }
+    };
+
+    render_overview(id, pool, template, vec![alert])
+}
+
+// Submit handler for the form to change the email address. A confirmation code is sent to the new
+// address, the email address is only changed after the code has been confirmed.
+pub async fn email_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<EmailForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let email = input.email.trim();
+
+    let alert =
+        if db::user::verify_password(&connection, &user.email, &input.current_password, &config)
+            .is_err()
+        {
+            Alert::danger(template.translate("account-password-incorrect"))
+        } else if email == user.email {
+            Alert::danger(template.translate("account-email-unchanged"))
+        } else {
+            match db::email_change_code::get(&connection, &user, email) {
+                Ok(email_change_code) => {
+                    // If the address belongs to another account, don't send the code. The change
+                    // can't be confirmed, but we don't disclose that the address is registered.
+                    if db::user::read(&connection, email).is_err() {
+                        notifications::email_change(&user, &email_change_code, &config)
+                            .await
+                            .map_err(error::ErrorInternalServerError)?;
+                    }
+                    Alert::success(template.translate_with(
+                        "account-email-code-sent",
+                        &[("email", email_change_code.email)],
+                    ))
+                }
+                Err(e) => Alert::danger(e),
+            }
+        };
+
+    render_overview(id, pool, template, vec![alert])
+}
+
+// Submit handler for the form to confirm a change of email address.
+pub async fn email_confirm_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<EmailConfirmForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match input.code.trim().parse::<i32>() {
+        Err(_) => Alert::danger(template.translate("account-code-format")),
+        Ok(code) => match db::email_change_code::change_email(&connection, user, code) {
+            Ok(user) => {
+                // The session is tied to the email address, update it.
+                id.remember(user.email.clone());
+                Alert::success(
+                    template.translate_with("account-email-changed", &[("email", user.email)]),
+                )
+            }
+            Err(e) => Alert::danger(e),
+        },
+    };
+
+    render_overview(id, pool, template, vec![alert])
+}
+
+// Submit handler for cancelling a pending change of email address.
+pub async fn email_cancel_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::email_change_code::delete(&connection, &user) {
+        Ok(_) => Alert::success(template.translate("account-email-change-cancelled")),
+        Err(e) => Alert::danger(e),
+    };
+
+    render_overview(id, pool, template, vec![alert])
+}
+
+// Submit handler for starting the two-factor authentication setup. Generates a new secret and
+// shows it as a QR code which can be scanned with an authenticator app.
+pub async fn two_factor_setup_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    match db::two_factor::start_enrollment(&connection, &user) {
+        Ok(totp_secret) => render_two_factor_setup(id, template, &user, &totp_secret, vec![]),
+        Err(e) => render_overview(id, pool, template, vec![Alert::danger(e)]),
+    }
+}
+
+// Submit handler for confirming the two-factor authentication setup. If the code is valid the
+// recovery codes are shown. They are stored as hashes so this is the only time they are visible.
+pub async fn two_factor_confirm_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<TwoFactorConfirmForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    match db::two_factor::confirm_enrollment(&connection, &user, &input.code, &config) {
+        Ok(recovery_codes) => {
+            let mut context = get_tera_context(template.translate("two-factor-title").as_str(), id);
+            context.insert("recovery_codes", &recovery_codes);
+            context.insert(
+                "alerts",
+                &vec![Alert::success(
+                    template.translate("account-two-factor-enabled-alert"),
+                )],
+            );
+            render(template, "account/two_factor.html", &context)
+        }
+        // Show the QR code again so the user can retry.
+        Err(TwoFactorErrorKind::InvalidCode) => {
+            let totp_secret = db::two_factor::read(&connection, user.id)
+                .map_err(error::ErrorInternalServerError)?
+                .ok_or_else(|| error::ErrorInternalServerError("Missing two-factor secret"))?;
+            let alert = Alert::danger(template.translate("account-two-factor-code-invalid"));
+            render_two_factor_setup(id, template, &user, &totp_secret, vec![alert])
+        }
+        Err(e) => render_overview(id, pool, template, vec![Alert::danger(e)]),
+    }
+}
+
+// Submit handler for disabling two-factor authentication. The current password is required.
+pub async fn two_factor_disable_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<TwoFactorDisableForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert =
+        if db::user::verify_password(&connection, &user.email, &input.current_password, &config)
+            .is_err()
+        {
+            Alert::danger(template.translate("account-password-incorrect"))
+        } else {
+            match db::two_factor::disable(&connection, &user) {
+                Ok(_) => Alert::success(template.translate("account-two-factor-disabled-alert")),
+                Err(e) => Alert::danger(e),
+            }
+        };
+
+    render_overview(id, pool, template, vec![alert])
+}
+
+// Request handler for the page that lists the active sessions of the user.
+pub async fn sessions_handler(
+    req: HttpRequest,
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_sessions(req, id, pool, template, vec![])
+}
+
+// Submit handler for the form to revoke one of the sessions of the user.
+pub async fn session_revoke_submit(
+    req: HttpRequest,
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<SessionRevokeForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    // Revoking the current session is the same as logging out.
+    if get_current_session_id(&req) == Some(input.id) {
+        id.forget();
+        return Ok(HttpResponse::SeeOther()
+            .header("location", "/user/login")
+            .finish());
+    }
+
+    let alert = match db::user_session::delete(&connection, &user, input.id) {
+        Ok(_) => Alert::success(template.translate("session-revoked")),
+        Err(e) => Alert::danger(e),
+    };
+
+    render_sessions(req, id, pool, template, vec![alert])
+}
+
+// Submit handler for logging out everywhere. Ends all sessions of the user, including the current
+// one.
+pub async fn session_revoke_all_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    db::user_session::delete_all(&connection, &user, None)
+        .map_err(error::ErrorInternalServerError)?;
+    id.forget();
+
+    Ok(HttpResponse::SeeOther()
+        .header("location", "/user/login")
+        .finish())
+}
+
+// Request handler for the page that lists the API tokens of the user.
+pub async fn api_tokens_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_api_tokens(id, pool, template, None, vec![])
+}
+
+// Submit handler for the form to create an API token. The token is shown once, only its hash is
+// stored.
+pub async fn api_token_add_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<ApiTokenForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let scope = input.scope.parse::<Scope>();
+    let expires_in = match input.expires_in.trim() {
+        "" => Ok(None),
+        days => days.parse::<u16>().map(Some),
+    };
+    let result = match (scope, expires_in) {
+        (Ok(scope), Ok(expires_in)) => {
+            let expiration_time = expires_in.map(|days| {
+                chrono::Local::now().naive_local() + chrono::Duration::days(days.into())
+            });
+            db::api_token::create(&connection, &user, &input.name, scope, expiration_time)
+                .map_err(Alert::danger)
+        }
+        (Err(e), _) => Err(Alert::danger(e)),
+        (_, Err(_)) => Err(Alert::danger(template.translate("api-token-days-invalid"))),
+    };
+    drop(connection);
+
+    match result {
+        Ok((api_token, token)) => {
+            let alert = Alert::success(
+                template.translate_with("api-token-created", &[("name", api_token.name)]),
+            );
+            render_api_tokens(id, pool, template, Some(token), vec![alert])
+        }
+        Err(alert) => render_api_tokens(id, pool, template, None, vec![alert]),
+    }
+}
+
+// Submit handler for the form to revoke an API token.
+pub async fn api_token_revoke_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<ApiTokenRevokeForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::api_token::delete(&connection, &user, input.id) {
+        Ok(_) => Alert::success(template.translate("api-token-revoked")),
+        Err(e) => Alert::danger(e),
+    };
+    drop(connection);
+
+    render_api_tokens(id, pool, template, None, vec![alert])
+}
+
+// Request handler for the page where the user can change their preferences.
+pub async fn preferences_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_preferences(id, pool, template, vec![])
+}
+
+// Submit handler for the form to change the preferences of the user. The page is shown in the newly
+// chosen language.
+pub async fn preferences_submit(
+    req: HttpRequest,
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    input: web::Form<PreferencesForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let language = match input.language.as_str() {
+        "" => Ok(None),
+        language => language.parse::<Language>().map(Some),
+    };
+
+    let preferences = input
+        .locale
+        .parse::<Locale>()
+        .and_then(|locale| {
+            Ok(UserPreferences {
+                user_id: user.id,
+                locale,
+                currency: input.currency.parse::<Currency>()?,
+                date_format: input.date_format.parse::<DateFormat>()?,
+                first_day_of_week: input.first_day_of_week.parse::<FirstDayOfWeek>()?,
+                language: language?,
+            })
+        })
+        .and_then(|preferences| db::user_preference::update(&connection, &preferences));
+    drop(connection);
+
+    let template = Templates::for_request(&req)?;
+    let alert = match preferences {
+        Ok(_) => Alert::success(template.translate("preferences-saved")),
+        Err(e) => Alert::danger(e),
+    };
+
+    render_preferences(id, pool, template, vec![alert])
+}
+
+// Renders the page that shows the TOTP secret as a QR code, with a form to confirm the setup.
+fn render_two_factor_setup(
+    id: Identity,
+    template: Templates,
+    user: &User,
+    totp_secret: &TotpSecret,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let uri = db::two_factor::get_otpauth_uri(&user.email, &totp_secret.secret);
+    let qr_code = qrcode::QrCode::new(uri.as_bytes())
+        .map_err(error::ErrorInternalServerError)?
+        .render::<qrcode::render::svg::Color>()
+        .min_dimensions(200, 200)
+        .build();
+
+    let mut context = get_tera_context(template.translate("two-factor-title").as_str(), id);
+    context.insert("qr_code", &qr_code);
+    context.insert("secret", &totp_secret.secret);
+    context.insert("alerts", &alerts);
+    render(template, "account/two_factor.html", &context)
+}
+
+// Renders the account settings page. Used by both GET and POST requests.
+fn render_overview(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let pending_email_change = db::email_change_code::get_pending(&connection, &user);
+
+    // The shared ledgers are kept when the account is deleted, list them on the form so the user
+    // knows who takes over the ledgers they own.
+    let last_owner_ledgers = db::ledger::get_last_owner_ledgers(&connection, user.id)
+        .map_err(error::ErrorInternalServerError)?;
+    let shared_ledgers = db::ledger::get_shared_ledgers(&connection, user.id)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|ledger| {
+            let successor = if last_owner_ledgers.contains(&ledger) {
+                db::ledger::get_successor(&connection, &ledger, &user)
+                    .map_err(error::ErrorInternalServerError)?
+                    .map(|successor| successor.email)
+            } else {
+                None
+            };
+            Ok(SharedLedger { ledger, successor })
+        })
+        .collect::<Result<Vec<SharedLedger>, Error>>()?;
+
+    let mut context = get_tera_context(template.translate("account-title").as_str(), id);
+    context.insert("email", &user.email);
+    context.insert("admin", &user.admin);
+    context.insert("pending_email_change", &pending_email_change);
+    context.insert("shared_ledgers", &shared_ledgers);
+    context.insert(
+        "two_factor_enabled",
+        &db::two_factor::is_enabled(&connection, &user).map_err(error::ErrorInternalServerError)?,
+    );
+    context.insert(
+        "recovery_codes_left",
+        &db::two_factor::count_recovery_codes(&connection, &user)
+            .map_err(error::ErrorInternalServerError)?,
+    );
+    context.insert("alerts", &alerts);
+
+    render(template, "account/overview.html", &context)
+}
+
+// Renders the page that lists the active sessions of the user.
+fn render_sessions(
+    req: HttpRequest,
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let user_sessions =
+        db::user_session::list(&connection, &user).map_err(error::ErrorInternalServerError)?;
+
+    let mut context = get_tera_context(template.translate("sessions-title").as_str(), id);
+    context.insert("user_sessions", &user_sessions);
+    context.insert("current_session_id", &get_current_session_id(&req));
+    context.insert("alerts", &alerts);
+
+    render(template, "account/sessions.html", &context)
+}
+
+// Renders the page that lists the API tokens of the user. A newly created token is shown once.
+fn render_api_tokens(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    new_token: Option<String>,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let api_tokens =
+        db::api_token::list(&connection, &user).map_err(error::ErrorInternalServerError)?;
+
+    let mut context = get_tera_context(template.translate("api-tokens-title").as_str(), id);
+    context.insert("api_tokens", &api_tokens);
+    context.insert("new_token", &new_token);
+    context.insert("alerts", &alerts);
+
+    render(template, "account/api_tokens.html", &context)
+}
+
+// Renders the page where the user can change their preferences. The options are shown with an
+// example so the user can see the effect of their choice.
+fn render_preferences(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let example_amount = Decimal::new(123_456, 2);
+    let example_date = chrono::NaiveDate::from_ymd(2021, 12, 31);
+    let locales: Vec<PreferenceOption> = Locale::ALL
+        .iter()
+        .map(|locale| PreferenceOption {
+            value: locale.as_str(),
+            label: format!(
+                "{} ({})",
+                locale.name(),
+                locale.format_number(example_amount)
+            ),
+        })
+        .collect();
+    let currencies: Vec<PreferenceOption> = Currency::ALL
+        .iter()
+        .map(|currency| PreferenceOption {
+            value: currency.as_str(),
+            label: format!("{} ({})", currency.as_str(), currency.symbol()),
+        })
+        .collect();
+    let date_formats: Vec<PreferenceOption> = DateFormat::ALL
+        .iter()
+        .map(|date_format| PreferenceOption {
+            value: date_format.as_str(),
+            label: example_date.format(date_format.pattern()).to_string(),
+        })
+        .collect();
+    let first_days_of_week: Vec<PreferenceOption> = FirstDayOfWeek::ALL
+        .iter()
+        .map(|day| PreferenceOption {
+            value: day.as_str(),
+            label: template.translate(format!("day-{}", day.as_str()).as_str()),
+        })
+        .collect();
+    let languages: Vec<PreferenceOption> = Language::ALL
+        .iter()
+        .map(|language| PreferenceOption {
+            value: language.as_str(),
+            label: language.name().to_string(),
+        })
+        .collect();
+
+    let mut context = get_tera_context(&template.translate("preferences-title"), id);
+    context.insert("preferences", &preferences);
+    context.insert("languages", &languages);
+    context.insert("locales", &locales);
+    context.insert("currencies", &currencies);
+    context.insert("date_formats", &date_formats);
+    context.insert("first_days_of_week", &first_days_of_week);
+    context.insert("alerts", &alerts);
+
+    render(template, "account/preferences.html", &context)
+}
+
+// Renders the given template.
+fn render(template: Templates, name: &str, context: &tera::Context) -> Result<HttpResponse, Error> {
+    let content = template
+        .render(name, context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
@@ -0,0 +1,365 @@
+use super::{assert_admin, get_connection_and_user, get_tera_context};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use actix_identity::Identity;
+use actix_web::{error, web, Error, HttpResponse};
+use app::AppConfig;
+use db::activation_code::ActivationCode;
+use db::user::User;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::PgConnection;
+use std::collections::HashMap;
+
+// The number of user accounts that are shown per page in the user administration.
+const USERS_PER_PAGE: i64 = 50;
+
+// The query parameters of the user administration page.
+#[derive(Deserialize)]
+pub struct UsersQuery {
+    q: Option<String>,
+    page: Option<i64>,
+}
+
+// The POST data of the forms that perform an action on a user account.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UserActionForm {
+    email: String,
+}
+
+impl UserActionForm {
+    #[cfg(test)]
+    pub fn new(email: String) -> UserActionForm {
+        UserActionForm { email }
+    }
+}
+
+// The POST data of the forms that invite people to register, or revoke their invitation.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct InvitationForm {
+    email: String,
+}
+
+impl InvitationForm {
+    #[cfg(test)]
+    pub fn new(email: String) -> InvitationForm {
+        InvitationForm { email }
+    }
+}
+
+// A pending registration invitation as it is shown in the administration. The code itself is only
+// sent to the invitee.
+#[derive(Serialize)]
+struct InvitationSummary {
+    email: String,
+    expiration_time: chrono::NaiveDateTime,
+}
+
+// A user account as it is shown in the user administration.
+#[derive(Serialize)]
+struct UserSummary {
+    email: String,
+    created: chrono::NaiveDateTime,
+    activated: bool,
+    admin: bool,
+    disabled: bool,
+    activation_code: Option<ActivationCodeSummary>,
+}
+
+// The state of the activation code of a user account that has not been activated yet. The code
+// itself is not shown.
+#[derive(Serialize)]
+struct ActivationCodeSummary {
+    expiration_time: chrono::NaiveDateTime,
+    attempts: i16,
+    expired: bool,
+    attempts_exceeded: bool,
+}
+
+impl From<ActivationCode> for ActivationCodeSummary {
+    fn from(activation_code: ActivationCode) -> Self {
+        ActivationCodeSummary {
+            expiration_time: activation_code.expiration_time,
+            attempts: activation_code.attempts,
+            expired: activation_code.is_expired(),
+            attempts_exceeded: activation_code.attempts_exceeded(),
+        }
+    }
+}
+
+// Request handler for the page that lists the user accounts. The accounts can be searched by email
+// address.
+pub async fn users_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    query: web::Query<UsersQuery>,
+) -> Result<HttpResponse, Error> {
+    render_users(id, pool, template, query.q.as_deref(), query.page, vec![])
+}
+
+// Submit handler for activating a user account. Any pending activation code is discarded.
+pub async fn activate_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<UserActionForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, _) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = match db::user::read(&connection, &input.email)
+        .and_then(|user| db::user::activate(&connection, user))
+    {
+        Ok(user) => {
+            db::activation_code::delete(&connection, &user)
+                .map_err(error::ErrorInternalServerError)?;
+            Alert::success(
+                template.translate_with("admin-account-activated", &[("email", user.email)]),
+            )
+        }
+        Err(e) => Alert::danger(e),
+    };
+    drop(connection);
+
+    render_users(id, pool, template, None, None, vec![alert])
+}
+
+// Submit handler for disabling a user account. All sessions of the user are ended, and the user can
+// no longer log in or use their API tokens until the account is enabled again.
+pub async fn disable_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<UserActionForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, admin) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = if input.email == admin.email {
+        Alert::danger(template.translate("admin-cannot-disable-self"))
+    } else {
+        match db::user::read(&connection, &input.email)
+            .and_then(|user| db::user::set_disabled(&connection, user, true))
+        {
+            Ok(user) => {
+                db::user_session::delete_all(&connection, &user, None)
+                    .map_err(error::ErrorInternalServerError)?;
+                Alert::success(
+                    template.translate_with("admin-account-disabled", &[("email", user.email)]),
+                )
+            }
+            Err(e) => Alert::danger(e),
+        }
+    };
+    drop(connection);
+
+    render_users(id, pool, template, None, None, vec![alert])
+}
+
+// Submit handler for enabling a user account that has been disabled.
+pub async fn enable_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<UserActionForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, _) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = match db::user::read(&connection, &input.email)
+        .and_then(|user| db::user::set_disabled(&connection, user, false))
+    {
+        Ok(user) => Alert::success(
+            template.translate_with("admin-account-enabled", &[("email", user.email)]),
+        ),
+        Err(e) => Alert::danger(e),
+    };
+    drop(connection);
+
+    render_users(id, pool, template, None, None, vec![alert])
+}
+
+// Submit handler for deleting a user account. Administrators can delete their own account from the
+// account settings, which requires their password.
+pub async fn delete_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<UserActionForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, admin) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = if input.email == admin.email {
+        Alert::danger(template.translate("admin-cannot-delete-self"))
+    } else {
+        match db::user::delete(&connection, &input.email) {
+            Ok(_) => Alert::success(template.translate_with(
+                "admin-account-deleted",
+                &[("email", input.email.to_owned())],
+            )),
+            Err(e) => Alert::danger(e),
+        }
+    };
+    drop(connection);
+
+    render_users(id, pool, template, None, None, vec![alert])
+}
+
+// Request handler for the page that lists the pending registration invitations.
+pub async fn invitations_handler(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, Error> {
+    render_invitations(id, pool, template, &config, vec![])
+}
+
+// Submit handler for inviting someone to register. Inviting someone again sends a new code.
+pub async fn invite_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<InvitationForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, admin) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = match db::registration_invitation::create(&connection, &input.email, Some(&admin)) {
+        Ok(invitation) => {
+            notifications::registration_invitation(&invitation, Some(&admin), &config)
+                .await
+                .map_err(error::ErrorInternalServerError)?;
+            Alert::success(
+                template.translate_with("admin-invitation-sent", &[("email", invitation.email)]),
+            )
+        }
+        Err(e) => Alert::danger(e),
+    };
+    drop(connection);
+
+    render_invitations(id, pool, template, &config, vec![alert])
+}
+
+// Submit handler for revoking a registration invitation.
+pub async fn revoke_invitation_submit(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<InvitationForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, _) = get_connection_and_admin(&id, &pool)?;
+
+    let alert = match db::registration_invitation::delete(&connection, &input.email) {
+        Ok(_) => Alert::success(template.translate_with(
+            "admin-invitation-revoked",
+            &[("email", input.email.trim().to_string())],
+        )),
+        Err(e) => Alert::danger(e),
+    };
+    drop(connection);
+
+    render_invitations(id, pool, template, &config, vec![alert])
+}
+
+// Returns a database connection and the currently logged in user, who needs to be an
+// administrator.
+fn get_connection_and_admin(
+    id: &Identity,
+    pool: &web::Data<db::ConnectionPool>,
+) -> Result<(PooledConnection<ConnectionManager<PgConnection>>, User), Error> {
+    let (connection, user) = get_connection_and_user(id, pool)?;
+    assert_admin(&user)?;
+    Ok((connection, user))
+}
+
+// Renders a page of the list of user accounts. Pages outside of the range are clamped to the first
+// or last page. Used by both GET and POST requests.
+fn render_users(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    query: Option<&str>,
+    page: Option<i64>,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, _) = get_connection_and_admin(&id, &pool)?;
+    let query = query.map(str::trim).filter(|q| !q.is_empty());
+
+    let total = db::user::count(&connection, query).map_err(error::ErrorInternalServerError)?;
+    let pages = ((total + USERS_PER_PAGE - 1) / USERS_PER_PAGE).max(1);
+    let page = page.unwrap_or(1).max(1).min(pages);
+    let users = db::user::list(
+        &connection,
+        query,
+        USERS_PER_PAGE,
+        (page - 1) * USERS_PER_PAGE,
+    )
+    .map_err(error::ErrorInternalServerError)?;
+
+    // Retrieve the activation codes of the users that have not been activated in a single query.
+    let inactive_users: Vec<User> = users.iter().filter(|u| !u.activated).cloned().collect();
+    let mut activation_codes: HashMap<i32, ActivationCode> =
+        db::activation_code::find_all(&connection, &inactive_users)
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|activation_code| (activation_code.id, activation_code))
+            .collect();
+
+    let users: Vec<UserSummary> = users
+        .into_iter()
+        .map(|user| {
+            let activation_code = activation_codes
+                .remove(&user.id)
+                .map(ActivationCodeSummary::from);
+            UserSummary {
+                email: user.email,
+                created: user.created,
+                activated: user.activated,
+                admin: user.admin,
+                disabled: user.disabled,
+                activation_code,
+            }
+        })
+        .collect();
+
+    let mut context = get_tera_context(template.translate("admin-users-title").as_str(), id);
+    context.insert("users", &users);
+    context.insert("query", &query.unwrap_or(""));
+    context.insert("page", &page);
+    context.insert("pages", &pages);
+    context.insert("alerts", &alerts);
+
+    let content = template
+        .render("admin/users.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Renders the page that lists the pending registration invitations. Used by both GET and POST
+// requests.
+fn render_invitations(
+    id: Identity,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: &AppConfig,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, _) = get_connection_and_admin(&id, &pool)?;
+    let invitations: Vec<InvitationSummary> = db::registration_invitation::list(&connection)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|invitation| InvitationSummary {
+            email: invitation.email,
+            expiration_time: invitation.expiration_time,
+        })
+        .collect();
+
+    let mut context = get_tera_context(template.translate("admin-invitations-title").as_str(), id);
+    context.insert("invitations", &invitations);
+    context.insert("registration_mode", &config.registration_mode().to_string());
+    context.insert("alerts", &alerts);
+
+    let content = template
+        .render("admin/invitations.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
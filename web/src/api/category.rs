@@ -0,0 +1,162 @@
+use super::{get_connection, get_description, get_id, get_ledger, ApiError, ApiUser};
+use actix_web::{web, Error, HttpResponse};
+use db::category::{Categories, Category, CategoryErrorKind};
+use db::user::User;
+use diesel::PgConnection;
+use schemars::JsonSchema;
+
+// The query string of the category listings.
+#[derive(Deserialize, JsonSchema)]
+pub struct ListQuery {
+    /// The ID of the ledger. Defaults to the default ledger of the user.
+    ledger: Option<i32>,
+}
+
+// The JSON body of a request to create a category.
+#[derive(Deserialize, JsonSchema)]
+pub struct CreateInput {
+    /// The ID of the ledger. Defaults to the default ledger of the user.
+    #[serde(default)]
+    ledger_id: Option<i32>,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    parent_id: Option<i32>,
+}
+
+// The JSON body of a request to update a category.
+#[derive(Deserialize, JsonSchema)]
+pub struct UpdateInput {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    parent_id: Option<i32>,
+}
+
+// Request handler that returns the categories of a ledger as a flat list.
+pub async fn list_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    query: Result<web::Query<ListQuery>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let ledger = get_ledger(&connection, &api_user.user, query.ledger)?;
+    let categories: Vec<Category> = db::category::get_categories(&connection, &ledger)?;
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+// Request handler that returns the categories of a ledger as a tree.
+pub async fn tree_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    query: Result<web::Query<ListQuery>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let ledger = get_ledger(&connection, &api_user.user, query.ledger)?;
+    let categories: Vec<Categories> =
+        db::category::get_categories_tree(&connection, &ledger)?.children;
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+// Request handler that creates a category.
+pub async fn create_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    input: Result<web::Json<CreateInput>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let input = input.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let ledger = get_ledger(&connection, &api_user.user, input.ledger_id)?;
+    let parent = get_parent(&connection, &api_user.user, input.parent_id)?;
+
+    let category = db::category::create(
+        &connection,
+        &api_user.user,
+        &ledger,
+        &input.name,
+        get_description(&input.description),
+        parent.as_ref(),
+    )?;
+    Ok(HttpResponse::Created()
+        .header("location", format!("/api/v1/categories/{}", category.id))
+        .json(category))
+}
+
+// Request handler that returns a single category.
+pub async fn read_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    let id = get_id(path)?;
+    let connection = get_connection(&pool)?;
+    let category = get_category(&connection, &api_user.user, id)?;
+    Ok(HttpResponse::Ok().json(category))
+}
+
+// Request handler that updates the name, description and parent of a category.
+pub async fn update_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+    input: Result<web::Json<UpdateInput>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let id = get_id(path)?;
+    let input = input.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let category = get_category(&connection, &api_user.user, id)?;
+    let parent = get_parent(&connection, &api_user.user, input.parent_id)?;
+
+    let category = db::category::update(
+        &connection,
+        &api_user.user,
+        &category,
+        &input.name,
+        get_description(&input.description),
+        parent.as_ref(),
+    )?;
+    Ok(HttpResponse::Ok().json(category))
+}
+
+// Request handler that deletes a category. Categories that still contain expenses or child
+// categories cannot be deleted.
+pub async fn delete_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let id = get_id(path)?;
+    let connection = get_connection(&pool)?;
+    let category = get_category(&connection, &api_user.user, id)?;
+
+    match db::ledger::get_role(&connection, category.ledger_id, api_user.user.id) {
+        Ok(Some(role)) if role.can_edit() => {}
+        Ok(_) => return Err(CategoryErrorKind::InsufficientPermissions(category.ledger_id).into()),
+        Err(_) => return Err(ApiError::internal()),
+    }
+
+    db::category::delete(&connection, category.id)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Returns the category with the given ID if it is in a ledger the user is a member of.
+fn get_category(connection: &PgConnection, user: &User, id: i32) -> Result<Category, ApiError> {
+    db::category::read(connection, id, Some(user.id))
+        .ok_or_else(|| CategoryErrorKind::NotFound(id).into())
+}
+
+// Returns the parent category with the given ID, if one is passed.
+fn get_parent(
+    connection: &PgConnection,
+    user: &User,
+    id: Option<i32>,
+) -> Result<Option<Category>, ApiError> {
+    id.map(|id| get_category(connection, user, id)).transpose()
+}
@@ -0,0 +1,156 @@
+use super::{
+    get_connection, get_description, get_id, get_ledger, ApiError, ApiUser, Page, Pagination,
+};
+use actix_web::{web, Error, HttpResponse};
+use chrono::{NaiveDate, Utc};
+use db::category::CategoryErrorKind;
+use db::expense::{Expense, ExpenseErrorKind};
+use db::user::User;
+use diesel::PgConnection;
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+
+// The query string of the expense listing.
+#[derive(Deserialize, JsonSchema)]
+pub struct ListQuery {
+    /// The ID of the ledger. Defaults to the default ledger of the user.
+    ledger: Option<i32>,
+    /// The page number, starting from 1.
+    page: Option<i64>,
+    /// The number of expenses per page, up to 100. Defaults to 50.
+    per_page: Option<i64>,
+}
+
+// The JSON body of a request to create or update an expense.
+#[derive(Deserialize, JsonSchema)]
+pub struct ExpenseInput {
+    amount: Decimal,
+    category_id: i32,
+    #[serde(default)]
+    description: Option<String>,
+    /// Defaults to the current date for new expenses, and to the existing date for updates.
+    #[serde(default)]
+    date: Option<NaiveDate>,
+}
+
+// Request handler that returns the expenses of a ledger, one page at a time. The most recent
+// expenses are returned first.
+pub async fn list_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    query: Result<web::Query<ListQuery>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    let query = query.map_err(ApiError::bad_request)?;
+    let pagination = Pagination::new(query.page, query.per_page)?;
+    let connection = get_connection(&pool)?;
+    let ledger = get_ledger(&connection, &api_user.user, query.ledger)?;
+
+    let total = db::expense::count_by_ledger(&connection, &ledger)?;
+    let expenses = db::expense::list_by_ledger(
+        &connection,
+        &ledger,
+        pagination.per_page,
+        pagination.offset()?,
+    )?;
+
+    let url = match query.ledger {
+        Some(id) => format!("/api/v1/expenses?ledger={}&", id),
+        None => "/api/v1/expenses?".to_string(),
+    };
+    Ok(HttpResponse::Ok().json(Page::new(expenses, &pagination, total, &url)))
+}
+
+// Request handler that creates an expense in the ledger of the given category.
+pub async fn create_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    input: Result<web::Json<ExpenseInput>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let input = input.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let category = db::category::read(&connection, input.category_id, Some(api_user.user.id))
+        .ok_or(CategoryErrorKind::NotFound(input.category_id))?;
+
+    let expense = db::expense::create(
+        &connection,
+        &api_user.user,
+        &input.amount,
+        &category,
+        get_description(&input.description),
+        Some(&input.date.unwrap_or_else(|| Utc::now().naive_utc().date())),
+    )?;
+    Ok(HttpResponse::Created()
+        .header("location", format!("/api/v1/expenses/{}", expense.id))
+        .json(expense))
+}
+
+// Request handler that returns a single expense.
+pub async fn read_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    let id = get_id(path)?;
+    let connection = get_connection(&pool)?;
+    let expense = get_expense(&connection, &api_user.user, id)?;
+    Ok(HttpResponse::Ok().json(expense))
+}
+
+// Request handler that updates an expense.
+pub async fn update_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+    input: Result<web::Json<ExpenseInput>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let id = get_id(path)?;
+    let input = input.map_err(ApiError::bad_request)?;
+    let connection = get_connection(&pool)?;
+    let expense = get_expense(&connection, &api_user.user, id)?;
+    let category = db::category::read(&connection, input.category_id, Some(api_user.user.id))
+        .ok_or(CategoryErrorKind::NotFound(input.category_id))?;
+
+    let expense = db::expense::update(
+        &connection,
+        &api_user.user,
+        &expense,
+        &input.amount,
+        &category,
+        get_description(&input.description),
+        &input.date.unwrap_or(expense.date),
+    )?;
+    Ok(HttpResponse::Ok().json(expense))
+}
+
+// Request handler that deletes an expense.
+pub async fn delete_handler(
+    api_user: ApiUser,
+    pool: web::Data<db::ConnectionPool>,
+    path: Result<web::Path<i32>, Error>,
+) -> Result<HttpResponse, ApiError> {
+    api_user.assert_can_write()?;
+    let id = get_id(path)?;
+    let connection = get_connection(&pool)?;
+    let expense = get_expense(&connection, &api_user.user, id)?;
+
+    match db::ledger::get_role(&connection, expense.ledger_id, api_user.user.id) {
+        Ok(Some(role)) if role.can_edit() => {}
+        Ok(_) => return Err(ExpenseErrorKind::InsufficientPermissions(expense.ledger_id).into()),
+        Err(_) => return Err(ApiError::internal()),
+    }
+
+    db::expense::delete(&connection, expense.id)?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Returns the expense with the given ID if it is in a ledger the user is a member of.
+fn get_expense(connection: &PgConnection, user: &User, id: i32) -> Result<Expense, ApiError> {
+    let expense = db::expense::read(connection, id).ok_or(ExpenseErrorKind::NotFound(id))?;
+    match db::ledger::get_role(connection, expense.ledger_id, user.id) {
+        Ok(Some(_)) => Ok(expense),
+        Ok(None) => Err(ExpenseErrorKind::NotFound(id).into()),
+        Err(_) => Err(ApiError::internal()),
+    }
+}
@@ -0,0 +1,425 @@
+pub mod category;
+pub mod expense;
+pub mod openapi;
+
+use actix_web::dev::Payload;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, Error, FromRequest, HttpRequest, HttpResponse, ResponseError, Route};
+use db::api_token::{ApiToken, Scope};
+use db::category::CategoryErrorKind;
+use db::expense::ExpenseErrorKind;
+use db::ledger::{Ledger, LedgerErrorKind};
+use db::user::User;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::PgConnection;
+use futures::future::{ready, Ready};
+use schemars::JsonSchema;
+use std::fmt;
+
+/// The number of items that are returned per page if the client doesn't specify a page size.
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// The maximum number of items that can be requested per page.
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+/// Returns the routes of the JSON API, with their paths relative to `/api/v1`. Every route should
+/// be described in the OpenAPI document. Paths that have a fixed segment in the place of a
+/// parameter are listed first, so they take precedence.
+pub fn routes() -> Vec<(&'static str, Method, Route)> {
+    vec![
+        (
+            "/categories",
+            Method::GET,
+            web::get().to(category::list_handler),
+        ),
+        (
+            "/categories",
+            Method::POST,
+            web::post().to(category::create_handler),
+        ),
+        (
+            "/categories/tree",
+            Method::GET,
+            web::get().to(category::tree_handler),
+        ),
+        (
+            "/categories/{id}",
+            Method::GET,
+            web::get().to(category::read_handler),
+        ),
+        (
+            "/categories/{id}",
+            Method::PUT,
+            web::put().to(category::update_handler),
+        ),
+        (
+            "/categories/{id}",
+            Method::DELETE,
+            web::delete().to(category::delete_handler),
+        ),
+        (
+            "/expenses",
+            Method::GET,
+            web::get().to(expense::list_handler),
+        ),
+        (
+            "/expenses",
+            Method::POST,
+            web::post().to(expense::create_handler),
+        ),
+        (
+            "/expenses/{id}",
+            Method::GET,
+            web::get().to(expense::read_handler),
+        ),
+        (
+            "/expenses/{id}",
+            Method::PUT,
+            web::put().to(expense::update_handler),
+        ),
+        (
+            "/expenses/{id}",
+            Method::DELETE,
+            web::delete().to(expense::delete_handler),
+        ),
+        ("/user", Method::GET, web::get().to(user_handler)),
+    ]
+}
+
+/// Registers the routes of the JSON API. Requests that use a method that is not supported by a
+/// resource are answered with a 405 Method Not Allowed response.
+pub fn configure(config: &mut web::ServiceConfig) {
+    // Group the routes by path, keeping the order in which the paths are listed.
+    let mut resources: Vec<(&str, Vec<Route>)> = vec![];
+    for (path, _, route) in routes() {
+        match resources.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, routes)) => routes.push(route),
+            None => resources.push((path, vec![route])),
+        }
+    }
+
+    for (path, routes) in resources {
+        let resource = routes
+            .into_iter()
+            .fold(web::resource(path), |resource, route| resource.route(route))
+            .default_service(web::route().to(method_not_allowed_handler));
+        config.service(resource);
+    }
+}
+
+/// The user that is authenticated by the API token in the `Authorization: Bearer` header of an API
+/// request. Requests without a valid token are rejected with a 401 Unauthorized response.
+pub struct ApiUser {
+    pub user: User,
+    pub api_token: ApiToken,
+}
+
+impl ApiUser {
+    /// Checks that the API token is allowed to make changes.
+    pub fn assert_can_write(&self) -> Result<(), ApiError> {
+        if !self.api_token.scope.can_write() {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "This API token only has read access.",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromRequest for ApiUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(authenticate(req))
+    }
+}
+
+/// An error that is returned by the API as a JSON object containing the error message.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new<T: ToString>(status: StatusCode, message: T) -> ApiError {
+        ApiError {
+            status,
+            message: message.to_string(),
+        }
+    }
+
+    /// Returns an error for a request that could not be parsed, e.g. a malformed JSON body.
+    pub fn bad_request(err: Error) -> ApiError {
+        ApiError::new(StatusCode::BAD_REQUEST, err)
+    }
+
+    /// Returns an error for a resource that doesn't exist.
+    pub fn not_found() -> ApiError {
+        ApiError::new(StatusCode::NOT_FOUND, "Not found.")
+    }
+
+    /// Returns an error for a server side failure. The details are not exposed to the client.
+    pub fn internal() -> ApiError {
+        ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error.")
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if self.status == StatusCode::UNAUTHORIZED {
+            response.header("www-authenticate", "Bearer");
+        }
+        response.json(ErrorResponse {
+            error: self.message.clone(),
+        })
+    }
+}
+
+// Converts category errors into API errors with a matching status code.
+impl From<CategoryErrorKind> for ApiError {
+    fn from(err: CategoryErrorKind) -> Self {
+        let status = match err {
+            CategoryErrorKind::AlreadyPopulated(_)
+            | CategoryErrorKind::CategoryAlreadyExists { .. }
+            | CategoryErrorKind::HasChildren(_, _) => StatusCode::CONFLICT,
+            CategoryErrorKind::CircularParent(_)
+            | CategoryErrorKind::MissingData(_)
+            | CategoryErrorKind::ParentCategoryHasWrongLedger => StatusCode::UNPROCESSABLE_ENTITY,
+            CategoryErrorKind::InsufficientPermissions(_) => StatusCode::FORBIDDEN,
+            CategoryErrorKind::NotFound(_) => StatusCode::NOT_FOUND,
+            CategoryErrorKind::DatabaseError(_)
+            | CategoryErrorKind::IoError(_, _)
+            | CategoryErrorKind::MalformedCategoryList => return ApiError::internal(),
+        };
+        ApiError::new(status, err)
+    }
+}
+
+// Converts expense errors into API errors with a matching status code.
+impl From<ExpenseErrorKind> for ApiError {
+    fn from(err: ExpenseErrorKind) -> Self {
+        let status = match err {
+            ExpenseErrorKind::CategoryHasWrongLedger(_) | ExpenseErrorKind::InvalidAmount => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ExpenseErrorKind::InsufficientPermissions(_) => StatusCode::FORBIDDEN,
+            ExpenseErrorKind::IsShared(_) => StatusCode::CONFLICT,
+            ExpenseErrorKind::NotFound(_) => StatusCode::NOT_FOUND,
+            ExpenseErrorKind::CreationFailed(_)
+            | ExpenseErrorKind::DeletionFailed(_)
+            | ExpenseErrorKind::ReadFailed(_)
+            | ExpenseErrorKind::UpdateFailed(_) => return ApiError::internal(),
+        };
+        ApiError::new(status, err)
+    }
+}
+
+// Converts ledger errors into API errors with a matching status code.
+impl From<LedgerErrorKind> for ApiError {
+    fn from(err: LedgerErrorKind) -> Self {
+        let status = match err {
+            LedgerErrorKind::InsufficientPermissions(_) => StatusCode::FORBIDDEN,
+            LedgerErrorKind::InvalidRole(_) | LedgerErrorKind::MissingData(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            LedgerErrorKind::LastOwner(_) | LedgerErrorKind::MemberAlreadyExists(_) => {
+                StatusCode::CONFLICT
+            }
+            LedgerErrorKind::MemberNotFound(_) | LedgerErrorKind::NotFound(_) => {
+                StatusCode::NOT_FOUND
+            }
+            LedgerErrorKind::DatabaseError(_) => return ApiError::internal(),
+        };
+        ApiError::new(status, err)
+    }
+}
+
+// The body of an error response.
+#[derive(JsonSchema, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// A page of items in a paginated listing, with links to the neighbouring pages.
+#[derive(JsonSchema, Serialize)]
+#[schemars(rename = "{T}Page")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub links: PageLinks,
+}
+
+/// Links to the pages of a paginated listing. The previous and next links are omitted on the first
+/// and last pages.
+#[derive(JsonSchema, Serialize)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub first: String,
+    pub last: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Returns a page of items. The links are built by appending the page parameters to the given
+    /// URL, which should end in either `?` or `&`.
+    pub fn new(items: Vec<T>, pagination: &Pagination, total: i64, url: &str) -> Page<T> {
+        let per_page = pagination.per_page;
+        let link = |page: i64| format!("{}page={}&per_page={}", url, page, per_page);
+        let last = ((total + per_page - 1) / per_page).max(1);
+        let page = pagination.page;
+        Page {
+            items,
+            page,
+            per_page,
+            total,
+            links: PageLinks {
+                current: link(page),
+                first: link(1),
+                last: link(last),
+                prev: Some(page - 1).filter(|p| *p >= 1).map(link),
+                next: Some(page + 1).filter(|p| *p <= last).map(link),
+            },
+        }
+    }
+}
+
+/// The requested page number and page size of a paginated listing.
+pub struct Pagination {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl Pagination {
+    /// Validates the page number and page size that were passed in the query string.
+    pub fn new(page: Option<i64>, per_page: Option<i64>) -> Result<Pagination, ApiError> {
+        let page = page.unwrap_or(1);
+        let per_page = per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+        if page < 1 {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "The page should be 1 or greater.",
+            ));
+        }
+        if !(1..=MAX_PAGE_SIZE).contains(&per_page) {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                format!("The page size should be between 1 and {}.", MAX_PAGE_SIZE),
+            ));
+        }
+        Ok(Pagination { page, per_page })
+    }
+
+    /// Returns the number of items to skip.
+    pub fn offset(&self) -> Result<i64, ApiError> {
+        (self.page - 1)
+            .checked_mul(self.per_page)
+            .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "The page is out of range."))
+    }
+}
+
+// The details of the user that is authenticated by the API token.
+#[derive(JsonSchema, Serialize)]
+struct UserResponse {
+    email: String,
+    scope: Scope,
+}
+
+// Request handler that returns the user the API token belongs to. This allows scripts to check
+// that their token is valid.
+pub async fn user_handler(api_user: ApiUser) -> Result<HttpResponse, ApiError> {
+    Ok(HttpResponse::Ok().json(UserResponse {
+        email: api_user.user.email,
+        scope: api_user.api_token.scope,
+    }))
+}
+
+// Fallback handler for API requests that don't match any route.
+pub async fn not_found_handler() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::not_found())
+}
+
+// Fallback handler for API requests that use an unsupported method.
+pub async fn method_not_allowed_handler() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::new(
+        StatusCode::METHOD_NOT_ALLOWED,
+        "Method not allowed.",
+    ))
+}
+
+// Returns a database connection from the pool.
+fn get_connection(
+    pool: &web::Data<db::ConnectionPool>,
+) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ApiError> {
+    pool.get().map_err(|_| ApiError::internal())
+}
+
+// Returns the ID from the request path. IDs that are not numeric cannot exist.
+fn get_id(path: Result<web::Path<i32>, Error>) -> Result<i32, ApiError> {
+    path.map(|path| path.into_inner())
+        .map_err(|_| ApiError::not_found())
+}
+
+// Returns the trimmed description, or `None` if it is empty.
+fn get_description(description: &Option<String>) -> Option<&str> {
+    description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+}
+
+// Returns the ledger with the given ID if the user is a member of it. If no ID is given the
+// default ledger of the user is returned.
+fn get_ledger(connection: &PgConnection, user: &User, id: Option<i32>) -> Result<Ledger, ApiError> {
+    match id {
+        Some(id) => {
+            db::ledger::read(connection, id, Some(user.id)).ok_or(LedgerErrorKind::NotFound(id))
+        }
+        None => db::ledger::get_default(connection, user),
+    }
+    .map_err(ApiError::from)
+}
+
+// Returns the user that is authenticated by the bearer token of the request.
+fn authenticate(req: &HttpRequest) -> Result<ApiUser, ApiError> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim())
+        .ok_or_else(|| ApiError::new(StatusCode::UNAUTHORIZED, "An API token is required."))?;
+
+    let pool = req
+        .app_data::<web::Data<db::ConnectionPool>>()
+        .ok_or_else(ApiError::internal)?;
+    let connection = pool.get().map_err(|_| ApiError::internal())?;
+    match db::api_token::authenticate(&connection, token) {
+        Ok(Some((api_token, user))) => Ok(ApiUser { user, api_token }),
+        Ok(None) => Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "The API token is invalid or has expired.",
+        )),
+        Err(_) => Err(ApiError::internal()),
+    }
+}
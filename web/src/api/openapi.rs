@@ -0,0 +1,333 @@
+use super::{category, expense, ErrorResponse, Page, UserResponse};
+use actix_web::HttpResponse;
+use db::category::{Categories, Category};
+use db::expense::Expense;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+// Request handler that serves the OpenAPI document of the JSON API.
+pub async fn spec_handler() -> HttpResponse {
+    HttpResponse::Ok().json(spec())
+}
+
+/// Returns the OpenAPI 3 document describing the JSON API. The schemas of the query strings and the
+/// request and response bodies are generated from the types that are used by the request handlers.
+pub fn spec() -> Value {
+    let mut spec = SpecBuilder::new();
+    let id = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": {"type": "integer", "format": "int32"},
+    });
+
+    let categories = json!({"type": "array", "items": spec.schema::<Category>()});
+    let query = spec.query_parameters::<category::ListQuery>();
+    spec.add(
+        "get",
+        "/categories",
+        Operation::new("listCategories", "Lists the categories of a ledger.")
+            .parameters(query.clone())
+            .response(200, "The categories as a flat list.", Some(categories))
+            .errors(&[400, 401, 404]),
+    );
+    let body = spec.schema::<category::CreateInput>();
+    let category = spec.schema::<Category>();
+    spec.add(
+        "post",
+        "/categories",
+        Operation::new("createCategory", "Creates a category.")
+            .request(body)
+            .response(201, "The category was created.", Some(category.clone()))
+            .errors(&[400, 401, 403, 404, 409, 422]),
+    );
+    let tree = json!({"type": "array", "items": spec.schema::<Categories>()});
+    spec.add(
+        "get",
+        "/categories/tree",
+        Operation::new(
+            "getCategoryTree",
+            "Lists the categories of a ledger as a tree.",
+        )
+        .parameters(query)
+        .response(200, "The root categories with their children.", Some(tree))
+        .errors(&[400, 401, 404]),
+    );
+    spec.add(
+        "get",
+        "/categories/{id}",
+        Operation::new("getCategory", "Returns a category.")
+            .parameters(vec![id.clone()])
+            .response(200, "The category.", Some(category.clone()))
+            .errors(&[401, 404]),
+    );
+    let body = spec.schema::<category::UpdateInput>();
+    spec.add(
+        "put",
+        "/categories/{id}",
+        Operation::new("updateCategory", "Updates a category.")
+            .parameters(vec![id.clone()])
+            .request(body)
+            .response(200, "The updated category.", Some(category))
+            .errors(&[400, 401, 403, 404, 409, 422]),
+    );
+    spec.add(
+        "delete",
+        "/categories/{id}",
+        Operation::new(
+            "deleteCategory",
+            "Deletes a category that doesn't contain any expenses or child categories.",
+        )
+        .parameters(vec![id.clone()])
+        .response(204, "The category was deleted.", None)
+        .errors(&[401, 403, 404, 409]),
+    );
+
+    let query = spec.query_parameters::<expense::ListQuery>();
+    let page = spec.schema::<Page<Expense>>();
+    spec.add(
+        "get",
+        "/expenses",
+        Operation::new(
+            "listExpenses",
+            "Lists the expenses of a ledger, most recent first.",
+        )
+        .parameters(query)
+        .response(200, "A page of expenses.", Some(page))
+        .errors(&[400, 401, 404]),
+    );
+    let body = spec.schema::<expense::ExpenseInput>();
+    let expense = spec.schema::<Expense>();
+    spec.add(
+        "post",
+        "/expenses",
+        Operation::new("createExpense", "Creates an expense.")
+            .request(body.clone())
+            .response(201, "The expense was created.", Some(expense.clone()))
+            .errors(&[400, 401, 403, 404, 422]),
+    );
+    spec.add(
+        "get",
+        "/expenses/{id}",
+        Operation::new("getExpense", "Returns an expense.")
+            .parameters(vec![id.clone()])
+            .response(200, "The expense.", Some(expense.clone()))
+            .errors(&[401, 404]),
+    );
+    spec.add(
+        "put",
+        "/expenses/{id}",
+        Operation::new("updateExpense", "Updates an expense.")
+            .parameters(vec![id.clone()])
+            .request(body)
+            .response(200, "The updated expense.", Some(expense))
+            .errors(&[400, 401, 403, 404, 409, 422]),
+    );
+    spec.add(
+        "delete",
+        "/expenses/{id}",
+        Operation::new("deleteExpense", "Deletes an expense.")
+            .parameters(vec![id])
+            .response(204, "The expense was deleted.", None)
+            .errors(&[401, 403, 404]),
+    );
+
+    let user = spec.schema::<UserResponse>();
+    spec.add(
+        "get",
+        "/user",
+        Operation::new(
+            "getUser",
+            "Returns the user and the scope of the API token.",
+        )
+        .response(200, "The user the API token belongs to.", Some(user))
+        .errors(&[401]),
+    );
+
+    spec.build()
+}
+
+// Collects the operations and the schemas of the OpenAPI document.
+struct SpecBuilder {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl SpecBuilder {
+    fn new() -> SpecBuilder {
+        SpecBuilder {
+            generator: SchemaSettings::openapi3().into_generator(),
+            paths: Map::new(),
+        }
+    }
+
+    // Returns a reference to the schema of the given type, adding the schema to the components.
+    fn schema<T: JsonSchema>(&mut self) -> Value {
+        serde_json::to_value(self.generator.subschema_for::<T>()).unwrap()
+    }
+
+    // Returns the query parameters that are described by the fields of the given type.
+    fn query_parameters<T: JsonSchema>(&mut self) -> Vec<Value> {
+        let schema = serde_json::to_value(self.generator.root_schema_for::<T>().schema).unwrap();
+        let required = schema["required"].as_array().cloned().unwrap_or_default();
+        schema["properties"]
+            .as_object()
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, schema)| {
+                        let mut schema = schema.clone();
+                        let description = schema
+                            .as_object_mut()
+                            .and_then(|schema| schema.remove("description"));
+                        let mut parameter = json!({
+                            "name": name,
+                            "in": "query",
+                            "required": required.contains(&json!(name)),
+                            "schema": schema,
+                        });
+                        if let Some(description) = description {
+                            parameter["description"] = description;
+                        }
+                        parameter
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Adds the given operation to the document.
+    fn add(&mut self, method: &str, path: &str, operation: Operation) {
+        let operation = operation.build(self.schema::<ErrorResponse>());
+        self.paths
+            .entry(path.to_string())
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .unwrap()
+            .insert(method.to_string(), operation);
+    }
+
+    // Returns the complete OpenAPI document.
+    fn build(mut self) -> Value {
+        let schemas: Map<String, Value> = self
+            .generator
+            .take_definitions()
+            .into_iter()
+            .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap()))
+            .collect();
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "Firetrack API",
+                "version": "1",
+            },
+            "servers": [{"url": "/api/v1"}],
+            "security": [{"bearerAuth": []}],
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "securitySchemes": {
+                    "bearerAuth": {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "description": "A personal API token, which can be created on the account page.",
+                    },
+                },
+            },
+        })
+    }
+}
+
+// A single operation of the JSON API.
+struct Operation {
+    id: String,
+    summary: String,
+    parameters: Vec<Value>,
+    request: Option<Value>,
+    responses: Map<String, Value>,
+    errors: Vec<u16>,
+}
+
+impl Operation {
+    fn new(id: &str, summary: &str) -> Operation {
+        Operation {
+            id: id.to_string(),
+            summary: summary.to_string(),
+            parameters: vec![],
+            request: None,
+            responses: Map::new(),
+            errors: vec![],
+        }
+    }
+
+    fn parameters(mut self, parameters: Vec<Value>) -> Operation {
+        self.parameters = parameters;
+        self
+    }
+
+    // Sets the schema of the JSON request body.
+    fn request(mut self, schema: Value) -> Operation {
+        self.request = Some(schema);
+        self
+    }
+
+    // Adds a successful response, with an optional schema of the JSON response body.
+    fn response(mut self, status: u16, description: &str, schema: Option<Value>) -> Operation {
+        let mut response = json!({ "description": description });
+        if let Some(schema) = schema {
+            response["content"] = json!({"application/json": {"schema": schema}});
+        }
+        self.responses.insert(status.to_string(), response);
+        self
+    }
+
+    // Adds the error responses with the given status codes.
+    fn errors(mut self, statuses: &[u16]) -> Operation {
+        self.errors = statuses.to_vec();
+        self
+    }
+
+    // Returns the operation object, using the given schema for the error responses.
+    fn build(self, error: Value) -> Value {
+        let mut responses = self.responses;
+        for status in self.errors {
+            responses.insert(
+                status.to_string(),
+                json!({
+                    "description": error_description(status),
+                    "content": {"application/json": {"schema": error}},
+                }),
+            );
+        }
+
+        let mut operation = json!({
+            "operationId": self.id,
+            "summary": self.summary,
+            "responses": responses,
+        });
+        if !self.parameters.is_empty() {
+            operation["parameters"] = json!(self.parameters);
+        }
+        if let Some(schema) = self.request {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": {"application/json": {"schema": schema}},
+            });
+        }
+        operation
+    }
+}
+
+// Returns the description of an error response with the given status code.
+fn error_description(status: u16) -> &'static str {
+    match status {
+        400 => "The request is malformed.",
+        401 => "The API token is missing, invalid or expired.",
+        403 => "The API token or the user doesn't have the required permissions.",
+        404 => "The resource doesn't exist or is not accessible.",
+        409 => "The request conflicts with existing data.",
+        422 => "The submitted data is invalid.",
+        _ => "An error occurred.",
+    }
+}
@@ -0,0 +1,148 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context, insert_preferences};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use crate::ledger::get_current_ledger;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use db::settlement::Settlement;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+// The POST data of the form to record a settlement.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SettleForm {
+    payer: i32,
+    payee: i32,
+    amount: String,
+    date: String,
+}
+
+// A settlement, as shown in the settlement listing.
+#[derive(Serialize)]
+struct SettlementItem {
+    payer: String,
+    payee: String,
+    settlement: Settlement,
+}
+
+// Request handler for the balances overview.
+pub async fn overview_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_overview(id, session, pool, template, vec![])
+}
+
+// Submit handler for the form to record a settlement between two members of the current ledger.
+pub async fn settle_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<SettleForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let amount = preferences
+        .parse_amount(input.amount.as_str())
+        .map_err(|_| {
+            format!(
+                "Amount should be in the format '{}'.",
+                preferences.locale.format_number(Decimal::new(14999, 2))
+            )
+        });
+    let date = match input.date.trim() {
+        "" => Ok(None),
+        date => preferences.parse_date(date).map(Some).map_err(|_| {
+            format!(
+                "Date should be in the format {}.",
+                preferences.date_format.placeholder()
+            )
+        }),
+    };
+
+    let alert = match (amount, date) {
+        (Err(e), _) | (_, Err(e)) => Alert::danger(e),
+        (Ok(amount), Ok(date)) => match db::settlement::create(
+            &connection,
+            &user,
+            &ledger,
+            input.payer,
+            input.payee,
+            &amount,
+            date.as_ref(),
+        ) {
+            Ok(settlement) => Alert::success(format!(
+                "Recorded a payment of {}.",
+                preferences.format_amount(settlement.amount)
+            )),
+            Err(e) => Alert::danger(e),
+        },
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Renders the balances overview. Used by both GET and POST requests.
+fn render_overview(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, role) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let balances = db::settlement::get_balances(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?;
+    let debts = db::settlement::get_debts(&balances);
+    let members: Vec<(i32, String)> = db::ledger::get_members(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|(member, _)| (member.id, member.email))
+        .collect();
+
+    // The balances include everyone that was ever involved in a settlement, so they can be used to
+    // look up the email addresses of the payers and payees. Deleted users no longer have one.
+    let emails: HashMap<i32, &str> = balances
+        .iter()
+        .map(|b| (b.user_id, b.email.as_str()))
+        .collect();
+    let deleted_user = template.translate("balance-deleted-user");
+    let email = |user_id: Option<i32>| match user_id {
+        Some(user_id) => emails.get(&user_id).unwrap_or(&"").to_string(),
+        None => deleted_user.clone(),
+    };
+    let settlements: Vec<SettlementItem> = db::settlement::list(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|settlement| SettlementItem {
+            payer: email(settlement.payer_id),
+            payee: email(settlement.payee_id),
+            settlement,
+        })
+        .collect();
+
+    let mut context = get_tera_context(template.translate("balances-title").as_str(), id);
+    context.insert("user_id", &user.id);
+    context.insert("ledger", &ledger);
+    context.insert("can_edit", &role.can_edit());
+    context.insert("balances", &balances);
+    context.insert("debts", &debts);
+    context.insert("members", &members);
+    context.insert("settlements", &settlements);
+    context.insert("alerts", &alerts);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("balances/overview.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
@@ -5,6 +5,24 @@ pub struct Alert {
     pub message: String,
 }
 
+impl Alert {
+    // Returns a success alert with the given message.
+    pub fn success(message: String) -> Alert {
+        Alert {
+            alert_type: AlertType::Success,
+            message,
+        }
+    }
+
+    // Returns a danger alert for the given error.
+    pub fn danger<E: std::fmt::Display>(e: E) -> Alert {
+        Alert {
+            alert_type: AlertType::Danger,
+            message: format!("Error: {}", e),
+        }
+    }
+}
+
 // Types of Bootstrap alerts.
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -0,0 +1,260 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context, insert_preferences};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use crate::ledger::get_current_ledger;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::Utc;
+use db::budget::{BudgetErrorKind, RecurringExpense};
+use db::category::CategoryErrorKind;
+use db::ledger::LedgerErrorKind;
+use db::user_preference::UserPreferences;
+use rust_decimal::Decimal;
+
+// The POST data of the form to set the budget of a category.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SetForm {
+    category: i32,
+    amount: String,
+}
+
+// The POST data of the form to remove the budget of a category.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RemoveForm {
+    category: i32,
+}
+
+// The POST data of the form to add a recurring expense.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AddRecurringForm {
+    category: i32,
+    amount: String,
+    #[serde(default)]
+    description: String,
+    day_of_month: u32,
+}
+
+// The POST data of the form to delete a recurring expense.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeleteRecurringForm {
+    recurring_expense: i32,
+}
+
+// A recurring expense as shown in the overview.
+#[derive(Serialize)]
+struct RecurringExpenseItem {
+    recurring_expense: RecurringExpense,
+    category: String,
+}
+
+// Request handler for the budgets overview.
+pub async fn overview_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_overview(id, session, pool, template, vec![])
+}
+
+// Submit handler for the form to set the budget of a category.
+pub async fn set_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<SetForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let alert = match (
+        db::category::read(&connection, input.category, Some(user.id)),
+        parse_amount(&preferences, input.amount.as_str()),
+    ) {
+        (None, _) => Alert::danger(CategoryErrorKind::NotFound(input.category)),
+        (_, Err(e)) => Alert::danger(e),
+        (Some(category), Ok(amount)) => {
+            match db::budget::set_budget(&connection, &user, &category, &amount) {
+                Ok(budget) => Alert::success(template.translate_with(
+                    "budget-saved",
+                    &[
+                        ("category", category.name),
+                        ("amount", preferences.format_amount(budget.amount)),
+                    ],
+                )),
+                Err(e) => Alert::danger(e),
+            }
+        }
+    };
+    drop(connection);
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to remove the budget of a category.
+pub async fn remove_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<RemoveForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::category::read(&connection, input.category, Some(user.id)) {
+        None => Alert::danger(CategoryErrorKind::NotFound(input.category)),
+        Some(category) => match db::budget::remove_budget(&connection, &user, &category) {
+            Ok(_) => Alert::success(
+                template.translate_with("budget-removed", &[("category", category.name)]),
+            ),
+            Err(e) => Alert::danger(e),
+        },
+    };
+    drop(connection);
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to add a recurring expense.
+pub async fn add_recurring_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<AddRecurringForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let alert = match (
+        db::category::read(&connection, input.category, Some(user.id)),
+        parse_amount(&preferences, input.amount.as_str()),
+    ) {
+        (None, _) => Alert::danger(CategoryErrorKind::NotFound(input.category)),
+        (_, Err(e)) => Alert::danger(e),
+        (Some(category), Ok(amount)) => {
+            let description = Some(input.description.trim()).filter(|d| !d.is_empty());
+            match db::budget::add_recurring_expense(
+                &connection,
+                &user,
+                &category,
+                &amount,
+                description,
+                input.day_of_month,
+            ) {
+                Ok(recurring_expense) => Alert::success(template.translate_with(
+                    "budget-recurring-added",
+                    &[
+                        ("category", category.name),
+                        (
+                            "amount",
+                            preferences.format_amount(recurring_expense.amount),
+                        ),
+                    ],
+                )),
+                Err(e) => Alert::danger(e),
+            }
+        }
+    };
+    drop(connection);
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to delete a recurring expense.
+pub async fn delete_recurring_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<DeleteRecurringForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::budget::read_recurring_expense(
+        &connection,
+        input.recurring_expense,
+        Some(user.id),
+    )
+    .and_then(|r| {
+        db::category::read(&connection, r.category_id, None).map(|category| (r, category))
+    }) {
+        None => Alert::danger(BudgetErrorKind::RecurringExpenseNotFound(
+            input.recurring_expense,
+        )),
+        Some((recurring_expense, category)) => {
+            match db::ledger::get_role(&connection, category.ledger_id, user.id) {
+                Ok(Some(role)) if role.can_edit() => {
+                    match db::budget::delete_recurring_expense(&connection, recurring_expense.id) {
+                        Ok(_) => Alert::success(template.translate("budget-recurring-deleted")),
+                        Err(e) => Alert::danger(e),
+                    }
+                }
+                _ => Alert::danger(LedgerErrorKind::InsufficientPermissions(category.ledger_id)),
+            }
+        }
+    };
+    drop(connection);
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Renders the budgets overview. Used by both GET and POST requests.
+fn render_overview(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, role) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let today = Utc::now().naive_utc().date();
+    let forecast = db::forecast::get_forecast(&connection, &ledger, today, 0)
+        .map_err(error::ErrorInternalServerError)?;
+    let budgets: Vec<_> = forecast
+        .categories
+        .iter()
+        .filter(|c| c.budget.is_some())
+        .collect();
+    let recurring_expenses: Vec<RecurringExpenseItem> =
+        db::budget::get_recurring_expenses(&connection, &ledger)
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|(recurring_expense, category)| RecurringExpenseItem {
+                recurring_expense,
+                category: category.name,
+            })
+            .collect();
+    let mut categories = db::category::get_categories(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?;
+    categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut context = get_tera_context(template.translate("budgets-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("can_edit", &role.can_edit());
+    context.insert("budgets", &budgets);
+    context.insert("recurring_expenses", &recurring_expenses);
+    context.insert("categories", &categories);
+    context.insert("alerts", &alerts);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("budgets/overview.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Parses an amount entered in a form, in the number format of the user.
+fn parse_amount(preferences: &UserPreferences, amount: &str) -> Result<Decimal, String> {
+    preferences.parse_amount(amount.trim()).map_err(|_| {
+        format!(
+            "Amount should be in the format '{}'.",
+            preferences.locale.format_number(Decimal::new(14999, 2))
+        )
+    })
+}
@@ -0,0 +1,124 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context, insert_preferences};
+use crate::i18n::Templates;
+use crate::ledger::get_current_ledger;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::{Datelike, Utc};
+use db::expense::Expense;
+use rust_decimal::prelude::*;
+
+// The number of categories shown in the top categories widget.
+const TOP_CATEGORIES: i64 = 5;
+
+// The number of expenses shown in the latest expenses widget.
+const LATEST_EXPENSES: i64 = 10;
+
+// A category with the amount spent in it this month, as shown in the top categories widget.
+#[derive(Serialize)]
+struct CategoryItem {
+    name: String,
+    amount: Decimal,
+    percentage: u32,
+}
+
+// An expense, as shown in the latest expenses widget.
+#[derive(Serialize)]
+struct ExpenseItem {
+    category: String,
+    expense: Expense,
+}
+
+// Renders the dashboard of the current ledger. This is shown on the homepage to authenticated
+// users. The widgets are assembled from aggregate queries so that the number of database queries
+// does not depend on the amount of data in the ledger.
+pub fn render(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, role) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let today = Utc::now().naive_utc().date();
+    let month_start = today.with_day(1).unwrap();
+
+    let total = db::report::get_total(&connection, &ledger, &month_start, &today)
+        .map_err(error::ErrorInternalServerError)?;
+    let top_categories: Vec<CategoryItem> = db::report::get_category_totals(
+        &connection,
+        &ledger,
+        &month_start,
+        &today,
+        Some(TOP_CATEGORIES),
+    )
+    .map_err(error::ErrorInternalServerError)?
+    .into_iter()
+    .map(|item| CategoryItem {
+        percentage: percentage(item.amount, total.amount),
+        name: item.category.name,
+        amount: item.amount,
+    })
+    .collect();
+    let latest_expenses: Vec<ExpenseItem> =
+        db::report::get_latest_expenses(&connection, &ledger, LATEST_EXPENSES)
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|(expense, category)| ExpenseItem {
+                category: category.name,
+                expense,
+            })
+            .collect();
+    let forecast = db::forecast::get_forecast(&connection, &ledger, today, 0)
+        .map_err(error::ErrorInternalServerError)?;
+    let goals = db::goal::get_progress(&connection, &ledger, today)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut context = get_tera_context(template.translate("dashboard-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("can_edit", &role.can_edit());
+    context.insert("month", &month_start);
+    context.insert("total", &total);
+    context.insert("top_categories", &top_categories);
+    context.insert("latest_expenses", &latest_expenses);
+    context.insert("forecast", &forecast);
+    context.insert(
+        "above_average",
+        &(forecast.history_months > 0 && forecast.month_end > forecast.average),
+    );
+    context.insert("over_budget", &forecast.over_budget());
+    context.insert("goals", &goals);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("dashboard.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Returns the share of the given amount in the total as a whole percentage. If nothing has been
+// spent the share is 0.
+fn percentage(amount: Decimal, total: Decimal) -> u32 {
+    (amount * Decimal::new(100, 0))
+        .checked_div(total)
+        .unwrap_or_else(Decimal::zero)
+        .floor()
+        .to_u32()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests super::percentage().
+    #[test]
+    fn test_percentage() {
+        assert_eq!(25, percentage(Decimal::new(2599, 2), Decimal::new(100, 0)));
+        assert_eq!(100, percentage(Decimal::new(100, 0), Decimal::new(100, 0)));
+        assert_eq!(0, percentage(Decimal::new(100, 0), Decimal::zero()));
+        assert_eq!(0, percentage(Decimal::zero(), Decimal::zero()));
+    }
+}
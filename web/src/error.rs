@@ -1,13 +1,12 @@
 use crate::get_tera_context;
+use crate::i18n::Templates;
 use actix_http::body::{Body, ResponseBody};
 use actix_http::Response;
 use actix_identity::RequestIdentity;
 use actix_web::dev::ServiceResponse;
 use actix_web::http::StatusCode;
 use actix_web::middleware::errhandlers::{ErrorHandlerResponse, ErrorHandlers};
-use actix_web::web::Data;
 use actix_web::Result;
-use tera::Tera;
 
 /// Custom error handlers that show error messages as HTML pages.
 pub fn error_handlers() -> ErrorHandlers<Body> {
@@ -22,9 +21,9 @@ pub fn error_handlers() -> ErrorHandlers<Body> {
 fn not_found<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
     let response = get_response(
         &res,
-        "Page not found",
-        "Sorry, this page does not exist",
-        Some("We can't seem to find the page you're looking for."),
+        "error-not-found-title",
+        "error-not-found-message",
+        Some("error-not-found-explanation"),
     );
     Ok(ErrorHandlerResponse::Response(
         res.into_response(response.into_body()),
@@ -36,7 +35,7 @@ fn not_found<B>(res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
 #[allow(clippy::unnecessary_wraps)]
 fn forbidden(res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
     let resp = res.response();
-    let default_message = "Please log in and try again";
+    let default_message = "error-access-denied-message";
     let message = if let ResponseBody::Body(body) = resp.body() {
         // Convert the response in Bytes to a string slice.
         match body {
@@ -47,7 +46,7 @@ fn forbidden(res: ServiceResponse<Body>) -> Result<ErrorHandlerResponse<Body>> {
         default_message
     };
 
-    let response = get_response(&res, "Access denied", message, None);
+    let response = get_response(&res, "error-access-denied-title", message, None);
     Ok(ErrorHandlerResponse::Response(
         res.into_response(response.into_body()),
     ))
@@ -72,24 +71,25 @@ fn get_response<B>(
             .body(m.to_string())
     };
 
-    // Render the error page or fall back to a simple text message if Tera is not available.
-    let tera = request.app_data::<Data<Tera>>().map(|t| t.get_ref());
-    match tera {
-        Some(tera) => {
-            let mut context = get_tera_context(title, identity);
+    // Render the error page in the language of the request, or fall back to a simple text message
+    // if the templates are not available.
+    match Templates::for_request(request) {
+        Ok(template) => {
+            let message = template.translate(message);
+            let mut context = get_tera_context(template.translate(title).as_str(), identity);
             context.insert("body_classes", &vec!["error"]);
-            context.insert("message", message);
-            context.insert("explanation", &explanation);
+            context.insert("message", &message);
+            context.insert("explanation", &explanation.map(|e| template.translate(e)));
             context.insert("status_code", res.status().as_str());
-            let content = tera.render("error.html", &context);
+            let content = template.render("error.html", &context);
 
             match content {
                 Ok(content) => Response::build(res.status())
                     .content_type("text/html")
                     .body(content),
-                Err(_) => fallback(message),
+                Err(_) => fallback(message.as_str()),
             }
         }
-        None => fallback(message),
+        Err(_) => fallback(message),
     }
 }
@@ -1,16 +1,25 @@
-use super::{assert_authenticated, get_tera_context};
+use super::{
+    assert_authenticated, get_connection_and_user, get_preferences, get_tera_context,
+    insert_preferences,
+};
 use crate::category::CategoryDropdownItems;
+use crate::ledger::get_current_ledger;
 
 use crate::bootstrap_components::{Alert, AlertType};
+use crate::i18n::{Templates, Translator};
 use actix_identity::Identity;
+use actix_session::Session;
 use actix_web::{error, web, Error, HttpResponse};
 use chrono::Utc;
 use db::category::{get_categories_tree, Category};
-use db::expense::create;
+use db::expense::{create, Expense};
+use db::expense_share::{ExpenseShareErrorKind, SplitMethod};
+use db::ledger::Ledger;
 use db::user::User;
+use db::user_preference::UserPreferences;
 use diesel::PgConnection;
 use rust_decimal::Decimal;
-use std::str::FromStr;
+use std::collections::HashMap;
 
 // The POST data of the add expense form.
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -18,6 +27,15 @@ pub struct AddForm {
     amount: String,
     category: String,
     date: String,
+    // An optional description of the expense.
+    #[serde(default)]
+    description: String,
+    // The ID of the user that paid the expense. Defaults to the current user.
+    #[serde(default)]
+    paid_by: String,
+    // Set to "equal" to split the expense equally among the members of the ledger.
+    #[serde(default)]
+    shared: String,
 }
 
 impl AddForm {
@@ -26,17 +44,23 @@ impl AddForm {
             amount: amount.to_string(),
             category: category.to_string(),
             date: date.to_string(),
+            description: "".to_string(),
+            paid_by: "".to_string(),
+            shared: "".to_string(),
         }
     }
 
     // Resets the form input so it is ready for entering the next expense. This is intended to be
-    // called after successfully saving an expense. The date and category are kept intact so that
-    // multiple related expenses can be entered conveniently.
+    // called after successfully saving an expense. The date, category and sharing options are kept
+    // intact so that multiple related expenses can be entered conveniently.
     pub fn reset(&self) -> AddForm {
         AddForm {
             amount: "".to_string(),
             category: self.category.clone(),
             date: self.date.clone(),
+            description: "".to_string(),
+            paid_by: self.paid_by.clone(),
+            shared: self.shared.clone(),
         }
     }
 }
@@ -81,21 +105,35 @@ impl AddFormValidation {
         }
     }
 
-    // Validates the add expense form.
-    pub fn validate(input: &AddForm, user: &User, connection: &PgConnection) -> AddFormValidation {
+    // Validates the add expense form. The category should belong to the given ledger. The amount
+    // and date are expected in the number and date format of the user. The validation messages
+    // are translated with the given translator.
+    pub fn validate(
+        input: &AddForm,
+        ledger: &Ledger,
+        preferences: &UserPreferences,
+        translator: &Translator,
+        connection: &PgConnection,
+    ) -> AddFormValidation {
         let mut validation_state = AddFormValidation::default();
 
         // Validate the amount.
         if input.amount.is_empty() {
-            validation_state.amount = Err("Please enter an amount.".to_string());
+            validation_state.amount = Err(translator.translate("expense-amount-required"));
         } else {
-            validation_state.amount = match Decimal::from_str(input.amount.as_str()) {
-                Err(_) => Err("Amount should be in the format '149.99'.".to_string()),
+            validation_state.amount = match preferences.parse_amount(input.amount.as_str()) {
+                Err(_) => Err(translator.translate_with(
+                    "expense-amount-format",
+                    &[(
+                        "example",
+                        preferences.locale.format_number(Decimal::new(14999, 2)),
+                    )],
+                )),
                 Ok(amount) if amount < Decimal::new(1, 2) => {
-                    Err("Amount should be 0.01 or greater.".to_string())
+                    Err(translator.translate("expense-amount-too-small"))
                 }
                 Ok(amount) if amount > Decimal::new(999_999_999, 2) => {
-                    Err("Amount should be 9999999.99 or smaller.".to_string())
+                    Err(translator.translate("expense-amount-too-large"))
                 }
                 Ok(amount) => Ok(amount),
             }
@@ -103,26 +141,27 @@ impl AddFormValidation {
 
         // Validate the category.
         if input.category.is_empty() {
-            validation_state.category = Err("Please choose a category.".to_string());
+            validation_state.category = Err(translator.translate("expense-category-required"));
         } else {
             validation_state.category = match input.category.parse::<i32>() {
-                Err(_) => Err("Invalid category ID.".to_string()),
-                Ok(id) => match db::category::read(connection, id, Some(user.id)) {
-                    Some(cat) if cat.user_id == user.id => Ok(cat),
-                    _ => Err("Unknown category.".to_string()),
+                Err(_) => Err(translator.translate("expense-category-invalid")),
+                Ok(id) => match db::category::read(connection, id, None) {
+                    Some(cat) if cat.ledger_id == ledger.id => Ok(cat),
+                    _ => Err(translator.translate("expense-category-unknown")),
                 },
             }
         }
 
         // Validate the date.
         if input.date.is_empty() {
-            validation_state.date = Err("Please pick a date.".to_string());
+            validation_state.date = Err(translator.translate("expense-date-required"));
         } else {
-            validation_state.date =
-                match chrono::NaiveDate::parse_from_str(input.date.as_str(), "%Y-%m-%d") {
-                    Err(_) => Err("Date should be in the format YYYY-MM-DD.".to_string()),
-                    Ok(date) => Ok(date),
-                }
+            validation_state.date = preferences.parse_date(input.date.as_str()).map_err(|_| {
+                translator.translate_with(
+                    "expense-date-format",
+                    &[("format", preferences.date_format.placeholder().to_string())],
+                )
+            })
         }
 
         validation_state.form_is_validated = true;
@@ -142,14 +181,56 @@ impl AddFormValidation {
     }
 }
 
-// Request handler for the expenses overview.
+// The query string of the expenses overview.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct OverviewQuery {
+    #[serde(default)]
+    q: String,
+}
+
+// An expense, as shown in the expense listing.
+#[derive(Serialize)]
+struct ExpenseItem {
+    category: String,
+    expense: Expense,
+}
+
+// Request handler for the expenses overview. If a search query is passed the matching expenses in
+// the current ledger are listed.
 pub async fn overview_handler(
     id: Identity,
-    template: web::Data<tera::Tera>,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    query: web::Query<OverviewQuery>,
 ) -> Result<HttpResponse, Error> {
-    assert_authenticated(&id)?;
-
-    let context = get_tera_context("Expenses", id);
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let expenses = db::expense::search(&connection, query.q.as_str(), Some(&ledger))
+        .map_err(error::ErrorInternalServerError)?;
+    let categories: HashMap<i32, String> = db::category::get_categories(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|category| (category.id, category.name))
+        .collect();
+    let expenses: Vec<ExpenseItem> = expenses
+        .into_iter()
+        .map(|expense| ExpenseItem {
+            category: categories
+                .get(&expense.category_id)
+                .cloned()
+                .unwrap_or_default(),
+            expense,
+        })
+        .collect();
+
+    let mut context = get_tera_context(template.translate("expenses-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("query", &query.q);
+    context.insert("expenses", &expenses);
+    insert_preferences(&mut context, &preferences);
 
     let content = template
         .render("expenses/overview.html", &context)
@@ -160,22 +241,28 @@ pub async fn overview_handler(
 // GET request handler for the form to add an expense.
 pub async fn add_handler(
     id: Identity,
+    session: Session,
     pool: web::Data<db::ConnectionPool>,
-    template: web::Data<tera::Tera>,
+    template: Templates,
 ) -> Result<HttpResponse, Error> {
-    let today = Utc::now().naive_utc().date().format("%Y-%m-%d").to_string();
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let preferences = get_preferences(&connection, &user)?;
+    drop(connection);
+
+    let today = preferences.format_date(Utc::now().naive_utc().date());
     let input = AddForm::new("", "", today.as_str());
     let validation_state = AddFormValidation::default();
     let alerts = vec![];
 
-    render_add(id, pool, template, input, validation_state, alerts)
+    render_add(id, session, pool, template, input, validation_state, alerts)
 }
 
 // POST Submit handler for the form to add an expense.
 pub async fn add_submit(
     id: Identity,
+    session: Session,
     pool: web::Data<db::ConnectionPool>,
-    template: web::Data<tera::Tera>,
+    template: Templates,
     input: web::Form<AddForm>,
 ) -> Result<HttpResponse, Error> {
     let email = assert_authenticated(&id)?;
@@ -184,8 +271,17 @@ pub async fn add_submit(
     let user =
         db::user::read(&connection, email.as_str()).map_err(error::ErrorInternalServerError)?;
 
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
     let input = input.into_inner();
-    let validation_state = AddFormValidation::validate(&input, &user, &connection);
+    let validation_state = AddFormValidation::validate(
+        &input,
+        &ledger,
+        &preferences,
+        template.translator(),
+        &connection,
+    );
 
     // Create the expense if the form validates and return a success or failure alert. If the form
     // doesn't validate, don't set an alert since the user will already be notified about invalid
@@ -198,8 +294,8 @@ pub async fn add_submit(
     ) {
         (true, Ok(amount), Ok(category), Ok(date)) => {
             let (input, validation_state, alert) =
-                match create(&connection, &user, amount, category, None, Some(date)) {
-                    Ok(_) => {
+                match create_expense(&connection, &user, &ledger, &input, amount, category, date) {
+                    Ok(shared_with) => {
                         (
                             // The expense was saved successfully. Reset the form state so the next
                             // expense can be entered. Keep the date and category intact so that
@@ -208,63 +304,126 @@ pub async fn add_submit(
                             validation_state.reset(),
                             Alert {
                                 alert_type: AlertType::Success,
-                                message: format!(
-                                    "Successfully added €{:.2} expense to the {} category.",
-                                    amount, category.name
-                                ),
+                                message: match shared_with {
+                                    0 => template.translate_with(
+                                        "expense-added",
+                                        &[
+                                            ("amount", preferences.format_amount(*amount)),
+                                            ("category", category.name.clone()),
+                                        ],
+                                    ),
+                                    n => template.translate_with(
+                                        "expense-added-shared",
+                                        &[
+                                            ("amount", preferences.format_amount(*amount)),
+                                            ("category", category.name.clone()),
+                                            ("members", n.to_string()),
+                                        ],
+                                    ),
+                                },
                             },
                         )
                     }
-                    Err(e) => (
-                        input,
-                        validation_state,
-                        Alert {
-                            alert_type: AlertType::Danger,
-                            message: format!("Error: {}", e),
-                        },
-                    ),
+                    Err(e) => (input, validation_state, Alert::danger(e)),
                 };
             (input, validation_state, vec![alert])
         }
         _ => (input, validation_state, vec![]),
     };
 
-    let input = AddForm::new("", input.category.as_str(), input.date.as_str());
+    let input = input.reset();
+    drop(connection);
+
+    render_add(id, session, pool, template, input, validation_state, alerts)
+}
+
+// Creates the expense from the validated form input. If the expense is shared it is split equally
+// among the members of the ledger. Returns the number of members the expense is shared with.
+fn create_expense(
+    connection: &PgConnection,
+    user: &User,
+    ledger: &Ledger,
+    input: &AddForm,
+    amount: &Decimal,
+    category: &Category,
+    date: &chrono::NaiveDate,
+) -> Result<usize, String> {
+    let members = db::ledger::get_members(connection, ledger).map_err(|e| e.to_string())?;
+
+    // Check that the payer is a member before creating the expense.
+    let paid_by = input.paid_by.parse::<i32>().unwrap_or(user.id);
+    if !members.iter().any(|(member, _)| member.id == paid_by) {
+        return Err(ExpenseShareErrorKind::NotAMember(paid_by).to_string());
+    }
 
-    render_add(id, pool, template, input, validation_state, alerts)
+    let description = Some(input.description.trim()).filter(|d| !d.is_empty());
+    let expense = create(connection, user, amount, category, description, Some(date))
+        .map_err(|e| e.to_string())?;
+
+    if input.shared != "equal" {
+        return Ok(0);
+    }
+    let participants: Vec<(i32, Option<Decimal>)> = members
+        .iter()
+        .map(|(member, _)| (member.id, None))
+        .collect();
+    db::expense_share::split(
+        connection,
+        user,
+        &expense,
+        paid_by,
+        SplitMethod::Equal,
+        &participants,
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(participants.len())
 }
 
 // Renders the form to add an expense. Used by both GET and POST requests.
 fn render_add(
     id: Identity,
+    session: Session,
     pool: web::Data<db::ConnectionPool>,
-    template: web::Data<tera::Tera>,
+    template: Templates,
     input: AddForm,
     validation_state: AddFormValidation,
     alerts: Vec<Alert>,
 ) -> Result<HttpResponse, Error> {
     let email = assert_authenticated(&id)?;
 
-    // Retrieve the categories for the current user.
+    // Retrieve the categories of the ledger the user is currently working in.
     let connection = pool.get().map_err(error::ErrorInternalServerError)?;
     let user =
         db::user::read(&connection, email.as_str()).map_err(error::ErrorInternalServerError)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
     let categories =
-        get_categories_tree(&connection, &user).map_err(error::ErrorInternalServerError)?;
+        get_categories_tree(&connection, &ledger).map_err(error::ErrorInternalServerError)?;
 
     let categories_dropdown_items = CategoryDropdownItems::from(categories);
+    let members: Vec<(i32, String)> = db::ledger::get_members(&connection, &ledger)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|(member, _)| (member.id, member.email))
+        .collect();
 
     // Convert the category provided by the form input to an integer so we can select the chosen
     // category in the dropdown. Tera cannot compare two values of different types and doesn't
     // support type casting
     let current_category_id: Option<i32> = input.category.parse().ok();
+    let current_paid_by: i32 = input.paid_by.parse().unwrap_or(user.id);
 
-    let mut context = get_tera_context("Add expense", id);
+    let mut context = get_tera_context(template.translate("expense-add-title").as_str(), id);
     context.insert("input", &input);
     context.insert("validation", &validation_state);
     context.insert("categories", &categories_dropdown_items.items);
     context.insert("current_category_id", &current_category_id);
+    context.insert("members", &members);
+    context.insert("current_paid_by", &current_paid_by);
+    context.insert("ledger", &ledger);
     context.insert("alerts", &alerts);
+    insert_preferences(&mut context, &preferences);
 
     let content = template
         .render("expenses/add.html", &context)
@@ -276,8 +435,10 @@ fn render_add(
 mod tests {
     use super::*;
     use crate::get_database_url;
+    use crate::i18n::Translations;
     use app::AppConfig;
     use db::db_test::create_test_user;
+    use db::user_preference::Language;
     use diesel::Connection;
 
     // Tests UserFormInputValid::validate() and ::is_valid().
@@ -313,11 +474,15 @@ mod tests {
         let user = create_test_user(&conn, &config);
 
         conn.test_transaction::<_, Error, _>(|| {
+            let ledger = db::ledger::get_default(&conn, &user).unwrap();
+            let preferences = UserPreferences::defaults(user.id);
+            let translator = Translator::new(Translations::load(), Language::En);
             for test_case in &test_cases {
                 let input = &test_case.0;
                 let expected_validate_result = &test_case.1;
                 let expected_is_valid_result = test_case.2;
-                let actual_validate_result = AddFormValidation::validate(input, &user, &conn);
+                let actual_validate_result =
+                    AddFormValidation::validate(input, &ledger, &preferences, &translator, &conn);
                 assert_eq!(
                     expected_validate_result.amount,
                     actual_validate_result.amount
@@ -357,8 +522,12 @@ mod tests {
         let user = create_test_user(&conn, &config);
 
         conn.test_transaction::<_, Error, _>(|| {
+            let ledger = db::ledger::get_default(&conn, &user).unwrap();
+            let preferences = UserPreferences::defaults(user.id);
+            let translator = Translator::new(Translations::load(), Language::En);
             for input in &test_cases {
-                let actual_validate_result = AddFormValidation::validate(input, &user, &conn);
+                let actual_validate_result =
+                    AddFormValidation::validate(input, &ledger, &preferences, &translator, &conn);
                 assert_eq!(
                     Err("Amount should be in the format '149.99'.".to_string()),
                     actual_validate_result.amount
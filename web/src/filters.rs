@@ -0,0 +1,96 @@
+use chrono::NaiveDate;
+use db::user_preference::UserPreferences;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use tera::Value;
+
+// Registers the filters that format values according to the preferences of the user.
+pub fn register(tera: &mut tera::Tera) {
+    tera.register_filter("amount", amount);
+    tera.register_filter("local_date", local_date);
+}
+
+// Formats an amount in the currency and number format of the user. The preferences should be
+// passed in the `preferences` argument, e.g. `{{ total | amount(preferences=preferences) }}`.
+fn amount(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let preferences = get_preferences("amount", args)?;
+    let amount = match value {
+        Value::String(amount) => Decimal::from_str(amount).ok(),
+        Value::Number(amount) => Decimal::from_str(&amount.to_string()).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `amount` received an invalid amount: {}",
+            value
+        ))
+    })?;
+    Ok(Value::String(preferences.format_amount(amount)))
+}
+
+// Formats a date in the date format of the user. The preferences should be passed in the
+// `preferences` argument, e.g. `{{ expense.date | local_date(preferences=preferences) }}`.
+fn local_date(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let preferences = get_preferences("local_date", args)?;
+    let date = value
+        .as_str()
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .ok_or_else(|| {
+            tera::Error::msg(format!(
+                "Filter `local_date` received an invalid date: {}",
+                value
+            ))
+        })?;
+    Ok(Value::String(preferences.format_date(date)))
+}
+
+// Returns the user preferences that are passed to the given filter.
+fn get_preferences(filter: &str, args: &HashMap<String, Value>) -> tera::Result<UserPreferences> {
+    let preferences = args.get("preferences").ok_or_else(|| {
+        tera::Error::msg(format!(
+            "Filter `{}` expected an argument `preferences`",
+            filter
+        ))
+    })?;
+    serde_json::from_value(preferences.clone()).map_err(|e| {
+        tera::Error::msg(format!(
+            "Filter `{}` received invalid preferences: {}",
+            filter, e
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use db::user_preference::{DateFormat, Locale};
+
+    // Tests super::amount() and super::local_date().
+    #[test]
+    fn test_filters() {
+        let mut tera = tera::Tera::default();
+        register(&mut tera);
+
+        let preferences = db::user_preference::UserPreferences {
+            locale: Locale::Nl,
+            date_format: DateFormat::DayMonthYear,
+            ..db::user_preference::UserPreferences::defaults(1)
+        };
+        let mut context = tera::Context::new();
+        context.insert("preferences", &preferences);
+        context.insert("total", &Decimal::new(123_456, 2));
+        context.insert("date", &NaiveDate::from_ymd(2021, 5, 16));
+
+        let result = tera
+            .render_str(
+                "{{ total | amount(preferences=preferences) }} {{ date | local_date(preferences=preferences) }}",
+                &context,
+            )
+            .unwrap();
+        assert_eq!("€ 1.234,56 16/05/2021", result);
+
+        // The preferences are required.
+        assert!(tera.render_str("{{ total | amount }}", &context).is_err());
+    }
+}
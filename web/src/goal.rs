@@ -0,0 +1,172 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context, insert_preferences};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use crate::ledger::get_current_ledger;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::Utc;
+use db::goal::GoalErrorKind;
+use db::ledger::LedgerErrorKind;
+use db::user_preference::UserPreferences;
+use rust_decimal::Decimal;
+
+// The POST data of the form to create a savings goal.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AddForm {
+    name: String,
+    target_amount: String,
+    target_date: String,
+}
+
+// The POST data of the form to contribute to a savings goal.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContributeForm {
+    goal: i32,
+    amount: String,
+    #[serde(default)]
+    description: String,
+}
+
+// The POST data of the form to delete a savings goal.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct DeleteForm {
+    goal: i32,
+}
+
+// Request handler for the savings goals overview.
+pub async fn overview_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_overview(id, session, pool, template, vec![])
+}
+
+// Submit handler for the form to create a savings goal in the current ledger.
+pub async fn add_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<AddForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let amount = parse_amount(&preferences, input.target_amount.as_str());
+    // The target date is entered in a native date input, which always uses the ISO format.
+    let date = chrono::NaiveDate::parse_from_str(input.target_date.as_str(), "%Y-%m-%d")
+        .map_err(|_| "Date should be in the format YYYY-MM-DD.".to_string());
+
+    let alert = match (amount, date) {
+        (Err(e), _) | (_, Err(e)) => Alert::danger(e),
+        (Ok(amount), Ok(date)) => {
+            match db::goal::create(&connection, &user, &ledger, &input.name, &amount, &date) {
+                Ok(goal) => Alert::success(format!("Created the {} goal.", goal.name)),
+                Err(e) => Alert::danger(e),
+            }
+        }
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to contribute to a savings goal.
+pub async fn contribute_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<ContributeForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let alert = match (
+        db::goal::read(&connection, input.goal, Some(user.id)),
+        parse_amount(&preferences, input.amount.as_str()),
+    ) {
+        (None, _) => Alert::danger(GoalErrorKind::NotFound(input.goal)),
+        (_, Err(e)) => Alert::danger(e),
+        (Some(goal), Ok(amount)) => {
+            let description = Some(input.description.trim()).filter(|d| !d.is_empty());
+            match db::goal::add_contribution(&connection, &user, &goal, &amount, description, None)
+            {
+                Ok(contribution) => Alert::success(format!(
+                    "Added {} to the {} goal.",
+                    preferences.format_amount(contribution.amount),
+                    goal.name
+                )),
+                Err(e) => Alert::danger(e),
+            }
+        }
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to delete a savings goal.
+pub async fn delete_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<DeleteForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::goal::read(&connection, input.goal, Some(user.id)) {
+        None => Alert::danger(GoalErrorKind::NotFound(input.goal)),
+        Some(goal) => match db::ledger::get_role(&connection, goal.ledger_id, user.id) {
+            Ok(Some(role)) if role.can_edit() => match db::goal::delete(&connection, goal.id) {
+                Ok(_) => Alert::success(format!("Deleted the {} goal.", goal.name)),
+                Err(e) => Alert::danger(e),
+            },
+            _ => Alert::danger(LedgerErrorKind::InsufficientPermissions(goal.ledger_id)),
+        },
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Renders the savings goals overview. Used by both GET and POST requests.
+fn render_overview(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, role) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let today = Utc::now().naive_utc().date();
+    let goals = db::goal::get_progress(&connection, &ledger, today)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut context = get_tera_context(template.translate("goals-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("can_edit", &role.can_edit());
+    context.insert("goals", &goals);
+    context.insert("alerts", &alerts);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("goals/overview.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Parses an amount entered in a form, in the number format of the user.
+fn parse_amount(preferences: &UserPreferences, amount: &str) -> Result<Decimal, String> {
+    preferences.parse_amount(amount.trim()).map_err(|_| {
+        format!(
+            "Amount should be in the format '{}'.",
+            preferences.locale.format_number(Decimal::new(14999, 2))
+        )
+    })
+}
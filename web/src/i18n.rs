@@ -0,0 +1,355 @@
+use actix_identity::RequestIdentity;
+use actix_web::dev::Payload;
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{error, web, Error, FromRequest, HttpRequest};
+use db::user_preference::Language;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+use futures::future::{ready, Ready};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tera::Value;
+use unic_langid::LanguageIdentifier;
+
+// The language that is used when the browser does not request one of the supported languages. This
+// is also the fallback for messages that are missing from the catalog of another language.
+const DEFAULT_LANGUAGE: Language = Language::En;
+
+// Returns the translation catalog of the given language, in the Fluent format.
+fn get_catalog(language: Language) -> &'static str {
+    match language {
+        Language::En => include_str!("../locales/en/main.ftl"),
+        Language::Nl => include_str!("../locales/nl/main.ftl"),
+    }
+}
+
+/// The translation catalogs of all supported languages.
+#[derive(Clone)]
+pub struct Translations {
+    bundles: Arc<HashMap<Language, FluentBundle<FluentResource>>>,
+}
+
+impl Translations {
+    /// Loads the translation catalogs. Panics if a catalog contains syntax errors.
+    pub fn load() -> Translations {
+        let bundles = Language::ALL
+            .iter()
+            .map(|language| {
+                let resource = FluentResource::try_new(get_catalog(*language).to_string())
+                    .unwrap_or_else(|(_, errors)| {
+                        panic!("Invalid translation catalog {}: {:?}", language, errors)
+                    });
+                let mut bundle = FluentBundle::new_concurrent(vec![get_language_id(*language)]);
+                // Don't wrap placeables in Unicode isolation marks, these end up in the HTML.
+                bundle.set_use_isolating(false);
+                bundle.add_resource(resource).unwrap_or_else(|errors| {
+                    panic!("Invalid translation catalog {}: {:?}", language, errors)
+                });
+                (*language, bundle)
+            })
+            .collect();
+
+        Translations {
+            bundles: Arc::new(bundles),
+        }
+    }
+
+    /// Returns the message with the given ID in the given language. If the message is missing from
+    /// the catalog it is taken from the default language. If it doesn't exist at all the ID is
+    /// returned, so that text that is not in the catalogs is shown as-is.
+    pub fn translate(&self, language: Language, id: &str, args: Option<&FluentArgs>) -> String {
+        [language, DEFAULT_LANGUAGE]
+            .iter()
+            .filter_map(|language| self.bundles.get(language))
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = vec![];
+                Some(
+                    bundle
+                        .format_pattern(pattern, args, &mut errors)
+                        .to_string(),
+                )
+            })
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+/// Translates messages into the language of the current request.
+#[derive(Clone)]
+pub struct Translator {
+    translations: Translations,
+    language: Language,
+}
+
+impl Translator {
+    pub fn new(translations: Translations, language: Language) -> Translator {
+        Translator {
+            translations,
+            language,
+        }
+    }
+
+    /// Returns the language that messages are translated into.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// Returns the message with the given ID.
+    pub fn translate(&self, id: &str) -> String {
+        self.translations.translate(self.language, id, None)
+    }
+
+    /// Returns the message with the given ID, filling in the given variables.
+    pub fn translate_with(&self, id: &str, variables: &[(&str, String)]) -> String {
+        let mut args = FluentArgs::new();
+        for (name, value) in variables {
+            args.set(*name, FluentValue::from(value.as_str()));
+        }
+        self.translations.translate(self.language, id, Some(&args))
+    }
+}
+
+/// The templates, rendered in the language of the current request. This is used in route handlers
+/// instead of the Tera instance, and makes the `lang` variable available to the templates.
+#[derive(Clone)]
+pub struct Templates {
+    tera: web::Data<tera::Tera>,
+    translator: Translator,
+}
+
+impl Templates {
+    /// Returns the templates for the given request. The language is negotiated from the preferences
+    /// of the user and the `Accept-Language` header.
+    pub fn for_request(req: &HttpRequest) -> Result<Templates, Error> {
+        let tera = req
+            .app_data::<web::Data<tera::Tera>>()
+            .cloned()
+            .ok_or_else(|| error::ErrorInternalServerError("Templates are not configured."))?;
+        let translations = req
+            .app_data::<web::Data<Translations>>()
+            .ok_or_else(|| error::ErrorInternalServerError("Translations are not configured."))?;
+
+        Ok(Templates {
+            tera,
+            translator: Translator::new(translations.get_ref().clone(), negotiate(req)),
+        })
+    }
+
+    /// Renders the given template in the language of the request.
+    pub fn render(&self, name: &str, context: &tera::Context) -> tera::Result<String> {
+        let mut context = context.clone();
+        context.insert("lang", &self.translator.language());
+        self.tera.render(name, &context)
+    }
+
+    /// Returns the translator for the language of the request.
+    pub fn translator(&self) -> &Translator {
+        &self.translator
+    }
+
+    /// Returns the message with the given ID in the language of the request.
+    pub fn translate(&self, id: &str) -> String {
+        self.translator.translate(id)
+    }
+
+    /// Returns the message with the given ID in the language of the request, filling in the given
+    /// variables.
+    pub fn translate_with(&self, id: &str, variables: &[(&str, String)]) -> String {
+        self.translator.translate_with(id, variables)
+    }
+}
+
+impl FromRequest for Templates {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Templates::for_request(req))
+    }
+}
+
+// Registers the `t()` function which looks up a message in the translation catalogs. The message ID
+// is passed in the `key` argument and the language in the `lang` argument. Any other arguments are
+// passed to the message as variables, e.g. `{{ t(key="expense-search-empty", lang=lang,
+// query=query) }}`.
+pub fn register(tera: &mut tera::Tera, translations: &Translations) {
+    let translations = translations.clone();
+    tera.register_function("t", move |args: &HashMap<String, Value>| {
+        let id = args
+            .get("key")
+            .and_then(Value::as_str)
+            .ok_or_else(|| tera::Error::msg("Function `t` expected a string argument `key`"))?;
+        let language = args
+            .get("lang")
+            .and_then(Value::as_str)
+            .and_then(|language| language.parse::<Language>().ok())
+            .ok_or_else(|| {
+                tera::Error::msg(format!(
+                    "Function `t` expected a supported language in the argument `lang` for {}",
+                    id
+                ))
+            })?;
+
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            let value = match value {
+                _ if name == "key" || name == "lang" => continue,
+                Value::String(value) => FluentValue::from(value.as_str()),
+                Value::Number(number) => match number.as_f64() {
+                    Some(number) => FluentValue::from(number),
+                    None => FluentValue::from(number.to_string()),
+                },
+                value => FluentValue::from(value.to_string()),
+            };
+            fluent_args.set(name.as_str(), value);
+        }
+
+        Ok(Value::String(translations.translate(
+            language,
+            id,
+            Some(&fluent_args),
+        )))
+    });
+}
+
+// Returns the language for the given request. The language chosen by the user in their preferences
+// takes precedence over the languages requested by the browser.
+fn negotiate(req: &HttpRequest) -> Language {
+    if let Some(language) = get_preferred_language(req) {
+        return language;
+    }
+
+    let accept_language = req
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|header| header.to_str().ok())
+        .unwrap_or_default();
+    negotiate_accept_language(accept_language)
+}
+
+// Returns the language the logged in user has chosen in their preferences, if any.
+fn get_preferred_language(req: &HttpRequest) -> Option<Language> {
+    let email = req.get_identity()?;
+    let pool = req.app_data::<web::Data<db::ConnectionPool>>()?;
+    let connection = pool.get().ok()?;
+    let user = db::user::read(&connection, email.as_str()).ok()?;
+    db::user_preference::read(&connection, &user).ok()?.language
+}
+
+// Returns the supported language that best matches the given `Accept-Language` header.
+fn negotiate_accept_language(accept_language: &str) -> Language {
+    let requested = accepted_languages::parse(accept_language);
+    let available: Vec<LanguageIdentifier> = Language::ALL
+        .iter()
+        .map(|language| get_language_id(*language))
+        .collect();
+    let default = get_language_id(DEFAULT_LANGUAGE);
+
+    negotiate_languages(
+        &requested,
+        &available,
+        Some(&default),
+        NegotiationStrategy::Lookup,
+    )
+    .first()
+    .and_then(|id| id.language.as_str().parse().ok())
+    .unwrap_or(DEFAULT_LANGUAGE)
+}
+
+// Returns the Unicode language identifier of the given language.
+fn get_language_id(language: Language) -> LanguageIdentifier {
+    language
+        .as_str()
+        .parse()
+        .expect("Languages have a valid language identifier.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // Tests that the catalogs of all languages contain the same messages.
+    #[test]
+    fn test_catalogs_complete() {
+        // Every message starts on a new line with its ID, followed by an equals sign.
+        let get_ids = |language: Language| -> HashSet<&str> {
+            get_catalog(language)
+                .lines()
+                .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+                .filter_map(|line| line.split(" =").next())
+                .collect()
+        };
+
+        let expected = get_ids(DEFAULT_LANGUAGE);
+        for language in Language::ALL.iter() {
+            assert_eq!(expected, get_ids(*language), "Catalog {}", language);
+        }
+    }
+
+    // Tests super::Translations::translate().
+    #[test]
+    fn test_translate() {
+        let translator = Translator::new(Translations::load(), Language::Nl);
+        assert_eq!("Uitgaven", translator.translate("nav-expenses"));
+        assert_eq!(
+            "Er is een nieuwe activatiecode verstuurd naar jane@example.com.",
+            translator.translate_with(
+                "activation-code-sent",
+                &[("email", "jane@example.com".to_string())]
+            )
+        );
+
+        // Text that is not in the catalogs is returned as-is.
+        assert_eq!("Not a message", translator.translate("Not a message"));
+    }
+
+    // Tests super::negotiate_accept_language().
+    #[test]
+    fn test_negotiate_accept_language() {
+        let test_cases = [
+            ("", Language::En),
+            ("nl", Language::Nl),
+            ("nl-BE,nl;q=0.9,en;q=0.8", Language::Nl),
+            ("fr-FR,fr;q=0.9,nl;q=0.8", Language::Nl),
+            ("en-US,en;q=0.9,nl;q=0.8", Language::En),
+            ("de-DE", Language::En),
+            ("invalid;;", Language::En),
+        ];
+        for (accept_language, expected) in test_cases.iter() {
+            assert_eq!(
+                *expected,
+                negotiate_accept_language(accept_language),
+                "Accept-Language: {}",
+                accept_language
+            );
+        }
+    }
+
+    // Tests the `t()` template function.
+    #[test]
+    fn test_template_function() {
+        let mut tera = tera::Tera::default();
+        register(&mut tera, &Translations::load());
+
+        let mut context = tera::Context::new();
+        context.insert("lang", &Language::Nl);
+        let result = tera
+            .render_str(
+                "{{ t(key=\"nav-expenses\", lang=lang) }}, {{ t(key=\"expense-search-empty\", lang=lang, query=\"pizza\") }}",
+                &context,
+            )
+            .unwrap();
+        assert_eq!(
+            "Uitgaven, Er zijn geen uitgaven gevonden voor \"pizza\".",
+            result
+        );
+
+        // The language is required.
+        assert!(tera
+            .render_str("{{ t(key=\"nav-expenses\") }}", &context)
+            .is_err());
+    }
+}
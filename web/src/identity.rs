@@ -0,0 +1,138 @@
+use actix_identity::{CookieIdentityPolicy, IdentityPolicy};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::{web, Error, HttpMessage, HttpRequest};
+use app::AppConfig;
+use futures::future::{ready, Ready};
+
+/// An identity policy that keeps track of the sessions of logged in users in the database, so they
+/// can be listed and revoked, and expire after the idle and absolute timeouts from the
+/// configuration.
+///
+/// The session cookie only contains a random session token. The identity that is exposed to route
+/// handlers through `actix_identity::Identity` is the email address of the user the session
+/// belongs to, so calling `Identity::remember()` with an email address starts a new session for
+/// that user, and `Identity::forget()` ends the current session.
+pub struct DatabaseIdentityPolicy {
+    cookie: CookieIdentityPolicy,
+}
+
+// The session token that was sent in the request, and whether it belongs to an active session.
+struct SessionToken {
+    token: String,
+    valid: bool,
+}
+
+// The ID of the active session of the current request.
+struct CurrentSession(i32);
+
+impl DatabaseIdentityPolicy {
+    /// Creates a new identity policy that stores the session token in the given cookie.
+    pub fn new(cookie: CookieIdentityPolicy) -> DatabaseIdentityPolicy {
+        DatabaseIdentityPolicy { cookie }
+    }
+
+    // Returns the email address of the user whose session token was sent in the request.
+    fn load_identity(&self, req: &mut ServiceRequest) -> Result<Option<String>, Error> {
+        let token = match self.cookie.from_request(req).into_inner()? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        let pool = get_app_data::<db::ConnectionPool>(req.app_data())?;
+        let config = get_app_data::<AppConfig>(req.app_data())?;
+        let connection = pool.get().map_err(ErrorInternalServerError)?;
+        let result = db::user_session::authenticate(&connection, token.as_str(), &config)
+            .map_err(ErrorInternalServerError)?;
+
+        let mut extensions = req.extensions_mut();
+        extensions.insert(SessionToken {
+            token,
+            valid: result.is_some(),
+        });
+        Ok(result.map(|(user_session, user)| {
+            extensions.insert(CurrentSession(user_session.id));
+            user.email
+        }))
+    }
+
+    // Starts or ends a session when the identity has changed.
+    fn store_identity<B>(
+        &self,
+        identity: Option<String>,
+        changed: bool,
+        res: &mut ServiceResponse<B>,
+    ) -> Result<(), Error> {
+        let req = res.request().clone();
+        let previous = req
+            .extensions()
+            .get::<SessionToken>()
+            .map(|t| (t.token.clone(), t.valid));
+
+        if !changed {
+            // Remove the cookie if it refers to a session that has expired or has been revoked.
+            if let Some((_, false)) = previous {
+                return self.cookie.to_response(None, true, res).into_inner();
+            }
+            return Ok(());
+        }
+
+        let pool = get_app_data::<db::ConnectionPool>(
+            req.app_data::<web::Data<db::ConnectionPool>>().cloned(),
+        )?;
+        let connection = pool.get().map_err(ErrorInternalServerError)?;
+
+        // End the previous session. A new session token is issued on every login so that a token
+        // which may have been exposed before the login cannot be used.
+        if let Some((token, _)) = previous {
+            db::user_session::delete_by_token(&connection, token.as_str())
+                .map_err(ErrorInternalServerError)?;
+        }
+
+        let token = match identity {
+            Some(email) => {
+                let user = db::user::read(&connection, email.as_str())
+                    .map_err(ErrorInternalServerError)?;
+                let ip_address = crate::get_ip_address(&req);
+                let user_agent = req
+                    .headers()
+                    .get("user-agent")
+                    .and_then(|ua| ua.to_str().ok());
+                let (_, token) =
+                    db::user_session::create(&connection, &user, ip_address.as_deref(), user_agent)
+                        .map_err(ErrorInternalServerError)?;
+                Some(token)
+            }
+            None => None,
+        };
+        self.cookie.to_response(token, true, res).into_inner()
+    }
+}
+
+impl IdentityPolicy for DatabaseIdentityPolicy {
+    type Future = Ready<Result<Option<String>, Error>>;
+    type ResponseFuture = Ready<Result<(), Error>>;
+
+    fn from_request(&self, req: &mut ServiceRequest) -> Self::Future {
+        ready(self.load_identity(req))
+    }
+
+    fn to_response<B>(
+        &self,
+        identity: Option<String>,
+        changed: bool,
+        res: &mut ServiceResponse<B>,
+    ) -> Self::ResponseFuture {
+        ready(self.store_identity(identity, changed, res))
+    }
+}
+
+/// Returns the ID of the session of the current request, if the user is logged in.
+pub fn get_current_session_id(req: &HttpRequest) -> Option<i32> {
+    req.extensions().get::<CurrentSession>().map(|s| s.0)
+}
+
+// Unwraps application data, returning an error if it has not been registered.
+fn get_app_data<T>(data: Option<web::Data<T>>) -> Result<web::Data<T>, Error> {
+    data.ok_or_else(|| ErrorInternalServerError("Application data is not configured."))
+}
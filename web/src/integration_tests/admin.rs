@@ -0,0 +1,304 @@
+use super::super::*;
+
+use actix_http::cookie::Cookie;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+
+// Integration tests for the user administration.
+#[actix_rt::test]
+async fn test_user_administration() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let admin = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let admin = db::user::set_admin(&pool.get().unwrap(), admin, true).unwrap();
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+
+    // Anonymous users and users that are not administrators don't have access.
+    let req = test::TestRequest::get().uri("/admin/users").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    let user_auth = log_in(&mut app, user.email.as_str()).await;
+    let req = test::TestRequest::get()
+        .uri("/admin/users")
+        .cookie(user_auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/delete")
+        .cookie(user_auth.clone())
+        .set_form(&admin::UserActionForm::new(admin.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    assert!(db::user::read(&pool.get().unwrap(), admin.email.as_str()).is_ok());
+
+    // Administrators can search the users by email address. The state of the activation code is
+    // shown for users that are not activated yet.
+    let admin_auth = log_in(&mut app, admin.email.as_str()).await;
+    let body = get_users_page(&mut app, &admin_auth, user.email.as_str()).await;
+    assert!(body.contains(user.email.as_str()));
+    assert!(!body.contains(admin.email.as_str()));
+    assert!(body.contains("Not activated"));
+
+    db::activation_code::get(&pool.get().unwrap(), &user).unwrap();
+    let body = get_users_page(&mut app, &admin_auth, user.email.as_str()).await;
+    assert!(body.contains("Valid until"));
+
+    // Administrators can activate an account. The activation code is discarded.
+    let req = test::TestRequest::post()
+        .uri("/admin/users/activate")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::UserActionForm::new(user.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!(
+        "The account of {} has been activated.",
+        user.email
+    )));
+    assert!(
+        db::user::read(&pool.get().unwrap(), user.email.as_str())
+            .unwrap()
+            .activated
+    );
+    assert!(db::activation_code::find(&pool.get().unwrap(), &user).is_none());
+
+    // Disabling an account ends the sessions of the user, and rejects their API tokens and any
+    // further login attempts. The account stays activated.
+    let (_, token) = db::api_token::create(
+        &pool.get().unwrap(),
+        &user,
+        "Script",
+        db::api_token::Scope::Read,
+        None,
+    )
+    .unwrap();
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .header("authorization", format!("Bearer {}", token))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+
+    let req = test::TestRequest::post()
+        .uri("/admin/users/disable")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::UserActionForm::new(user.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("The account of {} has been disabled.", user.email)));
+    let disabled_user = db::user::read(&pool.get().unwrap(), user.email.as_str()).unwrap();
+    assert!(disabled_user.disabled);
+    assert!(disabled_user.activated);
+    let body = get_users_page(&mut app, &admin_auth, user.email.as_str()).await;
+    assert!(body.contains("Disabled"));
+
+    let req = test::TestRequest::get()
+        .uri("/account")
+        .cookie(user_auth)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .header("authorization", format!("Bearer {}", token))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    let req = test::TestRequest::post()
+        .uri("/user/login")
+        .set_form(&user::UserForm::new(
+            user.email.clone(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    assert!(response.response().cookies().all(|c| c.name() != "auth"));
+    let body = get_response_body(response.response());
+    assert!(body.contains("This account has been disabled."));
+
+    // Enabling the account allows the user to log in and use their API tokens again.
+    let req = test::TestRequest::post()
+        .uri("/admin/users/enable")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::UserActionForm::new(user.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("The account of {} has been enabled.", user.email)));
+    assert!(
+        !db::user::read(&pool.get().unwrap(), user.email.as_str())
+            .unwrap()
+            .disabled
+    );
+    log_in(&mut app, user.email.as_str()).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .header("authorization", format!("Bearer {}", token))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+
+    // Administrators cannot disable or delete their own account.
+    for (action, message) in &[
+        ("disable", "You cannot disable your own account."),
+        ("delete", "You cannot delete your own account here."),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/admin/users/{}", action))
+            .cookie(admin_auth.clone())
+            .set_form(&admin::UserActionForm::new(admin.email.clone()))
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        let body = get_response_body(response.response());
+        assert!(body.contains(message));
+    }
+    assert!(db::user::read(&pool.get().unwrap(), admin.email.as_str()).is_ok());
+
+    // Administrators can delete other accounts. Ledgers that are shared with other members are
+    // passed on to them.
+    let connection = pool.get().unwrap();
+    let shared_ledger = db::ledger::get_default(&connection, &user).unwrap();
+    db::ledger::add_member(
+        &connection,
+        &shared_ledger,
+        &admin,
+        db::ledger::Role::Viewer,
+    )
+    .unwrap();
+    drop(connection);
+    let req = test::TestRequest::post()
+        .uri("/admin/users/delete")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::UserActionForm::new(user.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("The account of {} has been deleted.", user.email)));
+    assert!(db::user::read(&pool.get().unwrap(), user.email.as_str()).is_err());
+    assert_eq!(
+        Ok(Some(db::ledger::Role::Owner)),
+        db::ledger::get_role(&pool.get().unwrap(), shared_ledger.id, admin.id)
+    );
+    let body = get_users_page(&mut app, &admin_auth, user.email.as_str()).await;
+    assert!(body.contains("No users found."));
+}
+
+// Integration tests for the pagination of the user administration.
+#[actix_rt::test]
+async fn test_user_pagination() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let connection = pool.get().unwrap();
+    let admin = db::db_test::create_test_user(&connection, &config);
+    let admin = db::user::set_admin(&connection, admin, true).unwrap();
+    for i in 0..51 {
+        let email = format!("pagination-{:02}@example.com", i);
+        db::user::create(&connection, email.as_str(), "letmein", &config).unwrap();
+    }
+    drop(connection);
+    let admin_auth = log_in(&mut app, admin.email.as_str()).await;
+
+    // The first page shows 50 users.
+    let body = get_users_page(&mut app, &admin_auth, "pagination-").await;
+    assert!(body.contains("pagination-00@example.com"));
+    assert!(body.contains("pagination-49@example.com"));
+    assert!(!body.contains("pagination-50@example.com"));
+    assert!(body.contains("Page 1 of 2"));
+
+    // Pages outside of the range show the nearest page.
+    for (page, expected) in [
+        ("2", "Page 2 of 2"),
+        ("99", "Page 2 of 2"),
+        ("-1", "Page 1 of 2"),
+    ]
+    .iter()
+    {
+        let req = test::TestRequest::get()
+            .uri(&format!("/admin/users?q=pagination-&page={}", page))
+            .cookie(admin_auth.clone())
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_ok(response.response());
+        let body = get_response_body(response.response());
+        assert!(body.contains(expected), "page {}", page);
+        assert_eq!(*page != "-1", body.contains("pagination-50@example.com"));
+    }
+
+    // There is no pagination if all users fit on a single page.
+    let body = get_users_page(&mut app, &admin_auth, "pagination-5").await;
+    assert!(body.contains("pagination-50@example.com"));
+    assert!(!body.contains("user-pages"));
+}
+
+// Logs in the test user with the given email address. Returns the authentication cookie.
+pub async fn log_in<S, B>(app: &mut S, email: &str) -> Cookie<'static>
+where
+    S: Service<
+        Request = actix_http::Request,
+        Response = ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+{
+    let req = test::TestRequest::post()
+        .uri("/user/login")
+        .set_form(&user::UserForm::new(
+            email.to_string(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .unwrap()
+        .into_owned()
+}
+
+// Returns the body of the user administration page, searching for the given string.
+async fn get_users_page<S>(app: &mut S, auth: &Cookie<'static>, query: &str) -> String
+where
+    S: Service<
+        Request = actix_http::Request,
+        Response = ServiceResponse<actix_http::body::Body>,
+        Error = actix_web::Error,
+    >,
+{
+    let req = test::TestRequest::get()
+        .uri(&format!("/admin/users?q={}", query))
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    get_response_body(response.response())
+}
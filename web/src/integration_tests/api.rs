@@ -0,0 +1,364 @@
+use super::super::*;
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{test, App};
+use db::api_token::Scope;
+use serde_json::json;
+
+// Integration tests for authenticating API requests with personal API tokens.
+#[actix_rt::test]
+async fn test_api_authentication() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // Requests without a token are rejected.
+    let req = test::TestRequest::get().uri("/api/v1/user").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers().get("www-authenticate").unwrap(),
+        "Bearer"
+    );
+    let body = get_response_body(response.response());
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["error"], "An API token is required.");
+
+    // Invalid tokens are rejected.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .header("authorization", "Bearer ft_invalid")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // A valid token authenticates the user it belongs to.
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let (_, token) =
+        db::api_token::create(&pool.get().unwrap(), &user, "Script", Scope::Read, None).unwrap();
+    let req = test::TestRequest::get()
+        .uri("/api/v1/user")
+        .header("authorization", format!("Bearer {}", token))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["email"], user.email.as_str());
+    assert_eq!(json["scope"], "read");
+
+    // Unknown API endpoints return a JSON error.
+    let req = test::TestRequest::get()
+        .uri("/api/v1/unknown")
+        .header("authorization", format!("Bearer {}", token))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = get_response_body(response.response());
+    let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["error"], "Not found.");
+}
+
+// Integration tests for managing categories through the API.
+#[actix_rt::test]
+async fn test_api_categories() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let (_, token) =
+        db::api_token::create(&pool.get().unwrap(), &user, "App", Scope::Write, None).unwrap();
+    let (_, read_token) =
+        db::api_token::create(&pool.get().unwrap(), &user, "Viewer", Scope::Read, None).unwrap();
+
+    // Create a root category and a child category.
+    let req = api_request(Method::POST, "/api/v1/categories", &token)
+        .set_json(&json!({"name": "Food"}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let food = get_json(&response);
+    assert_eq!(food["name"], "Food");
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        format!("/api/v1/categories/{}", food["id"]).as_str()
+    );
+
+    let req = api_request(Method::POST, "/api/v1/categories", &token)
+        .set_json(&json!({"name": "Snacks", "parent_id": food["id"]}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let snacks = get_json(&response);
+    assert_eq!(snacks["parent_id"], food["id"]);
+
+    // Invalid requests return a JSON error with a matching status code.
+    let test_cases = vec![
+        (json!({"name": " "}), StatusCode::UNPROCESSABLE_ENTITY),
+        (json!({"description": "No name"}), StatusCode::BAD_REQUEST),
+        (
+            json!({"name": "Drinks", "parent_id": 0}),
+            StatusCode::NOT_FOUND,
+        ),
+        (
+            json!({"name": "Drinks", "ledger_id": 0}),
+            StatusCode::NOT_FOUND,
+        ),
+    ];
+    for (body, status) in test_cases {
+        let req = api_request(Method::POST, "/api/v1/categories", &token)
+            .set_json(&body)
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), status);
+        assert!(get_json(&response)["error"].is_string());
+    }
+
+    // Tokens with read access cannot make changes.
+    let req = api_request(Method::POST, "/api/v1/categories", &read_token)
+        .set_json(&json!({"name": "Drinks"}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // The categories can be retrieved as a flat list and as a tree.
+    let req = api_request(Method::GET, "/api/v1/categories", &read_token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    assert_eq!(2, get_json(&response).as_array().unwrap().len());
+
+    let req = api_request(Method::GET, "/api/v1/categories/tree", &read_token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let tree = get_json(&response);
+    assert_eq!(1, tree.as_array().unwrap().len());
+    assert_eq!(tree[0]["category"], food);
+    assert_eq!(tree[0]["children"][0]["category"], snacks);
+
+    // Update the child category.
+    let uri = format!("/api/v1/categories/{}", snacks["id"]);
+    let req = api_request(Method::PUT, &uri, &token)
+        .set_json(&json!({"name": "Sweets", "description": "Candy", "parent_id": food["id"]}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let req = api_request(Method::GET, &uri, &read_token).to_request();
+    let response = app.call(req).await.unwrap();
+    let sweets = get_json(&response);
+    assert_eq!(sweets["name"], "Sweets");
+    assert_eq!(sweets["description"], "Candy");
+
+    // A category cannot be moved inside its own child.
+    let food_uri = format!("/api/v1/categories/{}", food["id"]);
+    let req = api_request(Method::PUT, &food_uri, &token)
+        .set_json(&json!({"name": "Food", "parent_id": sweets["id"]}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Other users cannot access the categories.
+    let other_user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let (_, other_token) =
+        db::api_token::create(&pool.get().unwrap(), &other_user, "App", Scope::Write, None)
+            .unwrap();
+    let ledger_uri = format!("/api/v1/categories?ledger={}", food["ledger_id"]);
+    for (method, uri) in &[
+        (Method::GET, &uri),
+        (Method::DELETE, &uri),
+        (Method::GET, &ledger_uri),
+    ] {
+        let req = api_request(method.clone(), uri, &other_token).to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // Delete the child category.
+    let req = api_request(Method::DELETE, &uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let req = api_request(Method::GET, &uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Unsupported methods are rejected.
+    let req = api_request(Method::PATCH, &food_uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    // A category that has children cannot be deleted. This is checked last since the
+    // failing query aborts the test transaction.
+    let req = api_request(Method::POST, "/api/v1/categories", &token)
+        .set_json(&json!({"name": "Fruit", "parent_id": food["id"]}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let req = api_request(Method::DELETE, &food_uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+// Integration tests for managing expenses through the API.
+#[actix_rt::test]
+async fn test_api_expenses() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let category = db::db_test::create_test_category(&pool.get().unwrap(), &user);
+    let (_, token) =
+        db::api_token::create(&pool.get().unwrap(), &user, "App", Scope::Write, None).unwrap();
+
+    // Create an expense.
+    let req = api_request(Method::POST, "/api/v1/expenses", &token)
+        .set_json(&json!({
+            "amount": "12.50",
+            "category_id": category.id,
+            "description": "Milk",
+            "date": "2021-04-01"
+        }))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let expense = get_json(&response);
+    assert_eq!(expense["amount"], "12.50");
+    assert_eq!(expense["description"], "Milk");
+    assert_eq!(expense["date"], "2021-04-01");
+    let uri = format!("/api/v1/expenses/{}", expense["id"]);
+    assert_eq!(response.headers().get("location").unwrap(), uri.as_str());
+
+    // Invalid requests return a JSON error with a matching status code.
+    let test_cases = vec![
+        (
+            json!({"amount": "0", "category_id": category.id}),
+            StatusCode::UNPROCESSABLE_ENTITY,
+        ),
+        (
+            json!({"amount": "1", "category_id": 0}),
+            StatusCode::NOT_FOUND,
+        ),
+        (json!({"category_id": category.id}), StatusCode::BAD_REQUEST),
+    ];
+    for (body, status) in test_cases {
+        let req = api_request(Method::POST, "/api/v1/expenses", &token)
+            .set_json(&body)
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), status);
+        assert!(get_json(&response)["error"].is_string());
+    }
+
+    // Add 2 more expenses and page through the listing. The newest expenses come first.
+    for date in &["2021-04-02", "2021-03-31"] {
+        let req = api_request(Method::POST, "/api/v1/expenses", &token)
+            .set_json(&json!({"amount": 5, "category_id": category.id, "date": date}))
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    let req = api_request(Method::GET, "/api/v1/expenses?per_page=2", &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let page = get_json(&response);
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["items"].as_array().unwrap().len(), 2);
+    assert_eq!(page["items"][0]["date"], "2021-04-02");
+    assert_eq!(page["items"][1], expense);
+    assert_eq!(page["links"]["self"], "/api/v1/expenses?page=1&per_page=2");
+    assert_eq!(page["links"]["last"], "/api/v1/expenses?page=2&per_page=2");
+    assert_eq!(page["links"]["next"], "/api/v1/expenses?page=2&per_page=2");
+    assert!(page["links"]["prev"].is_null());
+
+    let next = page["links"]["next"].as_str().unwrap();
+    let req = api_request(Method::GET, next, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    let page = get_json(&response);
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+    assert_eq!(page["items"][0]["date"], "2021-03-31");
+    assert_eq!(page["links"]["prev"], "/api/v1/expenses?page=1&per_page=2");
+    assert!(page["links"]["next"].is_null());
+
+    for uri in &["/api/v1/expenses?page=0", "/api/v1/expenses?per_page=101"] {
+        let req = api_request(Method::GET, uri, &token).to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    // Update the expense. The date is kept if it is omitted.
+    let req = api_request(Method::PUT, &uri, &token)
+        .set_json(&json!({"amount": "13.75", "category_id": category.id}))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let updated = get_json(&response);
+    assert_eq!(updated["amount"], "13.75");
+    assert_eq!(updated["date"], "2021-04-01");
+    assert!(updated["description"].is_null());
+
+    // Other users cannot access the expense.
+    let other_user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let (_, other_token) =
+        db::api_token::create(&pool.get().unwrap(), &other_user, "App", Scope::Write, None)
+            .unwrap();
+    for method in &[Method::GET, Method::DELETE] {
+        let req = api_request(method.clone(), &uri, &other_token).to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    // Delete the expense.
+    let req = api_request(Method::DELETE, &uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let req = api_request(Method::GET, &uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // IDs that are not numeric do not exist.
+    let req = api_request(Method::GET, "/api/v1/expenses/abc", &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The category cannot be deleted while it contains expenses. This is checked last since the
+    // failing query aborts the test transaction.
+    let category_uri = format!("/api/v1/categories/{}", category.id);
+    let req = api_request(Method::DELETE, &category_uri, &token).to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+// Returns a test request for the given API endpoint, authenticated with the given token.
+fn api_request(method: Method, uri: &str, token: &str) -> test::TestRequest {
+    test::TestRequest::with_uri(uri)
+        .method(method)
+        .header("authorization", format!("Bearer {}", token))
+}
+
+// Returns the JSON body of the given response.
+fn get_json(response: &ServiceResponse) -> serde_json::Value {
+    serde_json::from_str(&get_response_body(response.response())).unwrap()
+}
@@ -0,0 +1,151 @@
+use super::super::*;
+
+use super::admin::log_in;
+use actix_web::dev::Service;
+use actix_web::{test, App};
+use rust_decimal::Decimal;
+
+// Integration tests for managing budgets and recurring expenses, and the budget warnings.
+#[actix_rt::test]
+async fn test_budgets() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let connection = pool.get().unwrap();
+    let user = db::db_test::create_test_user(&connection, &config);
+    let stranger = db::db_test::create_test_user(&connection, &config);
+    let category = db::db_test::create_test_category(&connection, &user);
+    let amount = Decimal::new(1000, 2);
+    db::expense::create(&connection, &user, &amount, &category, None, None).unwrap();
+    drop(connection);
+    let auth = log_in(&mut app, user.email.as_str()).await;
+
+    // Without budgets there are no warnings.
+    let req = test::TestRequest::get()
+        .uri("/budgets")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("No budgets have been set yet."));
+    assert!(body.contains("No recurring expenses have been added yet."));
+
+    // Set a budget that has already been exceeded this month.
+    let payload = [
+        ("category", category.id.to_string()),
+        ("amount", "1.00".to_string()),
+    ];
+    let req = test::TestRequest::post()
+        .uri("/budgets/set")
+        .cookie(auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(format!("The budget of {} has been set to", category.name).as_str()));
+    assert!(body.contains("table-danger"));
+
+    // The dashboard and the reports warn about the exceeded budget.
+    for uri in ["/", "/reports"].iter() {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .cookie(auth.clone())
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_ok(response.response());
+        let body = get_response_body(response.response());
+        let warning = format!("<strong>{}</strong> is expected to cost", category.name);
+        assert!(body.contains(warning.as_str()), "{} warns", uri);
+    }
+
+    // Add a recurring expense.
+    let payload = [
+        ("category", category.id.to_string()),
+        ("amount", "50.00".to_string()),
+        ("description", "Subscription".to_string()),
+        ("day_of_month", "31".to_string()),
+    ];
+    let req = test::TestRequest::post()
+        .uri("/budgets/recurring/add")
+        .cookie(auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Added a recurring expense of"));
+    assert!(body.contains("Subscription"));
+
+    let connection = pool.get().unwrap();
+    let ledger = db::ledger::get_default(&connection, &user).unwrap();
+    let recurring_expenses = db::budget::get_recurring_expenses(&connection, &ledger).unwrap();
+    assert_eq!(1, recurring_expenses.len());
+    let recurring_expense = recurring_expenses[0].0.clone();
+    drop(connection);
+
+    // Users cannot manage the budgets of ledgers they are not a member of.
+    let stranger_auth = log_in(&mut app, stranger.email.as_str()).await;
+    let payload = [
+        ("category", category.id.to_string()),
+        ("amount", "100.00".to_string()),
+    ];
+    let req = test::TestRequest::post()
+        .uri("/budgets/set")
+        .cookie(stranger_auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(body.contains(format!("Category {} not found", category.id).as_str()));
+    let payload = [("recurring_expense", recurring_expense.id.to_string())];
+    let req = test::TestRequest::post()
+        .uri("/budgets/recurring/delete")
+        .cookie(stranger_auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    let message = format!("Recurring expense {} not found", recurring_expense.id);
+    assert!(body.contains(message.as_str()));
+
+    // Delete the recurring expense and remove the budget.
+    let req = test::TestRequest::post()
+        .uri("/budgets/recurring/delete")
+        .cookie(auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(body.contains("The recurring expense has been deleted."));
+
+    let payload = [("category", category.id.to_string())];
+    let req = test::TestRequest::post()
+        .uri("/budgets/remove")
+        .cookie(auth.clone())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(body.contains(format!("The budget of {} has been removed.", category.name).as_str()));
+    assert!(body.contains("No budgets have been set yet."));
+    assert!(body.contains("No recurring expenses have been added yet."));
+
+    // The warning is gone.
+    let req = test::TestRequest::get()
+        .uri("/")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(!body.contains("over-budget"));
+}
@@ -0,0 +1,182 @@
+use super::super::*;
+
+use super::admin::log_in;
+use actix_web::dev::Service;
+use actix_web::http::header::ACCEPT_LANGUAGE;
+use actix_web::{test, App};
+
+// Integration tests for the translation of the interface. The language is negotiated from the
+// `Accept-Language` header, unless the user has chosen a language in their preferences.
+#[actix_rt::test]
+async fn test_language_negotiation() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // Without a language preference the interface is shown in English.
+    let req = test::TestRequest::get().uri("/user/login").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("<html lang=\"en\">"));
+    assert!(body.contains("Forgot your password?"));
+
+    // The browser can request Dutch.
+    let req = test::TestRequest::get()
+        .uri("/user/login")
+        .header(ACCEPT_LANGUAGE, "nl-BE,nl;q=0.9,en;q=0.8")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("<html lang=\"nl\">"));
+    assert!(body.contains("Wachtwoord vergeten?"));
+
+    // Error pages are translated too.
+    let req = test::TestRequest::get()
+        .uri("/non-existing-path")
+        .header(ACCEPT_LANGUAGE, "nl")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+    assert!(body.contains("Pagina niet gevonden"));
+
+    let connection = pool.get().unwrap();
+    let user = db::db_test::create_test_user(&connection, &config);
+    drop(connection);
+    let auth = log_in(&mut app, user.email.as_str()).await;
+
+    // Validation messages are shown in the requested language.
+    let req = test::TestRequest::post()
+        .uri("/expenses/add")
+        .cookie(auth.clone())
+        .header(ACCEPT_LANGUAGE, "nl")
+        .set_form(&expense::AddForm::new("", "", ""))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Vul een bedrag in."));
+    assert!(body.contains("Kies een datum."));
+
+    // The language chosen in the preferences takes precedence over the browser language.
+    let req = test::TestRequest::post()
+        .uri("/account/preferences")
+        .cookie(auth.clone())
+        .header(ACCEPT_LANGUAGE, "en")
+        .set_form(&account::PreferencesForm::new("en", "EUR", "iso", "monday").with_language("nl"))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Je voorkeuren zijn opgeslagen."));
+    assert!(body.contains("<option value=\"nl\" selected>Nederlands</option>"));
+    let preferences = db::user_preference::read(&pool.get().unwrap(), &user).unwrap();
+    assert_eq!(
+        Some(db::user_preference::Language::Nl),
+        preferences.language
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/expenses/add")
+        .cookie(auth.clone())
+        .header(ACCEPT_LANGUAGE, "en")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Uitgave toevoegen"));
+    assert!(body.contains("Uitgaven"));
+
+    // Resetting the preference falls back to the browser language.
+    let req = test::TestRequest::post()
+        .uri("/account/preferences")
+        .cookie(auth.clone())
+        .header(ACCEPT_LANGUAGE, "en")
+        .set_form(&account::PreferencesForm::new("en", "EUR", "iso", "monday"))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Your preferences have been saved."));
+}
+
+// Integration tests for the translation of the pages that are only available to logged in users.
+#[actix_rt::test]
+async fn test_translated_pages() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let connection = pool.get().unwrap();
+    let user = db::db_test::create_test_user(&connection, &config);
+    let user = db::user::set_admin(&connection, user, true).unwrap();
+    let ledger = db::ledger::get_default(&connection, &user).unwrap();
+    let target_date = chrono::Local::today().naive_local() + chrono::Duration::days(365);
+    db::goal::create(
+        &connection,
+        &user,
+        &ledger,
+        "Holiday",
+        &rust_decimal::Decimal::new(120000, 2),
+        &target_date,
+    )
+    .unwrap();
+    drop(connection);
+    let auth = log_in(&mut app, user.email.as_str()).await;
+
+    let pages = [
+        ("/", "0 uitgaven in"),
+        ("/balances", "Alle saldi zijn vereffend."),
+        ("/goals", "per maand tijdens de volgende"),
+        ("/budgets", "Terugkerende uitgaven"),
+        ("/ledgers", "Jouw kasboeken"),
+        ("/reports", "Prognose voor"),
+        ("/reports/compare", "vergeleken met"),
+        ("/account", "Je bent aangemeld als <strong>"),
+        ("/account/sessions", "Actieve sessies"),
+        ("/account/api-tokens", "Je hebt nog geen API-tokens."),
+        ("/admin/users", "Niet geactiveerd"),
+        (
+            "/admin/invitations",
+            "Er zijn geen openstaande uitnodigingen.",
+        ),
+    ];
+    for (uri, expected) in pages.iter() {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .cookie(auth.clone())
+            .header(ACCEPT_LANGUAGE, "nl")
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_ok(response.response());
+        let body = get_response_body(response.response());
+        assert!(body.contains(expected), "{} contains {}", uri, expected);
+    }
+
+    // Status messages are translated too.
+    let req = test::TestRequest::post()
+        .uri("/admin/users/enable")
+        .cookie(auth.clone())
+        .header(ACCEPT_LANGUAGE, "nl")
+        .set_form(&admin::UserActionForm::new(user.email.clone()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("Het account van {} is ingeschakeld.", user.email)));
+}
@@ -5,8 +5,17 @@ use actix_service::Service;
 use actix_web::{dev::ServiceResponse, test, App};
 use app::AppConfig;
 
+pub mod admin;
+pub mod api;
+pub mod budget;
 pub mod error;
 pub mod homepage;
+pub mod i18n;
+pub mod oidc;
+pub mod openapi;
+pub mod preferences;
+pub mod registration;
+pub mod report;
 pub mod user;
 
 /// Returns the Firetrack web application using the default test configuration.
@@ -0,0 +1,299 @@
+use super::super::*;
+
+use actix_http::body::Body;
+use actix_http::cookie::Cookie;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use reqwest::Url;
+use std::time::Duration;
+
+// Tests logging in through the OpenID Connect mock identity provider.
+#[actix_rt::test]
+async fn test_oidc_login() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // Start the mock identity provider, and give it a moment to start listening.
+    let mock_config = config.clone();
+    actix_rt::spawn(async move {
+        oidc_mock::serve(mock_config).await.unwrap();
+    });
+    actix_rt::time::delay_for(Duration::from_millis(100)).await;
+
+    // The login form links to the identity provider.
+    let req = test::TestRequest::get().uri("/user/login").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(
+        r#"<a class="btn btn-lg btn-outline-secondary btn-block" href="/user/login/oidc">Log in with Mock identity provider</a>"#
+    ));
+
+    // The user is sent to the authorization endpoint, using PKCE.
+    let req = test::TestRequest::get()
+        .uri("/user/login/oidc")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    let location = get_location(&response);
+    assert!(location.starts_with(&format!("{}/authorize?", config.oidc_issuer_url())));
+    let authorization_url = Url::parse(location.as_str()).unwrap();
+    let params: Vec<(String, String)> = authorization_url.query_pairs().into_owned().collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+    };
+    assert_eq!(
+        Some(config.oidc_client_id().to_string()),
+        param("client_id")
+    );
+    assert_eq!(Some("S256".to_string()), param("code_challenge_method"));
+    assert!(param("code_challenge").is_some());
+    assert!(param("state").is_some());
+    assert!(param("nonce").is_some());
+
+    // A user without an account cannot log in.
+    let session = get_session_cookie(&response);
+    let callback = authorize(location.as_str(), "unknown@example.com", true).await;
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_login_failed(
+        &mut app,
+        response,
+        "There is no account with the email address unknown@example.com",
+    )
+    .await;
+
+    // An identity cannot be linked to an account if the email address is not verified.
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let (location, session) = start_login(&mut app).await;
+    let callback = authorize(location.as_str(), user.email.as_str(), false).await;
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_login_failed(
+        &mut app,
+        response,
+        "The identity provider has not verified your email address",
+    )
+    .await;
+    assert!(db::user_identity::list(&pool.get().unwrap(), &user)
+        .unwrap()
+        .is_empty());
+
+    // The login fails if the user cancels it at the identity provider.
+    let (location, session) = start_login(&mut app).await;
+    let callback = authorize(&format!("{}&action=cancel", location), "", true).await;
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_login_failed(&mut app, response, "The login was cancelled.").await;
+
+    // The login fails if the state doesn't match, since the request might be forged.
+    let (location, session) = start_login(&mut app).await;
+    let callback = authorize(location.as_str(), user.email.as_str(), true).await;
+    let req = test::TestRequest::get()
+        .uri(format!("{}x", callback).as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_login_failed(&mut app, response, "The login has expired.").await;
+
+    // With a verified email address, the identity is linked to the account and the user is logged
+    // in. Since the email address is verified the account is activated. The account was not
+    // activated before, so it might have been registered by someone else who doesn't own the email
+    // address. Their password no longer gives access to the account.
+    assert!(!user.activated);
+    let (location, session) = start_login(&mut app).await;
+    let callback = authorize(location.as_str(), user.email.as_str(), true).await;
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/");
+    let session = get_session_cookie(&response);
+    let auth = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .unwrap()
+        .into_owned();
+    let identities = db::user_identity::list(&pool.get().unwrap(), &user).unwrap();
+    assert_eq!(1, identities.len());
+    assert_eq!(config.oidc_issuer_url(), identities[0].issuer);
+    assert!(
+        db::user::read(&pool.get().unwrap(), user.email.as_str())
+            .unwrap()
+            .activated
+    );
+    assert!(db::user::verify_password(
+        &pool.get().unwrap(),
+        user.email.as_str(),
+        "letmein",
+        &config
+    )
+    .is_err());
+    let req = test::TestRequest::post()
+        .uri("/user/login")
+        .set_form(&user::UserForm::new(
+            user.email.clone(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    assert!(response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .is_none());
+
+    // The callback cannot be used a second time, since the pending login has been removed from the
+    // session.
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_login_failed(&mut app, response, "The login has expired.").await;
+
+    // A logged in user cannot log in again.
+    let req = test::TestRequest::get()
+        .uri("/user/login/oidc")
+        .cookie(auth)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    // Logging in through the identity provider doesn't bypass two-factor authentication.
+    db::db_test::enable_test_two_factor(&pool.get().unwrap(), &user, &config);
+    let (location, session) = start_login(&mut app).await;
+    let callback = authorize(location.as_str(), user.email.as_str(), true).await;
+    let req = test::TestRequest::get()
+        .uri(callback.as_str())
+        .cookie(session)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login/two-factor");
+    assert!(response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .is_none());
+}
+
+// Starts logging in through the identity provider. Returns the URL of the authorization endpoint
+// and the session cookie.
+async fn start_login<S, B>(app: &mut S) -> (String, Cookie<'static>)
+where
+    S: Service<
+        Request = actix_http::Request,
+        Response = ServiceResponse<B>,
+        Error = actix_web::Error,
+    >,
+{
+    let req = test::TestRequest::get()
+        .uri("/user/login/oidc")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    (get_location(&response), get_session_cookie(&response))
+}
+
+// Logs in at the mock identity provider with the given email address. Returns the path and query
+// of the callback URL the identity provider redirects to.
+async fn authorize(location: &str, email: &str, email_verified: bool) -> String {
+    let mut url = Url::parse(location).unwrap();
+    url.query_pairs_mut().append_pair("email", email);
+    if !email_verified {
+        url.query_pairs_mut().append_pair("email_verified", "false");
+    }
+    let response = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get(url)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(reqwest::StatusCode::FOUND, response.status());
+    let callback = Url::parse(
+        response
+            .headers()
+            .get("location")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+    )
+    .unwrap();
+    format!("{}?{}", callback.path(), callback.query().unwrap())
+}
+
+// Checks that the response redirects to the login form, which shows the given error.
+async fn assert_login_failed<S, B>(app: &mut S, response: ServiceResponse<B>, message: &str)
+where
+    S: Service<
+        Request = actix_http::Request,
+        Response = ServiceResponse<Body>,
+        Error = actix_web::Error,
+    >,
+{
+    assert_eq!(StatusCode::SEE_OTHER, response.status());
+    assert_eq!("/user/login", get_location(&response));
+    assert!(response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .is_none());
+
+    let req = test::TestRequest::get()
+        .uri("/user/login")
+        .cookie(get_session_cookie(&response))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(
+        body.contains(message),
+        "The login form shows '{}'.",
+        message
+    );
+}
+
+// Returns the location the response redirects to.
+fn get_location<B>(response: &ServiceResponse<B>) -> String {
+    response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+// Returns the session cookie that is set in the response.
+fn get_session_cookie<B>(response: &ServiceResponse<B>) -> Cookie<'static> {
+    response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "actix-session")
+        .unwrap()
+        .into_owned()
+}
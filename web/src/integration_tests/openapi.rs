@@ -0,0 +1,225 @@
+use super::super::*;
+
+use actix_web::dev::Service;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{test, App};
+use db::api_token::Scope;
+use serde_json::{json, Value};
+
+// Tests that the OpenAPI document matches the JSON API. Every route of the API should be
+// documented. Every documented operation is called and the responses are checked against the
+// documented status codes and schemas.
+#[actix_rt::test]
+async fn test_openapi_spec() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // The document is publicly available.
+    let req = test::TestRequest::get()
+        .uri("/api/openapi.json")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let spec: Value = serde_json::from_str(&get_response_body(response.response())).unwrap();
+    assert_eq!(spec["openapi"], "3.0.3");
+    let base_url = spec["servers"][0]["url"].as_str().unwrap();
+
+    // Every route of the API is documented.
+    let routes = api::routes();
+    for (path, method, _) in &routes {
+        let operation = &spec["paths"][path][method.as_str().to_lowercase()];
+        assert!(
+            !operation.is_null(),
+            "{} {} is not documented",
+            method,
+            path
+        );
+    }
+
+    // Create a category to manage through the API, and one that contains the expense.
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let category = db::db_test::create_test_category(&pool.get().unwrap(), &user);
+    let expense_category = db::db_test::create_test_category(&pool.get().unwrap(), &user);
+    let expense = db::db_test::create_test_expense(&pool.get().unwrap(), &user, &expense_category);
+    let (_, token) =
+        db::api_token::create(&pool.get().unwrap(), &user, "App", Scope::Write, None).unwrap();
+
+    // Example request bodies for the operations that require one.
+    let bodies = json!({
+        "createCategory": {"name": "Drinks", "description": "Coffee and tea"},
+        "updateCategory": {"name": "Beverages"},
+        "createExpense": {"amount": "9.99", "category_id": expense_category.id},
+        "updateExpense": {
+            "amount": "10.99",
+            "category_id": expense_category.id,
+            "date": "2021-04-18",
+        },
+    });
+
+    // Collect the operations. Deletions are done last, so the other operations can use the
+    // resources. The expense is deleted before its category.
+    let mut operations = vec![];
+    for (path, methods) in spec["paths"].as_object().unwrap() {
+        for (method, operation) in methods.as_object().unwrap() {
+            operations.push((path.clone(), method.clone(), operation.clone()));
+        }
+    }
+    operations
+        .sort_by_key(|(path, method, _)| (method == "delete", path.starts_with("/categories")));
+    assert_eq!(routes.len(), operations.len());
+
+    for (path, method, operation) in operations {
+        let id = operation["operationId"].as_str().unwrap();
+        let uri = format!(
+            "{}{}",
+            base_url,
+            path.replace(
+                "{id}",
+                &if path.starts_with("/categories") {
+                    category.id
+                } else {
+                    expense.id
+                }
+                .to_string(),
+            )
+        );
+        let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+
+        // Requests without a token are rejected as documented.
+        let req = test::TestRequest::with_uri(&uri)
+            .method(method.clone())
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", id);
+        assert_response_matches(&spec, &operation, &response, id);
+
+        // Authenticated requests return the documented response.
+        let mut req = test::TestRequest::with_uri(&uri)
+            .method(method)
+            .header("authorization", format!("Bearer {}", token));
+        if let Some(schema) = operation["requestBody"]["content"]["application/json"].get("schema")
+        {
+            let body = &bodies[id];
+            assert!(!body.is_null(), "{} has no example request body", id);
+            assert_matches_schema(&spec, schema, body, id);
+            req = req.set_json(body);
+        }
+        let response = app.call(req.to_request()).await.unwrap();
+        assert!(
+            response.status().is_success(),
+            "{}: {}",
+            id,
+            response.status()
+        );
+        assert_response_matches(&spec, &operation, &response, id);
+    }
+
+    // Methods that are not documented are not supported.
+    for (path, methods) in spec["paths"].as_object().unwrap() {
+        let uri = format!("{}{}", base_url, path.replace("{id}", "1"));
+        for method in &["get", "post", "put", "patch", "delete"] {
+            if methods.get(*method).is_some() {
+                continue;
+            }
+            let req = test::TestRequest::with_uri(&uri)
+                .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                .header("authorization", format!("Bearer {}", token))
+                .to_request();
+            let response = app.call(req).await.unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::METHOD_NOT_ALLOWED,
+                "{} {}",
+                method,
+                path
+            );
+        }
+    }
+}
+
+// Checks that the status code and the body of the given response are documented for the given
+// operation.
+fn assert_response_matches(
+    spec: &Value,
+    operation: &Value,
+    response: &actix_web::dev::ServiceResponse,
+    id: &str,
+) {
+    let status = response.status().as_u16().to_string();
+    let documented = &operation["responses"][&status];
+    assert!(
+        !documented.is_null(),
+        "{} returns undocumented {}",
+        id,
+        status
+    );
+
+    let body = get_response_body(response.response());
+    match documented["content"]["application/json"].get("schema") {
+        Some(schema) => {
+            let body: Value = serde_json::from_str(&body).unwrap();
+            assert_matches_schema(spec, schema, &body, id);
+        }
+        None => assert!(body.is_empty(), "{} returns an undocumented body", id),
+    }
+}
+
+// Checks that the given value matches the given schema. This supports the subset of the OpenAPI
+// schema object that is used in the document. Properties that are not documented are not allowed.
+fn assert_matches_schema(spec: &Value, schema: &Value, value: &Value, path: &str) {
+    if value.is_null() {
+        assert_eq!(schema["nullable"], true, "{} should not be null", path);
+        return;
+    }
+    if let Some(reference) = schema["$ref"].as_str() {
+        let name = reference.trim_start_matches("#/components/schemas/");
+        let schema = &spec["components"]["schemas"][name];
+        assert!(
+            !schema.is_null(),
+            "{} refers to unknown {}",
+            path,
+            reference
+        );
+        return assert_matches_schema(spec, schema, value, path);
+    }
+    if let Some(values) = schema["enum"].as_array() {
+        assert!(
+            values.contains(value),
+            "{} should be one of {:?}",
+            path,
+            values
+        );
+    }
+    match schema["type"].as_str() {
+        Some("object") => {
+            let object = value.as_object().expect(path);
+            for required in schema["required"].as_array().unwrap_or(&vec![]) {
+                let key = required.as_str().unwrap();
+                assert!(object.contains_key(key), "{}.{} is missing", path, key);
+            }
+            for (key, value) in object {
+                let property = &schema["properties"][key];
+                assert!(!property.is_null(), "{}.{} is undocumented", path, key);
+                assert_matches_schema(spec, property, value, &format!("{}.{}", path, key));
+            }
+        }
+        Some("array") => {
+            for (i, item) in value.as_array().expect(path).iter().enumerate() {
+                assert_matches_schema(spec, &schema["items"], item, &format!("{}[{}]", path, i));
+            }
+        }
+        Some("string") => assert!(value.is_string(), "{} should be a string", path),
+        Some("integer") => assert!(value.is_i64(), "{} should be an integer", path),
+        Some("number") => assert!(value.is_number(), "{} should be a number", path),
+        Some("boolean") => assert!(value.is_boolean(), "{} should be a boolean", path),
+        _ => {}
+    }
+}
@@ -0,0 +1,110 @@
+use super::super::*;
+
+use super::admin::log_in;
+use actix_web::dev::Service;
+use actix_web::{test, App};
+
+// Integration tests for the user preferences, and how they affect the rendering and parsing of
+// amounts and dates.
+#[actix_rt::test]
+async fn test_preferences() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let connection = pool.get().unwrap();
+    let user = db::db_test::create_test_user(&connection, &config);
+    let ledger = db::ledger::get_default(&connection, &user).unwrap();
+    let category =
+        db::category::create(&connection, &user, &ledger, "Groceries", None, None).unwrap();
+    drop(connection);
+
+    let auth = log_in(&mut app, user.email.as_str()).await;
+
+    // The preferences page shows the default preferences.
+    let req = test::TestRequest::get()
+        .uri("/account/preferences")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("<option value=\"iso\" selected>2021-12-31</option>"));
+
+    // An invalid choice is refused.
+    let req = test::TestRequest::post()
+        .uri("/account/preferences")
+        .cookie(auth.clone())
+        .set_form(&account::PreferencesForm::new("nl", "BTC", "dmy", "sunday"))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Unsupported currency: BTC"));
+
+    // Save Dutch number formatting with day-month-year dates.
+    let req = test::TestRequest::post()
+        .uri("/account/preferences")
+        .cookie(auth.clone())
+        .set_form(&account::PreferencesForm::new("nl", "EUR", "dmy", "sunday"))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Your preferences have been saved."));
+    // Tera escapes the slashes in the rendered dates.
+    assert!(body.contains("<option value=\"dmy\" selected>31&#x2F;12&#x2F;2021</option>"));
+    let preferences = db::user_preference::read(&pool.get().unwrap(), &user).unwrap();
+    assert_eq!(db::user_preference::Locale::Nl, preferences.locale);
+    assert_eq!(
+        db::user_preference::FirstDayOfWeek::Sunday,
+        preferences.first_day_of_week
+    );
+
+    // The expense form expects amounts and dates in the chosen format.
+    let req = test::TestRequest::post()
+        .uri("/expenses/add")
+        .cookie(auth.clone())
+        .set_form(&expense::AddForm::new(
+            "12,50",
+            category.id.to_string().as_str(),
+            "2021-05-16",
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Date should be in the format DD&#x2F;MM&#x2F;YYYY."));
+
+    let req = test::TestRequest::post()
+        .uri("/expenses/add")
+        .cookie(auth.clone())
+        .set_form(&expense::AddForm::new(
+            "12,50",
+            category.id.to_string().as_str(),
+            "16/05/2021",
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Successfully added € 12,50 expense to the Groceries category."));
+
+    // The expense is found in the chosen formats.
+    let req = test::TestRequest::get()
+        .uri("/expenses?q=groceries")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("16&#x2F;05&#x2F;2021"));
+    assert!(body.contains("€ 12,50"));
+}
@@ -0,0 +1,191 @@
+use super::super::*;
+
+use super::admin::log_in;
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use app::RegistrationMode;
+
+// Integration tests for the registration form when registration is closed.
+#[actix_rt::test]
+async fn test_closed_registration() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let mut config = app::AppConfig::from_test_defaults();
+    config.set_registration_mode(RegistrationMode::Closed);
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // The registration form is not shown.
+    let req = test::TestRequest::get().uri("/user/register").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Registration is closed."));
+    assert!(!body.contains("form-register"));
+
+    // Submitting the form anyway is not allowed.
+    let email = "closed@example.com";
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(&user::UserForm::new(
+            email.to_string(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+    assert!(db::user::read(&pool.get().unwrap(), email).is_err());
+}
+
+// Integration tests for the registration form and the invitation administration when registration
+// is invite-only.
+#[actix_rt::test]
+async fn test_invite_only_registration() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let mut config = app::AppConfig::from_test_defaults();
+    config.set_registration_mode(RegistrationMode::InviteOnly);
+    let _mock = mailgun_mock(&config);
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let admin = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    let admin = db::user::set_admin(&pool.get().unwrap(), admin, true).unwrap();
+    let email = "invitee@example.com";
+
+    // The registration form asks for an invitation code.
+    let req = test::TestRequest::get().uri("/user/register").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("name=\"invitation_code\""));
+
+    // Registering without an invitation is not possible.
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(&user::UserForm::new(
+            email.to_string(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(
+        body.contains("invitation_code\" id=\"invitation_code\" class=\"form-control is-invalid\"")
+    );
+    assert!(db::user::read(&pool.get().unwrap(), email).is_err());
+
+    // Only administrators can invite people.
+    let req = test::TestRequest::post()
+        .uri("/admin/invitations/add")
+        .set_form(&admin::InvitationForm::new(email.to_string()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+    // An administrator invites the person. The invitation is listed, but the code is not shown.
+    let admin_auth = log_in(&mut app, admin.email.as_str()).await;
+    let req = test::TestRequest::post()
+        .uri("/admin/invitations/add")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::InvitationForm::new(email.to_string()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("An invitation has been sent to {}.", email)));
+    let invitation = db::registration_invitation::read(&pool.get().unwrap(), email).unwrap();
+    assert_eq!(Some(admin.id), invitation.invited_by);
+    assert!(body.contains(email));
+    assert!(!body.contains(invitation.code.as_str()));
+
+    // The invitation code is only valid for the email address it was sent to.
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(
+            &user::UserForm::new("other@example.com".to_string(), "letmein".to_string())
+                .with_invitation_code(invitation.code.clone()),
+        )
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    assert!(db::user::read(&pool.get().unwrap(), "other@example.com").is_err());
+
+    // The invitee can register using the code. The invitation is used up.
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(
+            &user::UserForm::new(email.to_string(), "letmein".to_string())
+                .with_invitation_code(invitation.code.to_uppercase()),
+        )
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/activate");
+    assert!(db::user::read(&pool.get().unwrap(), email).is_ok());
+    assert!(db::registration_invitation::read(&pool.get().unwrap(), email).is_none());
+
+    // Administrators can revoke invitations.
+    let email = "revoked@example.com";
+    db::registration_invitation::create(&pool.get().unwrap(), email, Some(&admin)).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/admin/invitations/revoke")
+        .cookie(admin_auth.clone())
+        .set_form(&admin::InvitationForm::new(email.to_string()))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains(&format!("The invitation for {} has been revoked.", email)));
+    assert!(db::registration_invitation::read(&pool.get().unwrap(), email).is_none());
+}
+
+// Tests that the owner of an account is notified about attempts to register with their email
+// address at most once every 30 minutes, so the registration form cannot be used to flood their
+// inbox.
+#[actix_rt::test]
+async fn test_registration_attempt_notification() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+
+    // Only a single notification is sent for repeated attempts with a wrong password.
+    let mock = mockito::mock("POST", notifications::get_mailgun_uri(&config).as_str())
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body("{\"id\": \"<0123456789abcdef@example.com>\", \"message\": \"Queued.\"}")
+        .expect(1)
+        .create();
+    for _ in 0..3 {
+        let req = test::TestRequest::post()
+            .uri("/user/register")
+            .set_form(&user::UserForm::new(
+                user.email.clone(),
+                "wrong password".to_string(),
+            ))
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_see_other(response.response(), "/user/activate");
+    }
+    mock.assert();
+}
@@ -0,0 +1,57 @@
+use super::super::*;
+
+use super::admin::log_in;
+use actix_web::dev::Service;
+use actix_web::{http::StatusCode, test, App};
+
+// Integration tests for the validation of report periods.
+#[actix_rt::test]
+async fn test_invalid_periods() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let connection = pool.get().unwrap();
+    let user = db::db_test::create_test_user(&connection, &config);
+    drop(connection);
+    let auth = log_in(&mut app, user.email.as_str()).await;
+
+    let uris = [
+        // The start date lies after the end date.
+        "/reports/data/time-series?start=2021-03-02&end=2021-03-01&interval=day",
+        // The period is too long.
+        "/reports/data/time-series?start=1000-01-01&end=2021-03-01&interval=day",
+        // The period reaches beyond the latest supported date.
+        "/reports/data/time-series?start=%2B262143-12-30&end=%2B262143-12-31&interval=month",
+        "/reports/data/categories?start=1000-01-01&end=2021-03-01",
+        "/reports?start=1000-01-01&end=2021-03-01",
+        // Both compared periods are validated.
+        "/reports/compare?start=1000-01-01&end=2021-03-01",
+        "/reports/compare?start=2021-03-01&end=2021-03-31&previous_start=1000-01-01",
+        // There is no previous year for the earliest supported dates.
+        "/reports/compare?start=-262144-01-01&end=-262144-01-31",
+    ];
+    for uri in uris.iter() {
+        let req = test::TestRequest::get()
+            .uri(uri)
+            .cookie(auth.clone())
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status(), "{}", uri);
+    }
+
+    // A period of the maximum length is accepted.
+    let req = test::TestRequest::get()
+        .uri("/reports/data/time-series?start=2011-01-01&end=2021-01-07&interval=month")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+}
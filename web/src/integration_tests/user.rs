@@ -67,9 +67,9 @@ async fn register_with_valid_data() {
     // assert_response_see_other(&response.response(), "/user/activate");
     assert_response_see_other(response.response(), "/");
 
-    // Try to register an account using the user's email address and an invalid password.
-    // Todo This should not result in an error and should not disclose that the user exists.
-    // https://github.com/pfrenssen/firetrack/issues/68
+    // Try to register an account using the user's email address and an invalid password. The
+    // owner of the account is informed by email, and the response is the same as for a successful
+    // registration so that it does not disclose that the user exists.
     let password = "some-other-password";
     let payload = user::UserForm::new(email.to_string(), password.to_string());
     let req = test::TestRequest::post()
@@ -78,12 +78,15 @@ async fn register_with_valid_data() {
         .to_request();
 
     let response = app.call(req).await.unwrap();
-    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR,);
-    let body = get_response_body(response.response());
-    assert_eq!(
-        body.as_str(),
-        "email test@example.com already exists but password is incorrect. Ref https://github.com/pfrenssen/firetrack/issues/68"
-    );
+    assert_response_see_other(response.response(), "/user/activate");
+
+    // The password of the existing account is unchanged.
+    let user = db::user::read(&pool.get().unwrap(), email).unwrap();
+    assert!(hashed_password_is_valid(
+        user.password.as_str(),
+        "mypass",
+        config.secret_key()
+    ));
 }
 
 // Integration tests for the user login form handler.
@@ -134,3 +137,450 @@ async fn test_register_handler() {
     assert_form_input(&body, "password", "password", "password", "Password");
     assert_form_submit(&body, "Sign up");
 }
+
+// Integration tests for the password reset form handlers.
+#[actix_rt::test]
+async fn test_password_reset_handler() {
+    let mut app = build_test_app().await;
+
+    let req = test::TestRequest::get()
+        .uri("/user/password-reset")
+        .to_request();
+
+    let response = app.call(req).await.unwrap();
+    let body = get_response_body(response.response());
+
+    assert_response_ok(response.response());
+    assert_page(
+        &body,
+        PageAssertOptions {
+            title: Some("Reset password".to_string()),
+            has_sidebar: false,
+            is_user_form: true,
+            ..PageAssertOptions::default()
+        },
+    );
+    assert_form_input(&body, "email", "email", "email", "Email address");
+    assert_form_submit(&body, "Send code");
+
+    // Requesting a password reset for an email address that is not registered is treated the same
+    // as for an existing account, so the form does not disclose which accounts exist.
+    let payload = user::PasswordResetForm::new("non-existing@example.com".to_string());
+    let req = test::TestRequest::post()
+        .uri("/user/password-reset")
+        .set_form(&payload)
+        .to_request();
+
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/password-reset/confirm");
+
+    // The form to choose a new password can only be accessed after requesting a password reset.
+    let req = test::TestRequest::get()
+        .uri("/user/password-reset/confirm")
+        .to_request();
+
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+// Integration tests for the handler that resends the activation code.
+#[actix_rt::test]
+async fn test_activate_resend_submit() {
+    let mut app = build_test_app().await;
+
+    // Activation codes can only be resent for the account that has just been registered, which is
+    // stored in the session. Without it access is denied.
+    let req = test::TestRequest::post()
+        .uri("/user/activate/resend")
+        .to_request();
+
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+// Integration tests for the second login step for users that have enabled two-factor
+// authentication.
+#[actix_rt::test]
+async fn test_two_factor_handler() {
+    let mut app = build_test_app().await;
+
+    // The second login step is only accessible after the password has been verified in the first
+    // step, which is stored in the session. Without it access is denied.
+    let req = test::TestRequest::get()
+        .uri("/user/login/two-factor")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/user/login/two-factor")
+        .set_form(&[("code", "123456")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+// Tests that the number of incorrect codes in the second login step is limited, and that the limit
+// cannot be lifted by replaying an earlier session cookie.
+#[actix_rt::test]
+async fn test_two_factor_max_attempts() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    let user = db::db_test::create_test_user(&pool.get().unwrap(), &config);
+    db::db_test::enable_test_two_factor(&pool.get().unwrap(), &user, &config);
+
+    // Enter the password to get to the second login step.
+    let req = test::TestRequest::post()
+        .uri("/user/login")
+        .set_form(&user::UserForm::new(
+            user.email.clone(),
+            "letmein".to_string(),
+        ))
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login/two-factor");
+    let session = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "actix-session")
+        .unwrap()
+        .into_owned();
+
+    // After too many incorrect codes the user needs to log in again.
+    for _ in 1..db::two_factor::MAX_ATTEMPTS {
+        let req = test::TestRequest::post()
+            .uri("/user/login/two-factor")
+            .cookie(session.clone())
+            .set_form(&[("code", "000000")])
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_ok(response.response());
+    }
+    let req = test::TestRequest::post()
+        .uri("/user/login/two-factor")
+        .cookie(session.clone())
+        .set_form(&[("code", "000000")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login");
+
+    // Replaying the session cookie of the second login step doesn't allow more codes to be tried,
+    // even after the lockout of the account has expired.
+    db::login_attempt::delete(
+        &pool.get().unwrap(),
+        db::login_attempt::Scope::Account,
+        user.email.as_str(),
+    )
+    .unwrap();
+    let code = db::db_test::get_test_two_factor_code(&pool.get().unwrap(), &user, 1);
+    let req = test::TestRequest::post()
+        .uri("/user/login/two-factor")
+        .cookie(session.clone())
+        .set_form(&[("code", code.as_str())])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login");
+    assert!(response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .is_none());
+}
+
+// Integration tests for the throttling of failed logins.
+#[actix_rt::test]
+async fn test_login_throttling() {
+    let mut app = build_test_app().await;
+
+    // Failed logins are allowed up to a limit, after which the account is locked.
+    let payload = user::UserForm::new(
+        "throttled@example.com".to_string(),
+        "wrong-password".to_string(),
+    );
+    for _ in 0..5 {
+        let req = test::TestRequest::post()
+            .uri("/user/login")
+            .peer_addr("192.0.2.1:4321".parse().unwrap())
+            .set_form(&payload)
+            .to_request();
+        let response = app.call(req).await.unwrap();
+        assert_response_ok(response.response());
+        let body = get_response_body(response.response());
+        assert!(!body.contains("Too many failed login attempts"));
+    }
+
+    // While the account is locked the password is not checked and an error is shown.
+    let req = test::TestRequest::post()
+        .uri("/user/login")
+        .peer_addr("192.0.2.2:4321".parse().unwrap())
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Too many failed login attempts. Please try again in 1 minute."));
+}
+
+// Integration tests for the session management pages.
+#[actix_rt::test]
+async fn test_sessions() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+
+    let _mock = mailgun_mock(&config);
+
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // The session management pages are only accessible for authenticated users.
+    let req = test::TestRequest::get()
+        .uri("/account/sessions")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/account/sessions/revoke-all")
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Register a user. Submitting the registration form again with the same credentials logs the
+    // user in, which starts a session that is stored in the database.
+    let email = "sessions@example.com";
+    let payload = user::UserForm::new(email.to_string(), "mypass".to_string());
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/activate");
+
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .peer_addr("192.0.2.1:4321".parse().unwrap())
+        .header("user-agent", "Test browser")
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/");
+    let cookie = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .unwrap()
+        .into_owned();
+
+    let user = db::user::read(&pool.get().unwrap(), email).unwrap();
+    let user_sessions = db::user_session::list(&pool.get().unwrap(), &user).unwrap();
+    assert_eq!(user_sessions.len(), 1);
+    assert_eq!(user_sessions[0].ip_address, Some("192.0.2.1".to_string()));
+    assert_eq!(
+        user_sessions[0].user_agent,
+        Some("Test browser".to_string())
+    );
+
+    // The session is listed on the sessions page.
+    let req = test::TestRequest::get()
+        .uri("/account/sessions")
+        .cookie(cookie.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("Test browser"));
+    assert!(body.contains("This device"));
+
+    // Log out everywhere. The session is ended and the cookie can no longer be used.
+    let req = test::TestRequest::post()
+        .uri("/account/sessions/revoke-all")
+        .cookie(cookie.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login");
+    let user_sessions = db::user_session::list(&pool.get().unwrap(), &user).unwrap();
+    assert!(user_sessions.is_empty());
+
+    let req = test::TestRequest::get()
+        .uri("/account/sessions")
+        .cookie(cookie)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+// Integration tests for exporting the user data and deleting the account.
+#[actix_rt::test]
+async fn test_account_deletion() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+
+    let _mock = mailgun_mock(&config);
+
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // Exporting data and deleting the account is only possible for authenticated users.
+    let req = test::TestRequest::get().uri("/account/export").to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/account/delete")
+        .set_form(&[("current_password", "mypass")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Register a user and log in by submitting the registration form a second time.
+    let email = "deleted@example.com";
+    let payload = user::UserForm::new(email.to_string(), "mypass".to_string());
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/activate");
+
+    let req = test::TestRequest::post()
+        .uri("/user/register")
+        .set_form(&payload)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/");
+    let cookie = response
+        .response()
+        .cookies()
+        .find(|c| c.name() == "auth")
+        .unwrap()
+        .into_owned();
+
+    // The data is offered as a JSON download.
+    let req = test::TestRequest::get()
+        .uri("/account/export")
+        .cookie(cookie.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    assert_eq!(
+        response.headers().get("content-disposition").unwrap(),
+        "attachment; filename=\"firetrack-export.json\""
+    );
+    let body = get_response_body(response.response());
+    let export: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(export["account"]["email"], email);
+
+    // The account is not deleted if the password is wrong.
+    let req = test::TestRequest::post()
+        .uri("/account/delete")
+        .cookie(cookie.clone())
+        .set_form(&[("current_password", "wrong")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("The current password is incorrect."));
+    assert!(db::user::read(&pool.get().unwrap(), email).is_ok());
+
+    // With the right password the account is deleted and the user is logged out.
+    let req = test::TestRequest::post()
+        .uri("/account/delete")
+        .cookie(cookie)
+        .set_form(&[("current_password", "mypass")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login");
+    assert!(db::user::read(&pool.get().unwrap(), email).is_err());
+}
+
+// Integration tests for deleting an account that shares a ledger with other members.
+#[actix_rt::test]
+async fn test_account_deletion_shared_ledger() {
+    dotenv::dotenv().ok();
+    dotenv::from_filename(".env.dist").ok();
+
+    let config = app::AppConfig::from_test_defaults();
+
+    let _mock = mailgun_mock(&config);
+
+    let database_url = config.database_url();
+    let pool = db::create_test_connection_pool(database_url).unwrap();
+    let mut app = test::init_service(
+        App::new().configure(|c| configure_application(c, pool.clone(), config.clone())),
+    )
+    .await;
+
+    // The owner shares a ledger with an editor, who records an expense in it.
+    let connection = pool.get().unwrap();
+    let owner = db::db_test::create_test_user(&connection, &config);
+    let editor = db::db_test::create_test_user(&connection, &config);
+    let ledger = db::ledger::create(&connection, &owner, "Household").unwrap();
+    db::ledger::add_member(&connection, &ledger, &editor, db::ledger::Role::Editor).unwrap();
+    let category =
+        db::category::create(&connection, &owner, &ledger, "Groceries", None, None).unwrap();
+    let expense = db::db_test::create_test_expense(&connection, &editor, &category);
+    drop(connection);
+
+    // The shared ledger is listed on the form, together with the member that takes it over.
+    let auth = super::admin::log_in(&mut app, owner.email.as_str()).await;
+    let req = test::TestRequest::get()
+        .uri("/account")
+        .cookie(auth.clone())
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(body.contains("You share these ledgers with other members."));
+    assert!(body.contains("Household"));
+    assert!(body.contains(&format!("{} becomes the owner", editor.email)));
+
+    // When the account is deleted the ledger is kept, and the editor becomes its owner.
+    let req = test::TestRequest::post()
+        .uri("/account/delete")
+        .cookie(auth)
+        .set_form(&[("current_password", "letmein")])
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_see_other(response.response(), "/user/login");
+    let connection = pool.get().unwrap();
+    assert!(db::user::read(&connection, &owner.email).is_err());
+    assert!(db::ledger::read(&connection, ledger.id, None).is_some());
+    assert_eq!(
+        Ok(Some(db::ledger::Role::Owner)),
+        db::ledger::get_role(&connection, ledger.id, editor.id)
+    );
+    assert!(db::expense::read(&connection, expense.id).is_some());
+    drop(connection);
+
+    // The editor no longer shares the ledger, so it is not listed.
+    let auth = super::admin::log_in(&mut app, editor.email.as_str()).await;
+    let req = test::TestRequest::get()
+        .uri("/account")
+        .cookie(auth)
+        .to_request();
+    let response = app.call(req).await.unwrap();
+    assert_response_ok(response.response());
+    let body = get_response_body(response.response());
+    assert!(!body.contains("You share these ledgers with other members."));
+}
@@ -0,0 +1,292 @@
+use super::{get_connection_and_user, get_tera_context};
+use crate::bootstrap_components::Alert;
+use crate::i18n::Templates;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use app::AppConfig;
+use db::ledger::{Ledger, Role};
+use db::user::User;
+use diesel::PgConnection;
+
+// The session key that holds the ID of the ledger the user is currently working in.
+const SESSION_KEY: &str = "ledger_id";
+
+// The POST data of the form to create a ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct AddForm {
+    name: String,
+}
+
+// The POST data of the form to switch to a different ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SelectForm {
+    ledger: i32,
+}
+
+// The POST data of the form to invite a new member to the current ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct InviteForm {
+    email: String,
+    role: String,
+}
+
+// The POST data of the forms to accept or decline an invitation.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct InvitationForm {
+    invitation: i32,
+}
+
+// The POST data of the form to remove a member from the current ledger.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct MemberForm {
+    user: i32,
+}
+
+// A member of a ledger, as shown in the member listing.
+#[derive(Serialize)]
+struct MemberItem {
+    id: i32,
+    email: String,
+    role: Role,
+}
+
+// Returns the ledger the user is currently working in, together with the user's role. This is the
+// ledger that has been selected on the ledgers page, or the user's default ledger if they did not
+// select a ledger or are no longer a member of the selected ledger.
+pub fn get_current_ledger(
+    connection: &PgConnection,
+    session: &Session,
+    user: &User,
+) -> Result<(Ledger, Role), Error> {
+    if let Some(ledger_id) = session.get::<i32>(SESSION_KEY).unwrap_or(None) {
+        let role = db::ledger::get_role(connection, ledger_id, user.id)
+            .map_err(error::ErrorInternalServerError)?;
+        if let (Some(role), Some(ledger)) = (role, db::ledger::read(connection, ledger_id, None)) {
+            return Ok((ledger, role));
+        }
+        session.remove(SESSION_KEY);
+    }
+
+    let ledger =
+        db::ledger::get_default(connection, user).map_err(error::ErrorInternalServerError)?;
+    Ok((ledger, Role::Owner))
+}
+
+// Request handler for the ledgers overview.
+pub async fn overview_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    render_overview(id, session, pool, template, vec![])
+}
+
+// Submit handler for the form to create a ledger. The new ledger becomes the current ledger.
+pub async fn add_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<AddForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::ledger::create(&connection, &user, &input.name) {
+        Ok(ledger) => {
+            session
+                .set(SESSION_KEY, ledger.id)
+                .map_err(error::ErrorInternalServerError)?;
+            Alert::success(template.translate_with("ledger-created", &[("ledger", ledger.name)]))
+        }
+        Err(e) => Alert::danger(e),
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to switch to a different ledger.
+pub async fn select_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<SelectForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::ledger::read(&connection, input.ledger, Some(user.id)) {
+        Some(ledger) => {
+            session
+                .set(SESSION_KEY, ledger.id)
+                .map_err(error::ErrorInternalServerError)?;
+            Alert::success(template.translate_with("ledger-selected", &[("ledger", ledger.name)]))
+        }
+        None => Alert::danger(db::ledger::LedgerErrorKind::NotFound(input.ledger)),
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for the form to invite a new member to the current ledger. The invitation is sent
+// by email.
+pub async fn invite_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    config: web::Data<AppConfig>,
+    input: web::Form<InviteForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+
+    let alert = match input.role.parse::<Role>() {
+        Err(e) => Alert::danger(e),
+        Ok(role) => {
+            match db::ledger_invitation::create(&connection, &ledger, &user, &input.email, role) {
+                Err(e) => Alert::danger(e),
+                Ok(invitation) => {
+                    notifications::ledger_invitation(&invitation, &ledger, &user, &config)
+                        .await
+                        .map_err(error::ErrorInternalServerError)?;
+                    Alert::success(
+                        template.translate_with(
+                            "ledger-invitation-sent",
+                            &[("email", invitation.email)],
+                        ),
+                    )
+                }
+            }
+        }
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for accepting an invitation. The ledger becomes the current ledger.
+pub async fn accept_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<InvitationForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::ledger_invitation::accept(&connection, &user, input.invitation) {
+        Ok(ledger) => {
+            session
+                .set(SESSION_KEY, ledger.id)
+                .map_err(error::ErrorInternalServerError)?;
+            Alert::success(template.translate_with("ledger-joined", &[("ledger", ledger.name)]))
+        }
+        Err(e) => Alert::danger(e),
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for declining an invitation.
+pub async fn decline_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<InvitationForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+
+    let alert = match db::ledger_invitation::decline(&connection, &user, input.invitation) {
+        Ok(_) => Alert::success(template.translate("ledger-invitation-declined")),
+        Err(e) => Alert::danger(e),
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Submit handler for removing a member from the current ledger. Owners can remove any member,
+// other members can only remove themselves.
+pub async fn remove_member_submit(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    input: web::Form<MemberForm>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, role) = get_current_ledger(&connection, &session, &user)?;
+
+    let alert = if input.user != user.id && !role.can_manage() {
+        Alert::danger(db::ledger::LedgerErrorKind::InsufficientPermissions(
+            ledger.id,
+        ))
+    } else {
+        match db::ledger::remove_member(&connection, &ledger, input.user) {
+            Ok(_) if input.user == user.id => {
+                session.remove(SESSION_KEY);
+                Alert::success(template.translate_with("ledger-left", &[("ledger", ledger.name)]))
+            }
+            Ok(_) => Alert::success(template.translate("ledger-member-removed")),
+            Err(e) => Alert::danger(e),
+        }
+    };
+
+    render_overview(id, session, pool, template, vec![alert])
+}
+
+// Renders the ledgers overview. Used by both GET and POST requests.
+fn render_overview(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    alerts: Vec<Alert>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (current_ledger, role) = get_current_ledger(&connection, &session, &user)?;
+
+    let ledgers =
+        db::ledger::get_ledgers(&connection, &user).map_err(error::ErrorInternalServerError)?;
+    let members: Vec<MemberItem> = db::ledger::get_members(&connection, &current_ledger)
+        .map_err(error::ErrorInternalServerError)?
+        .into_iter()
+        .map(|(member, role)| MemberItem {
+            id: member.id,
+            email: member.email,
+            role,
+        })
+        .collect();
+    let invitations: Vec<(String, Role)> = if role.can_manage() {
+        db::ledger_invitation::get_for_ledger(&connection, &current_ledger)
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|i| (i.email, i.role))
+            .collect()
+    } else {
+        vec![]
+    };
+    let pending_invitations: Vec<(i32, Ledger, Role)> =
+        db::ledger_invitation::get_pending(&connection, &user)
+            .map_err(error::ErrorInternalServerError)?
+            .into_iter()
+            .map(|(i, ledger)| (i.id, ledger, i.role))
+            .collect();
+
+    let mut context = get_tera_context(template.translate("ledgers-title").as_str(), id);
+    context.insert("user_id", &user.id);
+    context.insert("current_ledger", &current_ledger);
+    context.insert("role", &role);
+    context.insert("can_manage", &role.can_manage());
+    context.insert("ledgers", &ledgers);
+    context.insert("members", &members);
+    context.insert("invitations", &invitations);
+    context.insert("pending_invitations", &pending_invitations);
+    context.insert("alerts", &alerts);
+
+    let content = template
+        .render("ledgers/overview.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
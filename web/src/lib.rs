@@ -9,18 +9,38 @@ mod integration_tests;
 #[cfg(test)]
 use crate::firetrack_test::*;
 
+mod account;
+mod admin;
+mod api;
+mod balance;
 mod bootstrap_components;
+mod budget;
 mod category;
+mod dashboard;
 mod error;
 mod expense;
+mod filters;
+mod goal;
+mod i18n;
+mod identity;
+mod ledger;
+mod oidc;
+mod report;
 mod user;
 
 use actix_http::cookie::SameSite;
 use actix_identity::{CookieIdentityPolicy, Identity, IdentityService};
-use actix_session::CookieSession;
+use actix_session::{CookieSession, Session};
 use actix_web::error::ErrorInternalServerError;
-use actix_web::{middleware::Logger, web, App, Error, HttpResponse, HttpServer};
+use actix_web::{middleware::Logger, web, App, Error, HttpRequest, HttpResponse, HttpServer};
 use app::AppConfig;
+use db::user::User;
+use db::user_preference::UserPreferences;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::PgConnection;
+use i18n::Templates;
+use identity::DatabaseIdentityPolicy;
+use rust_decimal::Decimal;
 use std::env;
 
 // Starts the web server on the host address and port as configured in the application.
@@ -48,9 +68,18 @@ pub async fn serve(config: AppConfig) -> Result<(), String> {
     }
 }
 
-// Controller for the homepage.
-async fn index(id: Identity, template: web::Data<tera::Tera>) -> Result<HttpResponse, Error> {
-    let context = get_tera_context("Home", id);
+// Controller for the homepage. Authenticated users are shown the dashboard.
+async fn index(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+) -> Result<HttpResponse, Error> {
+    if id.identity().is_some() {
+        return dashboard::render(id, session, pool, template);
+    }
+
+    let context = get_tera_context(template.translate("nav-home").as_str(), id);
 
     let content = template
         .render("index.html", &context)
@@ -101,10 +130,12 @@ pub fn configure_application(
     pool: db::ConnectionPool,
     app_config: AppConfig,
 ) {
-    let tera = compile_templates();
+    let translations = i18n::Translations::load();
+    let tera = compile_templates(&translations);
     let session_key = app_config.session_key();
     config
         .data(tera)
+        .data(translations)
         .data(pool)
         .data(app_config)
         .service(actix_files::Files::new("/css", "web/static/css/"))
@@ -114,6 +145,17 @@ pub fn configure_application(
             "/third-party",
             "web/static/third-party/",
         ))
+        .route(
+            "/api/openapi.json",
+            web::get().to(api::openapi::spec_handler),
+        )
+        // The JSON API authenticates requests with API tokens rather than cookies, so it doesn't
+        // use the session and identity middleware.
+        .service(
+            web::scope("/api/v1")
+                .configure(api::configure)
+                .default_service(web::route().to(api::not_found_handler)),
+        )
         .service(
             web::scope("")
                 // Middleware is executed in the reverse order. Define the error handlers first so they
@@ -126,29 +168,188 @@ pub fn configure_application(
                         .same_site(SameSite::Lax)
                         .secure(false),
                 )
-                .wrap(IdentityService::new(
+                .wrap(IdentityService::new(DatabaseIdentityPolicy::new(
                     CookieIdentityPolicy::new(&session_key)
                         .name("auth")
                         .same_site(SameSite::Lax)
                         .secure(false),
-                ))
+                )))
                 .route("/", web::get().to(index))
+                .route("/account", web::get().to(account::overview_handler))
+                .route(
+                    "/account/api-tokens",
+                    web::get().to(account::api_tokens_handler),
+                )
+                .route(
+                    "/account/api-tokens/add",
+                    web::post().to(account::api_token_add_submit),
+                )
+                .route(
+                    "/account/api-tokens/revoke",
+                    web::post().to(account::api_token_revoke_submit),
+                )
+                .route("/account/delete", web::post().to(account::delete_submit))
+                .route("/account/email", web::post().to(account::email_submit))
+                .route(
+                    "/account/email/cancel",
+                    web::post().to(account::email_cancel_submit),
+                )
+                .route(
+                    "/account/email/confirm",
+                    web::post().to(account::email_confirm_submit),
+                )
+                .route("/account/export", web::get().to(account::export_handler))
+                .route(
+                    "/account/password",
+                    web::post().to(account::password_submit),
+                )
+                .route(
+                    "/account/preferences",
+                    web::get().to(account::preferences_handler),
+                )
+                .route(
+                    "/account/preferences",
+                    web::post().to(account::preferences_submit),
+                )
+                .route(
+                    "/account/sessions",
+                    web::get().to(account::sessions_handler),
+                )
+                .route(
+                    "/account/sessions/revoke",
+                    web::post().to(account::session_revoke_submit),
+                )
+                .route(
+                    "/account/sessions/revoke-all",
+                    web::post().to(account::session_revoke_all_submit),
+                )
+                .route(
+                    "/account/two-factor/confirm",
+                    web::post().to(account::two_factor_confirm_submit),
+                )
+                .route(
+                    "/account/two-factor/disable",
+                    web::post().to(account::two_factor_disable_submit),
+                )
+                .route(
+                    "/account/two-factor/setup",
+                    web::post().to(account::two_factor_setup_submit),
+                )
+                .route(
+                    "/admin/invitations",
+                    web::get().to(admin::invitations_handler),
+                )
+                .route(
+                    "/admin/invitations/add",
+                    web::post().to(admin::invite_submit),
+                )
+                .route(
+                    "/admin/invitations/revoke",
+                    web::post().to(admin::revoke_invitation_submit),
+                )
+                .route("/admin/users", web::get().to(admin::users_handler))
+                .route(
+                    "/admin/users/activate",
+                    web::post().to(admin::activate_submit),
+                )
+                .route("/admin/users/delete", web::post().to(admin::delete_submit))
+                .route(
+                    "/admin/users/disable",
+                    web::post().to(admin::disable_submit),
+                )
+                .route("/admin/users/enable", web::post().to(admin::enable_submit))
+                .route("/balances", web::get().to(balance::overview_handler))
+                .route("/balances/settle", web::post().to(balance::settle_submit))
+                .route("/budgets", web::get().to(budget::overview_handler))
+                .route(
+                    "/budgets/recurring/add",
+                    web::post().to(budget::add_recurring_submit),
+                )
+                .route(
+                    "/budgets/recurring/delete",
+                    web::post().to(budget::delete_recurring_submit),
+                )
+                .route("/budgets/remove", web::post().to(budget::remove_submit))
+                .route("/budgets/set", web::post().to(budget::set_submit))
                 .route("/expenses", web::get().to(expense::overview_handler))
                 .route("/expenses/add", web::get().to(expense::add_handler))
                 .route("/expenses/add", web::post().to(expense::add_submit))
                 .route("/favicon.ico", web::get().to(index))
+                .route("/goals", web::get().to(goal::overview_handler))
+                .route("/goals/add", web::post().to(goal::add_submit))
+                .route("/goals/contribute", web::post().to(goal::contribute_submit))
+                .route("/goals/delete", web::post().to(goal::delete_submit))
+                .route("/ledgers", web::get().to(ledger::overview_handler))
+                .route("/ledgers/add", web::post().to(ledger::add_submit))
+                .route(
+                    "/ledgers/invitations/accept",
+                    web::post().to(ledger::accept_submit),
+                )
+                .route(
+                    "/ledgers/invitations/decline",
+                    web::post().to(ledger::decline_submit),
+                )
+                .route("/ledgers/invite", web::post().to(ledger::invite_submit))
+                .route(
+                    "/ledgers/members/remove",
+                    web::post().to(ledger::remove_member_submit),
+                )
+                .route("/ledgers/select", web::post().to(ledger::select_submit))
+                .route("/reports", web::get().to(report::overview_handler))
+                .route("/reports/compare", web::get().to(report::compare_handler))
+                .route(
+                    "/reports/data/categories",
+                    web::get().to(report::categories_handler),
+                )
+                .route(
+                    "/reports/data/time-series",
+                    web::get().to(report::time_series_handler),
+                )
                 .route("/user/activate", web::get().to(user::activate_handler))
                 .route("/user/activate", web::post().to(user::activate_submit))
+                .route(
+                    "/user/activate/resend",
+                    web::post().to(user::activate_resend_submit),
+                )
                 .route("/user/login", web::get().to(user::login_handler))
                 .route("/user/login", web::post().to(user::login_submit))
+                .route("/user/login/oidc", web::get().to(oidc::login_handler))
+                .route(
+                    "/user/login/oidc/callback",
+                    web::get().to(oidc::callback_handler),
+                )
+                .route(
+                    "/user/login/two-factor",
+                    web::get().to(user::two_factor_handler),
+                )
+                .route(
+                    "/user/login/two-factor",
+                    web::post().to(user::two_factor_submit),
+                )
                 .route("/user/logout", web::get().to(user::logout_handler))
+                .route(
+                    "/user/password-reset",
+                    web::get().to(user::password_reset_handler),
+                )
+                .route(
+                    "/user/password-reset",
+                    web::post().to(user::password_reset_submit),
+                )
+                .route(
+                    "/user/password-reset/confirm",
+                    web::get().to(user::password_reset_confirm_handler),
+                )
+                .route(
+                    "/user/password-reset/confirm",
+                    web::post().to(user::password_reset_confirm_submit),
+                )
                 .route("/user/register", web::get().to(user::register_handler))
                 .route("/user/register", web::post().to(user::register_submit)),
         );
 }
 
 // Compile the Tera templates.
-fn compile_templates() -> tera::Tera {
+fn compile_templates(translations: &i18n::Translations) -> tera::Tera {
     // Determine the path to the templates folder. This depends on whether we are running from the
     // root of the application (e.g. when launched using `cargo run`) or from the library folder
     // (e.g. when running tests).
@@ -157,7 +358,10 @@ fn compile_templates() -> tera::Tera {
     } else {
         "web/templates/**/*"
     };
-    tera::Tera::new(path).unwrap()
+    let mut tera = tera::Tera::new(path).unwrap();
+    filters::register(&mut tera);
+    i18n::register(&mut tera, translations);
+    tera
 }
 
 // Checks that the user is authenticated.
@@ -170,6 +374,50 @@ fn assert_authenticated(id: &Identity) -> Result<String, Error> {
     ))
 }
 
+// Checks that the user is an administrator. Used to control access on the administration pages.
+fn assert_admin(user: &User) -> Result<(), Error> {
+    if user.admin {
+        return Ok(());
+    }
+    Err(actix_http::error::ErrorForbidden(
+        "You need to be an administrator to access this page.",
+    ))
+}
+
+// Returns a database connection and the currently logged in user.
+fn get_connection_and_user(
+    id: &Identity,
+    pool: &web::Data<db::ConnectionPool>,
+) -> Result<(PooledConnection<ConnectionManager<PgConnection>>, User), Error> {
+    let email = assert_authenticated(id)?;
+    let connection = pool.get().map_err(ErrorInternalServerError)?;
+    let user = db::user::read(&connection, email.as_str()).map_err(ErrorInternalServerError)?;
+    Ok((connection, user))
+}
+
+// Returns the preferences of the given user.
+fn get_preferences(connection: &PgConnection, user: &User) -> Result<UserPreferences, Error> {
+    db::user_preference::read(connection, user).map_err(ErrorInternalServerError)
+}
+
+// Adds the preferences of the user to the given Tera context. They are passed to the `amount` and
+// `local_date` filters, and are used by form fields for amounts and dates.
+fn insert_preferences(context: &mut tera::Context, preferences: &UserPreferences) {
+    context.insert("preferences", preferences);
+    context.insert("currency_symbol", preferences.currency.symbol());
+    context.insert("date_placeholder", preferences.date_format.placeholder());
+    context.insert(
+        "amount_placeholder",
+        &preferences.locale.format_number(Decimal::new(0, 2)),
+    );
+}
+
+// Returns the IP address of the client. The address of the peer is used rather than the forwarded
+// address from the request headers, since the headers can be set by the client.
+fn get_ip_address(req: &HttpRequest) -> Option<String> {
+    req.peer_addr().map(|addr| addr.ip().to_string())
+}
+
 // Checks that the user is not authenticated. Used to control access on login and registration
 // forms.
 fn assert_not_authenticated(id: &Identity) -> Result<(), Error> {
@@ -0,0 +1,476 @@
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use app::AppConfig;
+use db::user_identity::UserIdentityErrorKind;
+use rand::{thread_rng, Rng};
+use reqwest::Url;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::Duration;
+
+// The number of seconds to wait for a response from the identity provider.
+const REQUEST_TIMEOUT: u64 = 10;
+
+// The scopes that are requested from the identity provider. The email scope grants access to the
+// email address that is used to link the identity to an account.
+const SCOPES: &str = "openid email";
+
+// A login that has been started by redirecting the user to the identity provider. This is kept in
+// the session until the user returns.
+#[derive(Deserialize, Serialize)]
+struct PendingLogin {
+    // Ties the response of the identity provider to this browser, preventing cross-site request
+    // forgery.
+    state: String,
+    // Ties the ID token to this login, preventing replay of ID tokens.
+    nonce: String,
+    // The PKCE code verifier, which proves to the identity provider that the authorization code is
+    // exchanged by the client that started the login.
+    code_verifier: String,
+}
+
+// The metadata of the identity provider, as published at the discovery endpoint.
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+// The response of the token endpoint. The access token is not needed since the ID token contains
+// all required information.
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+// The claims of an ID token that are used to identify the user.
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    azp: Option<String>,
+    exp: i64,
+    nonce: Option<String>,
+    email: Option<String>,
+    // Some providers send this as a string rather than a boolean.
+    email_verified: Option<Value>,
+}
+
+impl Claims {
+    // Returns the email address of the user if the identity provider has verified it.
+    fn verified_email(&self) -> Option<&str> {
+        match self.email_verified {
+            Some(Value::Bool(true)) => self.email.as_deref(),
+            Some(Value::String(ref verified)) if verified == "true" => self.email.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+// The intended audience of an ID token, which can be a single client or a list of clients.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::Single(audience) => audience == client_id,
+            Audience::Multiple(audiences) => audiences.iter().any(|a| a == client_id),
+        }
+    }
+}
+
+// Possible errors when logging in through the identity provider.
+#[derive(Debug, PartialEq)]
+enum OidcErrorKind {
+    // The login was cancelled by the user or refused by the identity provider.
+    Denied(String),
+    // The ID token is invalid.
+    InvalidIdToken(&'static str),
+    // The user returned without a login in progress, or the state doesn't match. This happens when
+    // the login was started in another browser, or when the request was forged.
+    InvalidState,
+    // The identity provider could not be reached or returned an invalid response.
+    ProviderError(String),
+}
+
+impl fmt::Display for OidcErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OidcErrorKind::Denied(ref error) => match error.as_str() {
+                "access_denied" => write!(f, "The login was cancelled."),
+                _ => write!(f, "The identity provider refused the login ({}).", error),
+            },
+            OidcErrorKind::InvalidIdToken(reason) => {
+                write!(
+                    f,
+                    "The identity provider returned an invalid ID token: {}.",
+                    reason
+                )
+            }
+            OidcErrorKind::InvalidState => write!(f, "The login has expired. Please try again."),
+            OidcErrorKind::ProviderError(ref err) => {
+                write!(f, "The identity provider could not be reached: {}.", err)
+            }
+        }
+    }
+}
+
+// Request handler that starts logging in through the identity provider. The user is redirected to
+// the authorization endpoint of the provider, which redirects back to the callback handler.
+pub async fn login_handler(
+    id: Identity,
+    session: Session,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+    assert_enabled(&config)?;
+
+    let metadata = match get_provider_metadata(&config).await {
+        Ok(metadata) => metadata,
+        Err(e) => return fail(&session, e),
+    };
+    let mut url = match Url::parse(metadata.authorization_endpoint.as_str()) {
+        Ok(url) => url,
+        Err(e) => return fail(&session, OidcErrorKind::ProviderError(e.to_string())),
+    };
+
+    let login = PendingLogin {
+        state: generate_random_string(),
+        nonce: generate_random_string(),
+        code_verifier: generate_random_string(),
+    };
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", config.oidc_client_id())
+        .append_pair("redirect_uri", config.oidc_redirect_url())
+        .append_pair("scope", SCOPES)
+        .append_pair("state", login.state.as_str())
+        .append_pair("nonce", login.nonce.as_str())
+        .append_pair(
+            "code_challenge",
+            get_code_challenge(login.code_verifier.as_str()).as_str(),
+        )
+        .append_pair("code_challenge_method", "S256");
+    session
+        .set("oidc_login", login)
+        .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::SeeOther()
+        .header("location", url.as_str())
+        .finish())
+}
+
+// The query parameters that are passed by the identity provider when redirecting back.
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+// Request handler for the user returning from the identity provider. The authorization code is
+// exchanged for an ID token, and the user is logged in to the account that is linked to the
+// identity. If the identity is not linked yet, it is linked to the account that uses the same email
+// address, provided that the identity provider has verified it. When linking to an account that is
+// not activated yet, its password and sessions are removed, since it might have been registered by
+// someone who doesn't own the email address.
+pub async fn callback_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    config: web::Data<AppConfig>,
+    query: web::Query<CallbackQuery>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+    assert_enabled(&config)?;
+
+    // The login can only be completed once.
+    let login = session.get::<PendingLogin>("oidc_login").unwrap_or(None);
+    session.remove("oidc_login");
+
+    let claims = match get_claims(&config, login, &query).await {
+        Ok(claims) => claims,
+        Err(e) => return fail(&session, e),
+    };
+
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let user = match db::user_identity::link(
+        &connection,
+        claims.iss.as_str(),
+        claims.sub.as_str(),
+        claims.verified_email(),
+    ) {
+        Ok((_, user)) => user,
+        Err(e @ UserIdentityErrorKind::EmailNotVerified)
+        | Err(e @ UserIdentityErrorKind::UserNotFound(_)) => return fail(&session, e),
+        Err(e) => return Err(error::ErrorInternalServerError(e)),
+    };
+
+    // Disabled accounts cannot log in through the identity provider either. Check this before the
+    // account is activated, so that a disabled account is left untouched.
+    if user.disabled {
+        return fail(&session, "This account has been disabled.");
+    }
+
+    // The identity provider has verified the email address, so the account doesn't need to be
+    // activated with an activation code.
+    let user = db::user::activate(&connection, user).map_err(error::ErrorInternalServerError)?;
+
+    crate::user::log_in(id, &session, &connection, user.email.as_str())
+}
+
+// Returns the verified claims of the ID token for the login that was started in this session.
+async fn get_claims(
+    config: &AppConfig,
+    login: Option<PendingLogin>,
+    query: &CallbackQuery,
+) -> Result<Claims, OidcErrorKind> {
+    let login = login.ok_or(OidcErrorKind::InvalidState)?;
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return Err(OidcErrorKind::InvalidState);
+    }
+    if let Some(ref error) = query.error {
+        return Err(OidcErrorKind::Denied(error.to_owned()));
+    }
+    let code = query
+        .code
+        .as_deref()
+        .ok_or_else(|| OidcErrorKind::ProviderError("no authorization code".to_string()))?;
+
+    // Exchange the authorization code for the ID token.
+    let metadata = get_provider_metadata(config).await?;
+    let response = get_client()?
+        .post(metadata.token_endpoint.as_str())
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.oidc_redirect_url()),
+            ("client_id", config.oidc_client_id()),
+            ("client_secret", config.oidc_client_secret()),
+            ("code_verifier", login.code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| OidcErrorKind::ProviderError(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(OidcErrorKind::ProviderError(format!(
+            "the token endpoint returned {}",
+            response.status()
+        )));
+    }
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .map_err(|e| OidcErrorKind::ProviderError(e.to_string()))?;
+
+    let claims = decode_id_token(token.id_token.as_str())?;
+    validate_claims(&claims, config, login.nonce.as_str())?;
+    Ok(claims)
+}
+
+// Returns the claims contained in the given ID token.
+//
+// The signature is not checked: the token is received directly from the token endpoint of the
+// identity provider, in which case the TLS connection may be relied on to establish that the token
+// comes from the provider. Ref. OpenID Connect Core 1.0, section 3.1.3.7.
+fn decode_id_token(id_token: &str) -> Result<Claims, OidcErrorKind> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or(OidcErrorKind::InvalidIdToken("malformed token"))?;
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| OidcErrorKind::InvalidIdToken("malformed payload"))?;
+    serde_json::from_slice(&payload).map_err(|_| OidcErrorKind::InvalidIdToken("missing claims"))
+}
+
+// Checks that the ID token was issued by the identity provider, for this application, for the
+// current login, and that it has not expired.
+fn validate_claims(claims: &Claims, config: &AppConfig, nonce: &str) -> Result<(), OidcErrorKind> {
+    if claims.iss != config.oidc_issuer_url() {
+        return Err(OidcErrorKind::InvalidIdToken("unexpected issuer"));
+    }
+    if !claims.aud.contains(config.oidc_client_id())
+        || matches!(claims.azp.as_deref(), Some(azp) if azp != config.oidc_client_id())
+    {
+        return Err(OidcErrorKind::InvalidIdToken("unexpected audience"));
+    }
+    if claims.exp < chrono::Utc::now().timestamp() {
+        return Err(OidcErrorKind::InvalidIdToken("the token has expired"));
+    }
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(OidcErrorKind::InvalidIdToken("unexpected nonce"));
+    }
+    Ok(())
+}
+
+// Retrieves the metadata of the identity provider from the discovery endpoint.
+async fn get_provider_metadata(config: &AppConfig) -> Result<ProviderMetadata, OidcErrorKind> {
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        config.oidc_issuer_url().trim_end_matches('/')
+    );
+    let metadata = get_client()?
+        .get(url.as_str())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| OidcErrorKind::ProviderError(e.to_string()))?
+        .json::<ProviderMetadata>()
+        .await
+        .map_err(|e| OidcErrorKind::ProviderError(e.to_string()))?;
+
+    // The issuer should match exactly, otherwise the ID tokens will not be accepted.
+    if metadata.issuer != config.oidc_issuer_url() {
+        return Err(OidcErrorKind::ProviderError(format!(
+            "the issuer {} does not match the configuration",
+            metadata.issuer
+        )));
+    }
+    Ok(metadata)
+}
+
+// Returns an HTTP client for requests to the identity provider.
+fn get_client() -> Result<reqwest::Client, OidcErrorKind> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT))
+        .build()
+        .map_err(|e| OidcErrorKind::ProviderError(e.to_string()))
+}
+
+// Returns a random URL safe string with 256 bits of entropy.
+fn generate_random_string() -> String {
+    let bytes: [u8; 32] = thread_rng().gen();
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+// Returns the PKCE code challenge for the given code verifier, using the S256 method.
+fn get_code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(
+        Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+// Returns a 404 if logging in through an identity provider is not configured.
+fn assert_enabled(config: &AppConfig) -> Result<(), Error> {
+    if !config.oidc_enabled() {
+        return Err(error::ErrorNotFound("Single sign-on is not enabled."));
+    }
+    Ok(())
+}
+
+// Redirects to the login form, which shows the given error.
+fn fail<E: fmt::Display>(session: &Session, err: E) -> Result<HttpResponse, Error> {
+    session
+        .set("oidc_failed", err.to_string())
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .header("location", "/user/login")
+        .finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests super::get_code_challenge().
+    #[test]
+    fn test_get_code_challenge() {
+        // The example from RFC 7636, appendix B.
+        assert_eq!(
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM",
+            get_code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk")
+        );
+    }
+
+    // A change to valid claims that makes them invalid.
+    type Alteration = Box<dyn Fn(&mut Claims)>;
+
+    // Tests super::validate_claims().
+    #[test]
+    fn test_validate_claims() {
+        let config = AppConfig::from_test_defaults();
+        let valid = || Claims {
+            iss: config.oidc_issuer_url().to_string(),
+            sub: "1".to_string(),
+            aud: Audience::Single(config.oidc_client_id().to_string()),
+            azp: None,
+            exp: chrono::Utc::now().timestamp() + 60,
+            nonce: Some("nonce".to_string()),
+            email: Some("user@example.com".to_string()),
+            email_verified: Some(Value::Bool(true)),
+        };
+        assert_eq!(Ok(()), validate_claims(&valid(), &config, "nonce"));
+
+        // The token can be intended for multiple clients.
+        let mut claims = valid();
+        claims.aud = Audience::Multiple(vec![
+            "other".to_string(),
+            config.oidc_client_id().to_string(),
+        ]);
+        claims.azp = Some(config.oidc_client_id().to_string());
+        assert_eq!(Ok(()), validate_claims(&claims, &config, "nonce"));
+
+        let invalid: Vec<(Alteration, &str)> = vec![
+            (
+                Box::new(|c| c.iss = "https://evil.example.com".to_string()),
+                "unexpected issuer",
+            ),
+            (
+                Box::new(|c| c.aud = Audience::Single("other".to_string())),
+                "unexpected audience",
+            ),
+            (
+                Box::new(|c| c.azp = Some("other".to_string())),
+                "unexpected audience",
+            ),
+            (
+                Box::new(|c| c.exp = chrono::Utc::now().timestamp() - 60),
+                "the token has expired",
+            ),
+            (Box::new(|c| c.nonce = None), "unexpected nonce"),
+            (
+                Box::new(|c| c.nonce = Some("other".to_string())),
+                "unexpected nonce",
+            ),
+        ];
+        for (alter, reason) in invalid {
+            let mut claims = valid();
+            alter(&mut claims);
+            assert_eq!(
+                Err(OidcErrorKind::InvalidIdToken(reason)),
+                validate_claims(&claims, &config, "nonce")
+            );
+        }
+    }
+
+    // Tests Claims::verified_email().
+    #[test]
+    fn test_verified_email() {
+        let claims = |email_verified: Option<Value>| Claims {
+            iss: "".to_string(),
+            sub: "".to_string(),
+            aud: Audience::Single("".to_string()),
+            azp: None,
+            exp: 0,
+            nonce: None,
+            email: Some("user@example.com".to_string()),
+            email_verified,
+        };
+        let email = Some("user@example.com");
+        assert_eq!(email, claims(Some(Value::Bool(true))).verified_email());
+        assert_eq!(email, claims(Some(Value::from("true"))).verified_email());
+        assert_eq!(None, claims(Some(Value::Bool(false))).verified_email());
+        assert_eq!(None, claims(Some(Value::from("false"))).verified_email());
+        assert_eq!(None, claims(None).verified_email());
+    }
+}
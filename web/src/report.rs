@@ -0,0 +1,233 @@
+use super::{get_connection_and_user, get_preferences, get_tera_context, insert_preferences};
+use crate::i18n::Templates;
+use crate::ledger::get_current_ledger;
+use actix_identity::Identity;
+use actix_session::Session;
+use actix_web::{error, web, Error, HttpResponse};
+use chrono::{Datelike, NaiveDate, Utc};
+use db::category::Category;
+use db::ledger::Ledger;
+use db::report::{Interval, ReportErrorKind};
+use diesel::PgConnection;
+use std::str::FromStr;
+
+// The number of months following the current month that are shown in the forecast.
+const FORECAST_MONTHS: u32 = 3;
+
+// The query parameters that select the period of a report. The default period covers the current
+// month and the 11 months before it, grouped by month.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PeriodQuery {
+    start: Option<String>,
+    end: Option<String>,
+    interval: Option<String>,
+    parent: Option<i32>,
+}
+
+// The query parameters that select the periods of a comparison report. By default the current month
+// so far is compared with the same days a year earlier.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CompareQuery {
+    start: Option<String>,
+    end: Option<String>,
+    previous_start: Option<String>,
+    previous_end: Option<String>,
+    parent: Option<i32>,
+}
+
+// A report period, as selected through the query parameters.
+#[derive(Serialize)]
+struct Period {
+    start: NaiveDate,
+    end: NaiveDate,
+    interval: Interval,
+}
+
+impl Period {
+    // Returns the period selected by the given query parameters.
+    fn from_query(query: &PeriodQuery) -> Result<Period, Error> {
+        let today = Utc::now().naive_utc().date();
+        let default_start = match today.month() {
+            12 => NaiveDate::from_ymd(today.year(), 1, 1),
+            month => NaiveDate::from_ymd(today.year() - 1, month + 1, 1),
+        };
+        let start = parse_date(&query.start, default_start)?;
+        let end = parse_date(&query.end, today)?;
+        db::report::Period { start, end }
+            .validate()
+            .map_err(report_error)?;
+        let interval = match query.interval.as_deref() {
+            None | Some("") => Interval::Month,
+            Some(interval) => Interval::from_str(interval).map_err(error::ErrorBadRequest)?,
+        };
+
+        Ok(Period {
+            start,
+            end,
+            interval,
+        })
+    }
+}
+
+// Request handler for the report that compares the spending in two periods.
+pub async fn compare_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    query: web::Query<CompareQuery>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+
+    let today = Utc::now().naive_utc().date();
+    let current = db::report::Period {
+        start: parse_date(&query.start, today.with_day(1).unwrap())?,
+        end: parse_date(&query.end, today)?,
+    };
+    let shift = |months| {
+        current
+            .shift(months)
+            .ok_or_else(|| report_error(ReportErrorKind::InvalidPeriod(current.start, current.end)))
+    };
+    let default_previous = shift(-12)?;
+    let previous_month = shift(-1)?;
+    let previous = db::report::Period {
+        start: parse_date(&query.previous_start, default_previous.start)?,
+        end: parse_date(&query.previous_end, default_previous.end)?,
+    };
+    let parent = get_parent_category(&connection, &ledger, user.id, query.parent)?;
+
+    let comparison =
+        db::report::compare(&connection, &ledger, &previous, &current, parent.as_ref())
+            .map_err(report_error)?;
+
+    let mut context = get_tera_context(template.translate("report-compare-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("parent", &parent);
+    context.insert("comparison", &comparison);
+    context.insert("previous_month", &previous_month);
+    context.insert("previous_year", &default_previous);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("reports/compare.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Request handler for the reports overview.
+pub async fn overview_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    template: Templates,
+    query: web::Query<PeriodQuery>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+    let period = Period::from_query(&query)?;
+
+    let today = Utc::now().naive_utc().date();
+    let forecast = db::forecast::get_forecast(&connection, &ledger, today, FORECAST_MONTHS)
+        .map_err(error::ErrorInternalServerError)?;
+
+    let mut context = get_tera_context(template.translate("reports-title").as_str(), id);
+    context.insert("ledger", &ledger);
+    context.insert("period", &period);
+    context.insert("forecast", &forecast);
+    insert_preferences(&mut context, &preferences);
+
+    let content = template
+        .render("reports/overview.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Returns the total spending per day, week or month in the current ledger as JSON data.
+pub async fn time_series_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    query: web::Query<PeriodQuery>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let preferences = get_preferences(&connection, &user)?;
+    let period = Period::from_query(&query)?;
+
+    let series = db::report::get_time_series(
+        &connection,
+        &ledger,
+        &period.start,
+        &period.end,
+        period.interval,
+        preferences.first_day_of_week.weekday(),
+    )
+    .map_err(report_error)?;
+
+    Ok(HttpResponse::Ok().json(series))
+}
+
+// Returns the spending per category in the current ledger as JSON data. The amounts are rolled up
+// into the root categories, or into the children of the category passed in the `parent` parameter.
+pub async fn categories_handler(
+    id: Identity,
+    session: Session,
+    pool: web::Data<db::ConnectionPool>,
+    query: web::Query<PeriodQuery>,
+) -> Result<HttpResponse, Error> {
+    let (connection, user) = get_connection_and_user(&id, &pool)?;
+    let (ledger, _) = get_current_ledger(&connection, &session, &user)?;
+    let period = Period::from_query(&query)?;
+
+    let parent = get_parent_category(&connection, &ledger, user.id, query.parent)?;
+
+    let breakdown = db::report::get_category_breakdown(
+        &connection,
+        &ledger,
+        &period.start,
+        &period.end,
+        parent.as_ref(),
+    )
+    .map_err(error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(breakdown))
+}
+
+// Returns the category with the given ID that is used to limit a report to a subtree. The category
+// should belong to the given ledger.
+fn get_parent_category(
+    connection: &PgConnection,
+    ledger: &Ledger,
+    user_id: i32,
+    parent_id: Option<i32>,
+) -> Result<Option<Category>, Error> {
+    match parent_id {
+        Some(parent_id) => match db::category::read(connection, parent_id, Some(user_id)) {
+            Some(category) if category.ledger_id == ledger.id => Ok(Some(category)),
+            _ => Err(error::ErrorNotFound("Category not found.")),
+        },
+        None => Ok(None),
+    }
+}
+
+// Parses a date that is passed in a query parameter, falling back to the given default.
+fn parse_date(date: &Option<String>, default: NaiveDate) -> Result<NaiveDate, Error> {
+    match date.as_deref() {
+        None | Some("") => Ok(default),
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| error::ErrorBadRequest("Dates should be in the format YYYY-MM-DD.")),
+    }
+}
+
+// Converts an error that occurred while generating a report into an HTTP error. Invalid input is
+// reported as a bad request.
+fn report_error(err: ReportErrorKind) -> Error {
+    match err {
+        ReportErrorKind::DatabaseError(_) => error::ErrorInternalServerError(err),
+        _ => error::ErrorBadRequest(err),
+    }
+}
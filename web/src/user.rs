@@ -1,11 +1,15 @@
 use super::bootstrap_components::{Alert, AlertType};
-use super::get_tera_context;
+use super::{get_ip_address, get_tera_context};
+use crate::i18n::Templates;
 use actix_identity::Identity;
 use actix_session::Session;
-use actix_web::{error, web, Error, HttpResponse};
-use app::AppConfig;
+use actix_web::{error, web, Error, HttpRequest, HttpResponse};
+use app::{AppConfig, RegistrationMode};
 use db::activation_code::ActivationCodeErrorKind;
-use db::user::UserErrorKind;
+use db::login_attempt::{LoginAttemptErrorKind, Scope};
+use db::password_reset_code::PasswordResetCodeErrorKind;
+use db::two_factor::TwoFactorErrorKind;
+use db::user::{User, UserErrorKind};
 use diesel::PgConnection;
 use validator::validate_email;
 
@@ -14,11 +18,24 @@ use validator::validate_email;
 pub struct UserForm {
     email: String,
     password: String,
+    // Only used on the registration form when registration is invite-only.
+    #[serde(default)]
+    invitation_code: String,
 }
 
 impl UserForm {
     pub fn new(email: String, password: String) -> UserForm {
-        UserForm { email, password }
+        UserForm {
+            email,
+            password,
+            invitation_code: "".to_string(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_invitation_code(mut self, invitation_code: String) -> UserForm {
+        self.invitation_code = invitation_code;
+        self
     }
 }
 
@@ -28,6 +45,7 @@ struct UserFormValidation {
     form_is_validated: bool,
     email: bool,
     password: bool,
+    invitation_code: bool,
 }
 
 impl UserFormValidation {
@@ -38,6 +56,7 @@ impl UserFormValidation {
             form_is_validated,
             email,
             password,
+            invitation_code: true,
         }
     }
 
@@ -47,6 +66,7 @@ impl UserFormValidation {
             form_is_validated: false,
             email: true,
             password: true,
+            invitation_code: true,
         }
     }
 
@@ -88,7 +108,7 @@ impl UserFormValidation {
 
     // Returns whether the form is validated and found valid.
     pub fn is_valid(&self) -> bool {
-        self.form_is_validated && self.email && self.password
+        self.form_is_validated && self.email && self.password && self.invitation_code
     }
 }
 
@@ -96,48 +116,136 @@ impl UserFormValidation {
 pub async fn login_handler(
     id: Identity,
     session: Session,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
+    config: web::Data<AppConfig>,
 ) -> Result<HttpResponse, Error> {
     crate::assert_not_authenticated(&id)?;
 
     let input = UserForm::new("".to_string(), "".to_string());
     let validation_state = UserFormValidation::default();
-    render_login(id, session, tera, input, validation_state)
+    render_login(id, session, tera, &config, input, validation_state, vec![])
 }
 
 // Submit handler for the login form.
 pub async fn login_submit(
     session: Session,
     id: Identity,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
     input: web::Form<UserForm>,
     pool: web::Data<db::ConnectionPool>,
     config: web::Data<AppConfig>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     crate::assert_not_authenticated(&id)?;
 
     let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let ip = get_ip_address(&req);
+
+    // Don't check the password while the account or IP address is locked due to too many failed
+    // login attempts.
+    match db::login_attempt::check(&connection, &input.email, ip.as_deref()) {
+        Ok(_) => {}
+        Err(e @ LoginAttemptErrorKind::Locked(_)) => {
+            let input = UserForm::new(input.email.to_owned(), "".to_string());
+            let validation_state = UserFormValidation::default();
+            return render_login(
+                id,
+                session,
+                tera,
+                &config,
+                input,
+                validation_state,
+                vec![Alert::danger(e)],
+            );
+        }
+        Err(e) => return Err(error::ErrorInternalServerError(e)),
+    }
 
     // Validate the form input.
     let validation_state = UserFormValidation::validate_login(&connection, &config, &input);
 
     // If validation failed, show the form again with validation errors highlighted.
     if !validation_state.is_valid() {
-        return render_login(id, session, tera, input.into_inner(), validation_state);
+        if !input.email.is_empty() && !input.password.is_empty() {
+            db::login_attempt::register_failure(&connection, &input.email, ip.as_deref())
+                .map_err(error::ErrorInternalServerError)?;
+        }
+        return render_login(
+            id,
+            session,
+            tera,
+            &config,
+            input.into_inner(),
+            validation_state,
+            vec![],
+        );
+    }
+
+    // Disabled accounts cannot log in, even with the correct password.
+    let user =
+        db::user::read(&connection, &input.email).map_err(error::ErrorInternalServerError)?;
+    if user.disabled {
+        let input = UserForm::new(input.email.to_owned(), "".to_string());
+        let alert = Alert::danger(tera.translate("login-account-disabled"));
+        return render_login(
+            id,
+            session,
+            tera,
+            &config,
+            input,
+            UserFormValidation::default(),
+            vec![alert],
+        );
     }
 
-    // The user has been validated, create a session.
-    Ok(start_session(id, input.email.to_owned()))
+    // The user has been validated, log in.
+    log_in(id, &session, &connection, input.email.as_str())
+}
+
+// Logs in the user with the given email address, after their password or their identity at the
+// identity provider has been verified. If two-factor authentication is enabled the user is
+// redirected to the second login step, and the session is only started once a valid code has been
+// entered. Returns a 403 if the account has been disabled.
+pub(crate) fn log_in(
+    id: Identity,
+    session: &Session,
+    connection: &PgConnection,
+    email: &str,
+) -> Result<HttpResponse, Error> {
+    let user = db::user::read(connection, email).map_err(error::ErrorInternalServerError)?;
+    if user.disabled {
+        return Err(error::ErrorForbidden("This account has been disabled."));
+    }
+    if !db::two_factor::is_enabled(connection, &user).map_err(error::ErrorInternalServerError)? {
+        return start_session(id, connection, user.email);
+    }
+
+    // The password has been verified, so a new series of codes can be tried.
+    db::two_factor::reset_attempts(connection, &user).map_err(error::ErrorInternalServerError)?;
+    session
+        .set("two_factor_email", user.email)
+        .map_err(error::ErrorInternalServerError)?;
+    Ok(HttpResponse::SeeOther()
+        .header("location", "/user/login/two-factor")
+        .finish())
 }
 
 // Initiates a session for the user with the given email and redirects to the homepage.
-fn start_session(id: Identity, email: String) -> HttpResponse {
+fn start_session(
+    id: Identity,
+    connection: &PgConnection,
+    email: String,
+) -> Result<HttpResponse, Error> {
+    // The login succeeded, reset the failed login counter of the account.
+    db::login_attempt::delete(connection, Scope::Account, &email)
+        .map_err(error::ErrorInternalServerError)?;
+
     // Start the session.
     id.remember(email);
 
     // Redirect to the homepage, using HTTP 303 redirect which will execute the redirection as a GET
     // request.
-    HttpResponse::SeeOther().header("location", "/").finish()
+    Ok(HttpResponse::SeeOther().header("location", "/").finish())
 }
 
 // Renders the login form.
@@ -145,13 +253,18 @@ fn start_session(id: Identity, email: String) -> HttpResponse {
 fn render_login(
     id: Identity,
     session: Session,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
+    config: &AppConfig,
     input: UserForm,
     validation_state: UserFormValidation,
+    mut alerts: Vec<Alert>,
 ) -> Result<HttpResponse, Error> {
-    let mut context = get_tera_context("Log in", id);
+    let mut context = get_tera_context(tera.translate("login-title").as_str(), id);
     context.insert("input", &input);
     context.insert("validation", &validation_state);
+    if config.oidc_enabled() {
+        context.insert("oidc_provider_name", config.oidc_provider_name());
+    }
 
     // If the user is coming from the activation form, show a success message.
     if session
@@ -161,21 +274,171 @@ fn render_login(
     {
         let alert = Alert {
             alert_type: AlertType::Success,
-            message: "Your account has been activated. You can now log in.".to_string(),
+            message: tera.translate("login-account-activated"),
         };
-        context.insert("alerts", &vec![alert]);
+        alerts.push(alert);
 
         // Remove the values from the session so this message won't show up again.
         session.remove("account_activated");
         session.remove("email");
     }
 
+    // If the user has just deleted their account, show a confirmation.
+    if session
+        .get::<bool>("account_deleted")
+        .unwrap_or(None)
+        .is_some()
+    {
+        alerts.push(Alert::success(tera.translate("login-account-deleted")));
+
+        // Remove the flag from the session so this message won't show up again.
+        session.remove("account_deleted");
+    }
+
+    // If the user is coming from the password reset form, show a success message.
+    if session
+        .get::<bool>("password_reset")
+        .unwrap_or(None)
+        .is_some()
+    {
+        let alert = Alert {
+            alert_type: AlertType::Success,
+            message: tera.translate("login-password-changed"),
+        };
+        alerts.push(alert);
+
+        // Remove the flag from the session so this message won't show up again.
+        session.remove("password_reset");
+    }
+
+    // If the user entered too many incorrect codes in the second login step, show an error.
+    if session
+        .get::<bool>("two_factor_failed")
+        .unwrap_or(None)
+        .is_some()
+    {
+        let alert = Alert::danger(tera.translate("login-two-factor-failed"));
+        alerts.push(alert);
+
+        // Remove the flag from the session so this message won't show up again.
+        session.remove("two_factor_failed");
+    }
+
+    // If logging in through the identity provider failed, show the reason.
+    if let Some(message) = session.get::<String>("oidc_failed").unwrap_or(None) {
+        alerts.push(Alert::danger(message));
+
+        // Remove the message from the session so it won't show up again.
+        session.remove("oidc_failed");
+    }
+    context.insert("alerts", &alerts);
+
     let content = tera
         .render("user/login.html", &context)
         .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
     Ok(HttpResponse::Ok().content_type("text/html").body(content))
 }
 
+// The form fields of the second login step.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct TwoFactorForm {
+    code: String,
+}
+
+// Request handler for the second login step, in which users that have enabled two-factor
+// authentication enter a code from their authenticator app or a recovery code.
+pub async fn two_factor_handler(
+    id: Identity,
+    session: Session,
+    tera: Templates,
+    pool: web::Data<db::ConnectionPool>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    get_two_factor_user(&connection, &session)?;
+
+    render_two_factor(id, tera, "")
+}
+
+// Submit handler for the second login step.
+pub async fn two_factor_submit(
+    id: Identity,
+    session: Session,
+    tera: Templates,
+    input: web::Form<TwoFactorForm>,
+    pool: web::Data<db::ConnectionPool>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let user = get_two_factor_user(&connection, &session)?;
+    let ip = get_ip_address(&req);
+
+    // Incorrect codes count as failed logins, so don't check the code while the account or IP
+    // address is locked.
+    match db::login_attempt::check(&connection, &user.email, ip.as_deref()) {
+        Ok(_) => {}
+        Err(e @ LoginAttemptErrorKind::Locked(_)) => {
+            return render_two_factor(id, tera, e.to_string().as_str());
+        }
+        Err(e) => return Err(error::ErrorInternalServerError(e)),
+    }
+
+    match db::two_factor::verify(&connection, &user, &input.code, &config) {
+        Ok(_) => {
+            session.remove("two_factor_email");
+            start_session(id, &connection, user.email)
+        }
+        // After too many incorrect codes the password needs to be entered again, so the codes
+        // cannot be brute forced. The incorrect codes are counted in the database.
+        Err(TwoFactorErrorKind::MaxAttemptsExceeded) => {
+            db::login_attempt::register_failure(&connection, &user.email, ip.as_deref())
+                .map_err(error::ErrorInternalServerError)?;
+            session.remove("two_factor_email");
+            session
+                .set("two_factor_failed", true)
+                .map_err(error::ErrorInternalServerError)?;
+            Ok(HttpResponse::SeeOther()
+                .header("location", "/user/login")
+                .finish())
+        }
+        Err(TwoFactorErrorKind::InvalidCode) => {
+            db::login_attempt::register_failure(&connection, &user.email, ip.as_deref())
+                .map_err(error::ErrorInternalServerError)?;
+            let message = tera.translate("two-factor-code-incorrect");
+            render_two_factor(id, tera, message.as_str())
+        }
+        Err(e) => Err(error::ErrorInternalServerError(e)),
+    }
+}
+
+// Returns the user whose password has been verified in the first login step. Returns a 403 if the
+// first login step has not been completed, or if the account has been disabled in the meantime.
+fn get_two_factor_user(connection: &PgConnection, session: &Session) -> Result<User, Error> {
+    if let Some(email) = session.get::<String>("two_factor_email").unwrap_or(None) {
+        if let Ok(user) = db::user::read(connection, email.as_str()) {
+            if !user.disabled {
+                return Ok(user);
+            }
+        }
+    }
+    Err(error::ErrorForbidden("Please log in first."))
+}
+
+// Renders the form of the second login step, with an optional validation message.
+fn render_two_factor(id: Identity, tera: Templates, message: &str) -> Result<HttpResponse, Error> {
+    let mut context = get_tera_context(tera.translate("two-factor-title").as_str(), id);
+    context.insert("message", message);
+
+    let content = tera
+        .render("user/two_factor.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
 // Request handler for logging out.
 pub async fn logout_handler(id: Identity, session: Session) -> Result<HttpResponse, Error> {
     crate::assert_authenticated(&id)?;
@@ -190,7 +453,8 @@ pub async fn logout_handler(id: Identity, session: Session) -> Result<HttpRespon
 // Request handler for a GET request on the registration form.
 pub async fn register_handler(
     id: Identity,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
+    config: web::Data<AppConfig>,
 ) -> Result<HttpResponse, Error> {
     crate::assert_not_authenticated(&id)?;
 
@@ -198,50 +462,93 @@ pub async fn register_handler(
     // there are no validation errors.
     let input = UserForm::new("".to_string(), "".to_string());
     let validation_state = UserFormValidation::default();
-    render_register(id, tera, input, validation_state)
+    render_register(id, tera, &config, input, validation_state)
 }
 
 // Submit handler for the registration form.
 pub async fn register_submit(
     session: Session,
     id: Identity,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
     input: web::Form<UserForm>,
     pool: web::Data<db::ConnectionPool>,
     config: web::Data<AppConfig>,
+    req: HttpRequest,
 ) -> Result<HttpResponse, Error> {
     crate::assert_not_authenticated(&id)?;
+    let registration_mode = config.registration_mode();
+    if registration_mode == RegistrationMode::Closed {
+        return Err(error::ErrorForbidden("Registration is closed."));
+    }
 
     // Validate the form input.
-    let validation_state = UserFormValidation::validate_registration(&input);
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let mut validation_state = UserFormValidation::validate_registration(&input);
+
+    // When registration is invite-only, a valid invitation code for the email address is required.
+    // This is checked for existing accounts too, so the response does not disclose which email
+    // addresses have an account.
+    if registration_mode == RegistrationMode::InviteOnly
+        && validation_state.is_valid()
+        && db::registration_invitation::validate(&connection, &input.email, &input.invitation_code)
+            .is_err()
+    {
+        validation_state.invitation_code = false;
+    }
 
     // If validation failed, show the form again with validation errors highlighted.
     if !validation_state.is_valid() {
-        return render_register(id, tera, input.into_inner(), validation_state);
+        drop(connection);
+        return render_register(id, tera, &config, input.into_inner(), validation_state);
     }
 
     // Create the user account.
-    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
     let result = db::user::create(&connection, &input.email, &input.password, &config);
 
     // Check if a user account already exists with the given email address. The user might have
     // forgotten that they already have an account, or they might have intended to log in instead of
     // register.
     if let Err(UserErrorKind::UserWithEmailAlreadyExists(_)) = result {
-        return if db::user::verify_password(&connection, &input.email, &input.password, &config)
-            .is_ok()
+        // The password is not checked while logins are locked, so the registration form cannot be
+        // used to bypass the login throttling.
+        let ip = get_ip_address(&req);
+        let locked = db::login_attempt::check(&connection, &input.email, ip.as_deref()).is_err();
+        return if !locked
+            && db::user::verify_password(&connection, &input.email, &input.password, &config)
+                .is_ok()
         {
             // If the supplied credentials are correct, just transparently log in the user.
-            Ok(start_session(id, input.email.to_owned()))
+            log_in(id, &session, &connection, input.email.as_str())
         } else {
             // If the supplied credentials are incorrect, inform the user by email that someone
-            // is trying to register using their email.
-            // Todo to be implemented. Ref issue #68.
-            Err(format!("email {} already exists but password is incorrect. Ref https://github.com/pfrenssen/firetrack/issues/68", input.email)).map_err(error::ErrorInternalServerError)
+            // is trying to register using their email. The registrant gets the same response as
+            // for a successful registration so this does not disclose that the account exists.
+            // No email is sent while logins are locked, and at most one every 30 minutes, so the
+            // form cannot be used to flood the inbox of the account owner.
+            let user = db::user::read(&connection, &input.email)
+                .map_err(error::ErrorInternalServerError)?;
+            if !locked {
+                db::login_attempt::register_failure(&connection, &input.email, ip.as_deref())
+                    .map_err(error::ErrorInternalServerError)?;
+                if db::registration_notification::claim(&connection, &user)
+                    .map_err(error::ErrorInternalServerError)?
+                {
+                    notifications::registration_attempt(&user, &config)
+                        .await
+                        .map_err(error::ErrorInternalServerError)?;
+                }
+            }
+            redirect_to_activation_form(&session, user.email.as_str())
         };
     }
     let user = result.map_err(error::ErrorInternalServerError)?;
 
+    // The invitation has been used.
+    if registration_mode == RegistrationMode::InviteOnly {
+        db::registration_invitation::delete(&connection, &user.email)
+            .map_err(error::ErrorInternalServerError)?;
+    }
+
     // Send an activation email.
     let activation_code =
         db::activation_code::get(&connection, &user).map_err(error::ErrorInternalServerError)?;
@@ -249,9 +556,14 @@ pub async fn register_submit(
         .await
         .map_err(error::ErrorInternalServerError)?;
 
+    redirect_to_activation_form(&session, user.email.as_str())
+}
+
+// Passes the given email address to the activation form and redirects to it.
+fn redirect_to_activation_form(session: &Session, email: &str) -> Result<HttpResponse, Error> {
     // Pass the email address to the activation form by setting it on the session.
     session
-        .set("email", user.email.as_str())
+        .set("email", email)
         .map_err(error::ErrorInternalServerError)?;
 
     // Redirect to the activation form, using HTTP 303 redirect which will execute the redirection
@@ -264,13 +576,15 @@ pub async fn register_submit(
 // Renders the registration form, including validation errors.
 fn render_register(
     id: Identity,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
+    config: &AppConfig,
     input: UserForm,
     validation_state: UserFormValidation,
 ) -> Result<HttpResponse, Error> {
-    let mut context = get_tera_context("Sign up", id);
+    let mut context = get_tera_context(tera.translate("register-title").as_str(), id);
     context.insert("input", &input);
     context.insert("validation", &validation_state);
+    context.insert("registration_mode", &config.registration_mode().to_string());
 
     let content = tera
         .render("user/register.html", &context)
@@ -326,21 +640,70 @@ impl ActivationFormInputValid {
 pub async fn activate_handler(
     id: Identity,
     session: Session,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
+    pool: web::Data<db::ConnectionPool>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    // The email address is passed in the session by the registration form. Return an error if it is
+    // not set or does not correspond with an existing user.
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    get_registered_user(&connection, &session)?;
+
+    let input = ActivationFormInput::new("".to_string());
+    let validation_state = ActivationFormInputValid::default();
+    render_activate(id, tera, input, validation_state, vec![])
+}
+
+// Submit handler for the form to resend the activation code. The number of activation codes that
+// can be requested is limited by the attempt counter of the activation code, so an attacker cannot
+// flood the user's inbox.
+pub async fn activate_resend_submit(
+    id: Identity,
+    session: Session,
+    tera: Templates,
     pool: web::Data<db::ConnectionPool>,
+    config: web::Data<AppConfig>,
 ) -> Result<HttpResponse, Error> {
     crate::assert_not_authenticated(&id)?;
 
-    // The email address is passed in the session by the registration / login form. Return an error
-    // if it is not set or does not correspond with an existing, non-activated user.
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let user = get_registered_user(&connection, &session)?;
+    let sent = Alert::success(
+        tera.translate_with("activation-code-sent", &[("email", user.email.clone())]),
+    );
+
+    let alert = match db::activation_code::get(&connection, &user) {
+        Ok(activation_code) => {
+            notifications::activate(&user, &activation_code, &config)
+                .await
+                .map_err(error::ErrorInternalServerError)?;
+            sent
+        }
+        // The account is already activated. This happens when someone tried to register with the
+        // email address of an existing account. Respond as if a code has been sent so this does
+        // not disclose that the account exists.
+        Err(ActivationCodeErrorKind::UserAlreadyActivated(_)) => sent,
+        Err(ActivationCodeErrorKind::MaxAttemptsExceeded) => {
+            Alert::danger(tera.translate("activation-code-max-requests"))
+        }
+        Err(e) => return Err(error::ErrorInternalServerError(e)),
+    };
+
+    let input = ActivationFormInput::new("".to_string());
+    let validation_state = ActivationFormInputValid::default();
+    render_activate(id, tera, input, validation_state, vec![alert])
+}
+
+// Returns the user whose email address is stored in the session by the registration form. Returns
+// a 403 if there is no such user.
+//
+// Note that the user might already be activated if someone tried to register using the email
+// address of an existing account. The activation forms should not disclose this.
+fn get_registered_user(connection: &PgConnection, session: &Session) -> Result<User, Error> {
     if let Some(email) = session.get::<String>("email").unwrap_or(None) {
-        let connection = pool.get().map_err(error::ErrorInternalServerError)?;
-        if let Ok(user) = db::user::read(&connection, email.as_str()) {
-            if !user.activated {
-                let input = ActivationFormInput::new("".to_string());
-                let validation_state = ActivationFormInputValid::default();
-                return render_activate(id, tera, input, validation_state);
-            }
+        if let Ok(user) = db::user::read(connection, email.as_str()) {
+            return Ok(user);
         }
     }
     Err(error::ErrorForbidden(
@@ -352,7 +715,7 @@ pub async fn activate_handler(
 pub async fn activate_submit(
     id: Identity,
     session: Session,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
     input: web::Form<ActivationFormInput>,
     pool: web::Data<db::ConnectionPool>,
 ) -> Result<HttpResponse, Error> {
@@ -361,12 +724,14 @@ pub async fn activate_submit(
     let activation_code = input.activation_code.clone();
 
     // Convenience functions for easily returning error messages.
-    let validation_error = |message| {
+    let validation_error = |key| {
+        let message = tera.translate(key);
         render_activate(
             id,
             tera,
             input.into_inner(),
-            ActivationFormInputValid::invalid(message),
+            ActivationFormInputValid::invalid(message.as_str()),
+            vec![],
         )
     };
     let authorization_failed = || {
@@ -380,7 +745,7 @@ pub async fn activate_submit(
         .map_err(error::ErrorInternalServerError)?
         .is_match(activation_code.as_str())
     {
-        return validation_error("Please enter a 6-digit number");
+        return validation_error("activate-code-format");
     }
 
     // Convert the user input to an integer. We know that the input is a 6 digit number, so we can
@@ -395,18 +760,17 @@ pub async fn activate_submit(
         if let Ok(user) = db::user::read(&connection, email.as_str()) {
             match db::activation_code::activate_user(&connection, user, activation_code) {
                 Err(ActivationCodeErrorKind::Expired) => {
-                    return validation_error("The expiration code has expired. Please re-send the activation email and try again.");
-                }
-                Err(ActivationCodeErrorKind::UserAlreadyActivated(_)) => {
-                    // In order to not disclose which email addresses are registered we treat this
-                    // the same as a non-existing user trying to access the form.
-                    return authorization_failed();
+                    return validation_error("activate-code-expired");
                 }
                 Err(ActivationCodeErrorKind::MaxAttemptsExceeded) => {
-                    return validation_error("You have exceeded the maximum number of activation attempts. Please try again later.");
+                    return validation_error("activate-max-attempts");
                 }
-                Err(ActivationCodeErrorKind::InvalidCode) => {
-                    return validation_error("Incorrect activation code. Please try again.");
+                // If the account is already activated, someone tried to register using the email
+                // address of an existing account. In order to not disclose which email addresses
+                // are registered we treat this the same as an incorrect code.
+                Err(ActivationCodeErrorKind::InvalidCode)
+                | Err(ActivationCodeErrorKind::UserAlreadyActivated(_)) => {
+                    return validation_error("activate-code-incorrect");
                 }
                 Err(e) => {
                     return Err(error::ErrorInternalServerError(e));
@@ -433,13 +797,15 @@ pub async fn activate_submit(
 // Renders the activation form.
 fn render_activate(
     id: Identity,
-    tera: web::Data<tera::Tera>,
+    tera: Templates,
     input: ActivationFormInput,
     validation_state: ActivationFormInputValid,
+    alerts: Vec<Alert>,
 ) -> Result<HttpResponse, Error> {
-    let mut context = get_tera_context("Activate account", id);
+    let mut context = get_tera_context(tera.translate("activate-title").as_str(), id);
     context.insert("input", &input);
     context.insert("validation", &validation_state);
+    context.insert("alerts", &alerts);
 
     let content = tera
         .render("user/activate.html", &context)
@@ -447,6 +813,285 @@ fn render_activate(
     Ok(HttpResponse::Ok().content_type("text/html").body(content))
 }
 
+// The form fields of the form to request a password reset.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordResetForm {
+    email: String,
+}
+
+impl PasswordResetForm {
+    pub fn new(email: String) -> PasswordResetForm {
+        PasswordResetForm { email }
+    }
+}
+
+// Whether the form fields of the form to request a password reset are valid.
+#[derive(Serialize, Deserialize)]
+struct PasswordResetFormValid {
+    // Whether or not the form input has been validated.
+    form_is_validated: bool,
+    // Whether or not the email address is valid.
+    email: bool,
+}
+
+// The form fields of the form to choose a new password.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct PasswordResetConfirmForm {
+    password_reset_code: String,
+    password: String,
+}
+
+impl PasswordResetConfirmForm {
+    pub fn new(password_reset_code: String, password: String) -> PasswordResetConfirmForm {
+        PasswordResetConfirmForm {
+            password_reset_code,
+            password,
+        }
+    }
+}
+
+// Whether the form fields of the form to choose a new password are valid.
+#[derive(Serialize, Deserialize)]
+struct PasswordResetConfirmFormValid {
+    // Whether or not the form input has been validated.
+    form_is_validated: bool,
+    // Whether or not the password reset code is valid.
+    password_reset_code: bool,
+    // Whether or not the password is valid.
+    password: bool,
+    // The validation message to show for the password reset code.
+    message: String,
+}
+
+impl PasswordResetConfirmFormValid {
+    // Instantiate a form validation struct with default values.
+    pub fn default() -> PasswordResetConfirmFormValid {
+        PasswordResetConfirmFormValid {
+            form_is_validated: false,
+            password_reset_code: true,
+            password: true,
+            message: "".to_string(),
+        }
+    }
+}
+
+// Request handler for the form to request a password reset.
+pub async fn password_reset_handler(id: Identity, tera: Templates) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    let input = PasswordResetForm::new("".to_string());
+    let validation_state = PasswordResetFormValid {
+        form_is_validated: false,
+        email: true,
+    };
+    render_password_reset(id, tera, input, validation_state)
+}
+
+// Submit handler for the form to request a password reset. If an account exists for the given
+// email address a password reset code is sent to it. To prevent enumeration attacks the response is
+// the same regardless of whether or not the account exists.
+pub async fn password_reset_submit(
+    id: Identity,
+    session: Session,
+    tera: Templates,
+    input: web::Form<PasswordResetForm>,
+    pool: web::Data<db::ConnectionPool>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    let email = input.email.trim().to_string();
+    if !validate_email(&email) {
+        let validation_state = PasswordResetFormValid {
+            form_is_validated: true,
+            email: false,
+        };
+        return render_password_reset(id, tera, input.into_inner(), validation_state);
+    }
+
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    if let Ok(user) = db::user::read(&connection, email.as_str()) {
+        match db::password_reset_code::get(&connection, &user) {
+            Ok(password_reset_code) => {
+                notifications::password_reset(&user, &password_reset_code, &config)
+                    .await
+                    .map_err(error::ErrorInternalServerError)?;
+            }
+            // If too many codes have been requested in a short time, stop sending mails so the
+            // user's inbox doesn't get flooded. The previously sent code remains valid.
+            Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded) => {}
+            Err(e) => return Err(error::ErrorInternalServerError(e)),
+        }
+    }
+
+    // Pass the email address to the form to choose a new password by setting it on the session.
+    session
+        .set("password_reset_email", email)
+        .map_err(error::ErrorInternalServerError)?;
+
+    // Redirect using HTTP 303 redirect which will execute the redirection as a GET request.
+    Ok(HttpResponse::SeeOther()
+        .header("location", "/user/password-reset/confirm")
+        .finish())
+}
+
+// Renders the form to request a password reset.
+fn render_password_reset(
+    id: Identity,
+    tera: Templates,
+    input: PasswordResetForm,
+    validation_state: PasswordResetFormValid,
+) -> Result<HttpResponse, Error> {
+    let mut context = get_tera_context(tera.translate("password-reset-title").as_str(), id);
+    context.insert("input", &input);
+    context.insert("validation", &validation_state);
+
+    let content = tera
+        .render("user/password_reset.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
+// Request handler for the form to choose a new password. This can only be accessed after a password
+// reset has been requested.
+pub async fn password_reset_confirm_handler(
+    id: Identity,
+    session: Session,
+    tera: Templates,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    match session
+        .get::<String>("password_reset_email")
+        .unwrap_or(None)
+    {
+        Some(email) => {
+            let input = PasswordResetConfirmForm::new("".to_string(), "".to_string());
+            let validation_state = PasswordResetConfirmFormValid::default();
+            render_password_reset_confirm(id, tera, email, input, validation_state)
+        }
+        None => Err(error::ErrorForbidden(
+            "Please request a password reset first.",
+        )),
+    }
+}
+
+// Submit handler for the form to choose a new password.
+pub async fn password_reset_confirm_submit(
+    id: Identity,
+    session: Session,
+    tera: Templates,
+    input: web::Form<PasswordResetConfirmForm>,
+    pool: web::Data<db::ConnectionPool>,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, Error> {
+    crate::assert_not_authenticated(&id)?;
+
+    let email = match session
+        .get::<String>("password_reset_email")
+        .unwrap_or(None)
+    {
+        Some(email) => email,
+        None => {
+            return Err(error::ErrorForbidden(
+                "Please request a password reset first.",
+            ))
+        }
+    };
+
+    // Check that the password reset code is a 6 digit number and that a password is entered.
+    let mut validation_state = PasswordResetConfirmFormValid {
+        form_is_validated: true,
+        ..PasswordResetConfirmFormValid::default()
+    };
+    if !regex::Regex::new(r"^\d{6}$")
+        .map_err(error::ErrorInternalServerError)?
+        .is_match(input.password_reset_code.as_str())
+    {
+        validation_state.password_reset_code = false;
+        validation_state.message = tera.translate("password-reset-code-format");
+    }
+    if input.password.is_empty() {
+        validation_state.password = false;
+    }
+    if !validation_state.password_reset_code || !validation_state.password {
+        return render_password_reset_confirm(
+            id,
+            tera,
+            email,
+            input.into_inner(),
+            validation_state,
+        );
+    }
+
+    // Convert the user input to an integer. We know that the input is a 6 digit number, so we can
+    // assume that the conversion will succeed, and return a 500 in the case that somehow doesn't.
+    let password_reset_code: i32 = input
+        .password_reset_code
+        .parse()
+        .map_err(error::ErrorInternalServerError)?;
+
+    // A non-existing account is reported in the same way as a wrong code, so that the form does not
+    // disclose which email addresses are registered.
+    let connection = pool.get().map_err(error::ErrorInternalServerError)?;
+    let result = match db::user::read(&connection, email.as_str()) {
+        Ok(user) => db::password_reset_code::reset_password(
+            &connection,
+            user,
+            password_reset_code,
+            input.password.as_str(),
+            &config,
+        ),
+        Err(_) => Err(PasswordResetCodeErrorKind::InvalidCode),
+    };
+
+    match result {
+        Ok(user) => {
+            // The password has been changed. Log out all sessions, since they might have been
+            // started by someone who knew the old password.
+            db::user_session::delete_all(&connection, &user, None)
+                .map_err(error::ErrorInternalServerError)?;
+
+            // Set a flag on the session and redirect to the login page using a HTTP 303 redirect
+            // which will issue a GET request.
+            session.remove("password_reset_email");
+            session
+                .set("password_reset", true)
+                .map_err(error::ErrorInternalServerError)?;
+            Ok(HttpResponse::SeeOther()
+                .header("location", "/user/login")
+                .finish())
+        }
+        Err(PasswordResetCodeErrorKind::InvalidCode)
+        | Err(PasswordResetCodeErrorKind::Expired)
+        | Err(PasswordResetCodeErrorKind::MaxAttemptsExceeded) => {
+            validation_state.password_reset_code = false;
+            validation_state.message = tera.translate("password-reset-code-incorrect");
+            render_password_reset_confirm(id, tera, email, input.into_inner(), validation_state)
+        }
+        Err(e) => Err(error::ErrorInternalServerError(e)),
+    }
+}
+
+// Renders the form to choose a new password.
+fn render_password_reset_confirm(
+    id: Identity,
+    tera: Templates,
+    email: String,
+    input: PasswordResetConfirmForm,
+    validation_state: PasswordResetConfirmFormValid,
+) -> Result<HttpResponse, Error> {
+    let mut context = get_tera_context(tera.translate("password-reset-confirm-title").as_str(), id);
+    context.insert("email", &email);
+    context.insert("input", &input);
+    context.insert("validation", &validation_state);
+
+    let content = tera
+        .render("user/password_reset_confirm.html", &context)
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error: {:?}", err)))?;
+    Ok(HttpResponse::Ok().content_type("text/html").body(content))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
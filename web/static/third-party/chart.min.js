@@ -0,0 +1 @@
+../../../vendor/almasaeed2010/adminlte/plugins/chart.js/Chart.min.js
\ No newline at end of file